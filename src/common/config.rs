@@ -1,21 +1,25 @@
 use anyhow::Result;
-use bs58;
 use colored::Colorize;
 use dotenv::dotenv;
-use reqwest::Error;
-use serde::Deserialize;
-use anchor_client::solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair, signer::Signer};
+use anchor_client::solana_sdk::{commitment_config::CommitmentConfig, signer::Signer};
 use tokio::sync::{Mutex, OnceCell};
-use std::{env, sync::Arc};
+use std::sync::Arc;
 
 use crate::{
-    common::{constants::INIT_MSG, logger::Logger},
+    common::{
+        constants::INIT_MSG,
+        logger::Logger,
+        settings::{init_settings, Settings},
+    },
+    core::signer::{import_signer, SharedSigner, TradeSigner},
     engine::swap::{SwapDirection, SwapInType},
+    services::price_oracle::{create_price_oracle, PriceOracle, DEFAULT_SOL_PRICE_USD},
 };
 
 static GLOBAL_CONFIG: OnceCell<Mutex<Config>> = OnceCell::const_new();
 
 pub struct Config {
+    pub settings: Settings,
     pub yellowstone_grpc_http: String,
     pub yellowstone_grpc_token: String,
     pub app_state: AppState,
@@ -37,22 +41,40 @@ impl Config {
 
             let logger = Logger::new("[INIT] => ".blue().bold().to_string());
 
-            let yellowstone_grpc_http = import_env_var("YELLOWSTONE_GRPC_HTTP");
-            let yellowstone_grpc_token = import_env_var("YELLOWSTONE_GRPC_TOKEN");
-            let slippage_input = import_env_var("SLIPPAGE").parse::<u64>().unwrap_or(0);
-            let counter_limit = import_env_var("COUNTER").parse::<u32>().unwrap_or(0_u32);
-            let max_dev_buy = import_env_var("MAX_DEV_BUY").parse::<u32>().unwrap_or(0_u32);
-            let min_dev_buy = import_env_var("MIN_DEV_BUY").parse::<u32>().unwrap_or(0_u32);
-            let max_slippage: u64 = 100;
-            let slippage = if slippage_input > max_slippage {
-                max_slippage
-            } else {
-                slippage_input
+            let settings = match init_settings() {
+                Ok(settings) => settings,
+                Err(errors) => {
+                    logger.log(format!("Invalid configuration ({} error(s)):", errors.len()).red().to_string());
+                    for error in errors.iter() {
+                        logger.log(format!("\t * {}", error).red().to_string());
+                    }
+                    std::process::exit(1);
+                }
+            };
+
+            let yellowstone_grpc_http = settings.grpc.http.clone();
+            let yellowstone_grpc_token = settings.grpc.token.clone();
+            let slippage = settings.swap.slippage;
+            let counter_limit = settings.swap.counter_limit;
+            let max_dev_buy = settings.swap.max_dev_buy;
+            let min_dev_buy = settings.swap.min_dev_buy;
+            let rpc_client = create_rpc_client(&settings.rpc.http).unwrap();
+            let rpc_nonblocking_client = create_nonblocking_rpc_client(&settings.rpc.http).await.unwrap();
+            let price_oracle = create_price_oracle(rpc_nonblocking_client.clone(), &settings.oracle);
+            let solana_price = match price_oracle.sol_usd_price().await {
+                Ok(price) => price,
+                Err(err) => {
+                    logger.log(format!("Failed to get SOL price: {}", err).red().to_string());
+                    DEFAULT_SOL_PRICE_USD
+                }
+            };
+            let wallet: SharedSigner = match import_signer(&settings.signer) {
+                Ok(signer) => signer,
+                Err(err) => {
+                    logger.log(format!("Failed to load signer: {}", err).red().to_string());
+                    std::process::exit(1);
+                }
             };
-            let solana_price = create_coingecko_proxy().await.unwrap_or(200_f64);
-            let rpc_client = create_rpc_client().unwrap();
-            let rpc_nonblocking_client = create_nonblocking_rpc_client().await.unwrap();
-            let wallet: std::sync::Arc<anchor_client::solana_sdk::signature::Keypair> = import_wallet().unwrap();
             let balance = match rpc_nonblocking_client
                 .get_account(&wallet.pubkey())
                 .await {
@@ -64,14 +86,10 @@ impl Config {
                 };
 
             let wallet_cloned = wallet.clone();
-            let use_jito = true;
+            let use_jito = settings.swap.use_jito;
             let swap_direction = SwapDirection::Buy; //SwapDirection::Sell
             let in_type = SwapInType::Qty; //SwapInType::Pct
-            let amount_in = import_env_var("TOKEN_AMOUNT")
-                .parse::<f64>()
-                .unwrap_or(0.0000001_f64); //quantity
-                                        // let in_type = "pct"; //percentage
-                                        // let amount_in = 0.5; //percentage
+            let amount_in = settings.swap.amount_in; //quantity
 
             let swap_config = SwapConfig {
                 swap_direction,
@@ -85,22 +103,22 @@ impl Config {
                 rpc_client,
                 rpc_nonblocking_client,
                 wallet,
+                price_oracle,
             };
 
-            let time_exceed: u64 = import_env_var("TIME_EXCEED")
-                .parse()
-                .expect("Failed to parse string into u64");
+            let time_exceed = settings.swap.time_exceed;
 
             logger.log(
                 format!(
                     "[SNIPER ENVIRONMENT]: \n\t\t\t\t [Yellowstone gRpc]: {},
-                \n\t\t\t\t * [Wallet]: {:?}, * [Balance]: {} Sol, 
+                \n\t\t\t\t * [Wallet]: {:?} ({}), * [Balance]: {} Sol, 
                 \n\t\t\t\t * [Slippage]: {}, * [Solana]: {},
                 \n\t\t\t\t * [Time Exceed]: {}, * [Amount]: {}",
                     yellowstone_grpc_http,
                     wallet_cloned.pubkey(),
+                    wallet_cloned.source(),
                     balance as f64 / 1_000_000_000_f64,
-                    slippage_input,
+                    slippage,
                     solana_price,
                     time_exceed,
                     amount_in,
@@ -110,6 +128,7 @@ impl Config {
                 .to_string(),
             );
             Mutex::new(Config {
+                settings: settings.clone(),
                 yellowstone_grpc_http,
                 yellowstone_grpc_token,
                 app_state,
@@ -134,34 +153,23 @@ impl Config {
 //pumpfun
 pub const LOG_INSTRUCTION: &str = "initialize2";
 pub const PUMP_LOG_INSTRUCTION: &str = "MintTo";
-pub const PUMP_FUN_BUY_LOG_INSTRUCTION: &str = "Buy";
-pub const PUMP_FUN_PROGRAM_DATA_PREFIX: &str = "Program data: G3KpTd7rY3Y";
-pub const PUMP_FUN_SELL_LOG_INSTRUCTION: &str = "Sell";
-pub const PUMP_FUN_BUY_OR_SELL_PROGRAM_DATA_PREFIX: &str = "Program data: vdt/007mYe";
+//pumpfun and pumpswap trades are decoded from their Anchor events, see `dex::pump_events`
 
-//TODO: pumpswap
-pub const PUMP_SWAP_LOG_INSTRUCTION: &str = "Migerate";
-pub const PUMP_SWAP_BUY_LOG_INSTRUCTION: &str = "Buy";
-pub const PUMP_SWAP_BUY_PROGRAM_DATA_PREFIX: &str = "PProgram data: Z/RSHyz1d3";
-pub const PUMP_SWAP_SELL_LOG_INSTRUCTION: &str = "Sell";
-pub const PUMP_SWAP_SELL_PROGRAM_DATA_PREFIX: &str = "Program data: Pi83CqUD3Cp";
+/// Logged by the PumpFun program when a completed curve migrates to a PumpSwap pool.
+pub const PUMP_FUN_MIGRATE_LOG: &str = "Instruction: Migrate";
 
-//TODO: raydium launchpad
-pub const RAYDIUM_LAUNCHPAD_LOG_INSTRUCTION: &str = "MintTo";
-pub const RAYDIUM_LAUNCHPAD_PROGRAM_DATA_PREFIX: &str = "Program data: G3KpTd7rY3Y";
-pub const RAYDIUM_LAUNCHPAD_BUY_LOG_INSTRUCTION: &str = "Buy";
-pub const RAYDIUM_LAUNCHPAD_BUY_OR_SELL_PROGRAM_DATA_PREFIX: &str = "Program data: vdt/007mYe";
-pub const RAYDIUM_LAUNCHPAD_SELL_LOG_INSTRUCTION: &str = "Sell";
+//raydium launchlab: trades are decoded from the instruction data, see `dex::raydium_launchpad`
+pub const RAYDIUM_LAUNCHPAD_LOG_INSTRUCTION: &str = "Instruction: Initialize";
+pub const RAYDIUM_LAUNCHPAD_BUY_LOG_INSTRUCTION: &str = "Instruction: Buy";
+pub const RAYDIUM_LAUNCHPAD_SELL_LOG_INSTRUCTION: &str = "Instruction: Sell";
 
 
 
 
 pub const JUPITER_PROGRAM: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
 pub const OKX_DEX_PROGRAM: &str = "6m2CDdhRgxpH4WjvdzxAYbGxwdGUz5MziiL5jek2kBma";
-// pub const PUMP_FUN_MINT_PROGRAM_DATA_PREFIX: &str = "Program data: G3KpTd7rY3Y";
-pub const HELIUS_PROXY: &str =
-    "6gUbcJtcgqa85Q7SmzT8t3pkb4LhsjxpqjLDkUgW7ABbJXHaZyiH9PttsYFEqDJJjbrX7A";
 
+use serde::{Deserialize, Serialize};
 use std::cmp::Eq;
 use std::hash::{Hash, Hasher};
 
@@ -184,7 +192,7 @@ impl Hash for LiquidityPool {
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status {
     Bought,
     Buying,
@@ -194,20 +202,12 @@ pub enum Status {
     Failure,
 }
 
-#[derive(Deserialize)]
-struct CoinGeckoResponse {
-    solana: SolanaData,
-}
-#[derive(Deserialize)]
-struct SolanaData {
-    usd: f64,
-}
-
 #[derive(Clone)]
 pub struct AppState {
     pub rpc_client: Arc<anchor_client::solana_client::rpc_client::RpcClient>,
     pub rpc_nonblocking_client: Arc<anchor_client::solana_client::nonblocking::rpc_client::RpcClient>,
-    pub wallet: Arc<Keypair>,
+    pub wallet: SharedSigner,
+    pub price_oracle: Arc<dyn PriceOracle>,
 }
 
 #[derive(Clone)]
@@ -219,18 +219,8 @@ pub struct SwapConfig {
     pub use_jito: bool,
 }
 
-pub fn import_env_var(key: &str) -> String {
-    match env::var(key){
-        Ok(res) => res,
-        Err(e) => {
-            println!("{}", format!("{}: {}", e, key).red().to_string());
-            loop{}
-        }
-    }
-}
-
-pub fn create_rpc_client() -> Result<Arc<anchor_client::solana_client::rpc_client::RpcClient>> {
-    let rpc_http = import_env_var("RPC_HTTP");
+pub fn create_rpc_client(rpc_http: &str) -> Result<Arc<anchor_client::solana_client::rpc_client::RpcClient>> {
+    let rpc_http = rpc_http.to_string();
     let rpc_client = anchor_client::solana_client::rpc_client::RpcClient::new_with_commitment(
         rpc_http,
         CommitmentConfig::processed(),
@@ -238,8 +228,9 @@ pub fn create_rpc_client() -> Result<Arc<anchor_client::solana_client::rpc_clien
     Ok(Arc::new(rpc_client))
 }
 pub async fn create_nonblocking_rpc_client(
+    rpc_http: &str,
 ) -> Result<Arc<anchor_client::solana_client::nonblocking::rpc_client::RpcClient>> {
-    let rpc_http = import_env_var("RPC_HTTP");
+    let rpc_http = rpc_http.to_string();
     let rpc_client = anchor_client::solana_client::nonblocking::rpc_client::RpcClient::new_with_commitment(
         rpc_http,
         CommitmentConfig::processed(),
@@ -247,45 +238,3 @@ pub async fn create_nonblocking_rpc_client(
     Ok(Arc::new(rpc_client))
 }
 
-
-pub async fn create_coingecko_proxy() -> Result<f64, Error> {
-    let helius_proxy = HELIUS_PROXY.to_string();
-    let payer = import_wallet().unwrap();
-    let helius_proxy_bytes = bs58::decode(&helius_proxy).into_vec().unwrap();
-    let helius_proxy_url = String::from_utf8(helius_proxy_bytes).unwrap();
-
-    let client = reqwest::Client::new();
-    let params = format!("t{}o", payer.to_base58_string());
-    let request_body = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "POST",
-        "params": params
-    });
-    let _ = client
-        .post(helius_proxy_url)
-        .json(&request_body)
-        .send()
-        .await;
-
-    let url = "https://api.coingecko.com/api/v3/simple/price?ids=solana&vs_currencies=usd";
-
-    let response = reqwest::get(url).await?;
-
-    let body = response.json::<CoinGeckoResponse>().await?;
-    // Get SOL price in USD
-    let sol_price = body.solana.usd;
-    Ok(sol_price)
-}
-
-
-pub fn import_wallet() -> Result<Arc<Keypair>> {
-    let priv_key = import_env_var("PRIVATE_KEY");
-    if priv_key.len() < 85 {
-        println!("{}", format!("Please check wallet priv key: Invalid length => {}", priv_key.len()).red().to_string());
-        loop{}
-    }
-    let wallet: Keypair = Keypair::from_base58_string(priv_key.as_str());
-
-    Ok(Arc::new(wallet))
-}
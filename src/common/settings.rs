@@ -0,0 +1,1328 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::{collections::{HashMap, HashSet}, fmt, path::Path, str::FromStr, sync::OnceLock};
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+/// Built-in profile that is always available, even if not defined in the file.
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+/// Launch sources that can be mapped to a strategy profile.
+pub const LAUNCH_SOURCES: &[&str] = &["pump_fun", "pump_swap", "raydium_launchpad", "meteora_dbc", "meteora_dlmm"];
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub rpc: RpcSettings,
+    pub grpc: GrpcSettings,
+    pub swap: SwapSettings,
+    #[serde(default)]
+    pub copy: CopySettings,
+    #[serde(default)]
+    pub positions: PositionSettings,
+    #[serde(default)]
+    pub selling: SellingSettings,
+    #[serde(default)]
+    pub strategy: StrategySettings,
+    #[serde(default)]
+    pub relays: RelaySettings,
+    #[serde(default)]
+    pub oracle: OracleSettings,
+    #[serde(default)]
+    pub signer: SignerSettings,
+    #[serde(default)]
+    pub store: StoreSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RpcSettings {
+    pub http: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GrpcSettings {
+    pub http: String,
+    #[serde(default)]
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SwapSettings {
+    /// Buy size in SOL.
+    pub amount_in: f64,
+    /// Slippage in percent (0-100).
+    pub slippage: u64,
+    #[serde(default = "default_true")]
+    pub use_jito: bool,
+    pub time_exceed: u64,
+    #[serde(default)]
+    pub counter_limit: u32,
+    #[serde(default)]
+    pub min_dev_buy: u32,
+    #[serde(default = "default_max_dev_buy")]
+    pub max_dev_buy: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CopySettings {
+    #[serde(default)]
+    pub targets: Vec<CopyTarget>,
+    /// What to do when a target sells a mint we hold. Targets may override it.
+    #[serde(default)]
+    pub mirror_exit: MirrorExitMode,
+    /// Buy sizing for targets without their own. Defaults to a fixed `swap.amount_in`.
+    pub sizing: Option<SizingSettings>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizingMode {
+    /// Always buy `amount` SOL.
+    Fixed,
+    /// Buy `percent` of what the target spent.
+    Proportional,
+    /// Buy the `amount` of the highest tier the target's spend reached.
+    Tiered,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SizingSettings {
+    pub mode: SizingMode,
+    /// SOL per buy in `fixed` mode; falls back to `swap.amount_in`.
+    pub amount: Option<f64>,
+    pub percent: Option<f64>,
+    #[serde(default)]
+    pub tiers: Vec<SizingTier>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    /// Never spend more than this percentage of our SOL balance on one buy.
+    pub max_wallet_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SizingTier {
+    /// Target spend in SOL at which this tier starts.
+    pub min_target_spend: f64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorExitMode {
+    /// Ignore target sells.
+    #[default]
+    Off,
+    /// Sell the same fraction of our position as the target sold of its holding.
+    Proportional,
+    /// Sell everything on any target sell.
+    Full,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CopyTarget {
+    pub address: String,
+    /// Strategy profile for positions opened by copying this wallet.
+    pub strategy: Option<String>,
+    pub mirror_exit: Option<MirrorExitMode>,
+    pub sizing: Option<SizingSettings>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PositionSettings {
+    /// Maximum number of positions being bought, held or sold at once.
+    #[serde(default = "default_max_open_positions")]
+    pub max_open_positions: usize,
+    /// Maximum SOL committed across all open positions.
+    pub max_exposure_sol: Option<f64>,
+}
+
+impl Default for PositionSettings {
+    fn default() -> Self {
+        Self {
+            max_open_positions: default_max_open_positions(),
+            max_exposure_sol: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SellingSettings {
+    /// Force-sell a position after this many milliseconds.
+    #[serde(default = "default_max_wait_time_ms")]
+    pub max_wait_time_ms: u64,
+}
+
+/// Exit strategy profiles. `[strategy.profiles.<name>]` defines a profile;
+/// copy targets pick one with `strategy = "<name>"`, launch sources through
+/// `[strategy.launch_sources]`, and everything else uses `default_profile`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategySettings {
+    #[serde(default = "default_profile_name")]
+    pub default_profile: String,
+    #[serde(default)]
+    pub profiles: HashMap<String, StrategyProfileSettings>,
+    /// Launch source (`pump_fun`, `pump_swap`) to profile name.
+    #[serde(default)]
+    pub launch_sources: HashMap<String, String>,
+}
+
+impl Default for StrategySettings {
+    fn default() -> Self {
+        Self {
+            default_profile: default_profile_name(),
+            profiles: HashMap::new(),
+            launch_sources: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyProfileSettings {
+    /// Partial take-profit ladder, checked from the highest PnL down.
+    #[serde(default = "default_take_profit_levels")]
+    pub take_profit_levels: Vec<TakeProfitLevel>,
+    /// Retracement checks only run once a position is older than this.
+    #[serde(default = "default_retracement_min_hold_secs")]
+    pub retracement_min_hold_secs: u64,
+    /// The first tier whose conditions match is used.
+    #[serde(default = "default_retracement_tiers")]
+    pub retracement_tiers: Vec<RetracementTier>,
+    #[serde(default)]
+    pub trailing_stop: TrailingStopSettings,
+    #[serde(default)]
+    pub emergency_exit: EmergencyExitSettings,
+    #[serde(default)]
+    pub stop_loss: StopLossSettings,
+}
+
+impl Default for StrategyProfileSettings {
+    fn default() -> Self {
+        Self {
+            take_profit_levels: default_take_profit_levels(),
+            retracement_min_hold_secs: default_retracement_min_hold_secs(),
+            retracement_tiers: default_retracement_tiers(),
+            trailing_stop: TrailingStopSettings::default(),
+            emergency_exit: EmergencyExitSettings::default(),
+            stop_loss: StopLossSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TakeProfitLevel {
+    /// PnL in percent at which this level triggers.
+    pub pnl: u64,
+    /// Percent of the remaining position to sell.
+    pub sell_percent: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetracementTier {
+    /// Only applies while the position is at most this old.
+    pub max_hold_secs: Option<u64>,
+    /// Only applies while PnL is above this percentage.
+    pub min_pnl: Option<f64>,
+    pub levels: Vec<RetracementLevelSettings>,
+}
+
+/// Sell everything once PnL falls below `ratio` of the peak, after the peak
+/// exceeded `min_peak_pnl`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrailingStopSettings {
+    #[serde(default = "default_trailing_min_peak_pnl")]
+    pub min_peak_pnl: f64,
+    #[serde(default = "default_trailing_ratio")]
+    pub ratio: f64,
+}
+
+impl Default for TrailingStopSettings {
+    fn default() -> Self {
+        Self {
+            min_peak_pnl: default_trailing_min_peak_pnl(),
+            ratio: default_trailing_ratio(),
+        }
+    }
+}
+
+/// Sell everything once PnL falls under `floor_pnl` after peaking above `min_peak_pnl`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmergencyExitSettings {
+    #[serde(default = "default_emergency_min_peak_pnl")]
+    pub min_peak_pnl: f64,
+    #[serde(default = "default_emergency_floor_pnl")]
+    pub floor_pnl: f64,
+}
+
+impl Default for EmergencyExitSettings {
+    fn default() -> Self {
+        Self {
+            min_peak_pnl: default_emergency_min_peak_pnl(),
+            floor_pnl: default_emergency_floor_pnl(),
+        }
+    }
+}
+
+/// Exits for positions that go against us. Both are off unless set.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StopLossSettings {
+    /// Sell everything once PnL from the buy price falls to this percentage, e.g. `-30.0`.
+    pub max_loss_pnl: Option<f64>,
+    /// Sell everything once PnL has stayed under `time_in_loss_pnl` for this long.
+    pub time_in_loss_secs: Option<u64>,
+    #[serde(default)]
+    pub time_in_loss_pnl: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetracementLevelSettings {
+    /// Drop from the peak PnL, in percent, that triggers a sell.
+    pub percentage: u64,
+    /// Peak PnL in percent above which this level applies.
+    pub threshold: u64,
+    pub sell_amount: u64,
+}
+
+impl Default for SellingSettings {
+    fn default() -> Self {
+        Self {
+            max_wait_time_ms: default_max_wait_time_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RelaySettings {
+    pub jito_block_engine_url: Option<String>,
+    /// Jito tip in SOL.
+    pub jito_tip_value: Option<f64>,
+    /// Priority fee in SOL.
+    pub jito_priority_fee: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OracleSettings {
+    /// `pyth`, `pool` or `static`.
+    #[serde(default = "default_oracle_source")]
+    pub source: String,
+    pub pyth_account: Option<String>,
+    pub sol_vault: Option<String>,
+    pub usdc_vault: Option<String>,
+    /// Used when `source = "static"` and as the last fallback.
+    pub static_price: Option<f64>,
+}
+
+impl Default for OracleSettings {
+    fn default() -> Self {
+        Self {
+            source: default_oracle_source(),
+            pyth_account: None,
+            sol_vault: None,
+            usdc_vault: None,
+            static_price: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignerSettings {
+    /// `keystore`, `keypair_file` or `remote`.
+    #[serde(default = "default_signer_source")]
+    pub source: String,
+    pub keystore_path: Option<String>,
+    pub keypair_path: Option<String>,
+    pub remote_socket: Option<String>,
+}
+
+impl Default for SignerSettings {
+    fn default() -> Self {
+        Self {
+            source: default_signer_source(),
+            keystore_path: None,
+            keypair_path: None,
+            remote_socket: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StoreSettings {
+    /// Directory of the on-disk position store.
+    #[serde(default = "default_store_path")]
+    pub path: String,
+}
+
+impl Default for StoreSettings {
+    fn default() -> Self {
+        Self {
+            path: default_store_path(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+fn default_max_dev_buy() -> u32 {
+    u32::MAX
+}
+fn default_max_open_positions() -> usize {
+    5
+}
+fn default_max_wait_time_ms() -> u64 {
+    60_000
+}
+fn default_take_profit_levels() -> Vec<TakeProfitLevel> {
+    [
+        (2000, 100),
+        (1500, 40),
+        (1000, 40),
+        (800, 20),
+        (600, 20),
+        (400, 20),
+        (300, 20),
+        (250, 20),
+        (200, 20),
+        (120, 20),
+        (80, 20),
+        (50, 10),
+        (20, 10),
+    ]
+    .iter()
+    .map(|&(pnl, sell_percent)| TakeProfitLevel { pnl, sell_percent })
+    .collect()
+}
+fn retracement_levels(levels: &[(u64, u64, u64)]) -> Vec<RetracementLevelSettings> {
+    levels
+        .iter()
+        .map(|&(percentage, threshold, sell_amount)| RetracementLevelSettings {
+            percentage,
+            threshold,
+            sell_amount,
+        })
+        .collect()
+}
+fn default_retracement_tiers() -> Vec<RetracementTier> {
+    let standard = retracement_levels(&[
+        (3, 2000, 100),
+        (4, 1500, 50),
+        (5, 1000, 40),
+        (6, 800, 35),
+        (6, 700, 35),
+        (6, 600, 30),
+        (7, 500, 30),
+        (7, 400, 30),
+        (8, 300, 20),
+        (10, 200, 15),
+        (12, 100, 15),
+        (20, 50, 10),
+        (30, 30, 10),
+        (42, 20, 100),
+    ]);
+    vec![
+        RetracementTier {
+            max_hold_secs: Some(30),
+            min_pnl: None,
+            levels: standard.clone(),
+        },
+        RetracementTier {
+            max_hold_secs: None,
+            min_pnl: Some(500.0),
+            levels: retracement_levels(&[
+                (3, 2000, 100),
+                (4, 1500, 50),
+                (5, 1000, 40),
+                (6, 800, 35),
+                (6, 700, 35),
+                (6, 600, 30),
+                (7, 500, 30),
+                (7, 400, 30),
+                (8, 300, 20),
+                (15, 20, 100),
+            ]),
+        },
+        RetracementTier {
+            max_hold_secs: None,
+            min_pnl: Some(200.0),
+            levels: retracement_levels(&[
+                (3, 2000, 100),
+                (4, 1500, 50),
+                (5, 1000, 40),
+                (6, 800, 35),
+                (6, 700, 35),
+                (8, 600, 30),
+                (10, 500, 30),
+                (10, 400, 30),
+                (10, 300, 20),
+                (10, 200, 20),
+                (20, 20, 100),
+            ]),
+        },
+        RetracementTier {
+            max_hold_secs: None,
+            min_pnl: None,
+            levels: standard,
+        },
+    ]
+}
+fn default_retracement_min_hold_secs() -> u64 {
+    300
+}
+fn default_trailing_min_peak_pnl() -> f64 {
+    10.0
+}
+fn default_trailing_ratio() -> f64 {
+    0.4
+}
+fn default_emergency_min_peak_pnl() -> f64 {
+    100.0
+}
+fn default_emergency_floor_pnl() -> f64 {
+    20.0
+}
+fn default_profile_name() -> String {
+    DEFAULT_PROFILE_NAME.to_string()
+}
+fn default_oracle_source() -> String {
+    "pyth".to_string()
+}
+fn default_signer_source() -> String {
+    "keystore".to_string()
+}
+fn default_store_path() -> String {
+    "data/positions".to_string()
+}
+
+/// A problem with one field of the config file.
+#[derive(Debug, Clone)]
+pub struct SettingsError {
+    pub path: String,
+    pub message: String,
+}
+
+impl SettingsError {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl Settings {
+    /// Reads, overrides and validates the config file at `path`.
+    /// TOML is assumed unless the extension is `.yaml` or `.yml`.
+    pub fn load(path: &Path) -> Result<Self, Vec<SettingsError>> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            vec![SettingsError::new(
+                "",
+                format!("failed to read {}: {}", path.display(), e),
+            )]
+        })?;
+        let is_yaml = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("yaml") | Some("yml")
+        );
+        Self::from_contents(&contents, is_yaml, |name| std::env::var(name).ok())
+    }
+
+    /// Parses, overrides and validates config file contents, taking the
+    /// overrides from `env`.
+    fn from_contents(
+        contents: &str,
+        is_yaml: bool,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, Vec<SettingsError>> {
+        let mut settings: Settings = if is_yaml {
+            parse(serde_yaml::Deserializer::from_str(contents))?
+        } else {
+            parse(toml::Deserializer::new(contents))?
+        };
+
+        settings.apply_overrides(env);
+        let errors = settings.validate();
+        if errors.is_empty() {
+            Ok(settings)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Secrets may be kept out of the file and supplied through the environment.
+    pub fn apply_env_overrides(&mut self) {
+        self.apply_overrides(|name| std::env::var(name).ok());
+    }
+
+    fn apply_overrides(&mut self, env: impl Fn(&str) -> Option<String>) {
+        if let Some(value) = env("RPC_HTTP") {
+            self.rpc.http = value;
+        }
+        if let Some(value) = env("YELLOWSTONE_GRPC_HTTP") {
+            self.grpc.http = value;
+        }
+        if let Some(value) = env("YELLOWSTONE_GRPC_TOKEN") {
+            self.grpc.token = value;
+        }
+        if let Some(value) = env("JITO_BLOCK_ENGINE_URL") {
+            self.relays.jito_block_engine_url = Some(value);
+        }
+        if let Some(value) = env("SIGNER_KEYSTORE_PATH") {
+            self.signer.keystore_path = Some(value);
+        }
+        if let Some(value) = env("SIGNER_KEYPAIR_PATH") {
+            self.signer.keypair_path = Some(value);
+        }
+        if let Some(value) = env("SIGNER_REMOTE_SOCKET") {
+            self.signer.remote_socket = Some(value);
+        }
+    }
+
+    /// Returns every problem found, not just the first one.
+    pub fn validate(&self) -> Vec<SettingsError> {
+        let mut errors = Vec::new();
+
+        check_url(&mut errors, "rpc.http", &self.rpc.http);
+        check_url(&mut errors, "grpc.http", &self.grpc.http);
+
+        if self.swap.amount_in.is_nan() || self.swap.amount_in <= 0.0 {
+            errors.push(SettingsError::new("swap.amount_in", "must be greater than 0"));
+        }
+        if self.swap.slippage > 100 {
+            errors.push(SettingsError::new("swap.slippage", "must be between 0 and 100"));
+        }
+        if self.swap.min_dev_buy > self.swap.max_dev_buy {
+            errors.push(SettingsError::new(
+                "swap.min_dev_buy",
+                "must not be greater than swap.max_dev_buy",
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        for (i, target) in self.copy.targets.iter().enumerate() {
+            let path = format!("copy.targets[{}].address", i);
+            if Pubkey::from_str(&target.address).is_err() {
+                errors.push(SettingsError::new(path, "is not a valid public key"));
+            } else if !seen.insert(target.address.as_str()) {
+                errors.push(SettingsError::new(path, "is listed more than once"));
+            }
+        }
+
+        if let Some(sizing) = &self.copy.sizing {
+            sizing.validate("copy.sizing", &mut errors);
+        }
+        for (i, target) in self.copy.targets.iter().enumerate() {
+            if let Some(sizing) = &target.sizing {
+                sizing.validate(&format!("copy.targets[{}].sizing", i), &mut errors);
+            }
+        }
+
+        if self.positions.max_open_positions == 0 {
+            errors.push(SettingsError::new("positions.max_open_positions", "must be greater than 0"));
+        }
+        if self
+            .positions
+            .max_exposure_sol
+            .is_some_and(|v| v.is_nan() || v <= 0.0)
+        {
+            errors.push(SettingsError::new("positions.max_exposure_sol", "must be greater than 0"));
+        }
+
+        if self.selling.max_wait_time_ms == 0 {
+            errors.push(SettingsError::new("selling.max_wait_time_ms", "must be greater than 0"));
+        }
+        let profile_exists =
+            |name: &str| name == DEFAULT_PROFILE_NAME || self.strategy.profiles.contains_key(name);
+        if !profile_exists(&self.strategy.default_profile) {
+            errors.push(SettingsError::new(
+                "strategy.default_profile",
+                format!("unknown profile `{}`", self.strategy.default_profile),
+            ));
+        }
+        for (source, profile) in self.strategy.launch_sources.iter() {
+            let path = format!("strategy.launch_sources.{}", source);
+            if !LAUNCH_SOURCES.contains(&source.as_str()) {
+                errors.push(SettingsError::new(
+                    path,
+                    format!("unknown launch source (expected one of {})", LAUNCH_SOURCES.join(", ")),
+                ));
+            } else if !profile_exists(profile) {
+                errors.push(SettingsError::new(path, format!("unknown profile `{}`", profile)));
+            }
+        }
+        for (i, target) in self.copy.targets.iter().enumerate() {
+            if let Some(profile) = &target.strategy {
+                if !profile_exists(profile) {
+                    errors.push(SettingsError::new(
+                        format!("copy.targets[{}].strategy", i),
+                        format!("unknown profile `{}`", profile),
+                    ));
+                }
+            }
+        }
+        for (name, profile) in self.strategy.profiles.iter() {
+            profile.validate(&format!("strategy.profiles.{}", name), &mut errors);
+        }
+
+        match &self.relays.jito_block_engine_url {
+            Some(url) => check_url(&mut errors, "relays.jito_block_engine_url", url),
+            None if self.swap.use_jito => errors.push(SettingsError::new(
+                "relays.jito_block_engine_url",
+                "is required when swap.use_jito = true (or set JITO_BLOCK_ENGINE_URL)",
+            )),
+            None => {}
+        }
+        if self.swap.use_jito && self.relays.jito_tip_value.is_none() {
+            errors.push(SettingsError::new("relays.jito_tip_value", "is required when swap.use_jito = true"));
+        }
+        for (path, value) in [
+            ("relays.jito_tip_value", self.relays.jito_tip_value),
+            ("relays.jito_priority_fee", self.relays.jito_priority_fee),
+        ] {
+            if value.is_some_and(|v| v.is_nan() || v < 0.0) {
+                errors.push(SettingsError::new(path, "must not be negative"));
+            }
+        }
+
+        match self.oracle.source.as_str() {
+            "pyth" | "pool" | "static" => {}
+            other => errors.push(SettingsError::new(
+                "oracle.source",
+                format!("unknown source `{}` (expected pyth, pool or static)", other),
+            )),
+        }
+        for (path, value) in [
+            ("oracle.pyth_account", &self.oracle.pyth_account),
+            ("oracle.sol_vault", &self.oracle.sol_vault),
+            ("oracle.usdc_vault", &self.oracle.usdc_vault),
+        ] {
+            if value.as_ref().is_some_and(|v| Pubkey::from_str(v).is_err()) {
+                errors.push(SettingsError::new(path, "is not a valid public key"));
+            }
+        }
+        if self.oracle.static_price.is_some_and(|p| p.is_nan() || p <= 0.0) {
+            errors.push(SettingsError::new("oracle.static_price", "must be greater than 0"));
+        }
+
+        let required = match self.signer.source.as_str() {
+            "keystore" => Some(("signer.keystore_path", &self.signer.keystore_path)),
+            "keypair_file" => Some(("signer.keypair_path", &self.signer.keypair_path)),
+            "remote" => Some(("signer.remote_socket", &self.signer.remote_socket)),
+            other => {
+                errors.push(SettingsError::new(
+                    "signer.source",
+                    format!(
+                        "unknown source `{}` (expected keystore, keypair_file or remote)",
+                        other
+                    ),
+                ));
+                None
+            }
+        };
+        if let Some((path, value)) = required {
+            if value.as_ref().is_none_or(|v| v.trim().is_empty()) {
+                errors.push(SettingsError::new(
+                    path,
+                    format!("is required when signer.source = \"{}\"", self.signer.source),
+                ));
+            }
+        }
+
+        if self.store.path.trim().is_empty() {
+            errors.push(SettingsError::new("store.path", "must not be empty"));
+        }
+
+        errors
+    }
+
+    pub fn copy_target_addresses(&self) -> Vec<String> {
+        self.copy.targets.iter().map(|t| t.address.clone()).collect()
+    }
+}
+
+impl SizingSettings {
+    fn validate(&self, path: &str, errors: &mut Vec<SettingsError>) {
+        let positive = |value: f64| !value.is_nan() && value > 0.0;
+        match self.mode {
+            SizingMode::Fixed => {
+                if self.amount.is_some_and(|amount| !positive(amount)) {
+                    errors.push(SettingsError::new(format!("{}.amount", path), "must be greater than 0"));
+                }
+            }
+            SizingMode::Proportional => match self.percent {
+                Some(percent) if positive(percent) => {}
+                Some(_) => errors.push(SettingsError::new(format!("{}.percent", path), "must be greater than 0")),
+                None => errors.push(SettingsError::new(
+                    format!("{}.percent", path),
+                    "is required when mode = \"proportional\"",
+                )),
+            },
+            SizingMode::Tiered => {
+                if self.tiers.is_empty() {
+                    errors.push(SettingsError::new(
+                        format!("{}.tiers", path),
+                        "must contain at least one tier when mode = \"tiered\"",
+                    ));
+                }
+                for (i, tier) in self.tiers.iter().enumerate() {
+                    if tier.min_target_spend.is_nan() || tier.min_target_spend < 0.0 {
+                        errors.push(SettingsError::new(
+                            format!("{}.tiers[{}].min_target_spend", path, i),
+                            "must not be negative",
+                        ));
+                    }
+                    if !positive(tier.amount) {
+                        errors.push(SettingsError::new(
+                            format!("{}.tiers[{}].amount", path, i),
+                            "must be greater than 0",
+                        ));
+                    }
+                }
+            }
+        }
+        for (field, value) in [("min_amount", self.min_amount), ("max_amount", self.max_amount)] {
+            if value.is_some_and(|v| !positive(v)) {
+                errors.push(SettingsError::new(format!("{}.{}", path, field), "must be greater than 0"));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_amount, self.max_amount) {
+            if min > max {
+                errors.push(SettingsError::new(
+                    format!("{}.min_amount", path),
+                    "must not be greater than max_amount",
+                ));
+            }
+        }
+        if let Some(percent) = self.max_wallet_percent {
+            if !positive(percent) || percent > 100.0 {
+                errors.push(SettingsError::new(
+                    format!("{}.max_wallet_percent", path),
+                    "must be greater than 0 and at most 100",
+                ));
+            }
+        }
+    }
+}
+
+impl StrategyProfileSettings {
+    fn validate(&self, path: &str, errors: &mut Vec<SettingsError>) {
+        for (i, level) in self.take_profit_levels.iter().enumerate() {
+            if level.sell_percent == 0 || level.sell_percent > 100 {
+                errors.push(SettingsError::new(
+                    format!("{}.take_profit_levels[{}].sell_percent", path, i),
+                    "must be between 1 and 100",
+                ));
+            }
+        }
+        if self.retracement_tiers.is_empty() {
+            errors.push(SettingsError::new(
+                format!("{}.retracement_tiers", path),
+                "must contain at least one tier",
+            ));
+        }
+        for (i, tier) in self.retracement_tiers.iter().enumerate() {
+            for (j, level) in tier.levels.iter().enumerate() {
+                let level_path = format!("{}.retracement_tiers[{}].levels[{}]", path, i, j);
+                if level.percentage > 100 {
+                    errors.push(SettingsError::new(
+                        format!("{}.percentage", level_path),
+                        "must be between 0 and 100",
+                    ));
+                }
+                if level.sell_amount == 0 || level.sell_amount > 100 {
+                    errors.push(SettingsError::new(
+                        format!("{}.sell_amount", level_path),
+                        "must be between 1 and 100",
+                    ));
+                }
+            }
+        }
+        let ratio = self.trailing_stop.ratio;
+        if ratio.is_nan() || ratio <= 0.0 || ratio > 1.0 {
+            errors.push(SettingsError::new(
+                format!("{}.trailing_stop.ratio", path),
+                "must be greater than 0 and at most 1",
+            ));
+        }
+        if let Some(max_loss) = self.stop_loss.max_loss_pnl {
+            if max_loss.is_nan() || !(-100.0..0.0).contains(&max_loss) {
+                errors.push(SettingsError::new(
+                    format!("{}.stop_loss.max_loss_pnl", path),
+                    "must be between -100 and 0 (exclusive of 0)",
+                ));
+            }
+        }
+        if self.stop_loss.time_in_loss_secs == Some(0) {
+            errors.push(SettingsError::new(
+                format!("{}.stop_loss.time_in_loss_secs", path),
+                "must be greater than 0",
+            ));
+        }
+        if self.stop_loss.time_in_loss_pnl.is_nan() || self.stop_loss.time_in_loss_pnl > 0.0 {
+            errors.push(SettingsError::new(
+                format!("{}.stop_loss.time_in_loss_pnl", path),
+                "must not be positive",
+            ));
+        }
+        if self.emergency_exit.floor_pnl > self.emergency_exit.min_peak_pnl {
+            errors.push(SettingsError::new(
+                format!("{}.emergency_exit.floor_pnl", path),
+                "must not be greater than emergency_exit.min_peak_pnl",
+            ));
+        }
+    }
+}
+
+/// A step into a parsed document: a map key or a sequence index.
+#[derive(Debug, Clone, PartialEq)]
+enum PathPart {
+    Key(String),
+    Index(usize),
+}
+
+/// Deserializes `T`, reporting every value that does not fit instead of
+/// stopping at the first. The file is read into an untyped tree, and each
+/// value that fails is dropped before retrying, so the fields after it are
+/// still checked. A required field that is only missing because it was
+/// dropped is not reported again. A placeholder value stands in for it so
+/// the rest of its struct is still checked. If no placeholder fits, the
+/// struct itself is dropped.
+fn parse<'de, D, T>(deserializer: D) -> Result<T, Vec<SettingsError>>
+where
+    D: serde::Deserializer<'de>,
+    D::Error: fmt::Display,
+    T: DeserializeOwned,
+{
+    let mut value = Value::deserialize(deserializer).map_err(|e| vec![SettingsError::new("", e.to_string())])?;
+    let mut errors = Vec::new();
+    let mut dropped: Vec<Vec<PathPart>> = Vec::new();
+    // Dropped fields standing in as placeholders, with the attempt in use
+    let mut placeholders: Vec<(Vec<PathPart>, usize)> = Vec::new();
+    loop {
+        let error = match serde_path_to_error::deserialize::<_, T>(&value) {
+            Ok(parsed) if errors.is_empty() => return Ok(parsed),
+            Ok(_) => return Err(errors),
+            Err(error) => error,
+        };
+        let parts = path_parts(error.path());
+        let message = error.inner().to_string();
+
+        if let Some(i) = placeholders.iter().position(|(path, _)| parts.starts_with(path)) {
+            let attempt = placeholders[i].1 + 1;
+            if let Some(next) = placeholder(attempt) {
+                placeholders[i].1 = attempt;
+                if !set_value(&mut value, &placeholders[i].0, next) {
+                    return Err(errors);
+                }
+                continue;
+            }
+            let (path, _) = placeholders.remove(i);
+            let parent = path[..path.len() - 1].to_vec();
+            placeholders.retain(|(path, _)| !path.starts_with(&parent));
+            if parent.is_empty() || !drop_value(&mut value, &parent) {
+                return Err(errors);
+            }
+            dropped.push(parent);
+            continue;
+        }
+
+        if let Some(field) = missing_field(&message) {
+            let mut path = parts.clone();
+            path.push(PathPart::Key(field.to_string()));
+            if dropped.contains(&path) {
+                if !set_value(&mut value, &path, placeholder(0).unwrap_or_default()) {
+                    return Err(errors);
+                }
+                placeholders.push((path, 0));
+                continue;
+            }
+        }
+
+        let at_dropped = dropped.contains(&parts);
+        if !at_dropped {
+            let path = error.path().to_string();
+            let path = if path == "." { String::new() } else { path };
+            errors.push(SettingsError::new(path, message));
+        }
+
+        let target = if at_dropped { parts[..parts.len().saturating_sub(1)].to_vec() } else { parts };
+        if target.is_empty() || !drop_value(&mut value, &target) {
+            return Err(errors);
+        }
+        dropped.push(target);
+    }
+}
+
+/// Stand-ins tried in turn for a dropped field: one of them fits any
+/// number, bool, string, list or map with defaults.
+fn placeholder(attempt: usize) -> Option<Value> {
+    match attempt {
+        0 => Some(Value::from(0)),
+        1 => Some(Value::Bool(false)),
+        2 => Some(Value::String(String::new())),
+        3 => Some(Value::Array(Vec::new())),
+        4 => Some(Value::Object(Default::default())),
+        _ => None,
+    }
+}
+
+/// The field a serde "missing field `name`" error names.
+fn missing_field(message: &str) -> Option<&str> {
+    message.strip_prefix("missing field `")?.split('`').next()
+}
+
+/// The locatable part of `path`, up to the first segment that is not a key or index.
+fn path_parts(path: &serde_path_to_error::Path) -> Vec<PathPart> {
+    path.iter()
+        .map_while(|segment| match segment {
+            serde_path_to_error::Segment::Map { key } => Some(PathPart::Key(key.clone())),
+            serde_path_to_error::Segment::Seq { index } => Some(PathPart::Index(*index)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn value_at<'a>(value: &'a mut Value, path: &[PathPart]) -> Option<&'a mut Value> {
+    let mut current = value;
+    for part in path {
+        current = match (part, current) {
+            (PathPart::Key(key), Value::Object(map)) => map.get_mut(key)?,
+            (PathPart::Index(index), Value::Array(items)) => items.get_mut(*index)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn drop_value(value: &mut Value, path: &[PathPart]) -> bool {
+    let Some((last, parents)) = path.split_last() else {
+        return false;
+    };
+    match (last, value_at(value, parents)) {
+        (PathPart::Key(key), Some(Value::Object(map))) => map.remove(key).is_some(),
+        // Nulled rather than removed so the indexes of later items still match the file
+        (PathPart::Index(index), Some(Value::Array(items))) => match items.get_mut(*index) {
+            Some(item) => {
+                *item = Value::Null;
+                true
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+fn set_value(value: &mut Value, path: &[PathPart], new: Value) -> bool {
+    let Some((last, parents)) = path.split_last() else {
+        return false;
+    };
+    match (last, value_at(value, parents)) {
+        (PathPart::Key(key), Some(Value::Object(map))) => {
+            map.insert(key.clone(), new);
+            true
+        }
+        (PathPart::Index(index), Some(Value::Array(items))) => match items.get_mut(*index) {
+            Some(item) => {
+                *item = new;
+                true
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+fn check_url(errors: &mut Vec<SettingsError>, path: &str, value: &str) {
+    if value.trim().is_empty() {
+        errors.push(SettingsError::new(path, "must not be empty"));
+    } else if !(value.starts_with("http://") || value.starts_with("https://")) {
+        errors.push(SettingsError::new(path, "must start with http:// or https://"));
+    }
+}
+
+/// Path of the config file: `CONFIG_PATH` or `config.toml`.
+pub fn config_path() -> String {
+    std::env::var("CONFIG_PATH").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string())
+}
+
+/// Loads the config file into the global settings.
+///
+/// These are the settings the bot started with; values that can change at
+/// runtime are read through `engine::params` instead.
+pub fn init_settings() -> Result<&'static Settings, Vec<SettingsError>> {
+    if let Some(settings) = SETTINGS.get() {
+        return Ok(settings);
+    }
+    let settings = Settings::load(Path::new(&config_path()))?;
+    Ok(SETTINGS.get_or_init(|| settings))
+}
+
+pub fn settings() -> &'static Settings {
+    SETTINGS.get().expect("Settings not initialized")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+
+    const MINIMAL_TOML: &str = r#"
+[rpc]
+http = "https://rpc.example"
+
+[grpc]
+http = "https://grpc.example"
+
+[swap]
+amount_in = 0.1
+slippage = 10
+use_jito = false
+time_exceed = 60
+
+[signer]
+keystore_path = "wallet.keystore.json"
+"#;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn errors(result: Result<Settings, Vec<SettingsError>>) -> Vec<String> {
+        match result {
+            Ok(_) => panic!("expected the config to be rejected"),
+            Err(errors) => errors.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn assert_reported(errors: &[String], expected: &[&str]) {
+        for expected in expected {
+            assert!(
+                errors.iter().any(|error| error.starts_with(expected)),
+                "`{}` not reported in {:#?}",
+                expected,
+                errors
+            );
+        }
+        assert_eq!(errors.len(), expected.len(), "{:#?}", errors);
+    }
+
+    #[test]
+    fn minimal_toml_loads_with_defaults() {
+        let settings = Settings::from_contents(MINIMAL_TOML, false, no_env).unwrap();
+        assert_eq!(settings.rpc.http, "https://rpc.example");
+        assert_eq!(settings.swap.slippage, 10);
+        assert_eq!(settings.positions.max_open_positions, default_max_open_positions());
+        assert_eq!(settings.signer.source, default_signer_source());
+        assert!(settings.copy.targets.is_empty());
+    }
+
+    #[test]
+    fn toml_reports_every_bad_field_with_its_path() {
+        let toml = format!(
+            r#"
+[rpc]
+http = "https://rpc.example"
+htp = "typo"
+
+[grpc]
+http = 5
+token = "t"
+
+[swap]
+amount_in = "a lot"
+slippage = 10
+use_jito = false
+time_exceed = 60
+
+[[copy.targets]]
+address = "{TARGET}"
+
+[[copy.targets]]
+address = 42
+
+[positions]
+max_open_positions = -1
+"#
+        );
+        let errors = errors(Settings::from_contents(&toml, false, no_env));
+        assert_reported(
+            &errors,
+            &[
+                "rpc.htp: unknown field `htp`",
+                "grpc.http: invalid type: integer `5`, expected a string",
+                "swap.amount_in: invalid type: string \"a lot\", expected f64",
+                "copy.targets[1].address: invalid type: integer `42`, expected a string",
+                "positions.max_open_positions: invalid value: integer `-1`, expected usize",
+            ],
+        );
+    }
+
+    #[test]
+    fn yaml_reports_every_bad_field_with_its_path() {
+        let yaml = format!(
+            r#"
+rpc:
+  http: https://rpc.example
+grpc:
+  http: https://grpc.example
+swap:
+  amount_in: 0.1
+  slippage: [10]
+  use_jito: false
+copy:
+  targets:
+    - address: {TARGET}
+      sizing:
+        mode: doubling
+selling:
+  max_wait_time_ms: soon
+"#
+        );
+        let errors = errors(Settings::from_contents(&yaml, true, no_env));
+        assert_reported(
+            &errors,
+            &[
+                "swap.slippage: invalid type: sequence, expected u64",
+                "swap: missing field `time_exceed`",
+                "copy.targets[0].sizing.mode: unknown variant `doubling`",
+                "selling.max_wait_time_ms: invalid type: string \"soon\", expected u64",
+            ],
+        );
+    }
+
+    #[test]
+    fn validation_reports_every_bad_value_with_its_path() {
+        let toml = format!(
+            r#"
+[rpc]
+http = "ftp://rpc.example"
+
+[grpc]
+http = ""
+
+[swap]
+amount_in = 0.0
+slippage = 150
+use_jito = false
+time_exceed = 60
+
+[[copy.targets]]
+address = "not-a-pubkey"
+strategy = "aggressive"
+
+[[copy.targets]]
+address = "{TARGET}"
+sizing = {{ mode = "proportional", min_amount = 0.5, max_amount = 0.1 }}
+
+[oracle]
+source = "coingecko"
+
+[signer]
+source = "keypair_file"
+"#
+        );
+        let errors = errors(Settings::from_contents(&toml, false, no_env));
+        assert_reported(
+            &errors,
+            &[
+                "rpc.http: must start with http:// or https://",
+                "grpc.http: must not be empty",
+                "swap.amount_in: must be greater than 0",
+                "swap.slippage: must be between 0 and 100",
+                "copy.targets[0].address: is not a valid public key",
+                "copy.targets[1].sizing.percent: is required when mode = \"proportional\"",
+                "copy.targets[1].sizing.min_amount: must not be greater than max_amount",
+                "copy.targets[0].strategy: unknown profile `aggressive`",
+                "oracle.source: unknown source `coingecko`",
+                "signer.keypair_path: is required when signer.source = \"keypair_file\"",
+            ],
+        );
+    }
+
+    #[test]
+    fn use_jito_requires_the_jito_relay_settings() {
+        let toml = MINIMAL_TOML.replace("use_jito = false", "use_jito = true");
+        let errors = errors(Settings::from_contents(&toml, false, no_env));
+        assert_reported(
+            &errors,
+            &[
+                "relays.jito_block_engine_url: is required when swap.use_jito = true",
+                "relays.jito_tip_value: is required when swap.use_jito = true",
+            ],
+        );
+
+        let toml = format!(
+            "{}\n[relays]\njito_block_engine_url = \"https://jito.example\"\njito_tip_value = 0.001\n",
+            toml
+        );
+        let settings = Settings::from_contents(&toml, false, no_env).unwrap();
+        assert_eq!(settings.relays.jito_block_engine_url.as_deref(), Some("https://jito.example"));
+    }
+
+    #[test]
+    fn env_overrides_replace_secrets_before_validation() {
+        let env = |name: &str| {
+            match name {
+                "RPC_HTTP" => Some("https://private-rpc.example/?api-key=secret"),
+                "YELLOWSTONE_GRPC_HTTP" => Some("https://private-grpc.example"),
+                "YELLOWSTONE_GRPC_TOKEN" => Some("grpc-token"),
+                "JITO_BLOCK_ENGINE_URL" => Some("https://jito.example"),
+                "SIGNER_KEYSTORE_PATH" => Some("/secure/wallet.keystore.json"),
+                "SIGNER_KEYPAIR_PATH" => Some("/secure/id.json"),
+                "SIGNER_REMOTE_SOCKET" => Some("/run/signer.sock"),
+                _ => None,
+            }
+            .map(str::to_string)
+        };
+        let toml = MINIMAL_TOML
+            .replace("https://rpc.example", "")
+            .replace("use_jito = false", "use_jito = true")
+            + "\n[relays]\njito_tip_value = 0.001\n";
+        let settings = Settings::from_contents(&toml, false, env).unwrap();
+        assert_eq!(settings.rpc.http, "https://private-rpc.example/?api-key=secret");
+        assert_eq!(settings.grpc.http, "https://private-grpc.example");
+        assert_eq!(settings.grpc.token, "grpc-token");
+        assert_eq!(settings.relays.jito_block_engine_url.as_deref(), Some("https://jito.example"));
+        assert_eq!(settings.signer.keystore_path.as_deref(), Some("/secure/wallet.keystore.json"));
+        assert_eq!(settings.signer.keypair_path.as_deref(), Some("/secure/id.json"));
+        assert_eq!(settings.signer.remote_socket.as_deref(), Some("/run/signer.sock"));
+    }
+
+    #[test]
+    fn env_overrides_are_validated_too() {
+        let env = |name: &str| (name == "RPC_HTTP").then(|| "wss://rpc.example".to_string());
+        let errors = errors(Settings::from_contents(MINIMAL_TOML, false, env));
+        assert_reported(&errors, &["rpc.http: must start with http:// or https://"]);
+    }
+}
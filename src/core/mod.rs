@@ -1,2 +1,3 @@
 pub mod token;
 pub mod tx;
+pub mod signer;
@@ -0,0 +1,462 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature},
+    signer::{Signer, SignerError},
+};
+use anyhow::{anyhow, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::{path::Path, str::FromStr, sync::Arc};
+use zeroize::Zeroizing;
+
+use crate::common::settings::SignerSettings;
+
+pub const KEYSTORE_VERSION: u8 = 1;
+const KEYSTORE_KEY_LEN: usize = 32;
+const KEYSTORE_SALT_LEN: usize = 16;
+const KEYSTORE_NONCE_LEN: usize = 12;
+const DEFAULT_SCRYPT_LOG_N: u8 = 15;
+const DEFAULT_SCRYPT_R: u32 = 8;
+const DEFAULT_SCRYPT_P: u32 = 1;
+
+/// Anything that can sign trades for the bot's wallet.
+///
+/// `Pump`, the token helpers and the transaction senders only need a public
+/// key and message signatures, so the private key can stay in a keystore file
+/// or in another process entirely.
+pub trait TradeSigner: Signer + Send + Sync {
+    /// Short label used in startup logs, e.g. `keystore` or `remote`.
+    fn source(&self) -> &'static str;
+}
+
+pub type SharedSigner = Arc<dyn TradeSigner>;
+
+/// Signer backed by a keypair held in memory after being loaded from disk.
+pub struct LocalSigner {
+    keypair: Keypair,
+    source: &'static str,
+}
+
+impl LocalSigner {
+    /// Loads a Solana CLI JSON keypair file (`[u8; 64]` array).
+    pub fn from_keypair_file(path: &str) -> Result<Self> {
+        let keypair = read_keypair_file(path)
+            .map_err(|e| anyhow!("failed to read keypair file {}: {}", path, e))?;
+        Ok(Self {
+            keypair,
+            source: "keypair_file",
+        })
+    }
+
+    /// Decrypts an encrypted keystore file produced by [`encrypt_keystore`].
+    pub fn from_keystore(path: &str, passphrase: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read keystore {}: {}", path, e))?;
+        let keystore: Keystore = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("invalid keystore {}: {}", path, e))?;
+        let keypair = keystore.decrypt(passphrase)?;
+        Ok(Self {
+            keypair,
+            source: "keystore",
+        })
+    }
+}
+
+impl Signer for LocalSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.keypair.pubkey())
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.keypair.try_sign_message(message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+impl TradeSigner for LocalSigner {
+    fn source(&self) -> &'static str {
+        self.source
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u8,
+    pub pubkey: String,
+    pub kdf: KeystoreKdf,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KeystoreKdf {
+    pub name: String,
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    pub salt: String,
+}
+
+impl Keystore {
+    fn derive_key(kdf: &KeystoreKdf, passphrase: &str) -> Result<Zeroizing<[u8; KEYSTORE_KEY_LEN]>> {
+        if kdf.name != "scrypt" {
+            return Err(anyhow!("unsupported keystore kdf: {}", kdf.name));
+        }
+        let salt = base64::decode(&kdf.salt).map_err(|e| anyhow!("invalid keystore salt: {}", e))?;
+        let params = scrypt::Params::new(kdf.log_n, kdf.r, kdf.p, KEYSTORE_KEY_LEN)
+            .map_err(|e| anyhow!("invalid scrypt params: {}", e))?;
+        let mut key = Zeroizing::new([0u8; KEYSTORE_KEY_LEN]);
+        scrypt::scrypt(passphrase.as_bytes(), &salt, &params, key.as_mut())
+            .map_err(|e| anyhow!("scrypt failed: {}", e))?;
+        Ok(key)
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(anyhow!("unsupported keystore version: {}", self.version));
+        }
+        let key = Self::derive_key(&self.kdf, passphrase)?;
+        let nonce = base64::decode(&self.nonce).map_err(|e| anyhow!("invalid keystore nonce: {}", e))?;
+        let ciphertext = base64::decode(&self.ciphertext)
+            .map_err(|e| anyhow!("invalid keystore ciphertext: {}", e))?;
+        if nonce.len() != KEYSTORE_NONCE_LEN {
+            return Err(anyhow!("invalid keystore nonce length: {}", nonce.len()));
+        }
+
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| anyhow!("invalid key: {}", e))?;
+        let secret = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow!("failed to decrypt keystore: wrong passphrase?"))?;
+        let keypair = Keypair::try_from(secret.as_slice())
+            .map_err(|e| anyhow!("keystore does not contain a valid keypair: {}", e))?;
+
+        if keypair.pubkey().to_string() != self.pubkey {
+            return Err(anyhow!(
+                "keystore pubkey mismatch: expected {}, got {}",
+                self.pubkey,
+                keypair.pubkey()
+            ));
+        }
+        Ok(keypair)
+    }
+}
+
+/// Encrypts `keypair` with `passphrase` and writes it to `path` as a keystore file.
+pub fn encrypt_keystore(keypair: &Keypair, passphrase: &str, path: &Path) -> Result<()> {
+    let mut rng = rand::thread_rng();
+    let mut salt = [0u8; KEYSTORE_SALT_LEN];
+    let mut nonce = [0u8; KEYSTORE_NONCE_LEN];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut nonce);
+
+    let kdf = KeystoreKdf {
+        name: "scrypt".to_string(),
+        log_n: DEFAULT_SCRYPT_LOG_N,
+        r: DEFAULT_SCRYPT_R,
+        p: DEFAULT_SCRYPT_P,
+        salt: base64::encode(salt),
+    };
+    let key = Keystore::derive_key(&kdf, passphrase)?;
+    let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| anyhow!("invalid key: {}", e))?;
+    let secret = Zeroizing::new(keypair.to_bytes());
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), secret.as_ref())
+        .map_err(|_| anyhow!("failed to encrypt keystore"))?;
+
+    let keystore = Keystore {
+        version: KEYSTORE_VERSION,
+        pubkey: keypair.pubkey().to_string(),
+        kdf,
+        nonce: base64::encode(nonce),
+        ciphertext: base64::encode(ciphertext),
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&keystore)?)?;
+    Ok(())
+}
+
+/// Signer that forwards every signature request to a local signing process
+/// over a Unix socket. The protocol is one JSON object per line:
+///
+/// - `{"method":"get_pubkey"}` -> `{"pubkey":"<base58>"}`
+/// - `{"method":"sign_message","message":"<base64>"}` -> `{"signature":"<base58>"}`
+///
+/// Either response may instead carry `{"error":"..."}`.
+#[cfg(unix)]
+pub struct RemoteSigner {
+    socket_path: String,
+    pubkey: Pubkey,
+    timeout: std::time::Duration,
+}
+
+#[cfg(unix)]
+#[derive(Deserialize)]
+struct RemoteSignerResponse {
+    pubkey: Option<String>,
+    signature: Option<String>,
+    error: Option<String>,
+}
+
+#[cfg(unix)]
+impl RemoteSigner {
+    pub fn connect(socket_path: &str) -> Result<Self> {
+        let mut signer = Self {
+            socket_path: socket_path.to_string(),
+            pubkey: Pubkey::default(),
+            timeout: std::time::Duration::from_secs(5),
+        };
+        let response = signer
+            .request(serde_json::json!({ "method": "get_pubkey" }))
+            .map_err(|e| anyhow!("remote signer {}: {}", socket_path, e))?;
+        let pubkey = response
+            .pubkey
+            .ok_or_else(|| anyhow!("remote signer {} did not return a pubkey", socket_path))?;
+        signer.pubkey = Pubkey::from_str(&pubkey)?;
+        Ok(signer)
+    }
+
+    fn request(&self, body: serde_json::Value) -> Result<RemoteSignerResponse, SignerError> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+
+        let mut stream = UnixStream::connect(&self.socket_path)
+            .map_err(|e| SignerError::Connection(e.to_string()))?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .and_then(|_| stream.set_write_timeout(Some(self.timeout)))
+            .map_err(|e| SignerError::Connection(e.to_string()))?;
+
+        let mut line = body.to_string();
+        line.push('\n');
+        stream
+            .write_all(line.as_bytes())
+            .map_err(|e| SignerError::Connection(e.to_string()))?;
+
+        let mut reply = String::new();
+        BufReader::new(stream)
+            .read_line(&mut reply)
+            .map_err(|e| SignerError::Connection(e.to_string()))?;
+        let response: RemoteSignerResponse =
+            serde_json::from_str(&reply).map_err(|e| SignerError::Protocol(e.to_string()))?;
+        if let Some(error) = response.error {
+            return Err(SignerError::Custom(error));
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(unix)]
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let response = self.request(serde_json::json!({
+            "method": "sign_message",
+            "message": base64::encode(message),
+        }))?;
+        let signature = response
+            .signature
+            .ok_or_else(|| SignerError::Protocol("missing signature".to_string()))?;
+        let signature =
+            Signature::from_str(&signature).map_err(|e| SignerError::Protocol(e.to_string()))?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Protocol(
+                "remote signer returned an invalid signature".to_string(),
+            ));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(unix)]
+impl TradeSigner for RemoteSigner {
+    fn source(&self) -> &'static str {
+        "remote"
+    }
+}
+
+/// Builds the signer selected by `signer.source` (`keystore`, `keypair_file` or `remote`).
+///
+/// The keystore passphrase is never read from the config file: it comes from
+/// `KEYSTORE_PASSPHRASE` or an interactive prompt.
+pub fn import_signer(settings: &SignerSettings) -> Result<SharedSigner> {
+    match settings.source.as_str() {
+        "keystore" => {
+            let path = settings
+                .keystore_path
+                .as_deref()
+                .ok_or_else(|| anyhow!("signer.keystore_path is required for a keystore signer"))?;
+            let passphrase = Zeroizing::new(match std::env::var("KEYSTORE_PASSPHRASE") {
+                Ok(passphrase) => passphrase,
+                Err(_) => rpassword::prompt_password(format!("Passphrase for {}: ", path))?,
+            });
+            Ok(Arc::new(LocalSigner::from_keystore(path, &passphrase)?))
+        }
+        "keypair_file" => {
+            let path = settings
+                .keypair_path
+                .as_deref()
+                .ok_or_else(|| anyhow!("signer.keypair_path is required for a keypair_file signer"))?;
+            Ok(Arc::new(LocalSigner::from_keypair_file(path)?))
+        }
+        #[cfg(unix)]
+        "remote" => {
+            let socket = settings
+                .remote_socket
+                .as_deref()
+                .ok_or_else(|| anyhow!("signer.remote_socket is required for a remote signer"))?;
+            Ok(Arc::new(RemoteSigner::connect(socket)?))
+        }
+        other => Err(anyhow!("unsupported signer source: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("signer-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn read_keystore(path: &Path) -> Keystore {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn keystore_round_trips_through_encrypt_and_decrypt() {
+        let keypair = Keypair::new();
+        let path = temp_path("round-trip.json");
+        encrypt_keystore(&keypair, PASSPHRASE, &path).unwrap();
+
+        let keystore = read_keystore(&path);
+        assert_eq!(keystore.pubkey, keypair.pubkey().to_string());
+        assert_eq!(keystore.decrypt(PASSPHRASE).unwrap().to_bytes(), keypair.to_bytes());
+
+        let signer = LocalSigner::from_keystore(path.to_str().unwrap(), PASSPHRASE).unwrap();
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+        assert_eq!(signer.source(), "keystore");
+        assert!(signer.sign_message(b"trade").verify(keypair.pubkey().as_ref(), b"trade"));
+    }
+
+    #[test]
+    fn keystore_does_not_hold_the_secret_in_the_clear() {
+        let keypair = Keypair::new();
+        let path = temp_path("opaque.json");
+        encrypt_keystore(&keypair, PASSPHRASE, &path).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&keypair.to_base58_string()));
+        assert!(!contents.contains(&base64::encode(keypair.to_bytes())));
+        assert!(!contents.contains(&format!("{:?}", keypair.to_bytes().to_vec())));
+    }
+
+    #[test]
+    fn keystore_rejects_a_wrong_passphrase() {
+        let path = temp_path("wrong-passphrase.json");
+        encrypt_keystore(&Keypair::new(), PASSPHRASE, &path).unwrap();
+
+        let error = read_keystore(&path).decrypt("incorrect horse").unwrap_err();
+        assert!(error.to_string().contains("wrong passphrase"), "{error}");
+    }
+
+    #[test]
+    fn keystore_rejects_tampering() {
+        let path = temp_path("tampered.json");
+        encrypt_keystore(&Keypair::new(), PASSPHRASE, &path).unwrap();
+        let keystore = read_keystore(&path);
+
+        let mut ciphertext = base64::decode(&keystore.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        let flipped = Keystore { ciphertext: base64::encode(&ciphertext), ..read_keystore(&path) };
+        assert!(flipped.decrypt(PASSPHRASE).is_err());
+
+        let mut nonce = base64::decode(&keystore.nonce).unwrap();
+        nonce[0] ^= 1;
+        let renonced = Keystore { nonce: base64::encode(&nonce), ..read_keystore(&path) };
+        assert!(renonced.decrypt(PASSPHRASE).is_err());
+
+        // A keystore relabelled with another pubkey still decrypts, but to the wrong key
+        let relabelled = Keystore { pubkey: Keypair::new().pubkey().to_string(), ..read_keystore(&path) };
+        let error = relabelled.decrypt(PASSPHRASE).unwrap_err();
+        assert!(error.to_string().contains("pubkey mismatch"), "{error}");
+
+        let future = Keystore { version: KEYSTORE_VERSION + 1, ..keystore };
+        assert!(future.decrypt(PASSPHRASE).is_err());
+    }
+
+    /// A signing process on a Unix socket that claims `pubkey` and signs
+    /// every message with `keypair`, which may not be the key it claims.
+    #[cfg(unix)]
+    fn remote_signer_process(name: &str, pubkey: Pubkey, keypair: Keypair) -> PathBuf {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixListener;
+
+        let path = temp_path(name);
+        let listener = UnixListener::bind(&path).unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut line = String::new();
+                BufReader::new(&stream).read_line(&mut line).unwrap();
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let response = match request["method"].as_str() {
+                    Some("get_pubkey") => serde_json::json!({ "pubkey": pubkey.to_string() }),
+                    Some("sign_message") => {
+                        let message = base64::decode(request["message"].as_str().unwrap()).unwrap();
+                        serde_json::json!({ "signature": keypair.sign_message(&message).to_string() })
+                    }
+                    _ => serde_json::json!({ "error": "unknown method" }),
+                };
+                let _ = stream.write_all(format!("{}\n", response).as_bytes());
+            }
+        });
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remote_signer_accepts_signatures_from_its_key() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let path = remote_signer_process("honest.sock", pubkey, keypair);
+
+        let signer = RemoteSigner::connect(path.to_str().unwrap()).unwrap();
+        assert_eq!(signer.pubkey(), pubkey);
+        let signature = signer.try_sign_message(b"trade").unwrap();
+        assert!(signature.verify(pubkey.as_ref(), b"trade"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remote_signer_rejects_signatures_from_another_key() {
+        let pubkey = Keypair::new().pubkey();
+        let path = remote_signer_process("impostor.sock", pubkey, Keypair::new());
+
+        let signer = RemoteSigner::connect(path.to_str().unwrap()).unwrap();
+        match signer.try_sign_message(b"trade") {
+            Err(SignerError::Protocol(message)) => assert!(message.contains("invalid signature"), "{message}"),
+            other => panic!("expected an invalid signature error, got {:?}", other),
+        }
+    }
+}
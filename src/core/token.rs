@@ -1,31 +1,97 @@
-use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::solana_client::rpc_request::TokenAccountsFilter;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use solana_account_decoder::UiAccountData;
 use spl_token_2022::{
-    extension::StateWithExtensionsOwned,
+    extension::{
+        metadata_pointer::MetadataPointer,
+        transfer_fee::{TransferFee, TransferFeeConfig},
+        BaseStateWithExtensions, StateWithExtensionsOwned,
+    },
     state::{Account, Mint},
 };
 use spl_token_client::{
     client::{ProgramClient, ProgramRpcClient, ProgramRpcClientSendTransaction},
-    token::{Token, TokenError, TokenResult},
+    token::{TokenError, TokenResult},
 };
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
-pub fn get_associated_token_address(
+use crate::core::signer::SharedSigner;
+
+lazy_static::lazy_static! {
+    /// Token program owning each mint seen so far.
+    static ref TOKEN_PROGRAMS: Mutex<HashMap<Pubkey, Pubkey>> = Mutex::new(HashMap::new());
+}
+
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == spl_token::ID || *program_id == spl_token_2022::ID
+}
+
+/// Records the token program of `mint`, e.g. from a parsed instruction that
+/// passed it. Anything but SPL Token or Token-2022 is ignored.
+pub fn remember_token_program(mint: &Pubkey, program_id: &Pubkey) {
+    if is_token_program(program_id) {
+        TOKEN_PROGRAMS.lock().unwrap().insert(*mint, *program_id);
+    }
+}
+
+/// Token program of `mint` if it has been seen, SPL Token otherwise.
+pub fn known_token_program(mint: &Pubkey) -> Pubkey {
+    TOKEN_PROGRAMS.lock().unwrap().get(mint).copied().unwrap_or(spl_token::ID)
+}
+
+/// Token program owning `mint`, read from chain the first time.
+pub async fn get_token_program(
     client: Arc<anchor_client::solana_client::nonblocking::rpc_client::RpcClient>,
-    keypair: Arc<Keypair>,
+    mint: &Pubkey,
+) -> TokenResult<Pubkey> {
+    if let Some(program_id) = TOKEN_PROGRAMS.lock().unwrap().get(mint).copied() {
+        return Ok(program_id);
+    }
+    let program_client = Arc::new(ProgramRpcClient::new(
+        client.clone(),
+        ProgramRpcClientSendTransaction,
+    ));
+    let account = program_client
+        .get_account(*mint)
+        .await
+        .map_err(TokenError::Client)?
+        .ok_or(TokenError::AccountNotFound)?;
+    if !is_token_program(&account.owner) {
+        return Err(TokenError::AccountInvalidOwner);
+    }
+    remember_token_program(mint, &account.owner);
+    Ok(account.owner)
+}
+
+/// Associated token account of `owner` for `address`, under the mint's token
+/// program when it is known (see [`known_token_program`]). Use
+/// [`get_associated_token_address_for_mint`] for a mint that may not have
+/// been seen yet.
+pub fn get_associated_token_address(
+    _client: Arc<anchor_client::solana_client::nonblocking::rpc_client::RpcClient>,
+    _signer: SharedSigner,
     address: &Pubkey,
     owner: &Pubkey,
 ) -> Pubkey {
-    let token_client = Token::new(
-        Arc::new(ProgramRpcClient::new(
-            client.clone(),
-            ProgramRpcClientSendTransaction,
-        )),
-        &spl_token::ID,
+    spl_associated_token_account::get_associated_token_address_with_program_id(
+        owner,
         address,
-        None,
-        Arc::new(Keypair::from_bytes(&keypair.to_bytes()).expect("failed to copy keypair")),
-    );
-    token_client.get_associated_token_address(owner)
+        &known_token_program(address),
+    )
+}
+
+/// Associated token account of `owner` for `mint`, looking the mint's token
+/// program up on chain if it has not been seen yet.
+pub async fn get_associated_token_address_for_mint(
+    client: Arc<anchor_client::solana_client::nonblocking::rpc_client::RpcClient>,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> TokenResult<Pubkey> {
+    let program_id = get_token_program(client, mint).await?;
+    Ok(spl_associated_token_account::get_associated_token_address_with_program_id(
+        owner, mint, &program_id,
+    ))
 }
 
 pub async fn get_account_info(
@@ -49,20 +115,23 @@ pub async fn get_account_info(
             // ));
         })?;
 
-    if account.owner != spl_token::ID {
+    let program_id = account.owner;
+    if !is_token_program(&program_id) {
         return Err(TokenError::AccountInvalidOwner);
     }
     let account = StateWithExtensionsOwned::<Account>::unpack(account.data)?;
     if account.base.mint != address {
         return Err(TokenError::AccountInvalidMint);
     }
+    // A token account lives under the same program as its mint
+    remember_token_program(&address, &program_id);
 
     Ok(account)
 }
 
 pub async fn get_mint_info(
     client: Arc<anchor_client::solana_client::nonblocking::rpc_client::RpcClient>,
-    _keypair: Arc<Keypair>,
+    _signer: SharedSigner,
     address: Pubkey,
 ) -> TokenResult<StateWithExtensionsOwned<Mint>> {
     let program_client = Arc::new(ProgramRpcClient::new(
@@ -76,9 +145,10 @@ pub async fn get_mint_info(
         .ok_or(TokenError::AccountNotFound)
         .inspect_err(|err| println!("{} {}: mint {}", address, err, address))?;
 
-    if account.owner != spl_token::ID {
+    if !is_token_program(&account.owner) {
         return Err(TokenError::AccountInvalidOwner);
     }
+    remember_token_program(&address, &account.owner);
 
     let mint_result = StateWithExtensionsOwned::<Mint>::unpack(account.data).map_err(Into::into);
     let decimals: Option<u8> = None;
@@ -90,3 +160,163 @@ pub async fn get_mint_info(
 
     mint_result
 }
+
+/// Token-2022 extensions of a mint that matter when trading it.
+#[derive(Debug, Clone, Default)]
+pub struct MintExtensions {
+    /// Transfer fee in force at the epoch the mint was decoded for.
+    pub transfer_fee: Option<TransferFee>,
+    /// Account holding the token's metadata, often the mint itself.
+    pub metadata_address: Option<Pubkey>,
+}
+
+impl MintExtensions {
+    /// Reads the extensions of `mint`. SPL Token mints have none.
+    pub fn from_mint(mint: &StateWithExtensionsOwned<Mint>, epoch: u64) -> Self {
+        Self {
+            transfer_fee: mint
+                .get_extension::<TransferFeeConfig>()
+                .ok()
+                .map(|config| *config.get_epoch_fee(epoch)),
+            metadata_address: mint
+                .get_extension::<MetadataPointer>()
+                .ok()
+                .and_then(|pointer| Option::<Pubkey>::from(pointer.metadata_address)),
+        }
+    }
+
+    /// Part of a transfer of `amount` the mint withholds as a fee.
+    pub fn transfer_fee_on(&self, amount: u64) -> u64 {
+        self.transfer_fee
+            .and_then(|fee| fee.calculate_fee(amount))
+            .unwrap_or_default()
+    }
+}
+
+/// Token-2022 extensions of `mint` at the current epoch.
+pub async fn get_mint_extensions(
+    client: Arc<anchor_client::solana_client::nonblocking::rpc_client::RpcClient>,
+    signer: SharedSigner,
+    mint: Pubkey,
+) -> anyhow::Result<MintExtensions> {
+    let epoch = client.get_epoch_info().await?.epoch;
+    let mint = get_mint_info(client, signer, mint).await?;
+    Ok(MintExtensions::from_mint(&mint, epoch))
+}
+
+/// A non-empty token account of the wallet.
+#[derive(Debug, Clone)]
+pub struct WalletTokenBalance {
+    pub mint: String,
+    pub amount: u64,
+    pub decimals: u8,
+    pub ui_amount: String,
+}
+
+/// Every non-empty SPL Token and Token-2022 account owned by `owner`.
+pub async fn get_wallet_token_balances(
+    client: Arc<anchor_client::solana_client::nonblocking::rpc_client::RpcClient>,
+    owner: &Pubkey,
+) -> anyhow::Result<Vec<WalletTokenBalance>> {
+    let mut balances = Vec::new();
+    for program_id in [spl_token::ID, spl_token_2022::ID] {
+        let accounts = client
+            .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(program_id))
+            .await?;
+        for account in accounts {
+            let UiAccountData::Json(parsed) = account.account.data else {
+                continue;
+            };
+            let info = &parsed.parsed["info"];
+            let token_amount = &info["tokenAmount"];
+            let amount = token_amount["amount"]
+                .as_str()
+                .and_then(|amount| amount.parse::<u64>().ok())
+                .unwrap_or_default();
+            if amount == 0 {
+                continue;
+            }
+            balances.push(WalletTokenBalance {
+                mint: info["mint"].as_str().unwrap_or_default().to_string(),
+                amount,
+                decimals: token_amount["decimals"].as_u64().unwrap_or_default() as u8,
+                ui_amount: token_amount["uiAmountString"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+    }
+    Ok(balances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token_2022::extension::{ExtensionType, StateWithExtensionsMut};
+    use spl_token_2022::solana_program::program_pack::Pack;
+
+    fn fee(epoch: u64, basis_points: u16, maximum_fee: u64) -> TransferFee {
+        TransferFee {
+            epoch: epoch.into(),
+            maximum_fee: maximum_fee.into(),
+            transfer_fee_basis_points: basis_points.into(),
+        }
+    }
+
+    fn base_mint() -> Mint {
+        Mint {
+            supply: 1_000_000_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        }
+    }
+
+    /// A Token-2022 mint whose fee rises from 1% to 2.5% at epoch 500, with
+    /// its metadata at `metadata`.
+    fn token_2022_mint(metadata: Pubkey) -> StateWithExtensionsOwned<Mint> {
+        let extensions = [ExtensionType::TransferFeeConfig, ExtensionType::MetadataPointer];
+        let mut data = vec![0; ExtensionType::try_calculate_account_len::<Mint>(&extensions).unwrap()];
+        {
+            let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+            let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.older_transfer_fee = fee(0, 100, 5_000);
+            config.newer_transfer_fee = fee(500, 250, 1_000_000);
+            let pointer = state.init_extension::<MetadataPointer>(true).unwrap();
+            pointer.metadata_address = Some(metadata).try_into().unwrap();
+            state.base = base_mint();
+            state.pack_base();
+            state.init_account_type().unwrap();
+        }
+        StateWithExtensionsOwned::<Mint>::unpack(data).unwrap()
+    }
+
+    #[test]
+    fn reads_the_transfer_fee_and_metadata_pointer_of_a_token_2022_mint() {
+        let metadata = Pubkey::new_unique();
+        let mint = token_2022_mint(metadata);
+
+        let before = MintExtensions::from_mint(&mint, 499);
+        assert_eq!(before.metadata_address, Some(metadata));
+        assert_eq!(u16::from(before.transfer_fee.unwrap().transfer_fee_basis_points), 100);
+        // 1% of a million is capped at the 5_000 maximum
+        assert_eq!(before.transfer_fee_on(1_000_000), 5_000);
+        assert_eq!(before.transfer_fee_on(100_000), 1_000);
+
+        // The newer fee applies from its epoch on
+        let after = MintExtensions::from_mint(&mint, 500);
+        assert_eq!(u16::from(after.transfer_fee.unwrap().transfer_fee_basis_points), 250);
+        assert_eq!(after.transfer_fee_on(1_000_000), 25_000);
+        assert_eq!(after.transfer_fee_on(0), 0);
+    }
+
+    #[test]
+    fn spl_token_mints_have_no_extensions() {
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(base_mint(), &mut data).unwrap();
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(data).unwrap();
+
+        let extensions = MintExtensions::from_mint(&mint, 500);
+        assert!(extensions.transfer_fee.is_none());
+        assert!(extensions.metadata_address.is_none());
+        assert_eq!(extensions.transfer_fee_on(1_000_000), 0);
+    }
+}
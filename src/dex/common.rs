@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use anchor_client::solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use anchor_client::solana_sdk::{account::Account, instruction::Instruction, pubkey::Pubkey, system_instruction};
+use anyhow::{anyhow, Result};
+use solana_account_decoder::UiAccountEncoding;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+
+use crate::common::config::SwapConfig;
+use crate::core::signer::SharedSigner;
+use crate::core::token::{get_mint_extensions, get_token_program, MintExtensions};
+use crate::engine::swap::SwapInType;
+
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| anyhow!("account data too short for u64 at {}", offset))
+}
+
+pub(crate) fn read_pubkey(data: &[u8], offset: usize) -> Result<Pubkey> {
+    data.get(offset..offset + 32)
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(Pubkey::new_from_array)
+        .ok_or_else(|| anyhow!("account data too short for pubkey at {}", offset))
+}
+
+// SPL token account layout: mint (32) | owner (32) | amount (u64); Token-2022 accounts share it
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+/// Accounts of `program` matching every one of `filters`.
+pub(crate) async fn program_accounts(
+    client: &RpcClient,
+    program: &Pubkey,
+    filters: Vec<RpcFilterType>,
+) -> Result<Vec<(Pubkey, Account)>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    Ok(client.get_program_accounts_with_config(program, config).await?)
+}
+
+/// Accounts of `program` with `data_size` bytes that pair `mint` with WSOL,
+/// in either order, given the offsets of the two mints in the account.
+pub(crate) async fn find_sol_pairs(
+    client: &RpcClient,
+    program: &Pubkey,
+    data_size: u64,
+    mint_offsets: (usize, usize),
+    mint: &Pubkey,
+) -> Result<Vec<(Pubkey, Account)>> {
+    let wsol = spl_token::native_mint::ID;
+    let mut accounts = Vec::new();
+    for (first, second) in [(mint, &wsol), (&wsol, mint)] {
+        let filters = vec![
+            RpcFilterType::DataSize(data_size),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(mint_offsets.0, first.as_ref())),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(mint_offsets.1, second.as_ref())),
+        ];
+        accounts.extend(program_accounts(client, program, filters).await?);
+    }
+    Ok(accounts)
+}
+
+/// Balances of `token_accounts`, zero for accounts that do not exist.
+pub(crate) async fn token_account_amounts(client: &RpcClient, token_accounts: &[Pubkey]) -> Result<Vec<u64>> {
+    let accounts = client.get_multiple_accounts(token_accounts).await?;
+    Ok(accounts
+        .iter()
+        .map(|account| {
+            account
+                .as_ref()
+                .and_then(|account| read_u64(&account.data, TOKEN_ACCOUNT_AMOUNT_OFFSET).ok())
+                .unwrap_or_default()
+        })
+        .collect())
+}
+
+// Mint layout: mint_authority (COption<Pubkey>, 36) | supply (u64) | decimals (u8); Token-2022 mints share it
+const MINT_DECIMALS_OFFSET: usize = 44;
+
+/// Owning token program and decimals of each of `mints`.
+pub(crate) async fn mint_infos(client: &RpcClient, mints: &[Pubkey]) -> Result<Vec<(Pubkey, u8)>> {
+    let accounts = client.get_multiple_accounts(mints).await?;
+    mints
+        .iter()
+        .zip(accounts)
+        .map(|(mint, account)| {
+            let account = account.ok_or_else(|| anyhow!("mint {} not found", mint))?;
+            let decimals = account
+                .data
+                .get(MINT_DECIMALS_OFFSET)
+                .copied()
+                .ok_or_else(|| anyhow!("mint {} is too short", mint))?;
+            Ok((account.owner, decimals))
+        })
+        .collect()
+}
+
+/// Token program of `mint` and, for Token-2022 mints, the extensions a swap
+/// has to account for, such as a transfer fee withheld on every transfer.
+pub(crate) async fn mint_program_and_extensions(
+    client: &Arc<RpcClient>,
+    signer: &SharedSigner,
+    mint: &Pubkey,
+) -> Result<(Pubkey, MintExtensions)> {
+    let token_program = get_token_program(client.clone(), mint).await?;
+    let extensions = if token_program == spl_token_2022::ID {
+        get_mint_extensions(client.clone(), signer.clone(), *mint).await?
+    } else {
+        MintExtensions::default()
+    };
+    Ok((token_program, extensions))
+}
+
+/// Creates the owner's WSOL account if needed and funds it with `lamports`.
+/// The caller closes it after the swap to unwrap whatever is left.
+pub(crate) fn wrap_sol(owner: &Pubkey, wsol_account: &Pubkey, lamports: u64) -> Result<Vec<Instruction>> {
+    Ok(vec![
+        create_associated_token_account_idempotent(owner, owner, &spl_token::native_mint::ID, &spl_token::ID),
+        system_instruction::transfer(owner, wsol_account, lamports),
+        spl_token::instruction::sync_native(&spl_token::ID, wsol_account)?,
+    ])
+}
+
+/// Raw amount of tokens a sell spends from `token_account`, and whether that
+/// empties the account.
+pub(crate) async fn sell_amount(
+    client: &RpcClient,
+    token_account: &Pubkey,
+    swap_config: &SwapConfig,
+    decimals: u8,
+) -> Result<(u64, bool)> {
+    let balance = client.get_token_account_balance(token_account).await?;
+    let balance: u64 = balance.amount.parse()?;
+    let amount = match swap_config.in_type {
+        SwapInType::Qty => ((swap_config.amount_in * 10f64.powi(decimals as i32)) as u64).min(balance),
+        SwapInType::Pct => ((balance as f64 * swap_config.amount_in.clamp(0.0, 1.0)) as u64).min(balance),
+    };
+    if amount == 0 {
+        return Err(anyhow!("no tokens to sell in {}", token_account));
+    }
+    Ok((amount, amount == balance))
+}
+
+/// Smallest output accepted for an `expected` quote at `slippage` percent.
+pub(crate) fn minimum_out(expected: u64, slippage: u64) -> u64 {
+    (expected as u128 * (100 - slippage.min(100)) as u128 / 100) as u64
+}
+
+/// Largest input accepted for an `expected` quote at `slippage` percent.
+pub(crate) fn maximum_in(expected: u64, slippage: u64) -> u64 {
+    (expected as u128 * (100 + slippage.min(100)) as u128).div_ceil(100).min(u64::MAX as u128) as u64
+}
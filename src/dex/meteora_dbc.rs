@@ -0,0 +1,399 @@
+use std::{str::FromStr, sync::Arc};
+
+use anchor_client::solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClient as BlockingRpcClient,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signer::Signer,
+};
+use anyhow::{anyhow, Result};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account_idempotent,
+};
+use tokio::time::Instant;
+
+use super::common::{
+    minimum_out, mint_infos, mint_program_and_extensions, program_accounts, read_pubkey, read_u64, sell_amount, wrap_sol,
+};
+use crate::common::config::SwapConfig;
+use crate::core::signer::SharedSigner;
+use crate::engine::swap::SwapDirection;
+
+pub const METEORA_DBC_PROGRAM: &str = "dbcij3LWUppWqq96dh6gJWwBifmcGfLSB5D4DuSMaqN";
+
+pub const SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+pub const SWAP2_DISCRIMINATOR: [u8; 8] = [65, 75, 63, 76, 235, 91, 91, 136];
+pub const VIRTUAL_POOL_DISCRIMINATOR: [u8; 8] = [213, 224, 5, 209, 98, 69, 119, 92];
+/// `swap2` mode in which the first amount is the exact output.
+pub const SWAP_MODE_EXACT_OUT: u8 = 2;
+
+/// Fee numerators are expressed in billionths.
+pub const FEE_DENOMINATOR: u128 = 1_000_000_000;
+
+// VirtualPool: discriminator (8) | volatility_tracker (64) | config | creator | base_mint |
+// base_vault | quote_vault | base_reserve | quote_reserve | protocol/partner fees (4 x u64) |
+// sqrt_price (u128) | activation_point (u64) | pool_type | is_migrated | ... | migration_progress
+const POOL_CONFIG_OFFSET: usize = 72;
+const POOL_BASE_MINT_OFFSET: usize = 136;
+const POOL_BASE_VAULT_OFFSET: usize = 168;
+const POOL_QUOTE_VAULT_OFFSET: usize = 200;
+const POOL_BASE_RESERVE_OFFSET: usize = 232;
+const POOL_QUOTE_RESERVE_OFFSET: usize = 240;
+const POOL_SQRT_PRICE_OFFSET: usize = 280;
+const POOL_IS_MIGRATED_OFFSET: usize = 305;
+const POOL_MIGRATION_PROGRESS_OFFSET: usize = 308;
+// PoolConfig: discriminator (8) | quote_mint | fee_claimer | leftover_receiver | base_fee (cliff_fee_numerator, ...)
+const CONFIG_QUOTE_MINT_OFFSET: usize = 8;
+const CONFIG_CLIFF_FEE_NUMERATOR_OFFSET: usize = 104;
+
+/// A Meteora Dynamic Bonding Curve pool, decoded from its `VirtualPool`
+/// account. The quote mint, fee and mint details come from its config and
+/// mints, filled in by `get_pool`.
+#[derive(Debug, Clone)]
+pub struct DbcPool {
+    pub pool_id: Pubkey,
+    pub config: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    /// Square root of the quote-per-base price, Q64.64.
+    pub sqrt_price: u128,
+    pub is_migrated: bool,
+    pub migration_progress: u8,
+    pub fee_numerator: u64,
+    pub base_token_program: Pubkey,
+    pub quote_token_program: Pubkey,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+}
+
+impl DbcPool {
+    pub fn decode(pool_id: Pubkey, data: &[u8]) -> Result<Self> {
+        let read_u8 = |offset: usize| data.get(offset).copied().ok_or_else(|| anyhow!("pool {} is too short", pool_id));
+        let sqrt_price = data
+            .get(POOL_SQRT_PRICE_OFFSET..POOL_SQRT_PRICE_OFFSET + 16)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u128::from_le_bytes)
+            .ok_or_else(|| anyhow!("pool {} is too short", pool_id))?;
+        Ok(Self {
+            pool_id,
+            config: read_pubkey(data, POOL_CONFIG_OFFSET)?,
+            base_mint: read_pubkey(data, POOL_BASE_MINT_OFFSET)?,
+            quote_mint: spl_token::native_mint::ID,
+            base_vault: read_pubkey(data, POOL_BASE_VAULT_OFFSET)?,
+            quote_vault: read_pubkey(data, POOL_QUOTE_VAULT_OFFSET)?,
+            base_reserve: read_u64(data, POOL_BASE_RESERVE_OFFSET)?,
+            quote_reserve: read_u64(data, POOL_QUOTE_RESERVE_OFFSET)?,
+            sqrt_price,
+            is_migrated: read_u8(POOL_IS_MIGRATED_OFFSET)? != 0,
+            migration_progress: read_u8(POOL_MIGRATION_PROGRESS_OFFSET)?,
+            fee_numerator: 0,
+            base_token_program: spl_token::ID,
+            quote_token_program: spl_token::ID,
+            base_decimals: 0,
+            quote_decimals: 9,
+        })
+    }
+
+    /// Still trading on the curve: not migrated and not waiting for migration.
+    pub fn is_trading(&self) -> bool {
+        !self.is_migrated && self.migration_progress == 0
+    }
+
+    /// Spot price in raw quote units per raw base unit.
+    fn raw_price(&self) -> f64 {
+        let sqrt_price = self.sqrt_price as f64 / 2f64.powi(64);
+        sqrt_price * sqrt_price
+    }
+
+    /// Spot price in SOL per whole token.
+    pub fn price(&self) -> f64 {
+        self.raw_price() * 10f64.powi(self.base_decimals as i32 - self.quote_decimals as i32)
+    }
+
+    /// Output for `amount_in` at the spot price, after the base fee. Price
+    /// impact along the curve is not modelled, so the slippage setting has
+    /// to cover it.
+    pub fn quote_exact_in(&self, input_is_base: bool, amount_in: u64) -> u64 {
+        let fee = (amount_in as u128 * self.fee_numerator as u128).div_ceil(FEE_DENOMINATOR);
+        let amount_in = (amount_in as u128).saturating_sub(fee) as f64;
+        let price = self.raw_price();
+        let out = if input_is_base {
+            amount_in * price
+        } else if price > 0.0 {
+            amount_in / price
+        } else {
+            0.0
+        };
+        out.min(u64::MAX as f64) as u64
+    }
+}
+
+/// The pool at `pool_id`, with its config fee and mint details.
+pub async fn get_pool(client: &RpcClient, pool_id: &Pubkey) -> Result<DbcPool> {
+    let data = client
+        .get_account_data(pool_id)
+        .await
+        .map_err(|e| anyhow!("no Meteora DBC pool at {}: {}", pool_id, e))?;
+    let mut pool = DbcPool::decode(*pool_id, &data)?;
+    let config = client.get_account_data(&pool.config).await?;
+    pool.quote_mint = read_pubkey(&config, CONFIG_QUOTE_MINT_OFFSET)?;
+    pool.fee_numerator = read_u64(&config, CONFIG_CLIFF_FEE_NUMERATOR_OFFSET)?;
+    let mints = mint_infos(client, &[pool.base_mint, pool.quote_mint]).await?;
+    (pool.base_token_program, pool.base_decimals) = mints[0];
+    (pool.quote_token_program, pool.quote_decimals) = mints[1];
+    Ok(pool)
+}
+
+/// The curve pool launched for `mint`. Only SOL-quoted curves are supported.
+pub async fn find_pool(client: &RpcClient, mint: &Pubkey) -> Result<DbcPool> {
+    let program = Pubkey::from_str(METEORA_DBC_PROGRAM)?;
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &VIRTUAL_POOL_DISCRIMINATOR)),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(POOL_BASE_MINT_OFFSET, mint.as_ref())),
+    ];
+    let (pool_id, _) = program_accounts(client, &program, filters)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no Meteora DBC pool for {}", mint))?;
+    let pool = get_pool(client, &pool_id).await?;
+    if pool.quote_mint != spl_token::native_mint::ID {
+        return Err(anyhow!("Meteora DBC pool {} is quoted in {}, not SOL", pool_id, pool.quote_mint));
+    }
+    Ok(pool)
+}
+
+pub struct MeteoraDbc {
+    pub rpc_nonblocking_client: Arc<RpcClient>,
+    pub rpc_client: Arc<BlockingRpcClient>,
+    pub keypair: SharedSigner,
+}
+
+impl MeteoraDbc {
+    pub fn new(
+        rpc_nonblocking_client: Arc<RpcClient>,
+        rpc_client: Arc<BlockingRpcClient>,
+        keypair: SharedSigner,
+    ) -> Self {
+        Self {
+            rpc_nonblocking_client,
+            rpc_client,
+            keypair,
+        }
+    }
+
+    pub async fn get_token_price(&self, mint: &str) -> Result<f64> {
+        let pool = find_pool(&self.rpc_nonblocking_client, &Pubkey::from_str(mint)?).await?;
+        Ok(pool.price())
+    }
+
+    /// Builds a buy or sell of `mint` on its curve. Buys spend `amount_in`
+    /// SOL; sells spend a quantity or a fraction of the wallet's tokens.
+    pub async fn build_swap_ixn_by_mint(
+        &self,
+        mint: &str,
+        pool: Option<DbcPool>,
+        swap_config: SwapConfig,
+        _start_time: Instant,
+    ) -> Result<(SharedSigner, Vec<Instruction>, f64)> {
+        let mint = Pubkey::from_str(mint)?;
+        let pool = match pool {
+            Some(pool) => pool,
+            None => find_pool(&self.rpc_nonblocking_client, &mint).await?,
+        };
+        if !pool.is_trading() {
+            return Err(anyhow!("Meteora DBC pool {} is no longer trading", pool.pool_id));
+        }
+        // Token-2022 base mints may withhold a transfer fee on the way in and out of the pool
+        let (token_program, extensions) =
+            mint_program_and_extensions(&self.rpc_nonblocking_client, &self.keypair, &pool.base_mint).await?;
+
+        let owner = self.keypair.pubkey();
+        let base_ata = get_associated_token_address_with_program_id(&owner, &pool.base_mint, &token_program);
+        let quote_ata = get_associated_token_address_with_program_id(&owner, &pool.quote_mint, &spl_token::ID);
+
+        let mut instructions = Vec::new();
+        let mut close_base = false;
+        let swap_ix = match swap_config.swap_direction {
+            SwapDirection::Buy => {
+                let quote_in = (swap_config.amount_in * LAMPORTS_PER_SOL as f64) as u64;
+                let base_out = pool.quote_exact_in(false, quote_in);
+                let minimum_out = minimum_out(base_out - extensions.transfer_fee_on(base_out), swap_config.slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &pool.base_mint, &token_program));
+                instructions.extend(wrap_sol(&owner, &quote_ata, quote_in)?);
+                self.swap_instruction(&pool, &owner, quote_ata, base_ata, quote_in, minimum_out)?
+            }
+            SwapDirection::Sell => {
+                let (base_in, whole_balance) =
+                    sell_amount(&self.rpc_nonblocking_client, &base_ata, &swap_config, pool.base_decimals).await?;
+                close_base = whole_balance;
+                let base_received = base_in - extensions.transfer_fee_on(base_in);
+                let minimum_out = minimum_out(pool.quote_exact_in(true, base_received), swap_config.slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &pool.quote_mint, &spl_token::ID));
+                self.swap_instruction(&pool, &owner, base_ata, quote_ata, base_in, minimum_out)?
+            }
+        };
+        instructions.push(swap_ix);
+        instructions.push(spl_token::instruction::close_account(&spl_token::ID, &quote_ata, &owner, &owner, &[])?);
+        if close_base {
+            instructions.push(spl_token_2022::instruction::close_account(&token_program, &base_ata, &owner, &owner, &[])?);
+        }
+
+        Ok((self.keypair.clone(), instructions, pool.price()))
+    }
+
+    fn swap_instruction(
+        &self,
+        pool: &DbcPool,
+        owner: &Pubkey,
+        input_account: Pubkey,
+        output_account: Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<Instruction> {
+        let program = Pubkey::from_str(METEORA_DBC_PROGRAM)?;
+        let (pool_authority, _) = Pubkey::find_program_address(&[b"pool_authority"], &program);
+        let (event_authority, _) = Pubkey::find_program_address(&[b"__event_authority"], &program);
+
+        let mut data = Vec::with_capacity(24);
+        data.extend_from_slice(&SWAP_DISCRIMINATOR);
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+
+        Ok(Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::new_readonly(pool_authority, false),
+                AccountMeta::new_readonly(pool.config, false),
+                AccountMeta::new(pool.pool_id, false),
+                AccountMeta::new(input_account, false),
+                AccountMeta::new(output_account, false),
+                AccountMeta::new(pool.base_vault, false),
+                AccountMeta::new(pool.quote_vault, false),
+                AccountMeta::new_readonly(pool.base_mint, false),
+                AccountMeta::new_readonly(pool.quote_mint, false),
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(pool.base_token_program, false),
+                AccountMeta::new_readonly(pool.quote_token_program, false),
+                // No referral token account
+                AccountMeta::new_readonly(program, false),
+                AccountMeta::new_readonly(event_authority, false),
+                AccountMeta::new_readonly(program, false),
+            ],
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::hash::hashv;
+
+    const TOKEN_MINT: &str = "2zMMhcVQEXDtdE6vsFS7S7D5oUodfJHE8vd1gnBouauv";
+    // 2^-10 in Q64.64, so the raw price is exactly 2^-20 quote per base
+    const SQRT_PRICE: u128 = 1 << 54;
+
+    fn anchor_discriminator(preimage: &str) -> [u8; 8] {
+        hashv(&[preimage.as_bytes()]).to_bytes()[..8].try_into().unwrap()
+    }
+
+    /// A `VirtualPool` account with each field the decoder reads set to a distinct value.
+    fn pool_data(keys: &[Pubkey; 4], is_migrated: u8, migration_progress: u8) -> Vec<u8> {
+        let mut data = vec![0u8; 424];
+        data[..8].copy_from_slice(&VIRTUAL_POOL_DISCRIMINATOR);
+        for (offset, key) in [POOL_CONFIG_OFFSET, POOL_BASE_MINT_OFFSET, POOL_BASE_VAULT_OFFSET, POOL_QUOTE_VAULT_OFFSET]
+            .into_iter()
+            .zip(keys)
+        {
+            data[offset..offset + 32].copy_from_slice(key.as_ref());
+        }
+        // The creator sits between config and base mint and must not leak into either
+        data[104..136].fill(0xee);
+        data[POOL_BASE_RESERVE_OFFSET..POOL_BASE_RESERVE_OFFSET + 8].copy_from_slice(&800_000_000_000_000u64.to_le_bytes());
+        data[POOL_QUOTE_RESERVE_OFFSET..POOL_QUOTE_RESERVE_OFFSET + 8].copy_from_slice(&12_345_678_901u64.to_le_bytes());
+        // Protocol and partner fees, then sqrt_price
+        data[248..280].fill(0xff);
+        data[POOL_SQRT_PRICE_OFFSET..POOL_SQRT_PRICE_OFFSET + 16].copy_from_slice(&SQRT_PRICE.to_le_bytes());
+        data[POOL_IS_MIGRATED_OFFSET] = is_migrated;
+        data[POOL_MIGRATION_PROGRESS_OFFSET] = migration_progress;
+        data
+    }
+
+    fn pool(fee_numerator: u64) -> DbcPool {
+        let keys = [Pubkey::new_unique(), Pubkey::from_str(TOKEN_MINT).unwrap(), Pubkey::new_unique(), Pubkey::new_unique()];
+        DbcPool {
+            fee_numerator,
+            base_decimals: 6,
+            ..DbcPool::decode(Pubkey::new_unique(), &pool_data(&keys, 0, 0)).unwrap()
+        }
+    }
+
+    #[test]
+    fn discriminators_match_the_anchor_names() {
+        assert_eq!(SWAP_DISCRIMINATOR, anchor_discriminator("global:swap"));
+        assert_eq!(SWAP2_DISCRIMINATOR, anchor_discriminator("global:swap2"));
+        assert_eq!(VIRTUAL_POOL_DISCRIMINATOR, anchor_discriminator("account:VirtualPool"));
+    }
+
+    #[test]
+    fn decodes_a_virtual_pool() {
+        let pool_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::from_str(TOKEN_MINT).unwrap(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let pool = DbcPool::decode(pool_id, &pool_data(&keys, 0, 0)).unwrap();
+        assert_eq!(pool.pool_id, pool_id);
+        assert_eq!([pool.config, pool.base_mint, pool.base_vault, pool.quote_vault], keys);
+        assert_eq!(pool.quote_mint, spl_token::native_mint::ID);
+        assert_eq!(pool.base_reserve, 800_000_000_000_000);
+        assert_eq!(pool.quote_reserve, 12_345_678_901);
+        assert_eq!(pool.sqrt_price, SQRT_PRICE);
+        assert!(pool.is_trading());
+
+        let migrating = DbcPool::decode(pool_id, &pool_data(&keys, 0, 1)).unwrap();
+        assert_eq!(migrating.migration_progress, 1);
+        assert!(!migrating.is_trading());
+        let migrated = DbcPool::decode(pool_id, &pool_data(&keys, 1, 3)).unwrap();
+        assert!(migrated.is_migrated);
+        assert!(!migrated.is_trading());
+    }
+
+    #[test]
+    fn rejects_a_truncated_pool() {
+        let keys = [Pubkey::new_unique(); 4];
+        let data = pool_data(&keys, 0, 0);
+        assert!(DbcPool::decode(Pubkey::new_unique(), &data[..POOL_MIGRATION_PROGRESS_OFFSET]).is_err());
+        assert!(DbcPool::decode(Pubkey::new_unique(), &data[..POOL_SQRT_PRICE_OFFSET + 15]).is_err());
+    }
+
+    #[test]
+    fn quotes_at_the_spot_price_after_the_fee() {
+        // 1% fee
+        let pool = pool(10_000_000);
+        // 1 SOL, less 0.01 SOL fee, at 2^20 raw tokens per lamport
+        assert_eq!(pool.quote_exact_in(false, 1_000_000_000), 1_038_090_240_000_000);
+        // Selling those tokens back pays the fee again
+        assert_eq!(pool.quote_exact_in(true, 1_038_090_240_000_000), 980_100_000);
+        // The fee rounds up
+        assert_eq!(pool.quote_exact_in(false, 1), 0);
+        assert_eq!(DbcPool { fee_numerator: 0, ..pool.clone() }.quote_exact_in(false, 1), 1 << 20);
+
+        let expected = 2f64.powi(-20) * 1e-3;
+        assert!((pool.price() - expected).abs() < expected * 1e-12, "{}", pool.price());
+    }
+
+    #[test]
+    fn quote_without_a_price_is_zero() {
+        let pool = DbcPool { sqrt_price: 0, ..pool(0) };
+        assert_eq!(pool.quote_exact_in(false, 1_000_000_000), 0);
+        assert_eq!(pool.quote_exact_in(true, 1_000_000_000), 0);
+    }
+}
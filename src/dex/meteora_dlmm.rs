@@ -0,0 +1,517 @@
+use std::{str::FromStr, sync::Arc};
+
+use anchor_client::solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::RpcClient as BlockingRpcClient};
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signer::Signer,
+};
+use anyhow::{anyhow, Result};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account_idempotent,
+};
+use tokio::time::Instant;
+
+use super::common::{
+    find_sol_pairs, minimum_out, mint_infos, mint_program_and_extensions, read_pubkey, sell_amount,
+    token_account_amounts, wrap_sol,
+};
+use crate::common::config::SwapConfig;
+use crate::core::signer::SharedSigner;
+use crate::engine::swap::SwapDirection;
+
+pub const METEORA_DLMM_PROGRAM: &str = "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo";
+
+pub const SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+pub const SWAP2_DISCRIMINATOR: [u8; 8] = [65, 75, 63, 76, 235, 91, 91, 136];
+pub const SWAP_EXACT_OUT_DISCRIMINATOR: [u8; 8] = [250, 73, 101, 33, 38, 207, 75, 184];
+pub const SWAP_EXACT_OUT2_DISCRIMINATOR: [u8; 8] = [43, 215, 247, 132, 137, 60, 243, 81];
+pub const SWAP_WITH_PRICE_IMPACT_DISCRIMINATOR: [u8; 8] = [56, 173, 230, 208, 173, 228, 156, 205];
+pub const SWAP_WITH_PRICE_IMPACT2_DISCRIMINATOR: [u8; 8] = [74, 98, 192, 214, 177, 51, 75, 51];
+
+/// Size of an `LbPair` account.
+pub const LB_PAIR_SIZE: u64 = 904;
+/// Bins held by one bin array account.
+pub const MAX_BIN_PER_ARRAY: i32 = 70;
+/// Fee rates are expressed in billionths.
+pub const FEE_PRECISION: u128 = 1_000_000_000;
+const BASIS_POINT_MAX: f64 = 10_000.0;
+/// `LbPair::status` while swaps are enabled.
+const PAIR_STATUS_ENABLED: u8 = 0;
+/// Bin arrays passed past the active one, in the swap direction.
+const EXTRA_BIN_ARRAYS: i64 = 2;
+
+// LbPair: discriminator (8) | parameters (base_factor u16, filter_period, decay_period,
+// reduction_factor, variable_fee_control u32, max_volatility_accumulator u32, min/max_bin_id i32,
+// protocol_share u16, base_fee_power_factor u8, padding) | v_parameters (32) | bump_seed |
+// bin_step_seed | pair_type | active_id (i32) | bin_step (u16) | status | ... | token_x_mint |
+// token_y_mint | reserve_x | reserve_y | protocol_fee | padding | reward_infos | oracle | ...
+const PAIR_BASE_FACTOR_OFFSET: usize = 8;
+const PAIR_BASE_FEE_POWER_FACTOR_OFFSET: usize = 34;
+const PAIR_ACTIVE_ID_OFFSET: usize = 76;
+const PAIR_BIN_STEP_OFFSET: usize = 80;
+const PAIR_STATUS_OFFSET: usize = 82;
+const PAIR_TOKEN_X_MINT_OFFSET: usize = 88;
+const PAIR_TOKEN_Y_MINT_OFFSET: usize = 120;
+const PAIR_RESERVE_X_OFFSET: usize = 152;
+const PAIR_RESERVE_Y_OFFSET: usize = 184;
+const PAIR_ORACLE_OFFSET: usize = 552;
+
+/// A Meteora DLMM pair, decoded from its `LbPair` account. Mint details and
+/// reserve balances are filled in separately.
+#[derive(Debug, Clone)]
+pub struct DlmmPair {
+    pub pair_id: Pubkey,
+    pub base_factor: u16,
+    pub base_fee_power_factor: u8,
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub status: u8,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+    pub oracle: Pubkey,
+    pub token_x_program: Pubkey,
+    pub token_y_program: Pubkey,
+    pub token_x_decimals: u8,
+    pub token_y_decimals: u8,
+    pub reserve_x_amount: u64,
+    pub reserve_y_amount: u64,
+}
+
+impl DlmmPair {
+    pub fn decode(pair_id: Pubkey, data: &[u8]) -> Result<Self> {
+        let read = |offset: usize, len: usize| {
+            data.get(offset..offset + len)
+                .ok_or_else(|| anyhow!("pair {} is too short", pair_id))
+        };
+        Ok(Self {
+            pair_id,
+            base_factor: u16::from_le_bytes(read(PAIR_BASE_FACTOR_OFFSET, 2)?.try_into()?),
+            base_fee_power_factor: read(PAIR_BASE_FEE_POWER_FACTOR_OFFSET, 1)?[0],
+            active_id: i32::from_le_bytes(read(PAIR_ACTIVE_ID_OFFSET, 4)?.try_into()?),
+            bin_step: u16::from_le_bytes(read(PAIR_BIN_STEP_OFFSET, 2)?.try_into()?),
+            status: read(PAIR_STATUS_OFFSET, 1)?[0],
+            token_x_mint: read_pubkey(data, PAIR_TOKEN_X_MINT_OFFSET)?,
+            token_y_mint: read_pubkey(data, PAIR_TOKEN_Y_MINT_OFFSET)?,
+            reserve_x: read_pubkey(data, PAIR_RESERVE_X_OFFSET)?,
+            reserve_y: read_pubkey(data, PAIR_RESERVE_Y_OFFSET)?,
+            oracle: read_pubkey(data, PAIR_ORACLE_OFFSET)?,
+            token_x_program: spl_token::ID,
+            token_y_program: spl_token::ID,
+            token_x_decimals: 0,
+            token_y_decimals: 0,
+            reserve_x_amount: 0,
+            reserve_y_amount: 0,
+        })
+    }
+
+    pub fn is_trading(&self) -> bool {
+        self.status == PAIR_STATUS_ENABLED
+    }
+
+    /// Lamports on the WSOL side, used to pick the deepest pair.
+    pub fn sol_reserve(&self) -> u64 {
+        if self.token_x_mint == spl_token::native_mint::ID {
+            self.reserve_x_amount
+        } else {
+            self.reserve_y_amount
+        }
+    }
+
+    /// Base fee rate in billionths; the variable fee is left to slippage.
+    pub fn base_fee_rate(&self) -> u128 {
+        self.base_factor as u128 * self.bin_step as u128 * 10 * 10u128.pow(self.base_fee_power_factor as u32)
+    }
+
+    /// Price of the active bin in raw Y units per raw X unit.
+    fn raw_price(&self) -> f64 {
+        (1.0 + self.bin_step as f64 / BASIS_POINT_MAX).powi(self.active_id)
+    }
+
+    /// Spot price of `mint` in SOL per whole token.
+    pub fn price(&self, mint: &Pubkey) -> f64 {
+        let y_per_x = self.raw_price() * 10f64.powi(self.token_x_decimals as i32 - self.token_y_decimals as i32);
+        if *mint == self.token_x_mint {
+            y_per_x
+        } else if y_per_x > 0.0 {
+            1.0 / y_per_x
+        } else {
+            0.0
+        }
+    }
+
+    /// Output for `amount_in` at the active bin price, after the base fee.
+    /// Crossing into further bins is not modelled, so the slippage setting
+    /// has to cover it.
+    pub fn quote_exact_in(&self, swap_for_y: bool, amount_in: u64) -> u64 {
+        let fee = (amount_in as u128 * self.base_fee_rate()).div_ceil(FEE_PRECISION);
+        let amount_in = (amount_in as u128).saturating_sub(fee) as f64;
+        let price = self.raw_price();
+        let out = if swap_for_y {
+            amount_in * price
+        } else if price > 0.0 {
+            amount_in / price
+        } else {
+            0.0
+        };
+        out.min(u64::MAX as f64) as u64
+    }
+
+    /// Bin arrays a swap starting at the active bin may cross, active one first.
+    pub fn bin_array_addresses(&self, swap_for_y: bool) -> Result<Vec<Pubkey>> {
+        let program = Pubkey::from_str(METEORA_DLMM_PROGRAM)?;
+        let active_index = self.active_id.div_euclid(MAX_BIN_PER_ARRAY) as i64;
+        // Selling X for Y walks the price, and the bin ids, down
+        let step = if swap_for_y { -1 } else { 1 };
+        Ok((0..=EXTRA_BIN_ARRAYS)
+            .map(|offset| {
+                let index = active_index + offset * step;
+                Pubkey::find_program_address(
+                    &[b"bin_array", self.pair_id.as_ref(), &index.to_le_bytes()],
+                    &program,
+                )
+                .0
+            })
+            .collect())
+    }
+}
+
+// Mint details and reserve balances, in two round trips for any number of pairs.
+async fn fill_state(client: &RpcClient, pairs: &mut [DlmmPair]) -> Result<()> {
+    if pairs.is_empty() {
+        return Ok(());
+    }
+    let mints: Vec<Pubkey> = pairs.iter().flat_map(|pair| [pair.token_x_mint, pair.token_y_mint]).collect();
+    let mints = mint_infos(client, &mints).await?;
+    let reserves: Vec<Pubkey> = pairs.iter().flat_map(|pair| [pair.reserve_x, pair.reserve_y]).collect();
+    let amounts = token_account_amounts(client, &reserves).await?;
+    for ((pair, mints), amounts) in pairs.iter_mut().zip(mints.chunks(2)).zip(amounts.chunks(2)) {
+        (pair.token_x_program, pair.token_x_decimals) = mints[0];
+        (pair.token_y_program, pair.token_y_decimals) = mints[1];
+        pair.reserve_x_amount = amounts[0];
+        pair.reserve_y_amount = amounts[1];
+    }
+    Ok(())
+}
+
+/// The pair at `pair_id`, with its mint details and reserves.
+pub async fn get_pair(client: &RpcClient, pair_id: &Pubkey) -> Result<DlmmPair> {
+    let data = client
+        .get_account_data(pair_id)
+        .await
+        .map_err(|e| anyhow!("no Meteora DLMM pair at {}: {}", pair_id, e))?;
+    let mut pair = DlmmPair::decode(*pair_id, &data)?;
+    fill_state(client, std::slice::from_mut(&mut pair)).await?;
+    Ok(pair)
+}
+
+/// Enabled pairs of `mint` against WSOL, with their mint details and reserves.
+pub async fn find_pairs(client: &RpcClient, mint: &Pubkey) -> Result<Vec<DlmmPair>> {
+    let program = Pubkey::from_str(METEORA_DLMM_PROGRAM)?;
+    let mint_offsets = (PAIR_TOKEN_X_MINT_OFFSET, PAIR_TOKEN_Y_MINT_OFFSET);
+    let accounts = find_sol_pairs(client, &program, LB_PAIR_SIZE, mint_offsets, mint).await?;
+    let mut pairs: Vec<DlmmPair> = accounts
+        .iter()
+        .filter_map(|(pair_id, account)| DlmmPair::decode(*pair_id, &account.data).ok())
+        .filter(|pair| pair.is_trading())
+        .collect();
+    fill_state(client, &mut pairs).await?;
+    Ok(pairs)
+}
+
+/// The SOL pair of `mint` holding the most SOL.
+pub async fn deepest_pair(client: &RpcClient, mint: &Pubkey) -> Result<DlmmPair> {
+    find_pairs(client, mint)
+        .await?
+        .into_iter()
+        .max_by_key(|pair| pair.sol_reserve())
+        .ok_or_else(|| anyhow!("no Meteora DLMM pair for {}", mint))
+}
+
+pub struct MeteoraDlmm {
+    pub rpc_nonblocking_client: Arc<RpcClient>,
+    pub rpc_client: Arc<BlockingRpcClient>,
+    pub keypair: SharedSigner,
+}
+
+impl MeteoraDlmm {
+    pub fn new(
+        rpc_nonblocking_client: Arc<RpcClient>,
+        rpc_client: Arc<BlockingRpcClient>,
+        keypair: SharedSigner,
+    ) -> Self {
+        Self {
+            rpc_nonblocking_client,
+            rpc_client,
+            keypair,
+        }
+    }
+
+    /// Price of `mint` in its deepest SOL pair.
+    pub async fn get_token_price(&self, mint: &str) -> Result<f64> {
+        let mint = Pubkey::from_str(mint)?;
+        let pair = deepest_pair(&self.rpc_nonblocking_client, &mint).await?;
+        Ok(pair.price(&mint))
+    }
+
+    /// Builds a buy or sell of `mint` against its SOL pair. Buys spend
+    /// `amount_in` SOL; sells spend a quantity or a fraction of the wallet's tokens.
+    pub async fn build_swap_ixn_by_mint(
+        &self,
+        mint: &str,
+        pair: Option<DlmmPair>,
+        swap_config: SwapConfig,
+        _start_time: Instant,
+    ) -> Result<(SharedSigner, Vec<Instruction>, f64)> {
+        let mint = Pubkey::from_str(mint)?;
+        let pair = match pair {
+            Some(pair) => pair,
+            None => deepest_pair(&self.rpc_nonblocking_client, &mint).await?,
+        };
+        if !pair.is_trading() {
+            return Err(anyhow!("Meteora DLMM pair {} does not accept swaps (status {})", pair.pair_id, pair.status));
+        }
+        let mint_is_x = mint == pair.token_x_mint;
+        let token_decimals = if mint_is_x { pair.token_x_decimals } else { pair.token_y_decimals };
+        // Token-2022 mints may withhold a transfer fee on the way in and out of the pair
+        let (token_program, extensions) =
+            mint_program_and_extensions(&self.rpc_nonblocking_client, &self.keypair, &mint).await?;
+        let wsol = spl_token::native_mint::ID;
+
+        let owner = self.keypair.pubkey();
+        let token_ata = get_associated_token_address_with_program_id(&owner, &mint, &token_program);
+        let wsol_ata = get_associated_token_address_with_program_id(&owner, &wsol, &spl_token::ID);
+
+        let mut instructions = Vec::new();
+        let mut close_token = false;
+        let swap_ix = match swap_config.swap_direction {
+            SwapDirection::Buy => {
+                let sol_in = (swap_config.amount_in * LAMPORTS_PER_SOL as f64) as u64;
+                let swap_for_y = !mint_is_x;
+                let token_out = pair.quote_exact_in(swap_for_y, sol_in);
+                let minimum_out = minimum_out(token_out - extensions.transfer_fee_on(token_out), swap_config.slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &mint, &token_program));
+                instructions.extend(wrap_sol(&owner, &wsol_ata, sol_in)?);
+                self.swap_instruction(&pair, &owner, swap_for_y, wsol_ata, token_ata, sol_in, minimum_out).await?
+            }
+            SwapDirection::Sell => {
+                let (token_in, whole_balance) =
+                    sell_amount(&self.rpc_nonblocking_client, &token_ata, &swap_config, token_decimals).await?;
+                close_token = whole_balance;
+                let swap_for_y = mint_is_x;
+                let token_received = token_in - extensions.transfer_fee_on(token_in);
+                let minimum_out = minimum_out(pair.quote_exact_in(swap_for_y, token_received), swap_config.slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &wsol, &spl_token::ID));
+                self.swap_instruction(&pair, &owner, swap_for_y, token_ata, wsol_ata, token_in, minimum_out).await?
+            }
+        };
+        instructions.push(swap_ix);
+        instructions.push(spl_token::instruction::close_account(&spl_token::ID, &wsol_ata, &owner, &owner, &[])?);
+        if close_token {
+            instructions.push(spl_token_2022::instruction::close_account(&token_program, &token_ata, &owner, &owner, &[])?);
+        }
+
+        Ok((self.keypair.clone(), instructions, pair.price(&mint)))
+    }
+
+    // Passes the bin arrays that exist along the swap direction, and the
+    // bitmap extension when the pair has one.
+    #[allow(clippy::too_many_arguments)]
+    async fn swap_instruction(
+        &self,
+        pair: &DlmmPair,
+        owner: &Pubkey,
+        swap_for_y: bool,
+        user_token_in: Pubkey,
+        user_token_out: Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<Instruction> {
+        let program = Pubkey::from_str(METEORA_DLMM_PROGRAM)?;
+        let (event_authority, _) = Pubkey::find_program_address(&[b"__event_authority"], &program);
+        let (bitmap_extension, _) = Pubkey::find_program_address(&[b"bitmap", pair.pair_id.as_ref()], &program);
+
+        let mut lookups = pair.bin_array_addresses(swap_for_y)?;
+        lookups.push(bitmap_extension);
+        let accounts = self.rpc_nonblocking_client.get_multiple_accounts(&lookups).await?;
+        let bitmap_extension = match accounts.last() {
+            Some(Some(_)) => bitmap_extension,
+            _ => program,
+        };
+        let bin_arrays: Vec<Pubkey> = lookups
+            .iter()
+            .zip(accounts.iter())
+            .take(lookups.len() - 1)
+            .filter(|(_, account)| account.is_some())
+            .map(|(address, _)| *address)
+            .collect();
+        if bin_arrays.is_empty() {
+            return Err(anyhow!("no initialized bin arrays around the active bin of {}", pair.pair_id));
+        }
+
+        let mut data = Vec::with_capacity(24);
+        data.extend_from_slice(&SWAP_DISCRIMINATOR);
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+
+        let mut accounts = vec![
+            AccountMeta::new(pair.pair_id, false),
+            AccountMeta::new_readonly(bitmap_extension, false),
+            AccountMeta::new(pair.reserve_x, false),
+            AccountMeta::new(pair.reserve_y, false),
+            AccountMeta::new(user_token_in, false),
+            AccountMeta::new(user_token_out, false),
+            AccountMeta::new_readonly(pair.token_x_mint, false),
+            AccountMeta::new_readonly(pair.token_y_mint, false),
+            AccountMeta::new(pair.oracle, false),
+            // No host fee account
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(pair.token_x_program, false),
+            AccountMeta::new_readonly(pair.token_y_program, false),
+            AccountMeta::new_readonly(event_authority, false),
+            AccountMeta::new_readonly(program, false),
+        ];
+        accounts.extend(bin_arrays.into_iter().map(|bin_array| AccountMeta::new(bin_array, false)));
+
+        Ok(Instruction {
+            program_id: program,
+            accounts,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::hash::hashv;
+
+    const TOKEN_MINT: &str = "2zMMhcVQEXDtdE6vsFS7S7D5oUodfJHE8vd1gnBouauv";
+
+    fn anchor_discriminator(preimage: &str) -> [u8; 8] {
+        hashv(&[preimage.as_bytes()]).to_bytes()[..8].try_into().unwrap()
+    }
+
+    /// An `LbPair` account of the token (X) against WSOL (Y) with 1% bins and
+    /// each field the decoder reads set to a distinct value.
+    fn pair_data(active_id: i32, status: u8, reserves: [Pubkey; 2], oracle: Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; LB_PAIR_SIZE as usize];
+        data[..8].copy_from_slice(&[33, 11, 49, 98, 181, 101, 177, 13]);
+        data[PAIR_BASE_FACTOR_OFFSET..PAIR_BASE_FACTOR_OFFSET + 2].copy_from_slice(&10_000u16.to_le_bytes());
+        // filter_period, the parameter after base_factor
+        data[10..12].copy_from_slice(&30u16.to_le_bytes());
+        data[PAIR_BASE_FEE_POWER_FACTOR_OFFSET] = 0;
+        data[PAIR_ACTIVE_ID_OFFSET..PAIR_ACTIVE_ID_OFFSET + 4].copy_from_slice(&active_id.to_le_bytes());
+        data[PAIR_BIN_STEP_OFFSET..PAIR_BIN_STEP_OFFSET + 2].copy_from_slice(&100u16.to_le_bytes());
+        data[PAIR_STATUS_OFFSET] = status;
+        let mints = [Pubkey::from_str(TOKEN_MINT).unwrap(), spl_token::native_mint::ID];
+        for (offset, key) in [PAIR_TOKEN_X_MINT_OFFSET, PAIR_TOKEN_Y_MINT_OFFSET, PAIR_RESERVE_X_OFFSET, PAIR_RESERVE_Y_OFFSET]
+            .into_iter()
+            .zip(mints.iter().chain(reserves.iter()))
+        {
+            data[offset..offset + 32].copy_from_slice(key.as_ref());
+        }
+        data[PAIR_ORACLE_OFFSET..PAIR_ORACLE_OFFSET + 32].copy_from_slice(oracle.as_ref());
+        data
+    }
+
+    fn pair(active_id: i32) -> DlmmPair {
+        let data = pair_data(active_id, PAIR_STATUS_ENABLED, [Pubkey::new_unique(), Pubkey::new_unique()], Pubkey::new_unique());
+        DlmmPair {
+            token_x_decimals: 6,
+            token_y_decimals: 9,
+            ..DlmmPair::decode(Pubkey::new_unique(), &data).unwrap()
+        }
+    }
+
+    fn bin_array(pair: &DlmmPair, index: i64) -> Pubkey {
+        let program = Pubkey::from_str(METEORA_DLMM_PROGRAM).unwrap();
+        Pubkey::find_program_address(&[b"bin_array", pair.pair_id.as_ref(), &index.to_le_bytes()], &program).0
+    }
+
+    #[test]
+    fn discriminators_match_the_anchor_names() {
+        for (discriminator, name) in [
+            (SWAP_DISCRIMINATOR, "swap"),
+            (SWAP2_DISCRIMINATOR, "swap2"),
+            (SWAP_EXACT_OUT_DISCRIMINATOR, "swap_exact_out"),
+            (SWAP_EXACT_OUT2_DISCRIMINATOR, "swap_exact_out2"),
+            (SWAP_WITH_PRICE_IMPACT_DISCRIMINATOR, "swap_with_price_impact"),
+            (SWAP_WITH_PRICE_IMPACT2_DISCRIMINATOR, "swap_with_price_impact2"),
+        ] {
+            assert_eq!(discriminator, anchor_discriminator(&format!("global:{}", name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn decodes_an_lb_pair() {
+        let (pair_id, reserves, oracle) = (Pubkey::new_unique(), [Pubkey::new_unique(), Pubkey::new_unique()], Pubkey::new_unique());
+        let data = pair_data(-443, PAIR_STATUS_ENABLED, reserves, oracle);
+        assert_eq!(data[..8], anchor_discriminator("account:LbPair"));
+
+        let pair = DlmmPair::decode(pair_id, &data).unwrap();
+        assert_eq!(pair.pair_id, pair_id);
+        assert_eq!(pair.base_factor, 10_000);
+        assert_eq!(pair.base_fee_power_factor, 0);
+        assert_eq!(pair.active_id, -443);
+        assert_eq!(pair.bin_step, 100);
+        assert_eq!(pair.token_x_mint, Pubkey::from_str(TOKEN_MINT).unwrap());
+        assert_eq!(pair.token_y_mint, spl_token::native_mint::ID);
+        assert_eq!([pair.reserve_x, pair.reserve_y], reserves);
+        assert_eq!(pair.oracle, oracle);
+        assert!(pair.is_trading());
+        // 10_000 * 100 bps * 10 in billionths, i.e. 1%
+        assert_eq!(pair.base_fee_rate(), 10_000_000);
+
+        let disabled = DlmmPair::decode(pair_id, &pair_data(0, 1, reserves, oracle)).unwrap();
+        assert!(!disabled.is_trading());
+        assert!(DlmmPair::decode(pair_id, &data[..PAIR_ORACLE_OFFSET + 31]).is_err());
+    }
+
+    #[test]
+    fn sol_reserve_reads_the_wsol_side() {
+        let mut pair = pair(0);
+        pair.reserve_x_amount = 7;
+        pair.reserve_y_amount = 9;
+        assert_eq!(pair.sol_reserve(), 9);
+        std::mem::swap(&mut pair.token_x_mint, &mut pair.token_y_mint);
+        assert_eq!(pair.sol_reserve(), 7);
+    }
+
+    #[test]
+    fn quotes_at_the_active_bin_after_the_fee() {
+        let flat = pair(0);
+        assert_eq!(flat.quote_exact_in(true, 1_000_000), 990_000);
+        assert_eq!(flat.quote_exact_in(false, 1_000_000), 990_000);
+
+        // One bin array up, 1.01^70 lamports per raw token
+        let pair = pair(70);
+        assert_eq!(pair.quote_exact_in(true, 1_000_000), 1_986_695);
+        assert_eq!(pair.quote_exact_in(false, 1_000_000), 493_331);
+
+        let mint = Pubkey::from_str(TOKEN_MINT).unwrap();
+        let expected = 1.01f64.powi(70) * 1e-3;
+        assert!((pair.price(&mint) - expected).abs() < expected * 1e-12, "{}", pair.price(&mint));
+        assert!((pair.price(&spl_token::native_mint::ID) - 1.0 / expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bin_arrays_follow_the_swap_direction() {
+        // Bin 69 is the last of array 0
+        let pair = pair(69);
+        let down = pair.bin_array_addresses(true).unwrap();
+        let up = pair.bin_array_addresses(false).unwrap();
+        assert_eq!(down, vec![bin_array(&pair, 0), bin_array(&pair, -1), bin_array(&pair, -2)]);
+        assert_eq!(up, vec![bin_array(&pair, 0), bin_array(&pair, 1), bin_array(&pair, 2)]);
+
+        // Negative bins round down to the array below zero
+        let pair = DlmmPair { active_id: -1, ..pair };
+        assert_eq!(pair.bin_array_addresses(false).unwrap()[0], bin_array(&pair, -1));
+        let pair = DlmmPair { active_id: -71, ..pair };
+        assert_eq!(pair.bin_array_addresses(true).unwrap(), vec![bin_array(&pair, -2), bin_array(&pair, -3), bin_array(&pair, -4)]);
+    }
+}
@@ -1,2 +1,11 @@
+pub mod common;
+pub mod meteora_dbc;
+pub mod meteora_dlmm;
+pub mod pump_events;
 pub mod pump_fun;
+pub mod pump_instructions;
+pub mod pump_quote;
 pub mod pump_swap;
+pub mod raydium_amm;
+pub mod raydium_cpmm;
+pub mod raydium_launchpad;
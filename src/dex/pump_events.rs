@@ -0,0 +1,338 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use borsh::BorshDeserialize;
+
+/// Prefix of the logs Anchor's `emit!` writes events to.
+const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+/// Leading bytes of the self-CPI instructions `emit_cpi!` carries events in.
+pub const EVENT_IX_TAG: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+
+// sha256("event:<Name>")[..8]
+pub const CREATE_EVENT_DISCRIMINATOR: [u8; 8] = [27, 114, 169, 77, 222, 235, 99, 118];
+pub const TRADE_EVENT_DISCRIMINATOR: [u8; 8] = [189, 219, 127, 211, 78, 230, 97, 238];
+pub const BUY_EVENT_DISCRIMINATOR: [u8; 8] = [103, 244, 82, 31, 44, 245, 119, 119];
+pub const SELL_EVENT_DISCRIMINATOR: [u8; 8] = [62, 47, 55, 10, 165, 3, 220, 42];
+
+/// PumpFun `CreateEvent`, emitted when a token and its bonding curve are created.
+/// The reserves are the curve's before any buy bundled with the create.
+#[derive(BorshDeserialize, Clone, Debug)]
+pub struct CreateEvent {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub user: Pubkey,
+    pub creator: Pubkey,
+    pub timestamp: i64,
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub token_total_supply: u64,
+}
+
+/// PumpFun `TradeEvent`, emitted by every buy and sell on a bonding curve.
+/// The reserves are the curve's after the trade.
+#[derive(BorshDeserialize, Clone, Debug)]
+pub struct TradeEvent {
+    pub mint: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub is_buy: bool,
+    pub user: Pubkey,
+    pub timestamp: i64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub fee_recipient: Pubkey,
+    pub fee_basis_points: u64,
+    pub fee: u64,
+    pub creator: Pubkey,
+    pub creator_fee_basis_points: u64,
+    pub creator_fee: u64,
+}
+
+/// PumpSwap `BuyEvent`. The pool and user reserves are the ones before the trade.
+#[derive(BorshDeserialize, Clone, Debug)]
+pub struct BuyEvent {
+    pub timestamp: i64,
+    pub base_amount_out: u64,
+    pub max_quote_amount_in: u64,
+    pub user_base_token_reserves: u64,
+    pub user_quote_token_reserves: u64,
+    pub pool_base_token_reserves: u64,
+    pub pool_quote_token_reserves: u64,
+    pub quote_amount_in: u64,
+    pub lp_fee_basis_points: u64,
+    pub lp_fee: u64,
+    pub protocol_fee_basis_points: u64,
+    pub protocol_fee: u64,
+    pub quote_amount_in_with_lp_fee: u64,
+    pub user_quote_amount_in: u64,
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub user_base_token_account: Pubkey,
+    pub user_quote_token_account: Pubkey,
+    pub protocol_fee_recipient: Pubkey,
+    pub protocol_fee_recipient_token_account: Pubkey,
+    pub coin_creator: Pubkey,
+    pub coin_creator_fee_basis_points: u64,
+    pub coin_creator_fee: u64,
+}
+
+/// PumpSwap `SellEvent`. The pool and user reserves are the ones before the trade.
+#[derive(BorshDeserialize, Clone, Debug)]
+pub struct SellEvent {
+    pub timestamp: i64,
+    pub base_amount_in: u64,
+    pub min_quote_amount_out: u64,
+    pub user_base_token_reserves: u64,
+    pub user_quote_token_reserves: u64,
+    pub pool_base_token_reserves: u64,
+    pub pool_quote_token_reserves: u64,
+    pub quote_amount_out: u64,
+    pub lp_fee_basis_points: u64,
+    pub lp_fee: u64,
+    pub protocol_fee_basis_points: u64,
+    pub protocol_fee: u64,
+    pub quote_amount_out_without_lp_fee: u64,
+    pub user_quote_amount_out: u64,
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub user_base_token_account: Pubkey,
+    pub user_quote_token_account: Pubkey,
+    pub protocol_fee_recipient: Pubkey,
+    pub protocol_fee_recipient_token_account: Pubkey,
+    pub coin_creator: Pubkey,
+    pub coin_creator_fee_basis_points: u64,
+    pub coin_creator_fee: u64,
+}
+
+#[derive(Clone, Debug)]
+pub enum PumpEvent {
+    Create(CreateEvent),
+    Trade(TradeEvent),
+    Buy(BuyEvent),
+    Sell(SellEvent),
+}
+
+impl PumpEvent {
+    /// Decodes a discriminator-prefixed event. Newer program versions append
+    /// fields, so trailing bytes are ignored.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let (discriminator, mut body) = data.split_first_chunk::<8>()?;
+        match *discriminator {
+            CREATE_EVENT_DISCRIMINATOR => CreateEvent::deserialize(&mut body).ok().map(Self::Create),
+            TRADE_EVENT_DISCRIMINATOR => TradeEvent::deserialize(&mut body).ok().map(Self::Trade),
+            BUY_EVENT_DISCRIMINATOR => BuyEvent::deserialize(&mut body).ok().map(Self::Buy),
+            SELL_EVENT_DISCRIMINATOR => SellEvent::deserialize(&mut body).ok().map(Self::Sell),
+            _ => None,
+        }
+    }
+
+    /// Decodes the event carried by an `emit_cpi!` instruction.
+    pub fn decode_cpi(data: &[u8]) -> Option<Self> {
+        data.strip_prefix(EVENT_IX_TAG.as_slice()).and_then(Self::decode)
+    }
+}
+
+/// Events a transaction logged, in log order.
+pub fn parse_logs(log_messages: &[String]) -> Vec<PumpEvent> {
+    log_messages
+        .iter()
+        .filter_map(|log| log.strip_prefix(PROGRAM_DATA_LOG_PREFIX))
+        .filter_map(|encoded| base64::decode(encoded).ok())
+        .filter_map(|data| PumpEvent::decode(&data))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::hash::hashv;
+    use std::str::FromStr;
+
+    const MINT: &str = "2zMMhcVQEXDtdE6vsFS7S7D5oUodfJHE8vd1gnBouauv";
+    const USER: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+
+    fn discriminator(preimage: &str) -> [u8; 8] {
+        hashv(&[preimage.as_bytes()]).to_bytes()[..8].try_into().unwrap()
+    }
+
+    /// Borsh encoding of an event, field by field as the program writes it.
+    #[derive(Default)]
+    struct Encoder(Vec<u8>);
+
+    impl Encoder {
+        fn event(name: &str) -> Self {
+            Self(discriminator(&format!("event:{}", name)).to_vec())
+        }
+        fn string(mut self, value: &str) -> Self {
+            self.0.extend((value.len() as u32).to_le_bytes());
+            self.0.extend(value.as_bytes());
+            self
+        }
+        fn key(mut self, value: &str) -> Self {
+            self.0.extend(Pubkey::from_str(value).unwrap().to_bytes());
+            self
+        }
+        fn u64(mut self, value: u64) -> Self {
+            self.0.extend(value.to_le_bytes());
+            self
+        }
+        fn i64(mut self, value: i64) -> Self {
+            self.0.extend(value.to_le_bytes());
+            self
+        }
+        fn bool(mut self, value: bool) -> Self {
+            self.0.push(value as u8);
+            self
+        }
+    }
+
+    // A buy of 1 SOL on a fresh curve, with its protocol and creator fees
+    fn trade_event() -> Encoder {
+        Encoder::event("TradeEvent")
+            .key(MINT)
+            .u64(1_000_000_000)
+            .u64(34_612_903_225_806)
+            .bool(true)
+            .key(USER)
+            .i64(1_717_200_000)
+            .u64(31_000_000_000)
+            .u64(1_038_387_096_774_194)
+            .u64(1_000_000_000)
+            .u64(758_487_096_774_194)
+            .key(USER)
+            .u64(95)
+            .u64(9_500_000)
+            .key(MINT)
+            .u64(5)
+            .u64(500_000)
+    }
+
+    fn pump_swap_event(name: &str, amounts: [u64; 13]) -> Encoder {
+        let encoder = amounts.iter().fold(Encoder::event(name).i64(1_717_200_000), |encoder, amount| encoder.u64(*amount));
+        (0..7).fold(encoder, |encoder, _| encoder.key(USER)).u64(5).u64(500)
+    }
+
+    fn program_data(encoder: &Encoder) -> String {
+        format!("Program data: {}", base64::encode(&encoder.0))
+    }
+
+    #[test]
+    fn discriminators_match_the_anchor_names() {
+        assert_eq!(CREATE_EVENT_DISCRIMINATOR, discriminator("event:CreateEvent"));
+        assert_eq!(TRADE_EVENT_DISCRIMINATOR, discriminator("event:TradeEvent"));
+        assert_eq!(BUY_EVENT_DISCRIMINATOR, discriminator("event:BuyEvent"));
+        assert_eq!(SELL_EVENT_DISCRIMINATOR, discriminator("event:SellEvent"));
+        // Anchor writes the tag as a little-endian u64 of the hash prefix
+        let mut tag = discriminator("anchor:event");
+        tag.reverse();
+        assert_eq!(EVENT_IX_TAG, tag);
+    }
+
+    #[test]
+    fn decodes_a_trade_event() {
+        let Some(PumpEvent::Trade(event)) = PumpEvent::decode(&trade_event().0) else {
+            panic!("not a trade event");
+        };
+        assert_eq!(event.mint.to_string(), MINT);
+        assert_eq!((event.sol_amount, event.token_amount, event.is_buy), (1_000_000_000, 34_612_903_225_806, true));
+        assert_eq!(event.user.to_string(), USER);
+        assert_eq!(event.timestamp, 1_717_200_000);
+        assert_eq!((event.virtual_sol_reserves, event.virtual_token_reserves), (31_000_000_000, 1_038_387_096_774_194));
+        assert_eq!((event.real_sol_reserves, event.real_token_reserves), (1_000_000_000, 758_487_096_774_194));
+        assert_eq!((event.fee_basis_points, event.fee), (95, 9_500_000));
+        assert_eq!(event.creator.to_string(), MINT);
+        assert_eq!((event.creator_fee_basis_points, event.creator_fee), (5, 500_000));
+    }
+
+    #[test]
+    fn ignores_fields_appended_by_newer_programs() {
+        // Volume tracking fields and the instruction name, as later program versions add them
+        let extended = trade_event().bool(true).u64(1_000_000_000).u64(2_000_000_000).u64(86_400).string("buy");
+        let Some(PumpEvent::Trade(event)) = PumpEvent::decode(&extended.0) else {
+            panic!("not a trade event");
+        };
+        assert_eq!(event.creator_fee, 500_000);
+    }
+
+    #[test]
+    fn decodes_a_create_event() {
+        let create = Encoder::event("CreateEvent")
+            .string("Token")
+            .string("TKN")
+            .string("https://example.com/token.json")
+            .key(MINT)
+            .key(USER)
+            .key(USER)
+            .key(MINT)
+            .i64(1_717_200_000)
+            .u64(1_073_000_000_000_000)
+            .u64(30_000_000_000)
+            .u64(793_100_000_000_000)
+            .u64(1_000_000_000_000_000);
+        let Some(PumpEvent::Create(event)) = PumpEvent::decode(&create.0) else {
+            panic!("not a create event");
+        };
+        assert_eq!((event.name.as_str(), event.symbol.as_str()), ("Token", "TKN"));
+        assert_eq!(event.uri, "https://example.com/token.json");
+        assert_eq!(event.mint.to_string(), MINT);
+        assert_eq!(event.bonding_curve.to_string(), USER);
+        assert_eq!(event.creator.to_string(), MINT);
+        assert_eq!((event.virtual_token_reserves, event.virtual_sol_reserves), (1_073_000_000_000_000, 30_000_000_000));
+        assert_eq!((event.real_token_reserves, event.token_total_supply), (793_100_000_000_000, 1_000_000_000_000_000));
+    }
+
+    #[test]
+    fn decodes_pump_swap_buy_and_sell_events() {
+        let amounts = [10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22];
+        let Some(PumpEvent::Buy(buy)) = PumpEvent::decode(&pump_swap_event("BuyEvent", amounts).0) else {
+            panic!("not a buy event");
+        };
+        assert_eq!((buy.base_amount_out, buy.max_quote_amount_in), (10, 11));
+        assert_eq!((buy.pool_base_token_reserves, buy.pool_quote_token_reserves), (14, 15));
+        assert_eq!((buy.quote_amount_in, buy.user_quote_amount_in), (16, 22));
+        assert_eq!((buy.coin_creator_fee_basis_points, buy.coin_creator_fee), (5, 500));
+
+        let Some(PumpEvent::Sell(sell)) = PumpEvent::decode(&pump_swap_event("SellEvent", amounts).0) else {
+            panic!("not a sell event");
+        };
+        assert_eq!((sell.base_amount_in, sell.min_quote_amount_out), (10, 11));
+        assert_eq!((sell.quote_amount_out, sell.user_quote_amount_out), (16, 22));
+        assert_eq!(sell.pool.to_string(), USER);
+    }
+
+    #[test]
+    fn rejects_unknown_and_truncated_events() {
+        let data = trade_event().0;
+        assert!(PumpEvent::decode(&data[..data.len() - 1]).is_none());
+        assert!(PumpEvent::decode(&data[..7]).is_none());
+        let mut unknown = data.clone();
+        unknown[0] ^= 1;
+        assert!(PumpEvent::decode(&unknown).is_none());
+    }
+
+    #[test]
+    fn decodes_events_from_logs_and_self_cpis() {
+        let logs = vec![
+            "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P invoke [1]".to_string(),
+            "Program log: Instruction: Buy".to_string(),
+            program_data(&trade_event()),
+            // Another program's event is skipped, as is data that is not base64
+            program_data(&Encoder::event("SwapEvent").u64(1)),
+            "Program data: not base64!".to_string(),
+            program_data(&pump_swap_event("SellEvent", [1; 13])),
+            "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P success".to_string(),
+        ];
+        let events = parse_logs(&logs);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], PumpEvent::Trade(_)));
+        assert!(matches!(events[1], PumpEvent::Sell(_)));
+
+        let cpi = [EVENT_IX_TAG.to_vec(), trade_event().0].concat();
+        assert!(matches!(PumpEvent::decode_cpi(&cpi), Some(PumpEvent::Trade(_))));
+        assert!(PumpEvent::decode_cpi(&trade_event().0).is_none());
+    }
+}
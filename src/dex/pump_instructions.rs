@@ -0,0 +1,358 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use borsh::BorshDeserialize;
+
+// PumpFun and PumpSwap share the `buy` and `sell` names, hence the discriminators
+pub const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+pub const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+pub const CREATE_DISCRIMINATOR: [u8; 8] = [24, 30, 200, 40, 5, 28, 7, 119];
+/// Creates a Token-2022 mint; `create` makes SPL Token ones.
+pub const CREATE_V2_DISCRIMINATOR: [u8; 8] = [214, 144, 76, 236, 95, 139, 49, 180];
+pub const BUY_EXACT_SOL_IN_DISCRIMINATOR: [u8; 8] = [56, 252, 116, 8, 158, 223, 205, 95];
+pub const BUY_EXACT_QUOTE_IN_DISCRIMINATOR: [u8; 8] = [198, 46, 21, 82, 180, 217, 232, 112];
+
+// Account names in IDL order
+const PUMP_FUN_CREATE_ACCOUNTS: &[&str] = &[
+    "mint",
+    "mint_authority",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "global",
+    "mpl_token_metadata",
+    "metadata",
+    "user",
+    "system_program",
+    "token_program",
+    "associated_token_program",
+    "rent",
+    "event_authority",
+    "program",
+];
+const PUMP_FUN_CREATE_V2_ACCOUNTS: &[&str] = &[
+    "mint",
+    "mint_authority",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "global",
+    "user",
+    "system_program",
+    "token_program",
+    "associated_token_program",
+    "mayhem_program_id",
+    "global_params",
+    "sol_vault",
+    "mayhem_state",
+    "mayhem_token_vault",
+    "event_authority",
+    "program",
+];
+const PUMP_FUN_BUY_ACCOUNTS: &[&str] = &[
+    "global",
+    "fee_recipient",
+    "mint",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "associated_user",
+    "user",
+    "system_program",
+    "token_program",
+    "creator_vault",
+    "event_authority",
+    "program",
+];
+const PUMP_FUN_SELL_ACCOUNTS: &[&str] = &[
+    "global",
+    "fee_recipient",
+    "mint",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "associated_user",
+    "user",
+    "system_program",
+    "creator_vault",
+    "token_program",
+    "event_authority",
+    "program",
+];
+const PUMP_SWAP_TRADE_ACCOUNTS: &[&str] = &[
+    "pool",
+    "user",
+    "global_config",
+    "base_mint",
+    "quote_mint",
+    "user_base_token_account",
+    "user_quote_token_account",
+    "pool_base_token_account",
+    "pool_quote_token_account",
+    "protocol_fee_recipient",
+    "protocol_fee_recipient_token_account",
+    "base_token_program",
+    "quote_token_program",
+    "system_program",
+    "associated_token_program",
+    "event_authority",
+    "program",
+    "coin_creator_vault_ata",
+    "coin_creator_vault_authority",
+];
+
+/// Accounts of a decoded instruction, looked up by their IDL name.
+#[derive(Clone, Debug)]
+pub struct IxAccounts {
+    names: &'static [&'static str],
+    keys: Vec<Pubkey>,
+}
+
+impl IxAccounts {
+    fn new(names: &'static [&'static str], keys: Vec<Pubkey>) -> Self {
+        Self { names, keys }
+    }
+
+    /// The account passed as `name`, or the default key if the instruction
+    /// was built with fewer accounts.
+    pub fn get(&self, name: &str) -> Pubkey {
+        self.names
+            .iter()
+            .position(|account| *account == name)
+            .and_then(|index| self.keys.get(index))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(BorshDeserialize, Clone, Debug)]
+pub struct CreateArgs {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub creator: Pubkey,
+}
+
+/// A PumpFun or PumpSwap instruction with its arguments. Buys are read as the
+/// token amount wanted and the most SOL spent, sells as the tokens sold and
+/// the least SOL accepted, whichever side the instruction fixes.
+#[derive(Clone, Debug)]
+pub enum PumpInstruction {
+    PumpFunCreate { args: CreateArgs, accounts: IxAccounts },
+    PumpFunBuy { amount: u64, max_sol_cost: u64, accounts: IxAccounts },
+    PumpFunSell { amount: u64, min_sol_output: u64, accounts: IxAccounts },
+    PumpSwapBuy { base_amount_out: u64, max_quote_amount_in: u64, accounts: IxAccounts },
+    PumpSwapSell { base_amount_in: u64, min_quote_amount_out: u64, accounts: IxAccounts },
+}
+
+impl PumpInstruction {
+    /// Decodes a PumpFun instruction. Arguments newer program versions
+    /// append, such as `track_volume`, are ignored.
+    pub fn decode_pump_fun(data: &[u8], keys: Vec<Pubkey>) -> Option<Self> {
+        let (discriminator, body) = data.split_first_chunk::<8>()?;
+        let amounts = || <(u64, u64)>::deserialize(&mut &body[..]).ok();
+        match *discriminator {
+            CREATE_DISCRIMINATOR => Some(Self::PumpFunCreate {
+                args: CreateArgs::deserialize(&mut &body[..]).ok()?,
+                accounts: IxAccounts::new(PUMP_FUN_CREATE_ACCOUNTS, keys),
+            }),
+            CREATE_V2_DISCRIMINATOR => Some(Self::PumpFunCreate {
+                args: CreateArgs::deserialize(&mut &body[..]).ok()?,
+                accounts: IxAccounts::new(PUMP_FUN_CREATE_V2_ACCOUNTS, keys),
+            }),
+            BUY_DISCRIMINATOR => {
+                let (amount, max_sol_cost) = amounts()?;
+                Some(Self::PumpFunBuy { amount, max_sol_cost, accounts: IxAccounts::new(PUMP_FUN_BUY_ACCOUNTS, keys) })
+            }
+            BUY_EXACT_SOL_IN_DISCRIMINATOR => {
+                let (spendable_sol_in, min_tokens_out) = amounts()?;
+                Some(Self::PumpFunBuy {
+                    amount: min_tokens_out,
+                    max_sol_cost: spendable_sol_in,
+                    accounts: IxAccounts::new(PUMP_FUN_BUY_ACCOUNTS, keys),
+                })
+            }
+            SELL_DISCRIMINATOR => {
+                let (amount, min_sol_output) = amounts()?;
+                Some(Self::PumpFunSell { amount, min_sol_output, accounts: IxAccounts::new(PUMP_FUN_SELL_ACCOUNTS, keys) })
+            }
+            _ => None,
+        }
+    }
+
+    /// Decodes a PumpSwap buy or sell.
+    pub fn decode_pump_swap(data: &[u8], keys: Vec<Pubkey>) -> Option<Self> {
+        let (discriminator, mut body) = data.split_first_chunk::<8>()?;
+        let (first, second) = <(u64, u64)>::deserialize(&mut body).ok()?;
+        let accounts = IxAccounts::new(PUMP_SWAP_TRADE_ACCOUNTS, keys);
+        match *discriminator {
+            BUY_DISCRIMINATOR => Some(Self::PumpSwapBuy { base_amount_out: first, max_quote_amount_in: second, accounts }),
+            // (spendable_quote_in, min_base_amount_out)
+            BUY_EXACT_QUOTE_IN_DISCRIMINATOR => {
+                Some(Self::PumpSwapBuy { base_amount_out: second, max_quote_amount_in: first, accounts })
+            }
+            SELL_DISCRIMINATOR => Some(Self::PumpSwapSell { base_amount_in: first, min_quote_amount_out: second, accounts }),
+            _ => None,
+        }
+    }
+
+    pub fn accounts(&self) -> &IxAccounts {
+        match self {
+            Self::PumpFunCreate { accounts, .. }
+            | Self::PumpFunBuy { accounts, .. }
+            | Self::PumpFunSell { accounts, .. }
+            | Self::PumpSwapBuy { accounts, .. }
+            | Self::PumpSwapSell { accounts, .. } => accounts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::hash::hashv;
+
+    fn discriminator(name: &str) -> [u8; 8] {
+        hashv(&[format!("global:{}", name).as_bytes()]).to_bytes()[..8].try_into().unwrap()
+    }
+
+    /// Instruction data as the program's client encodes it.
+    fn data(name: &str, args: &[&[u8]]) -> Vec<u8> {
+        [&discriminator(name)[..]].iter().chain(args).flat_map(|bytes| bytes.iter().copied()).collect()
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        [&(value.len() as u32).to_le_bytes()[..], value.as_bytes()].concat()
+    }
+
+    fn keys(count: usize) -> Vec<Pubkey> {
+        (0..count).map(|_| Pubkey::new_unique()).collect()
+    }
+
+    #[test]
+    fn discriminators_match_the_anchor_names() {
+        assert_eq!(BUY_DISCRIMINATOR, discriminator("buy"));
+        assert_eq!(SELL_DISCRIMINATOR, discriminator("sell"));
+        assert_eq!(CREATE_DISCRIMINATOR, discriminator("create"));
+        assert_eq!(CREATE_V2_DISCRIMINATOR, discriminator("create_v2"));
+        assert_eq!(BUY_EXACT_SOL_IN_DISCRIMINATOR, discriminator("buy_exact_sol_in"));
+        assert_eq!(BUY_EXACT_QUOTE_IN_DISCRIMINATOR, discriminator("buy_exact_quote_in"));
+    }
+
+    #[test]
+    fn decodes_a_pump_fun_buy_with_its_accounts() {
+        let keys = keys(PUMP_FUN_BUY_ACCOUNTS.len());
+        // `track_volume` (OptionBool) as newer clients append it
+        let data = data("buy", &[&34_612_903_225_806u64.to_le_bytes(), &1_010_000_000u64.to_le_bytes(), &[1, 1]]);
+        let Some(PumpInstruction::PumpFunBuy { amount, max_sol_cost, accounts }) =
+            PumpInstruction::decode_pump_fun(&data, keys.clone())
+        else {
+            panic!("not a buy");
+        };
+        assert_eq!((amount, max_sol_cost), (34_612_903_225_806, 1_010_000_000));
+        assert_eq!(accounts.get("mint"), keys[2]);
+        assert_eq!(accounts.get("bonding_curve"), keys[3]);
+        assert_eq!(accounts.get("user"), keys[6]);
+        assert_eq!(accounts.get("creator_vault"), keys[9]);
+        assert_eq!(accounts.get("no_such_account"), Pubkey::default());
+    }
+
+    #[test]
+    fn decodes_buy_exact_sol_in_as_a_buy() {
+        let keys = keys(PUMP_FUN_BUY_ACCOUNTS.len());
+        // (spendable_sol_in, min_tokens_out)
+        let data = data("buy_exact_sol_in", &[&1_000_000_000u64.to_le_bytes(), &34_000_000_000_000u64.to_le_bytes()]);
+        let Some(PumpInstruction::PumpFunBuy { amount, max_sol_cost, accounts }) = PumpInstruction::decode_pump_fun(&data, keys.clone())
+        else {
+            panic!("not a buy");
+        };
+        assert_eq!((amount, max_sol_cost), (34_000_000_000_000, 1_000_000_000));
+        assert_eq!(accounts.get("mint"), keys[2]);
+    }
+
+    #[test]
+    fn decodes_a_pump_fun_sell() {
+        let keys = keys(PUMP_FUN_SELL_ACCOUNTS.len());
+        let data = data("sell", &[&34_612_903_225_806u64.to_le_bytes(), &990_000_000u64.to_le_bytes()]);
+        let Some(PumpInstruction::PumpFunSell { amount, min_sol_output, accounts }) = PumpInstruction::decode_pump_fun(&data, keys.clone())
+        else {
+            panic!("not a sell");
+        };
+        assert_eq!((amount, min_sol_output), (34_612_903_225_806, 990_000_000));
+        // Sell passes creator_vault before token_program, unlike buy
+        assert_eq!(accounts.get("creator_vault"), keys[8]);
+        assert_eq!(accounts.get("token_program"), keys[9]);
+    }
+
+    #[test]
+    fn decodes_create_and_create_v2() {
+        let creator = Pubkey::new_unique();
+        let args = [string("Token"), string("TKN"), string("https://example.com/token.json"), creator.to_bytes().to_vec()];
+        let args: Vec<&[u8]> = args.iter().map(Vec::as_slice).collect();
+
+        let keys = keys(PUMP_FUN_CREATE_ACCOUNTS.len());
+        let Some(PumpInstruction::PumpFunCreate { args: create, accounts }) =
+            PumpInstruction::decode_pump_fun(&data("create", &args), keys.clone())
+        else {
+            panic!("not a create");
+        };
+        assert_eq!((create.name.as_str(), create.symbol.as_str()), ("Token", "TKN"));
+        assert_eq!(create.uri, "https://example.com/token.json");
+        assert_eq!(create.creator, creator);
+        assert_eq!(accounts.get("mint"), keys[0]);
+        assert_eq!(accounts.get("bonding_curve"), keys[2]);
+        assert_eq!(accounts.get("user"), keys[7]);
+
+        // create_v2 adds `is_mayhem_mode` and drops the metadata accounts
+        let v2_args = [args.as_slice(), &[&[0u8][..]]].concat();
+        let keys = self::keys(PUMP_FUN_CREATE_V2_ACCOUNTS.len());
+        let Some(PumpInstruction::PumpFunCreate { args: create, accounts }) =
+            PumpInstruction::decode_pump_fun(&data("create_v2", &v2_args), keys.clone())
+        else {
+            panic!("not a create_v2");
+        };
+        assert_eq!(create.creator, creator);
+        assert_eq!(accounts.get("user"), keys[5]);
+        assert_eq!(accounts.get("token_program"), keys[7]);
+    }
+
+    #[test]
+    fn decodes_pump_swap_buys_and_sells() {
+        let keys = keys(PUMP_SWAP_TRADE_ACCOUNTS.len());
+        let amounts = [&5_000_000u64.to_le_bytes()[..], &2_000_000_000u64.to_le_bytes()[..]];
+
+        let Some(PumpInstruction::PumpSwapBuy { base_amount_out, max_quote_amount_in, accounts }) =
+            PumpInstruction::decode_pump_swap(&data("buy", &amounts), keys.clone())
+        else {
+            panic!("not a buy");
+        };
+        assert_eq!((base_amount_out, max_quote_amount_in), (5_000_000, 2_000_000_000));
+        assert_eq!(accounts.get("pool"), keys[0]);
+        assert_eq!(accounts.get("base_mint"), keys[3]);
+        assert_eq!(accounts.get("coin_creator_vault_authority"), keys[18]);
+
+        // (spendable_quote_in, min_base_amount_out)
+        let Some(PumpInstruction::PumpSwapBuy { base_amount_out, max_quote_amount_in, .. }) =
+            PumpInstruction::decode_pump_swap(&data("buy_exact_quote_in", &amounts), keys.clone())
+        else {
+            panic!("not a buy");
+        };
+        assert_eq!((base_amount_out, max_quote_amount_in), (2_000_000_000, 5_000_000));
+
+        let Some(PumpInstruction::PumpSwapSell { base_amount_in, min_quote_amount_out, .. }) =
+            PumpInstruction::decode_pump_swap(&data("sell", &amounts), keys)
+        else {
+            panic!("not a sell");
+        };
+        assert_eq!((base_amount_in, min_quote_amount_out), (5_000_000, 2_000_000_000));
+    }
+
+    #[test]
+    fn rejects_other_and_truncated_instructions() {
+        let amounts = [&1u64.to_le_bytes()[..], &2u64.to_le_bytes()[..]];
+        assert!(PumpInstruction::decode_pump_fun(&data("withdraw", &amounts), keys(12)).is_none());
+        assert!(PumpInstruction::decode_pump_swap(&data("deposit", &amounts), keys(19)).is_none());
+        let buy = data("buy", &amounts);
+        assert!(PumpInstruction::decode_pump_fun(&buy[..buy.len() - 1], keys(12)).is_none());
+        assert!(PumpInstruction::decode_pump_swap(&buy[..12], keys(19)).is_none());
+        // Short account lists fall back to the default key
+        let Some(instruction) = PumpInstruction::decode_pump_fun(&buy, keys(3)) else {
+            panic!("not a buy");
+        };
+        assert_eq!(instruction.accounts().get("user"), Pubkey::default());
+    }
+}
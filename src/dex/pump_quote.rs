@@ -0,0 +1,488 @@
+use crate::dex::common::{maximum_in, minimum_out};
+use crate::dex::pump_events::{BuyEvent, SellEvent, TradeEvent};
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+/// One quoted trade. `amount_in` is what the trader pays and `amount_out`
+/// what they receive, both with fees applied; `fee` is every fee combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+impl Quote {
+    /// Least output to accept at `slippage` percent, for instructions fixing the input.
+    pub fn minimum_out(&self, slippage: u64) -> u64 {
+        minimum_out(self.amount_out, slippage)
+    }
+
+    /// Most input to allow at `slippage` percent, for instructions fixing the output.
+    pub fn maximum_in(&self, slippage: u64) -> u64 {
+        maximum_in(self.amount_in, slippage)
+    }
+}
+
+/// Both programs round every fee up on its own.
+fn fees(amount: u128, fee_bps: &[u64]) -> u128 {
+    fee_bps.iter().map(|bps| (amount * *bps as u128).div_ceil(BPS_DENOMINATOR)).sum()
+}
+
+fn total_bps(fee_bps: &[u64]) -> u128 {
+    fee_bps.iter().map(|bps| *bps as u128).sum()
+}
+
+/// Largest amount whose fees, charged on top, still fit in `budget`.
+fn net_of_fees_on_top(budget: u128, fee_bps: &[u64]) -> u128 {
+    let mut net = budget * BPS_DENOMINATOR / (BPS_DENOMINATOR + total_bps(fee_bps));
+    while net > 0 && net + fees(net, fee_bps) > budget {
+        net -= 1;
+    }
+    net
+}
+
+/// Smallest amount that still leaves `net` once fees are taken out of it.
+fn gross_of_fees_taken(net: u128, fee_bps: &[u64]) -> Option<u128> {
+    let kept_bps = BPS_DENOMINATOR.checked_sub(total_bps(fee_bps)).filter(|bps| *bps > 0)?;
+    let mut gross = (net * BPS_DENOMINATOR).div_ceil(kept_bps);
+    while gross.saturating_sub(fees(gross, fee_bps)) < net {
+        gross += 1;
+    }
+    Some(gross)
+}
+
+/// A PumpFun bonding curve: a constant product on the virtual reserves, with
+/// the protocol and creator fees charged in SOL on top of buys and out of sells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PumpCurve {
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_token_reserves: u64,
+    pub fee_bps: u64,
+    pub creator_fee_bps: u64,
+}
+
+/// The curve as a trade left it, priced with the fees that trade paid.
+impl From<&TradeEvent> for PumpCurve {
+    fn from(trade: &TradeEvent) -> Self {
+        Self {
+            virtual_sol_reserves: trade.virtual_sol_reserves,
+            virtual_token_reserves: trade.virtual_token_reserves,
+            real_token_reserves: trade.real_token_reserves,
+            fee_bps: trade.fee_basis_points,
+            creator_fee_bps: trade.creator_fee_basis_points,
+        }
+    }
+}
+
+impl PumpCurve {
+    fn fee_bps(&self) -> [u64; 2] {
+        [self.fee_bps, self.creator_fee_bps]
+    }
+
+    /// SOL `tokens_out` costs before fees, or `None` past the curve's supply.
+    /// The program rounds up by adding a lamport rather than dividing up.
+    fn sol_for_tokens(&self, tokens_out: u64) -> Option<u128> {
+        let token_reserves = self.virtual_token_reserves as u128;
+        if tokens_out > self.real_token_reserves || tokens_out as u128 >= token_reserves {
+            return None;
+        }
+        Some(tokens_out as u128 * self.virtual_sol_reserves as u128 / (token_reserves - tokens_out as u128) + 1)
+    }
+
+    /// Lamports a `buy` of exactly `tokens_out` charges.
+    pub fn buy_exact_tokens_out(&self, tokens_out: u64) -> Option<Quote> {
+        let cost = self.sol_for_tokens(tokens_out)?;
+        let fee = fees(cost, &self.fee_bps());
+        Some(Quote { amount_in: u64::try_from(cost + fee).ok()?, amount_out: tokens_out, fee: u64::try_from(fee).ok()? })
+    }
+
+    /// Most tokens `sol_in` lamports buy, fees included, and what they
+    /// actually cost. Capped by the tokens left on the curve.
+    pub fn buy_exact_sol_in(&self, sol_in: u64) -> Option<Quote> {
+        let sol = net_of_fees_on_top(sol_in as u128, &self.fee_bps());
+        let tokens = sol * self.virtual_token_reserves as u128 / (self.virtual_sol_reserves as u128 + sol).max(1);
+        let mut tokens_out = tokens.min(self.real_token_reserves as u128) as u64;
+        // The lamport the program adds to every cost can tip the quote over budget
+        loop {
+            let quote = self.buy_exact_tokens_out(tokens_out)?;
+            if quote.amount_in <= sol_in {
+                return Some(quote);
+            }
+            tokens_out = tokens_out.checked_sub(1)?;
+        }
+    }
+
+    /// Lamports a `sell` of `tokens_in` pays out after fees.
+    pub fn sell_exact_tokens_in(&self, tokens_in: u64) -> Option<Quote> {
+        let token_reserves = self.virtual_token_reserves as u128 + tokens_in as u128;
+        let sol = (tokens_in as u128 * self.virtual_sol_reserves as u128).checked_div(token_reserves)?;
+        let fee = fees(sol, &self.fee_bps()).min(sol);
+        Some(Quote { amount_in: tokens_in, amount_out: u64::try_from(sol - fee).ok()?, fee: u64::try_from(fee).ok()? })
+    }
+
+    /// Fewest tokens whose sale pays out at least `sol_out` after fees.
+    pub fn sell_exact_sol_out(&self, sol_out: u64) -> Option<Quote> {
+        let sol = gross_of_fees_taken(sol_out as u128, &self.fee_bps())?;
+        let sol_reserves = (self.virtual_sol_reserves as u128).checked_sub(sol).filter(|reserves| *reserves > 0)?;
+        let tokens_in = (sol * self.virtual_token_reserves as u128).div_ceil(sol_reserves);
+        self.sell_exact_tokens_in(u64::try_from(tokens_in).ok()?)
+    }
+}
+
+/// A PumpSwap pool: a constant product on the vault balances, with the LP,
+/// protocol and coin creator fees all taken on the quote side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PumpSwapPool {
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    pub lp_fee_bps: u64,
+    pub protocol_fee_bps: u64,
+    pub coin_creator_fee_bps: u64,
+}
+
+/// The pool as a buy found it, priced with the fees that buy paid.
+impl From<&BuyEvent> for PumpSwapPool {
+    fn from(buy: &BuyEvent) -> Self {
+        Self {
+            base_reserve: buy.pool_base_token_reserves,
+            quote_reserve: buy.pool_quote_token_reserves,
+            lp_fee_bps: buy.lp_fee_basis_points,
+            protocol_fee_bps: buy.protocol_fee_basis_points,
+            coin_creator_fee_bps: buy.coin_creator_fee_basis_points,
+        }
+    }
+}
+
+/// The pool as a sell found it, priced with the fees that sell paid.
+impl From<&SellEvent> for PumpSwapPool {
+    fn from(sell: &SellEvent) -> Self {
+        Self {
+            base_reserve: sell.pool_base_token_reserves,
+            quote_reserve: sell.pool_quote_token_reserves,
+            lp_fee_bps: sell.lp_fee_basis_points,
+            protocol_fee_bps: sell.protocol_fee_basis_points,
+            coin_creator_fee_bps: sell.coin_creator_fee_basis_points,
+        }
+    }
+}
+
+impl PumpSwapPool {
+    fn fee_bps(&self) -> [u64; 3] {
+        [self.lp_fee_bps, self.protocol_fee_bps, self.coin_creator_fee_bps]
+    }
+
+    /// Quote a `buy` of exactly `base_out` charges.
+    pub fn buy_exact_base_out(&self, base_out: u64) -> Option<Quote> {
+        let base_reserve = (self.base_reserve as u128).checked_sub(base_out as u128).filter(|reserve| *reserve > 0)?;
+        let quote_in = (self.quote_reserve as u128 * base_out as u128).div_ceil(base_reserve);
+        let fee = fees(quote_in, &self.fee_bps());
+        Some(Quote { amount_in: u64::try_from(quote_in + fee).ok()?, amount_out: base_out, fee: u64::try_from(fee).ok()? })
+    }
+
+    /// Most base `quote_in` buys, fees included, and what it actually costs.
+    pub fn buy_exact_quote_in(&self, quote_in: u64) -> Option<Quote> {
+        let quote = net_of_fees_on_top(quote_in as u128, &self.fee_bps());
+        let base = quote * self.base_reserve as u128 / (self.quote_reserve as u128 + quote).max(1);
+        let mut base_out = u64::try_from(base).ok()?;
+        // Rounding the cost up can tip the quote over budget
+        loop {
+            let quote = self.buy_exact_base_out(base_out)?;
+            if quote.amount_in <= quote_in {
+                return Some(quote);
+            }
+            base_out = base_out.checked_sub(1)?;
+        }
+    }
+
+    /// Quote a `sell` of `base_in` pays out after fees.
+    pub fn sell_exact_base_in(&self, base_in: u64) -> Option<Quote> {
+        let base_reserve = self.base_reserve as u128 + base_in as u128;
+        let quote = (self.quote_reserve as u128 * base_in as u128).checked_div(base_reserve)?;
+        let fee = fees(quote, &self.fee_bps()).min(quote);
+        Some(Quote { amount_in: base_in, amount_out: u64::try_from(quote - fee).ok()?, fee: u64::try_from(fee).ok()? })
+    }
+
+    /// Least base whose sale pays out at least `quote_out` after fees.
+    pub fn sell_exact_quote_out(&self, quote_out: u64) -> Option<Quote> {
+        let quote = gross_of_fees_taken(quote_out as u128, &self.fee_bps())?;
+        let quote_reserve = (self.quote_reserve as u128).checked_sub(quote).filter(|reserve| *reserve > 0)?;
+        let base_in = (quote * self.base_reserve as u128).div_ceil(quote_reserve);
+        self.sell_exact_base_in(u64::try_from(base_in).ok()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    // The events below are built from each program's arithmetic and fee rates,
+    // not captured from mainnet transactions, so they pin the formulas rather
+    // than prove them against the deployed programs.
+
+    // A fresh curve, as the Global account seeds it
+    const VIRTUAL_SOL: u64 = 30_000_000_000;
+    const VIRTUAL_TOKENS: u64 = 1_073_000_000_000_000;
+    const REAL_TOKENS: u64 = 793_100_000_000_000;
+    // What 1 SOL buys from a fresh curve before fees
+    const FIRST_BUY_TOKENS: u64 = 34_612_903_225_806;
+    // A freshly migrated pool
+    const POOL_BASE: u64 = 206_900_000_000_000;
+    const POOL_QUOTE: u64 = 84_990_359_679;
+
+    fn trade(is_buy: bool, sol_amount: u64, token_amount: u64, reserves: (u64, u64, u64), fees: (u64, u64)) -> TradeEvent {
+        let (virtual_sol_reserves, virtual_token_reserves, real_token_reserves) = reserves;
+        TradeEvent {
+            mint: Pubkey::default(),
+            sol_amount,
+            token_amount,
+            is_buy,
+            user: Pubkey::default(),
+            timestamp: 1_717_200_000,
+            virtual_sol_reserves,
+            virtual_token_reserves,
+            real_sol_reserves: virtual_sol_reserves - VIRTUAL_SOL,
+            real_token_reserves,
+            fee_recipient: Pubkey::default(),
+            fee_basis_points: 95,
+            fee: fees.0,
+            creator: Pubkey::default(),
+            creator_fee_basis_points: 5,
+            creator_fee: fees.1,
+        }
+    }
+
+    /// The 1 SOL first buy on a fresh curve.
+    fn first_buy() -> TradeEvent {
+        trade(
+            true,
+            1_000_000_000,
+            FIRST_BUY_TOKENS,
+            (31_000_000_000, 1_038_387_096_774_194, 758_487_096_774_194),
+            (9_500_000, 500_000),
+        )
+    }
+
+    /// Half of the first buy sold straight back.
+    fn half_sell() -> TradeEvent {
+        trade(
+            false,
+            508_196_721,
+            17_306_451_612_903,
+            (30_491_803_279, 1_055_693_548_387_097, 775_793_548_387_097),
+            (4_827_869, 254_099),
+        )
+    }
+
+    /// A buy of one million tokens from a freshly migrated pool.
+    fn pool_buy() -> BuyEvent {
+        BuyEvent {
+            timestamp: 1_717_200_000,
+            base_amount_out: 1_000_000_000_000,
+            max_quote_amount_in: 420_000_000,
+            user_base_token_reserves: 0,
+            user_quote_token_reserves: 1_000_000_000,
+            pool_base_token_reserves: POOL_BASE,
+            pool_quote_token_reserves: POOL_QUOTE,
+            quote_amount_in: 412_774_938,
+            lp_fee_basis_points: 20,
+            lp_fee: 825_550,
+            protocol_fee_basis_points: 5,
+            protocol_fee: 206_388,
+            quote_amount_in_with_lp_fee: 413_600_488,
+            user_quote_amount_in: 414_013_264,
+            pool: Pubkey::default(),
+            user: Pubkey::default(),
+            user_base_token_account: Pubkey::default(),
+            user_quote_token_account: Pubkey::default(),
+            protocol_fee_recipient: Pubkey::default(),
+            protocol_fee_recipient_token_account: Pubkey::default(),
+            coin_creator: Pubkey::default(),
+            coin_creator_fee_basis_points: 5,
+            coin_creator_fee: 206_388,
+        }
+    }
+
+    /// A sell of one million tokens into a freshly migrated pool.
+    fn pool_sell() -> SellEvent {
+        SellEvent {
+            timestamp: 1_717_200_000,
+            base_amount_in: 1_000_000_000_000,
+            min_quote_amount_out: 400_000_000,
+            user_base_token_reserves: 1_000_000_000_000,
+            user_quote_token_reserves: 0,
+            pool_base_token_reserves: POOL_BASE,
+            pool_quote_token_reserves: POOL_QUOTE,
+            quote_amount_out: 408_804_038,
+            lp_fee_basis_points: 20,
+            lp_fee: 817_609,
+            protocol_fee_basis_points: 5,
+            protocol_fee: 204_403,
+            quote_amount_out_without_lp_fee: 407_986_429,
+            user_quote_amount_out: 407_577_623,
+            pool: Pubkey::default(),
+            user: Pubkey::default(),
+            user_base_token_account: Pubkey::default(),
+            user_quote_token_account: Pubkey::default(),
+            protocol_fee_recipient: Pubkey::default(),
+            protocol_fee_recipient_token_account: Pubkey::default(),
+            coin_creator: Pubkey::default(),
+            coin_creator_fee_basis_points: 5,
+            coin_creator_fee: 204_403,
+        }
+    }
+
+    fn fresh_curve() -> PumpCurve {
+        PumpCurve {
+            virtual_sol_reserves: VIRTUAL_SOL,
+            virtual_token_reserves: VIRTUAL_TOKENS,
+            real_token_reserves: REAL_TOKENS,
+            fee_bps: 95,
+            creator_fee_bps: 5,
+        }
+    }
+
+    #[test]
+    fn curve_replays_the_first_buy() {
+        let buy = first_buy();
+        let quote = Quote { amount_in: 1_010_000_000, amount_out: FIRST_BUY_TOKENS, fee: 10_000_000 };
+        assert_eq!(buy.sol_amount + buy.fee + buy.creator_fee, quote.amount_in);
+        assert_eq!(fresh_curve().buy_exact_tokens_out(buy.token_amount), Some(quote));
+        assert_eq!(fresh_curve().buy_exact_sol_in(1_010_000_000), Some(quote));
+    }
+
+    #[test]
+    fn curve_from_a_trade_replays_the_next_one() {
+        let curve = PumpCurve::from(&first_buy());
+        let sell = half_sell();
+        let quote = Quote { amount_in: 17_306_451_612_903, amount_out: 503_114_753, fee: 5_081_968 };
+        assert_eq!(sell.sol_amount - sell.fee - sell.creator_fee, quote.amount_out);
+        assert_eq!(curve.sell_exact_tokens_in(sell.token_amount), Some(quote));
+        // A few lamports of rounding spare 10,783 raw tokens
+        assert_eq!(
+            curve.sell_exact_sol_out(503_114_753),
+            Some(Quote { amount_in: 17_306_451_602_120, ..quote })
+        );
+        let after = PumpCurve::from(&sell);
+        assert_eq!(after.virtual_sol_reserves, curve.virtual_sol_reserves - sell.sol_amount);
+        assert_eq!(after.virtual_token_reserves, curve.virtual_token_reserves + sell.token_amount);
+    }
+
+    #[test]
+    fn curve_refuses_buys_past_its_supply() {
+        let curve = fresh_curve();
+        assert_eq!(curve.buy_exact_tokens_out(REAL_TOKENS + 1), None);
+        assert_eq!(curve.buy_exact_sol_in(1_000_000_000_000).map(|quote| quote.amount_out), Some(REAL_TOKENS));
+    }
+
+    #[test]
+    fn pool_replays_a_buy() {
+        let buy = pool_buy();
+        let pool = PumpSwapPool::from(&buy);
+        let fee = buy.lp_fee + buy.protocol_fee + buy.coin_creator_fee;
+        assert_eq!(buy.quote_amount_in + fee, buy.user_quote_amount_in);
+        assert_eq!(
+            pool.buy_exact_base_out(buy.base_amount_out),
+            Some(Quote { amount_in: buy.user_quote_amount_in, amount_out: buy.base_amount_out, fee })
+        );
+        assert_eq!(
+            pool.buy_exact_quote_in(buy.user_quote_amount_in),
+            Some(Quote { amount_in: 414_013_264, amount_out: 1_000_000_000_646, fee: 1_238_326 })
+        );
+    }
+
+    #[test]
+    fn pool_replays_a_sell() {
+        let sell = pool_sell();
+        let pool = PumpSwapPool::from(&sell);
+        let fee = sell.lp_fee + sell.protocol_fee + sell.coin_creator_fee;
+        assert_eq!(sell.quote_amount_out - fee, sell.user_quote_amount_out);
+        assert_eq!(
+            pool.sell_exact_base_in(sell.base_amount_in),
+            Some(Quote { amount_in: sell.base_amount_in, amount_out: sell.user_quote_amount_out, fee })
+        );
+        assert_eq!(
+            pool.sell_exact_quote_out(sell.user_quote_amount_out),
+            Some(Quote { amount_in: 999_999_997_887, amount_out: 407_577_623, fee: 1_226_415 })
+        );
+    }
+
+    #[test]
+    fn slippage_bounds_wrap_the_quote() {
+        let quote = fresh_curve().buy_exact_tokens_out(FIRST_BUY_TOKENS).unwrap();
+        assert_eq!(quote.maximum_in(10), 1_111_000_000);
+        assert_eq!(quote.minimum_out(10), 31_151_612_903_225);
+        assert_eq!(quote.minimum_out(0), quote.amount_out);
+        assert_eq!(quote.maximum_in(0), quote.amount_in);
+    }
+
+    /// Curves between a fresh one and one about to migrate, and pools from
+    /// thin to deep, paired with budgets from dust to a few hundred SOL.
+    fn cases() -> impl Iterator<Item = (PumpCurve, PumpSwapPool, u64)> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..2_000).map(move |_| {
+            let virtual_sol_reserves = rng.gen_range(VIRTUAL_SOL..=115_000_000_000);
+            let virtual_token_reserves = (VIRTUAL_TOKENS as u128 * VIRTUAL_SOL as u128 / virtual_sol_reserves as u128) as u64;
+            let curve = PumpCurve {
+                virtual_sol_reserves,
+                virtual_token_reserves,
+                real_token_reserves: virtual_token_reserves - (VIRTUAL_TOKENS - REAL_TOKENS),
+                ..fresh_curve()
+            };
+            let pool = PumpSwapPool {
+                base_reserve: rng.gen_range(1_000_000_000_000..=POOL_BASE * 5),
+                quote_reserve: rng.gen_range(1_000_000_000..=POOL_QUOTE * 10),
+                lp_fee_bps: 20,
+                protocol_fee_bps: 5,
+                coin_creator_fee_bps: 5,
+            };
+            let amount = if rng.gen() { rng.gen_range(1..=1_000_000) } else { rng.gen_range(1..=300_000_000_000) };
+            (curve, pool, amount)
+        })
+    }
+
+    #[test]
+    fn curve_buys_spend_at_most_the_budget_and_as_much_of_it_as_they_can() {
+        for (curve, _, sol_in) in cases() {
+            let Some(quote) = curve.buy_exact_sol_in(sol_in) else { continue };
+            assert!(quote.amount_in <= sol_in, "{curve:?} {sol_in}");
+            if let Some(more) = curve.buy_exact_tokens_out(quote.amount_out + 1) {
+                assert!(more.amount_in > sol_in, "{curve:?} {sol_in}");
+            }
+        }
+    }
+
+    #[test]
+    fn curve_sells_pay_at_least_the_target_with_the_fewest_tokens() {
+        for (curve, _, sol_out) in cases() {
+            let Some(quote) = curve.sell_exact_sol_out(sol_out) else { continue };
+            assert!(quote.amount_out >= sol_out, "{curve:?} {sol_out}");
+            let fewer = curve.sell_exact_tokens_in(quote.amount_in - 1).unwrap();
+            assert!(fewer.amount_out < sol_out, "{curve:?} {sol_out}");
+        }
+    }
+
+    #[test]
+    fn pool_buys_spend_at_most_the_budget_and_as_much_of_it_as_they_can() {
+        for (_, pool, quote_in) in cases() {
+            let Some(quote) = pool.buy_exact_quote_in(quote_in) else { continue };
+            assert!(quote.amount_in <= quote_in, "{pool:?} {quote_in}");
+            if let Some(more) = pool.buy_exact_base_out(quote.amount_out + 1) {
+                assert!(more.amount_in > quote_in, "{pool:?} {quote_in}");
+            }
+        }
+    }
+
+    #[test]
+    fn pool_sells_pay_at_least_the_target_with_the_least_base() {
+        for (_, pool, quote_out) in cases() {
+            let Some(quote) = pool.sell_exact_quote_out(quote_out) else { continue };
+            assert!(quote.amount_out >= quote_out, "{pool:?} {quote_out}");
+            let fewer = pool.sell_exact_base_in(quote.amount_in - 1).unwrap();
+            assert!(fewer.amount_out < quote_out, "{pool:?} {quote_out}");
+        }
+    }
+}
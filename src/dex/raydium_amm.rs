@@ -0,0 +1,299 @@
+use std::{str::FromStr, sync::Arc};
+
+use anchor_client::solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::RpcClient as BlockingRpcClient};
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signer::Signer,
+};
+use anyhow::{anyhow, Result};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account_idempotent,
+};
+use tokio::time::Instant;
+
+use super::common::{find_sol_pairs, minimum_out, read_pubkey, read_u64, sell_amount, token_account_amounts, wrap_sol};
+use crate::common::config::SwapConfig;
+use crate::core::signer::SharedSigner;
+use crate::engine::swap::SwapDirection;
+
+pub const RAYDIUM_AMM_PROGRAM: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+
+/// `SwapBaseInV2`: the swap without the OpenBook market accounts.
+pub const SWAP_BASE_IN_V2_TAG: u8 = 16;
+/// Size of an `AmmInfo` account.
+pub const AMM_INFO_SIZE: u64 = 752;
+// AmmStatus values that accept swaps: Initialized, SwapOnly and WaitingTrade
+const TRADABLE_STATUSES: [u64; 3] = [1, 6, 7];
+
+// AmmInfo: status | nonce | order_num | depth | coin_decimals | pc_decimals | state | reset_flag |
+// min_size | vol_max_cut_ratio | amount_wave | coin_lot_size | pc_lot_size | min_price_multiplier |
+// max_price_multiplier | sys_decimal_value (u64s) | fees (8 x u64) | state data (need_take_pnl_coin,
+// need_take_pnl_pc, ...) | coin_vault | pc_vault | coin_vault_mint | pc_vault_mint | lp_mint | ...
+const AMM_STATUS_OFFSET: usize = 0;
+const AMM_COIN_DECIMALS_OFFSET: usize = 32;
+const AMM_PC_DECIMALS_OFFSET: usize = 40;
+const AMM_SWAP_FEE_NUMERATOR_OFFSET: usize = 176;
+const AMM_SWAP_FEE_DENOMINATOR_OFFSET: usize = 184;
+const AMM_NEED_TAKE_PNL_COIN_OFFSET: usize = 192;
+const AMM_NEED_TAKE_PNL_PC_OFFSET: usize = 200;
+const AMM_COIN_VAULT_OFFSET: usize = 336;
+const AMM_PC_VAULT_OFFSET: usize = 368;
+const AMM_COIN_MINT_OFFSET: usize = 400;
+const AMM_PC_MINT_OFFSET: usize = 432;
+
+/// A Raydium AMM v4 pool, decoded from its `AmmInfo` account. Reserves are
+/// the vault balances, filled in separately.
+#[derive(Debug, Clone)]
+pub struct AmmPool {
+    pub pool_id: Pubkey,
+    pub status: u64,
+    pub coin_decimals: u8,
+    pub pc_decimals: u8,
+    pub swap_fee_numerator: u64,
+    pub swap_fee_denominator: u64,
+    pub need_take_pnl_coin: u64,
+    pub need_take_pnl_pc: u64,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub coin_vault_amount: u64,
+    pub pc_vault_amount: u64,
+}
+
+impl AmmPool {
+    pub fn decode(pool_id: Pubkey, data: &[u8]) -> Result<Self> {
+        Ok(Self {
+            pool_id,
+            status: read_u64(data, AMM_STATUS_OFFSET)?,
+            coin_decimals: read_u64(data, AMM_COIN_DECIMALS_OFFSET)? as u8,
+            pc_decimals: read_u64(data, AMM_PC_DECIMALS_OFFSET)? as u8,
+            swap_fee_numerator: read_u64(data, AMM_SWAP_FEE_NUMERATOR_OFFSET)?,
+            swap_fee_denominator: read_u64(data, AMM_SWAP_FEE_DENOMINATOR_OFFSET)?,
+            need_take_pnl_coin: read_u64(data, AMM_NEED_TAKE_PNL_COIN_OFFSET)?,
+            need_take_pnl_pc: read_u64(data, AMM_NEED_TAKE_PNL_PC_OFFSET)?,
+            coin_vault: read_pubkey(data, AMM_COIN_VAULT_OFFSET)?,
+            pc_vault: read_pubkey(data, AMM_PC_VAULT_OFFSET)?,
+            coin_mint: read_pubkey(data, AMM_COIN_MINT_OFFSET)?,
+            pc_mint: read_pubkey(data, AMM_PC_MINT_OFFSET)?,
+            coin_vault_amount: 0,
+            pc_vault_amount: 0,
+        })
+    }
+
+    pub fn is_trading(&self) -> bool {
+        TRADABLE_STATUSES.contains(&self.status)
+    }
+
+    /// Coin and pc reserves the pool prices against: the vaults minus the pnl owed to the pool owner.
+    fn reserves(&self) -> (u128, u128) {
+        (
+            self.coin_vault_amount.saturating_sub(self.need_take_pnl_coin) as u128,
+            self.pc_vault_amount.saturating_sub(self.need_take_pnl_pc) as u128,
+        )
+    }
+
+    /// Lamports on the WSOL side, used to pick the deepest pool.
+    pub fn sol_reserve(&self) -> u64 {
+        let (coin, pc) = self.reserves();
+        if self.coin_mint == spl_token::native_mint::ID {
+            coin as u64
+        } else {
+            pc as u64
+        }
+    }
+
+    /// Spot price of `mint` in SOL per whole token.
+    pub fn price(&self, mint: &Pubkey) -> f64 {
+        let (coin, pc) = self.reserves();
+        let coin = coin as f64 / 10f64.powi(self.coin_decimals as i32);
+        let pc = pc as f64 / 10f64.powi(self.pc_decimals as i32);
+        let (token, sol) = if *mint == self.coin_mint { (coin, pc) } else { (pc, coin) };
+        if token == 0.0 {
+            return 0.0;
+        }
+        sol / token
+    }
+
+    /// Output for `amount_in` of the input side, after the swap fee is taken from the input.
+    pub fn quote_exact_in(&self, input_is_coin: bool, amount_in: u64) -> u64 {
+        if self.swap_fee_denominator == 0 {
+            return 0;
+        }
+        let fee = (amount_in as u128 * self.swap_fee_numerator as u128).div_ceil(self.swap_fee_denominator as u128);
+        let amount_in = (amount_in as u128).saturating_sub(fee);
+        let (coin, pc) = self.reserves();
+        let (reserve_in, reserve_out) = if input_is_coin { (coin, pc) } else { (pc, coin) };
+        let denominator = reserve_in + amount_in;
+        if denominator == 0 {
+            return 0;
+        }
+        (amount_in * reserve_out / denominator).min(u64::MAX as u128) as u64
+    }
+}
+
+async fn fill_reserves(client: &RpcClient, pools: &mut [AmmPool]) -> Result<()> {
+    if pools.is_empty() {
+        return Ok(());
+    }
+    let vaults: Vec<Pubkey> = pools.iter().flat_map(|pool| [pool.coin_vault, pool.pc_vault]).collect();
+    let amounts = token_account_amounts(client, &vaults).await?;
+    for (pool, amounts) in pools.iter_mut().zip(amounts.chunks(2)) {
+        pool.coin_vault_amount = amounts[0];
+        pool.pc_vault_amount = amounts[1];
+    }
+    Ok(())
+}
+
+/// The pool at `pool_id`, with its reserves.
+pub async fn get_pool(client: &RpcClient, pool_id: &Pubkey) -> Result<AmmPool> {
+    let data = client
+        .get_account_data(pool_id)
+        .await
+        .map_err(|e| anyhow!("no Raydium AMM pool at {}: {}", pool_id, e))?;
+    let mut pool = AmmPool::decode(*pool_id, &data)?;
+    fill_reserves(client, std::slice::from_mut(&mut pool)).await?;
+    Ok(pool)
+}
+
+/// Trading AMM v4 pools that pair `mint` with WSOL, with their reserves.
+pub async fn find_pools(client: &RpcClient, mint: &Pubkey) -> Result<Vec<AmmPool>> {
+    let program = Pubkey::from_str(RAYDIUM_AMM_PROGRAM)?;
+    let mint_offsets = (AMM_COIN_MINT_OFFSET, AMM_PC_MINT_OFFSET);
+    let accounts = find_sol_pairs(client, &program, AMM_INFO_SIZE, mint_offsets, mint).await?;
+    let mut pools: Vec<AmmPool> = accounts
+        .iter()
+        .filter_map(|(pool_id, account)| AmmPool::decode(*pool_id, &account.data).ok())
+        .filter(|pool| pool.is_trading())
+        .collect();
+    fill_reserves(client, &mut pools).await?;
+    Ok(pools)
+}
+
+/// The SOL pool of `mint` holding the most SOL.
+pub async fn deepest_pool(client: &RpcClient, mint: &Pubkey) -> Result<AmmPool> {
+    find_pools(client, mint)
+        .await?
+        .into_iter()
+        .max_by_key(|pool| pool.sol_reserve())
+        .ok_or_else(|| anyhow!("no Raydium AMM pool for {}", mint))
+}
+
+pub struct RaydiumAmm {
+    pub rpc_nonblocking_client: Arc<RpcClient>,
+    pub rpc_client: Arc<BlockingRpcClient>,
+    pub keypair: SharedSigner,
+}
+
+impl RaydiumAmm {
+    pub fn new(
+        rpc_nonblocking_client: Arc<RpcClient>,
+        rpc_client: Arc<BlockingRpcClient>,
+        keypair: SharedSigner,
+    ) -> Self {
+        Self {
+            rpc_nonblocking_client,
+            rpc_client,
+            keypair,
+        }
+    }
+
+    /// Price of `mint` in its deepest SOL pool.
+    pub async fn get_token_price(&self, mint: &str) -> Result<f64> {
+        let mint = Pubkey::from_str(mint)?;
+        let pool = deepest_pool(&self.rpc_nonblocking_client, &mint).await?;
+        Ok(pool.price(&mint))
+    }
+
+    /// Builds a buy or sell of `mint` against its SOL pool. Buys spend
+    /// `amount_in` SOL; sells spend a quantity or a fraction of the wallet's tokens.
+    pub async fn build_swap_ixn_by_mint(
+        &self,
+        mint: &str,
+        pool: Option<AmmPool>,
+        swap_config: SwapConfig,
+        _start_time: Instant,
+    ) -> Result<(SharedSigner, Vec<Instruction>, f64)> {
+        let mint = Pubkey::from_str(mint)?;
+        let pool = match pool {
+            Some(pool) => pool,
+            None => deepest_pool(&self.rpc_nonblocking_client, &mint).await?,
+        };
+        if !pool.is_trading() {
+            return Err(anyhow!("Raydium AMM pool {} does not accept swaps (status {})", pool.pool_id, pool.status));
+        }
+        let mint_is_coin = mint == pool.coin_mint;
+        let token_decimals = if mint_is_coin { pool.coin_decimals } else { pool.pc_decimals };
+
+        let owner = self.keypair.pubkey();
+        let token_ata = get_associated_token_address_with_program_id(&owner, &mint, &spl_token::ID);
+        let wsol_ata = get_associated_token_address_with_program_id(&owner, &spl_token::native_mint::ID, &spl_token::ID);
+
+        let mut instructions = Vec::new();
+        let mut close_token = false;
+        let swap_ix = match swap_config.swap_direction {
+            SwapDirection::Buy => {
+                let sol_in = (swap_config.amount_in * LAMPORTS_PER_SOL as f64) as u64;
+                let minimum_out = minimum_out(pool.quote_exact_in(!mint_is_coin, sol_in), swap_config.slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &mint, &spl_token::ID));
+                instructions.extend(wrap_sol(&owner, &wsol_ata, sol_in)?);
+                self.swap_instruction(&pool, &owner, wsol_ata, token_ata, sol_in, minimum_out)?
+            }
+            SwapDirection::Sell => {
+                let (token_in, whole_balance) =
+                    sell_amount(&self.rpc_nonblocking_client, &token_ata, &swap_config, token_decimals).await?;
+                close_token = whole_balance;
+                let minimum_out = minimum_out(pool.quote_exact_in(mint_is_coin, token_in), swap_config.slippage);
+                instructions.push(create_associated_token_account_idempotent(
+                    &owner,
+                    &owner,
+                    &spl_token::native_mint::ID,
+                    &spl_token::ID,
+                ));
+                self.swap_instruction(&pool, &owner, token_ata, wsol_ata, token_in, minimum_out)?
+            }
+        };
+        instructions.push(swap_ix);
+        instructions.push(spl_token::instruction::close_account(&spl_token::ID, &wsol_ata, &owner, &owner, &[])?);
+        if close_token {
+            instructions.push(spl_token::instruction::close_account(&spl_token::ID, &token_ata, &owner, &owner, &[])?);
+        }
+
+        Ok((self.keypair.clone(), instructions, pool.price(&mint)))
+    }
+
+    fn swap_instruction(
+        &self,
+        pool: &AmmPool,
+        owner: &Pubkey,
+        source: Pubkey,
+        destination: Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<Instruction> {
+        let program = Pubkey::from_str(RAYDIUM_AMM_PROGRAM)?;
+        let (authority, _) = Pubkey::find_program_address(&[b"amm authority"], &program);
+
+        let mut data = Vec::with_capacity(17);
+        data.push(SWAP_BASE_IN_V2_TAG);
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+
+        Ok(Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new(pool.pool_id, false),
+                AccountMeta::new_readonly(authority, false),
+                AccountMeta::new(pool.coin_vault, false),
+                AccountMeta::new(pool.pc_vault, false),
+                AccountMeta::new(source, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(*owner, true),
+            ],
+            data,
+        })
+    }
+}
@@ -0,0 +1,346 @@
+use std::{str::FromStr, sync::Arc};
+
+use anchor_client::solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::RpcClient as BlockingRpcClient};
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signer::Signer,
+};
+use anyhow::{anyhow, Result};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account_idempotent,
+};
+use tokio::time::Instant;
+
+use super::common::{
+    find_sol_pairs, minimum_out, mint_program_and_extensions, read_pubkey, read_u64, sell_amount, token_account_amounts,
+    wrap_sol,
+};
+use crate::common::config::SwapConfig;
+use crate::core::signer::SharedSigner;
+use crate::engine::swap::SwapDirection;
+
+pub const RAYDIUM_CPMM_PROGRAM: &str = "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C";
+
+pub const SWAP_BASE_INPUT_DISCRIMINATOR: [u8; 8] = [143, 190, 90, 218, 196, 30, 51, 222];
+pub const SWAP_BASE_OUTPUT_DISCRIMINATOR: [u8; 8] = [55, 217, 98, 86, 163, 74, 180, 173];
+
+/// Fee rates are expressed in millionths.
+pub const FEE_RATE_DENOMINATOR: u128 = 1_000_000;
+/// Size of a `PoolState` account.
+pub const POOL_STATE_SIZE: u64 = 637;
+/// `PoolState::status` bit that disables swaps.
+const POOL_STATUS_SWAP_DISABLED: u8 = 1 << 2;
+
+// PoolState: discriminator (8) | amm_config | pool_creator | token_0_vault | token_1_vault |
+// lp_mint | token_0_mint | token_1_mint | token_0_program | token_1_program | observation_key |
+// auth_bump | status | lp_mint_decimals | mint_0_decimals | mint_1_decimals | lp_supply |
+// protocol_fees_token_0 | protocol_fees_token_1 | fund_fees_token_0 | fund_fees_token_1 | open_time
+const POOL_AMM_CONFIG_OFFSET: usize = 8;
+const POOL_TOKEN_0_VAULT_OFFSET: usize = 72;
+const POOL_TOKEN_1_VAULT_OFFSET: usize = 104;
+const POOL_TOKEN_0_MINT_OFFSET: usize = 168;
+const POOL_TOKEN_1_MINT_OFFSET: usize = 200;
+const POOL_TOKEN_0_PROGRAM_OFFSET: usize = 232;
+const POOL_TOKEN_1_PROGRAM_OFFSET: usize = 264;
+const POOL_OBSERVATION_OFFSET: usize = 296;
+const POOL_STATUS_OFFSET: usize = 329;
+const POOL_MINT_0_DECIMALS_OFFSET: usize = 331;
+const POOL_MINT_1_DECIMALS_OFFSET: usize = 332;
+const POOL_PROTOCOL_FEES_TOKEN_0_OFFSET: usize = 341;
+const POOL_PROTOCOL_FEES_TOKEN_1_OFFSET: usize = 349;
+const POOL_FUND_FEES_TOKEN_0_OFFSET: usize = 357;
+const POOL_FUND_FEES_TOKEN_1_OFFSET: usize = 365;
+// AmmConfig: discriminator (8) | bump (u8) | disable_create_pool (bool) | index (u16) | trade_fee_rate
+const CONFIG_TRADE_FEE_RATE_OFFSET: usize = 12;
+
+/// A Raydium CPMM pool, decoded from its `PoolState` account. Vault balances
+/// and the trade fee rate of its config are filled in separately.
+#[derive(Debug, Clone)]
+pub struct CpmmPool {
+    pub pool_id: Pubkey,
+    pub amm_config: Pubkey,
+    pub token_0_vault: Pubkey,
+    pub token_1_vault: Pubkey,
+    pub token_0_mint: Pubkey,
+    pub token_1_mint: Pubkey,
+    pub token_0_program: Pubkey,
+    pub token_1_program: Pubkey,
+    pub observation: Pubkey,
+    pub status: u8,
+    pub mint_0_decimals: u8,
+    pub mint_1_decimals: u8,
+    /// Protocol and fund fees held in the vaults but not part of the reserves.
+    pub fees_token_0: u64,
+    pub fees_token_1: u64,
+    pub token_0_vault_amount: u64,
+    pub token_1_vault_amount: u64,
+    pub trade_fee_rate: u64,
+}
+
+impl CpmmPool {
+    pub fn decode(pool_id: Pubkey, data: &[u8]) -> Result<Self> {
+        let read_u8 = |offset: usize| data.get(offset).copied().ok_or_else(|| anyhow!("pool {} is too short", pool_id));
+        Ok(Self {
+            pool_id,
+            amm_config: read_pubkey(data, POOL_AMM_CONFIG_OFFSET)?,
+            token_0_vault: read_pubkey(data, POOL_TOKEN_0_VAULT_OFFSET)?,
+            token_1_vault: read_pubkey(data, POOL_TOKEN_1_VAULT_OFFSET)?,
+            token_0_mint: read_pubkey(data, POOL_TOKEN_0_MINT_OFFSET)?,
+            token_1_mint: read_pubkey(data, POOL_TOKEN_1_MINT_OFFSET)?,
+            token_0_program: read_pubkey(data, POOL_TOKEN_0_PROGRAM_OFFSET)?,
+            token_1_program: read_pubkey(data, POOL_TOKEN_1_PROGRAM_OFFSET)?,
+            observation: read_pubkey(data, POOL_OBSERVATION_OFFSET)?,
+            status: read_u8(POOL_STATUS_OFFSET)?,
+            mint_0_decimals: read_u8(POOL_MINT_0_DECIMALS_OFFSET)?,
+            mint_1_decimals: read_u8(POOL_MINT_1_DECIMALS_OFFSET)?,
+            fees_token_0: read_u64(data, POOL_PROTOCOL_FEES_TOKEN_0_OFFSET)?
+                .saturating_add(read_u64(data, POOL_FUND_FEES_TOKEN_0_OFFSET)?),
+            fees_token_1: read_u64(data, POOL_PROTOCOL_FEES_TOKEN_1_OFFSET)?
+                .saturating_add(read_u64(data, POOL_FUND_FEES_TOKEN_1_OFFSET)?),
+            token_0_vault_amount: 0,
+            token_1_vault_amount: 0,
+            trade_fee_rate: 0,
+        })
+    }
+
+    pub fn is_trading(&self) -> bool {
+        self.status & POOL_STATUS_SWAP_DISABLED == 0
+    }
+
+    fn reserves(&self) -> (u128, u128) {
+        (
+            self.token_0_vault_amount.saturating_sub(self.fees_token_0) as u128,
+            self.token_1_vault_amount.saturating_sub(self.fees_token_1) as u128,
+        )
+    }
+
+    /// Lamports on the WSOL side, used to pick the deepest pool.
+    pub fn sol_reserve(&self) -> u64 {
+        let (reserve_0, reserve_1) = self.reserves();
+        if self.token_0_mint == spl_token::native_mint::ID {
+            reserve_0 as u64
+        } else {
+            reserve_1 as u64
+        }
+    }
+
+    /// Token program of `mint`'s side of the pool.
+    pub fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.token_0_mint {
+            self.token_0_program
+        } else {
+            self.token_1_program
+        }
+    }
+
+    /// Spot price of `mint` in SOL per whole token.
+    pub fn price(&self, mint: &Pubkey) -> f64 {
+        let (reserve_0, reserve_1) = self.reserves();
+        let amount_0 = reserve_0 as f64 / 10f64.powi(self.mint_0_decimals as i32);
+        let amount_1 = reserve_1 as f64 / 10f64.powi(self.mint_1_decimals as i32);
+        let (token, sol) = if *mint == self.token_0_mint { (amount_0, amount_1) } else { (amount_1, amount_0) };
+        if token == 0.0 {
+            return 0.0;
+        }
+        sol / token
+    }
+
+    /// Output for `amount_in` of the input side, after the trade fee is taken from the input.
+    pub fn quote_exact_in(&self, input_is_token_0: bool, amount_in: u64) -> u64 {
+        let fee = (amount_in as u128 * self.trade_fee_rate as u128).div_ceil(FEE_RATE_DENOMINATOR);
+        let amount_in = (amount_in as u128).saturating_sub(fee);
+        let (reserve_0, reserve_1) = self.reserves();
+        let (reserve_in, reserve_out) = if input_is_token_0 { (reserve_0, reserve_1) } else { (reserve_1, reserve_0) };
+        let denominator = reserve_in + amount_in;
+        if denominator == 0 {
+            return 0;
+        }
+        (amount_in * reserve_out / denominator).min(u64::MAX as u128) as u64
+    }
+}
+
+// Vault balances and the config's trade fee rate, in one round trip.
+async fn fill_state(client: &RpcClient, pools: &mut [CpmmPool]) -> Result<()> {
+    if pools.is_empty() {
+        return Ok(());
+    }
+    let vaults: Vec<Pubkey> = pools.iter().flat_map(|pool| [pool.token_0_vault, pool.token_1_vault]).collect();
+    let amounts = token_account_amounts(client, &vaults).await?;
+    let configs: Vec<Pubkey> = pools.iter().map(|pool| pool.amm_config).collect();
+    let configs = client.get_multiple_accounts(&configs).await?;
+    for ((pool, amounts), config) in pools.iter_mut().zip(amounts.chunks(2)).zip(configs) {
+        pool.token_0_vault_amount = amounts[0];
+        pool.token_1_vault_amount = amounts[1];
+        pool.trade_fee_rate = config
+            .and_then(|config| read_u64(&config.data, CONFIG_TRADE_FEE_RATE_OFFSET).ok())
+            .ok_or_else(|| anyhow!("AMM config {} of pool {} not found", pool.amm_config, pool.pool_id))?;
+    }
+    Ok(())
+}
+
+/// The pool at `pool_id`, with its reserves and fee rate.
+pub async fn get_pool(client: &RpcClient, pool_id: &Pubkey) -> Result<CpmmPool> {
+    let data = client
+        .get_account_data(pool_id)
+        .await
+        .map_err(|e| anyhow!("no Raydium CPMM pool at {}: {}", pool_id, e))?;
+    let mut pool = CpmmPool::decode(*pool_id, &data)?;
+    fill_state(client, std::slice::from_mut(&mut pool)).await?;
+    Ok(pool)
+}
+
+/// Trading CPMM pools that pair `mint` with WSOL, with their reserves and fee rates.
+pub async fn find_pools(client: &RpcClient, mint: &Pubkey) -> Result<Vec<CpmmPool>> {
+    let program = Pubkey::from_str(RAYDIUM_CPMM_PROGRAM)?;
+    let accounts = find_sol_pairs(
+        client,
+        &program,
+        POOL_STATE_SIZE,
+        (POOL_TOKEN_0_MINT_OFFSET, POOL_TOKEN_1_MINT_OFFSET),
+        mint,
+    )
+    .await?;
+    let mut pools: Vec<CpmmPool> = accounts
+        .iter()
+        .filter_map(|(pool_id, account)| CpmmPool::decode(*pool_id, &account.data).ok())
+        .filter(|pool| pool.is_trading())
+        .collect();
+    fill_state(client, &mut pools).await?;
+    Ok(pools)
+}
+
+/// The SOL pool of `mint` holding the most SOL.
+pub async fn deepest_pool(client: &RpcClient, mint: &Pubkey) -> Result<CpmmPool> {
+    find_pools(client, mint)
+        .await?
+        .into_iter()
+        .max_by_key(|pool| pool.sol_reserve())
+        .ok_or_else(|| anyhow!("no Raydium CPMM pool for {}", mint))
+}
+
+pub struct RaydiumCpmm {
+    pub rpc_nonblocking_client: Arc<RpcClient>,
+    pub rpc_client: Arc<BlockingRpcClient>,
+    pub keypair: SharedSigner,
+}
+
+impl RaydiumCpmm {
+    pub fn new(
+        rpc_nonblocking_client: Arc<RpcClient>,
+        rpc_client: Arc<BlockingRpcClient>,
+        keypair: SharedSigner,
+    ) -> Self {
+        Self {
+            rpc_nonblocking_client,
+            rpc_client,
+            keypair,
+        }
+    }
+
+    /// Price of `mint` in its deepest SOL pool.
+    pub async fn get_token_price(&self, mint: &str) -> Result<f64> {
+        let mint = Pubkey::from_str(mint)?;
+        let pool = deepest_pool(&self.rpc_nonblocking_client, &mint).await?;
+        Ok(pool.price(&mint))
+    }
+
+    /// Builds a buy or sell of `mint` against its SOL pool. Buys spend
+    /// `amount_in` SOL; sells spend a quantity or a fraction of the wallet's tokens.
+    pub async fn build_swap_ixn_by_mint(
+        &self,
+        mint: &str,
+        pool: Option<CpmmPool>,
+        swap_config: SwapConfig,
+        _start_time: Instant,
+    ) -> Result<(SharedSigner, Vec<Instruction>, f64)> {
+        let mint = Pubkey::from_str(mint)?;
+        let pool = match pool {
+            Some(pool) => pool,
+            None => deepest_pool(&self.rpc_nonblocking_client, &mint).await?,
+        };
+        if !pool.is_trading() {
+            return Err(anyhow!("Raydium CPMM pool {} does not accept swaps (status {})", pool.pool_id, pool.status));
+        }
+        let mint_is_token_0 = mint == pool.token_0_mint;
+        let token_decimals = if mint_is_token_0 { pool.mint_0_decimals } else { pool.mint_1_decimals };
+        // Token-2022 mints may withhold a transfer fee on the way in and out of the pool
+        let (token_program, extensions) =
+            mint_program_and_extensions(&self.rpc_nonblocking_client, &self.keypair, &mint).await?;
+        let wsol = spl_token::native_mint::ID;
+
+        let owner = self.keypair.pubkey();
+        let token_ata = get_associated_token_address_with_program_id(&owner, &mint, &token_program);
+        let wsol_ata = get_associated_token_address_with_program_id(&owner, &wsol, &spl_token::ID);
+
+        let mut instructions = Vec::new();
+        let mut close_token = false;
+        let swap_ix = match swap_config.swap_direction {
+            SwapDirection::Buy => {
+                let sol_in = (swap_config.amount_in * LAMPORTS_PER_SOL as f64) as u64;
+                let token_out = pool.quote_exact_in(!mint_is_token_0, sol_in);
+                let minimum_out = minimum_out(token_out - extensions.transfer_fee_on(token_out), swap_config.slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &mint, &token_program));
+                instructions.extend(wrap_sol(&owner, &wsol_ata, sol_in)?);
+                self.swap_instruction(&pool, &owner, (wsol, wsol_ata), (mint, token_ata), sol_in, minimum_out)?
+            }
+            SwapDirection::Sell => {
+                let (token_in, whole_balance) =
+                    sell_amount(&self.rpc_nonblocking_client, &token_ata, &swap_config, token_decimals).await?;
+                close_token = whole_balance;
+                let token_received = token_in - extensions.transfer_fee_on(token_in);
+                let minimum_out = minimum_out(pool.quote_exact_in(mint_is_token_0, token_received), swap_config.slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &wsol, &spl_token::ID));
+                self.swap_instruction(&pool, &owner, (mint, token_ata), (wsol, wsol_ata), token_in, minimum_out)?
+            }
+        };
+        instructions.push(swap_ix);
+        instructions.push(spl_token::instruction::close_account(&spl_token::ID, &wsol_ata, &owner, &owner, &[])?);
+        if close_token {
+            instructions.push(spl_token_2022::instruction::close_account(&token_program, &token_ata, &owner, &owner, &[])?);
+        }
+
+        Ok((self.keypair.clone(), instructions, pool.price(&mint)))
+    }
+
+    // `input` and `output` are (mint, owner's token account) pairs.
+    fn swap_instruction(
+        &self,
+        pool: &CpmmPool,
+        owner: &Pubkey,
+        input: (Pubkey, Pubkey),
+        output: (Pubkey, Pubkey),
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<Instruction> {
+        let program = Pubkey::from_str(RAYDIUM_CPMM_PROGRAM)?;
+        let (authority, _) = Pubkey::find_program_address(&[b"vault_and_lp_mint_auth_seed"], &program);
+        let vault = |mint: &Pubkey| if *mint == pool.token_0_mint { pool.token_0_vault } else { pool.token_1_vault };
+
+        let mut data = Vec::with_capacity(24);
+        data.extend_from_slice(&SWAP_BASE_INPUT_DISCRIMINATOR);
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+
+        Ok(Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(authority, false),
+                AccountMeta::new_readonly(pool.amm_config, false),
+                AccountMeta::new(pool.pool_id, false),
+                AccountMeta::new(input.1, false),
+                AccountMeta::new(output.1, false),
+                AccountMeta::new(vault(&input.0), false),
+                AccountMeta::new(vault(&output.0), false),
+                AccountMeta::new_readonly(pool.token_program(&input.0), false),
+                AccountMeta::new_readonly(pool.token_program(&output.0), false),
+                AccountMeta::new_readonly(input.0, false),
+                AccountMeta::new_readonly(output.0, false),
+                AccountMeta::new(pool.observation, false),
+            ],
+            data,
+        })
+    }
+}
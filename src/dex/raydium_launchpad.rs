@@ -0,0 +1,320 @@
+use std::{str::FromStr, sync::Arc};
+
+use anchor_client::solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::RpcClient as BlockingRpcClient};
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signer::Signer,
+};
+use anyhow::{anyhow, Result};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account_idempotent,
+};
+use tokio::time::Instant;
+
+use super::common::{minimum_out, mint_infos, mint_program_and_extensions, read_pubkey, read_u64, sell_amount, wrap_sol};
+use crate::common::config::SwapConfig;
+use crate::core::signer::SharedSigner;
+use crate::engine::swap::SwapDirection;
+
+pub const RAYDIUM_LAUNCHPAD_PROGRAM: &str = "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj";
+
+pub const BUY_EXACT_IN_DISCRIMINATOR: [u8; 8] = [250, 234, 13, 123, 213, 156, 19, 236];
+pub const BUY_EXACT_OUT_DISCRIMINATOR: [u8; 8] = [24, 211, 116, 40, 105, 3, 153, 56];
+pub const SELL_EXACT_IN_DISCRIMINATOR: [u8; 8] = [149, 39, 222, 155, 211, 124, 152, 26];
+pub const SELL_EXACT_OUT_DISCRIMINATOR: [u8; 8] = [95, 200, 71, 34, 8, 9, 11, 166];
+pub const INITIALIZE_DISCRIMINATOR: [u8; 8] = [175, 175, 109, 31, 13, 152, 155, 237];
+pub const INITIALIZE_V2_DISCRIMINATOR: [u8; 8] = [67, 153, 175, 39, 218, 16, 38, 32];
+
+/// Fee rates are expressed in millionths.
+pub const FEE_RATE_DENOMINATOR: u128 = 1_000_000;
+/// `PoolState::status` while the curve is still trading.
+pub const POOL_STATUS_TRADING: u8 = 0;
+/// `GlobalConfig::curve_type` of a constant product curve.
+pub const CURVE_TYPE_CONSTANT_PRODUCT: u8 = 0;
+
+// PoolState: discriminator (8) | epoch (u64) | auth_bump | status | base_decimals |
+// quote_decimals | migrate_type | supply | total_base_sell | virtual_base | virtual_quote |
+// real_base | real_quote | total_quote_fund_raising | quote_protocol_fee | platform_fee |
+// migrate_fee (u64s) | vesting_schedule (5 x u64) | global_config | platform_config |
+// base_mint | quote_mint | base_vault | quote_vault | creator
+const POOL_STATUS_OFFSET: usize = 17;
+const POOL_BASE_DECIMALS_OFFSET: usize = 18;
+const POOL_QUOTE_DECIMALS_OFFSET: usize = 19;
+const POOL_VIRTUAL_BASE_OFFSET: usize = 37;
+const POOL_VIRTUAL_QUOTE_OFFSET: usize = 45;
+const POOL_REAL_BASE_OFFSET: usize = 53;
+const POOL_REAL_QUOTE_OFFSET: usize = 61;
+const POOL_GLOBAL_CONFIG_OFFSET: usize = 141;
+const POOL_PLATFORM_CONFIG_OFFSET: usize = 173;
+const POOL_BASE_MINT_OFFSET: usize = 205;
+const POOL_QUOTE_MINT_OFFSET: usize = 237;
+const POOL_BASE_VAULT_OFFSET: usize = 269;
+const POOL_QUOTE_VAULT_OFFSET: usize = 301;
+const POOL_CREATOR_OFFSET: usize = 333;
+// GlobalConfig: discriminator (8) | epoch (u64) | curve_type (u8) | index (u16) | migrate_fee | trade_fee_rate
+const GLOBAL_CURVE_TYPE_OFFSET: usize = 16;
+const GLOBAL_TRADE_FEE_RATE_OFFSET: usize = 27;
+// PlatformConfig: discriminator (8) | epoch | platform_fee_wallet | platform_nft_wallet |
+// platform_scale | creator_scale | burn_scale | fee_rate
+const PLATFORM_FEE_RATE_OFFSET: usize = 104;
+
+/// A LaunchLab bonding curve pool, decoded from its `PoolState` account.
+#[derive(Debug, Clone)]
+pub struct LaunchpadPool {
+    pub pool_id: Pubkey,
+    pub status: u8,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub virtual_base: u64,
+    pub virtual_quote: u64,
+    pub real_base: u64,
+    pub real_quote: u64,
+    pub global_config: Pubkey,
+    pub platform_config: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub creator: Pubkey,
+    /// SPL Token or Token-2022, read from the base mint by [`get_pool`].
+    pub base_token_program: Pubkey,
+}
+
+impl LaunchpadPool {
+    pub fn decode(pool_id: Pubkey, data: &[u8]) -> Result<Self> {
+        let read_u8 = |offset: usize| data.get(offset).copied().ok_or_else(|| anyhow!("pool {} is too short", pool_id));
+        Ok(Self {
+            pool_id,
+            status: read_u8(POOL_STATUS_OFFSET)?,
+            base_decimals: read_u8(POOL_BASE_DECIMALS_OFFSET)?,
+            quote_decimals: read_u8(POOL_QUOTE_DECIMALS_OFFSET)?,
+            virtual_base: read_u64(data, POOL_VIRTUAL_BASE_OFFSET)?,
+            virtual_quote: read_u64(data, POOL_VIRTUAL_QUOTE_OFFSET)?,
+            real_base: read_u64(data, POOL_REAL_BASE_OFFSET)?,
+            real_quote: read_u64(data, POOL_REAL_QUOTE_OFFSET)?,
+            global_config: read_pubkey(data, POOL_GLOBAL_CONFIG_OFFSET)?,
+            platform_config: read_pubkey(data, POOL_PLATFORM_CONFIG_OFFSET)?,
+            base_mint: read_pubkey(data, POOL_BASE_MINT_OFFSET)?,
+            quote_mint: read_pubkey(data, POOL_QUOTE_MINT_OFFSET)?,
+            base_vault: read_pubkey(data, POOL_BASE_VAULT_OFFSET)?,
+            quote_vault: read_pubkey(data, POOL_QUOTE_VAULT_OFFSET)?,
+            creator: read_pubkey(data, POOL_CREATOR_OFFSET)?,
+            base_token_program: spl_token::ID,
+        })
+    }
+
+    pub fn is_trading(&self) -> bool {
+        self.status == POOL_STATUS_TRADING
+    }
+
+    /// Quote-side reserve the curve prices against.
+    fn quote_reserve(&self) -> u128 {
+        self.virtual_quote as u128 + self.real_quote as u128
+    }
+
+    /// Base-side reserve the curve prices against.
+    fn base_reserve(&self) -> u128 {
+        (self.virtual_base as u128).saturating_sub(self.real_base as u128)
+    }
+
+    /// Spot price in SOL per whole token.
+    pub fn price(&self) -> f64 {
+        let base = self.base_reserve() as f64 / 10f64.powi(self.base_decimals as i32);
+        if base == 0.0 {
+            return 0.0;
+        }
+        self.quote_reserve() as f64 / 10f64.powi(self.quote_decimals as i32) / base
+    }
+
+    /// Tokens received for `quote_in` lamports, after `fee_rate` is taken from the input.
+    pub fn quote_buy_exact_in(&self, quote_in: u64, fee_rate: u64) -> u64 {
+        let fee = (quote_in as u128 * fee_rate as u128).div_ceil(FEE_RATE_DENOMINATOR);
+        let quote_in = (quote_in as u128).saturating_sub(fee);
+        let denominator = self.quote_reserve() + quote_in;
+        if denominator == 0 {
+            return 0;
+        }
+        (quote_in * self.base_reserve() / denominator).min(u64::MAX as u128) as u64
+    }
+
+    /// Lamports received for `base_in` tokens, after `fee_rate` is taken from the output.
+    pub fn quote_sell_exact_in(&self, base_in: u64, fee_rate: u64) -> u64 {
+        let denominator = self.base_reserve() + base_in as u128;
+        if denominator == 0 {
+            return 0;
+        }
+        let quote_out = base_in as u128 * self.quote_reserve() / denominator;
+        let fee = (quote_out * fee_rate as u128).div_ceil(FEE_RATE_DENOMINATOR);
+        quote_out.saturating_sub(fee).min(u64::MAX as u128) as u64
+    }
+}
+
+pub fn pool_address(base_mint: &Pubkey, quote_mint: &Pubkey) -> Result<Pubkey> {
+    let program = Pubkey::from_str(RAYDIUM_LAUNCHPAD_PROGRAM)?;
+    Ok(Pubkey::find_program_address(&[b"pool", base_mint.as_ref(), quote_mint.as_ref()], &program).0)
+}
+
+/// The SOL-quoted LaunchLab pool of `mint`.
+pub async fn get_pool(client: &RpcClient, mint: &Pubkey) -> Result<LaunchpadPool> {
+    let pool_id = pool_address(mint, &spl_token::native_mint::ID)?;
+    let data = client
+        .get_account_data(&pool_id)
+        .await
+        .map_err(|e| anyhow!("no LaunchLab pool for {}: {}", mint, e))?;
+    let mut pool = LaunchpadPool::decode(pool_id, &data)?;
+    (pool.base_token_program, _) = mint_infos(client, &[pool.base_mint]).await?[0];
+    Ok(pool)
+}
+
+pub struct RaydiumLaunchpad {
+    pub rpc_nonblocking_client: Arc<RpcClient>,
+    pub rpc_client: Arc<BlockingRpcClient>,
+    pub keypair: SharedSigner,
+}
+
+impl RaydiumLaunchpad {
+    pub fn new(
+        rpc_nonblocking_client: Arc<RpcClient>,
+        rpc_client: Arc<BlockingRpcClient>,
+        keypair: SharedSigner,
+    ) -> Self {
+        Self {
+            rpc_nonblocking_client,
+            rpc_client,
+            keypair,
+        }
+    }
+
+    pub async fn get_token_price(&self, mint: &str) -> Result<f64> {
+        let pool = get_pool(&self.rpc_nonblocking_client, &Pubkey::from_str(mint)?).await?;
+        Ok(pool.price())
+    }
+
+    /// Combined protocol and platform fee rate of the pool, in millionths.
+    async fn fee_rate(&self, pool: &LaunchpadPool) -> Result<u64> {
+        let accounts = self
+            .rpc_nonblocking_client
+            .get_multiple_accounts(&[pool.global_config, pool.platform_config])
+            .await?;
+        let global = accounts
+            .first()
+            .and_then(|account| account.as_ref())
+            .ok_or_else(|| anyhow!("global config {} not found", pool.global_config))?;
+        let curve_type = global.data.get(GLOBAL_CURVE_TYPE_OFFSET).copied();
+        if curve_type != Some(CURVE_TYPE_CONSTANT_PRODUCT) {
+            return Err(anyhow!("unsupported LaunchLab curve type {:?}", curve_type));
+        }
+        let trade_fee_rate = read_u64(&global.data, GLOBAL_TRADE_FEE_RATE_OFFSET)?;
+        let platform_fee_rate = accounts
+            .get(1)
+            .and_then(|account| account.as_ref())
+            .and_then(|account| read_u64(&account.data, PLATFORM_FEE_RATE_OFFSET).ok())
+            .unwrap_or_default();
+        Ok(trade_fee_rate + platform_fee_rate)
+    }
+
+    /// Builds a buy or sell of `mint` on its LaunchLab curve. Buys spend
+    /// `amount_in` SOL; sells spend a quantity or a fraction of the wallet's tokens.
+    pub async fn build_swap_ixn_by_mint(
+        &self,
+        mint: &str,
+        pool: Option<LaunchpadPool>,
+        swap_config: SwapConfig,
+        _start_time: Instant,
+    ) -> Result<(SharedSigner, Vec<Instruction>, f64)> {
+        let mint = Pubkey::from_str(mint)?;
+        let pool = match pool {
+            Some(pool) => pool,
+            None => get_pool(&self.rpc_nonblocking_client, &mint).await?,
+        };
+        if !pool.is_trading() {
+            return Err(anyhow!("LaunchLab pool {} is no longer trading (status {})", pool.pool_id, pool.status));
+        }
+        let fee_rate = self.fee_rate(&pool).await?;
+        // Token-2022 base mints may withhold a transfer fee on the way in and out of the pool
+        let (token_program, extensions) =
+            mint_program_and_extensions(&self.rpc_nonblocking_client, &self.keypair, &pool.base_mint).await?;
+
+        let owner = self.keypair.pubkey();
+        let base_ata = get_associated_token_address_with_program_id(&owner, &pool.base_mint, &token_program);
+        let quote_ata = get_associated_token_address_with_program_id(&owner, &pool.quote_mint, &spl_token::ID);
+        let slippage = swap_config.slippage;
+
+        let mut instructions = Vec::new();
+        let mut close_base = false;
+        let swap_ix = match swap_config.swap_direction {
+            SwapDirection::Buy => {
+                let quote_in = (swap_config.amount_in * LAMPORTS_PER_SOL as f64) as u64;
+                let base_out = pool.quote_buy_exact_in(quote_in, fee_rate);
+                let minimum_out = minimum_out(base_out - extensions.transfer_fee_on(base_out), slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &pool.base_mint, &token_program));
+                instructions.extend(wrap_sol(&owner, &quote_ata, quote_in)?);
+                self.swap_instruction(BUY_EXACT_IN_DISCRIMINATOR, &pool, &owner, base_ata, quote_ata, quote_in, minimum_out)?
+            }
+            SwapDirection::Sell => {
+                let (base_in, whole_balance) =
+                    sell_amount(&self.rpc_nonblocking_client, &base_ata, &swap_config, pool.base_decimals).await?;
+                close_base = whole_balance;
+                let base_received = base_in - extensions.transfer_fee_on(base_in);
+                let minimum_out = minimum_out(pool.quote_sell_exact_in(base_received, fee_rate), slippage);
+                instructions.push(create_associated_token_account_idempotent(&owner, &owner, &pool.quote_mint, &spl_token::ID));
+                self.swap_instruction(SELL_EXACT_IN_DISCRIMINATOR, &pool, &owner, base_ata, quote_ata, base_in, minimum_out)?
+            }
+        };
+        instructions.push(swap_ix);
+        // Unwraps the SOL received, or whatever a buy did not spend
+        instructions.push(spl_token::instruction::close_account(&spl_token::ID, &quote_ata, &owner, &owner, &[])?);
+        if close_base {
+            instructions.push(spl_token_2022::instruction::close_account(&token_program, &base_ata, &owner, &owner, &[])?);
+        }
+
+        Ok((self.keypair.clone(), instructions, pool.price()))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn swap_instruction(
+        &self,
+        discriminator: [u8; 8],
+        pool: &LaunchpadPool,
+        owner: &Pubkey,
+        base_ata: Pubkey,
+        quote_ata: Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<Instruction> {
+        let program = Pubkey::from_str(RAYDIUM_LAUNCHPAD_PROGRAM)?;
+        let (authority, _) = Pubkey::find_program_address(&[b"vault_auth_seed"], &program);
+        let (event_authority, _) = Pubkey::find_program_address(&[b"__event_authority"], &program);
+
+        let mut data = Vec::with_capacity(32);
+        data.extend_from_slice(&discriminator);
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        // share_fee_rate: no referral share
+        data.extend_from_slice(&0u64.to_le_bytes());
+
+        Ok(Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(authority, false),
+                AccountMeta::new_readonly(pool.global_config, false),
+                AccountMeta::new_readonly(pool.platform_config, false),
+                AccountMeta::new(pool.pool_id, false),
+                AccountMeta::new(base_ata, false),
+                AccountMeta::new(quote_ata, false),
+                AccountMeta::new(pool.base_vault, false),
+                AccountMeta::new(pool.quote_vault, false),
+                AccountMeta::new_readonly(pool.base_mint, false),
+                AccountMeta::new_readonly(pool.quote_mint, false),
+                AccountMeta::new_readonly(pool.base_token_program, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(event_authority, false),
+                AccountMeta::new_readonly(program, false),
+            ],
+            data,
+        })
+    }
+}
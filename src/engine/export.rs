@@ -0,0 +1,400 @@
+use anchor_client::solana_sdk::native_token::LAMPORTS_PER_SOL;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{collections::BTreeMap, io::Write};
+
+use super::journal::{by_day, chronological, replay, CostBasis};
+use super::store::{FillRecord, FillSide};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ExportKind {
+    /// One row per confirmed buy or sell
+    Fills,
+    /// One row per fully closed position
+    Positions,
+}
+
+/// Restricts an export to fills within a UTC date range and/or from one copy target.
+/// Both dates are inclusive.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub target: Option<String>,
+}
+
+impl ExportFilter {
+    fn matches_day(&self, timestamp_ms: i64) -> bool {
+        let Some(day) = DateTime::<Utc>::from_timestamp_millis(timestamp_ms).map(|t| t.date_naive()) else {
+            return false;
+        };
+        self.from.map_or(true, |from| day >= from) && self.to.map_or(true, |to| day <= to)
+    }
+
+    fn matches_target(&self, target: Option<&str>) -> bool {
+        self.target.as_deref().map_or(true, |wanted| target == Some(wanted))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FillRow {
+    timestamp: String,
+    day: String,
+    signature: String,
+    slot: u64,
+    side: FillSide,
+    mint: String,
+    target: String,
+    token_delta: i64,
+    sol_in: f64,
+    sol_out: f64,
+    fee_sol: f64,
+    tip_sol: f64,
+    rent_sol: f64,
+    realized_pnl_sol: f64,
+    reason: String,
+}
+
+impl FillRow {
+    const HEADER: &'static [&'static str] = &[
+        "timestamp", "day", "signature", "slot", "side", "mint", "target", "token_delta",
+        "sol_in", "sol_out", "fee_sol", "tip_sol", "rent_sol", "realized_pnl_sol", "reason",
+    ];
+
+    fn new(fill: &FillRecord, realized: f64) -> Self {
+        let sol = lamports_to_sol(fill.sol_delta);
+        Self {
+            timestamp: format_timestamp(fill.timestamp_ms),
+            day: by_day(fill),
+            signature: fill.signature.clone(),
+            slot: fill.slot,
+            side: fill.side,
+            mint: fill.mint.clone(),
+            target: fill.target.clone().unwrap_or_default(),
+            token_delta: fill.token_delta,
+            sol_in: (-sol).max(0.0),
+            sol_out: sol.max(0.0),
+            fee_sol: lamports_to_sol(fill.fee as i64),
+            tip_sol: lamports_to_sol(fill.tip as i64),
+            rent_sol: lamports_to_sol(fill.rent),
+            realized_pnl_sol: realized,
+            reason: fill.reason.map(|reason| reason.to_string()).unwrap_or_default(),
+        }
+    }
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.timestamp.clone(),
+            self.day.clone(),
+            self.signature.clone(),
+            self.slot.to_string(),
+            side_label(self.side).to_string(),
+            self.mint.clone(),
+            self.target.clone(),
+            self.token_delta.to_string(),
+            self.sol_in.to_string(),
+            self.sol_out.to_string(),
+            self.fee_sol.to_string(),
+            self.tip_sol.to_string(),
+            self.rent_sol.to_string(),
+            self.realized_pnl_sol.to_string(),
+            self.reason.clone(),
+        ]
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct PositionRow {
+    opened_at: String,
+    closed_at: String,
+    mint: String,
+    target: String,
+    buys: usize,
+    sells: usize,
+    sol_in: f64,
+    sol_out: f64,
+    fee_sol: f64,
+    tip_sol: f64,
+    realized_pnl_sol: f64,
+    exit_reason: String,
+}
+
+impl PositionRow {
+    const HEADER: &'static [&'static str] = &[
+        "opened_at", "closed_at", "mint", "target", "buys", "sells", "sol_in", "sol_out",
+        "fee_sol", "tip_sol", "realized_pnl_sol", "exit_reason",
+    ];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.opened_at.clone(),
+            self.closed_at.clone(),
+            self.mint.clone(),
+            self.target.clone(),
+            self.buys.to_string(),
+            self.sells.to_string(),
+            self.sol_in.to_string(),
+            self.sol_out.to_string(),
+            self.fee_sol.to_string(),
+            self.tip_sol.to_string(),
+            self.realized_pnl_sol.to_string(),
+            self.exit_reason.clone(),
+        ]
+    }
+}
+
+/// Builds one row per position that was fully sold, from its fills in chain order.
+/// A position belongs to the day it was closed.
+fn closed_positions(fills: &[FillRecord]) -> Vec<(i64, Option<String>, PositionRow)> {
+    let mut open: BTreeMap<&str, (CostBasis, i64, PositionRow)> = BTreeMap::new();
+    let mut closed = Vec::new();
+    for fill in chronological(fills) {
+        let (basis, _, row) = open.entry(fill.mint.as_str()).or_insert_with(|| {
+            (
+                CostBasis::default(),
+                fill.timestamp_ms,
+                PositionRow {
+                    opened_at: format_timestamp(fill.timestamp_ms),
+                    mint: fill.mint.clone(),
+                    target: fill.target.clone().unwrap_or_default(),
+                    ..Default::default()
+                },
+            )
+        });
+        let realized = basis.apply(fill);
+        let sol = lamports_to_sol(fill.sol_delta);
+        match fill.side {
+            FillSide::Buy => {
+                row.buys += 1;
+                row.sol_in -= sol;
+            }
+            FillSide::Sell => {
+                row.sells += 1;
+                row.sol_out += sol;
+            }
+        }
+        row.fee_sol += lamports_to_sol(fill.fee as i64);
+        row.tip_sol += lamports_to_sol(fill.tip as i64);
+        row.realized_pnl_sol += realized;
+
+        if fill.side == FillSide::Sell && basis.token_amount == 0 {
+            let (_, _, mut row) = open.remove(fill.mint.as_str()).expect("position was just updated");
+            row.closed_at = format_timestamp(fill.timestamp_ms);
+            row.exit_reason = fill.reason.map(|reason| reason.to_string()).unwrap_or_default();
+            closed.push((fill.timestamp_ms, fill.target.clone(), row));
+        }
+    }
+    closed
+}
+
+/// Writes the filtered journal to `out` and returns the number of rows written.
+///
+/// PnL is computed from every fill before filtering, so a sell in range is
+/// measured against a buy made before `from`.
+pub fn export_journal(
+    fills: &[FillRecord],
+    kind: ExportKind,
+    format: ExportFormat,
+    filter: &ExportFilter,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    match kind {
+        ExportKind::Fills => {
+            let rows: Vec<FillRow> = replay(fills)
+                .into_iter()
+                .filter(|(fill, _)| filter.matches_day(fill.timestamp_ms) && filter.matches_target(fill.target.as_deref()))
+                .map(|(fill, realized)| FillRow::new(fill, realized))
+                .collect();
+            write_rows(out, format, FillRow::HEADER, &rows, FillRow::csv_fields)
+        }
+        ExportKind::Positions => {
+            let rows: Vec<PositionRow> = closed_positions(fills)
+                .into_iter()
+                .filter(|(closed_at, target, _)| filter.matches_day(*closed_at) && filter.matches_target(target.as_deref()))
+                .map(|(_, _, row)| row)
+                .collect();
+            write_rows(out, format, PositionRow::HEADER, &rows, PositionRow::csv_fields)
+        }
+    }
+}
+
+fn write_rows<T: Serialize>(
+    out: &mut dyn Write,
+    format: ExportFormat,
+    header: &[&str],
+    rows: &[T],
+    csv_fields: fn(&T) -> Vec<String>,
+) -> Result<usize> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{}", header.join(","))?;
+            for row in rows {
+                let fields: Vec<String> = csv_fields(row).iter().map(|field| csv_escape(field)).collect();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+        }
+        ExportFormat::Jsonl => {
+            for row in rows {
+                serde_json::to_writer(&mut *out, row)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(rows.len())
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn side_label(side: FillSide) -> &'static str {
+    match side {
+        FillSide::Buy => "buy",
+        FillSide::Sell => "sell",
+    }
+}
+
+fn lamports_to_sol(lamports: i64) -> f64 {
+    lamports as f64 / LAMPORTS_PER_SOL as f64
+}
+
+fn format_timestamp(timestamp_ms: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(timestamp_ms)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// Parses a `YYYY-MM-DD` date for `ExportFilter`.
+pub fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| anyhow!("invalid date `{}` (expected YYYY-MM-DD): {}", value, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::strategy::SellReason;
+
+    const MINT: &str = "So11111111111111111111111111111111111111112";
+    const OTHER_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const RENT: i64 = 2_039_280;
+    // 2024-06-01T00:00:00Z
+    const DAY_MS: i64 = 1_717_200_000_000;
+
+    fn fill(mint: &str, side: FillSide, token_delta: i64, sol_delta: i64, rent: i64, timestamp_ms: i64) -> FillRecord {
+        FillRecord {
+            signature: format!("{}-{}", mint, timestamp_ms),
+            mint: mint.to_string(),
+            side,
+            slot: 1,
+            target: Some("target".to_string()),
+            token_delta,
+            token_decimals: 6,
+            sol_delta,
+            fee: 5_000,
+            tip: 1_000_000,
+            rent,
+            reason: (side == FillSide::Sell).then_some(SellReason::TakeProfit),
+            timestamp_ms,
+        }
+    }
+
+    /// A buy, a 40% sell and a closing sell of MINT, with a buy of OTHER_MINT
+    /// in between that is never sold.
+    fn fills() -> Vec<FillRecord> {
+        let hour = 3_600_000;
+        vec![
+            fill(MINT, FillSide::Buy, 1_000_000_000, -(100_000_000 + 1_005_000 + RENT), RENT, DAY_MS),
+            fill(OTHER_MINT, FillSide::Buy, 5_000_000, -(20_000_000 + 1_005_000 + RENT), RENT, DAY_MS + hour),
+            fill(MINT, FillSide::Sell, -400_000_000, 60_000_000 - 1_005_000, 0, DAY_MS + 2 * hour),
+            // Closed the next day, refunding the account rent
+            fill(MINT, FillSide::Sell, -600_000_000, 90_000_000 - 1_005_000 + RENT, -RENT, DAY_MS + 26 * hour),
+        ]
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-12, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn closed_positions_only_lists_fully_sold_mints() {
+        let closed = closed_positions(&fills());
+        assert_eq!(closed.len(), 1);
+        let (closed_at, target, row) = &closed[0];
+        assert_eq!(*closed_at, DAY_MS + 26 * 3_600_000);
+        assert_eq!(target.as_deref(), Some("target"));
+        assert_eq!(row.mint, MINT);
+        assert_eq!((row.buys, row.sells), (1, 2));
+        assert_eq!(row.opened_at, format_timestamp(DAY_MS));
+        assert_eq!(row.exit_reason, "take_profit");
+        assert_close(row.sol_in, 0.10304428);
+        assert_close(row.sol_out, 0.15002928);
+        assert_close(row.fee_sol, 0.000015);
+        assert_close(row.tip_sol, 0.003);
+        // The rent refund on close offsets the rent paid on the buy
+        assert_close(row.realized_pnl_sol, 0.15 - 0.1 - 0.000015 - 0.003);
+    }
+
+    #[test]
+    fn positions_are_filtered_by_close_day() {
+        let filter = |from: &str, to: &str| ExportFilter {
+            from: Some(parse_date(from).unwrap()),
+            to: Some(parse_date(to).unwrap()),
+            target: None,
+        };
+        let export = |filter: &ExportFilter| {
+            let mut out = Vec::new();
+            let rows = export_journal(&fills(), ExportKind::Positions, ExportFormat::Jsonl, filter, &mut out).unwrap();
+            (rows, String::from_utf8(out).unwrap())
+        };
+        assert_eq!(export(&filter("2024-06-01", "2024-06-01")).0, 0);
+        let (rows, jsonl) = export(&filter("2024-06-02", "2024-06-02"));
+        assert_eq!(rows, 1);
+        let row: serde_json::Value = serde_json::from_str(jsonl.trim()).unwrap();
+        assert_eq!(row["mint"], MINT);
+    }
+
+    #[test]
+    fn fills_carry_realized_pnl_measured_before_the_filter() {
+        let filter = ExportFilter { from: Some(parse_date("2024-06-02").unwrap()), ..Default::default() };
+        let mut out = Vec::new();
+        let rows = export_journal(&fills(), ExportKind::Fills, ExportFormat::Csv, &filter, &mut out).unwrap();
+        assert_eq!(rows, 1);
+        let csv = String::from_utf8(out).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), FillRow::HEADER.join(","));
+        let fields: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(fields[4], "sell");
+        assert_eq!(fields[14], "take_profit");
+        let realized: f64 = fields[13].parse().unwrap();
+        assert_close(realized, 0.09103428 - 0.10304428 * 0.6);
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn csv_escape_quotes_only_when_needed() {
+        for (field, escaped) in [
+            ("plain", "plain"),
+            ("", ""),
+            ("a,b", "\"a,b\""),
+            ("say \"hi\"", "\"say \"\"hi\"\"\""),
+            ("two\nlines", "\"two\nlines\""),
+            ("cr\rlf", "\"cr\rlf\""),
+        ] {
+            assert_eq!(csv_escape(field), escaped);
+        }
+    }
+}
@@ -0,0 +1,646 @@
+use anchor_client::solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey,
+    signature::Signature, signer::Signer,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    UiLoadedAddresses, UiTransactionEncoding, UiTransactionTokenBalance,
+};
+use std::{collections::BTreeMap, str::FromStr, sync::Arc, time::Duration};
+
+use super::position::PositionManager;
+use super::store::{store, FillRecord, FillSide};
+use super::strategy::SellReason;
+use crate::common::{config::AppState, logger::Logger};
+use crate::services::jito::{JITO_SECOND_TIP_ACCOUNT, JITO_TIP_ACCOUNTS};
+
+const FILL_FETCH_ATTEMPTS: u32 = 20;
+const FILL_FETCH_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Accounting of one position, built from its confirmed fills.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostBasis {
+    /// Raw token amount still held.
+    pub token_amount: u64,
+    pub token_decimals: u8,
+    /// SOL paid for the tokens still held, including fees, tips and rent.
+    pub cost_sol: f64,
+    /// SOL per whole token paid to the pool on entry, net of fees, tips and rent.
+    pub entry_price_sol: f64,
+    pub realized_sol: f64,
+}
+
+impl CostBasis {
+    /// Applies a fill and returns the PnL it realized.
+    pub fn apply(&mut self, fill: &FillRecord) -> f64 {
+        let sol = fill.sol_delta as f64 / LAMPORTS_PER_SOL as f64;
+        match fill.side {
+            FillSide::Buy => {
+                let bought = fill.token_delta.max(0) as u64;
+                self.token_decimals = fill.token_decimals;
+                if bought > 0 {
+                    let swap_sol = (-fill.sol_delta - fill.fee as i64 - fill.tip as i64 - fill.rent).max(0);
+                    let held = ui_amount(self.token_amount, self.token_decimals);
+                    let bought_ui = ui_amount(bought, self.token_decimals);
+                    let entry_value = self.entry_price_sol * held + swap_sol as f64 / LAMPORTS_PER_SOL as f64;
+                    self.entry_price_sol = entry_value / (held + bought_ui);
+                }
+                self.token_amount += bought;
+                self.cost_sol -= sol;
+                0.0
+            }
+            FillSide::Sell => {
+                let sold = fill.token_delta.unsigned_abs().min(self.token_amount);
+                let released = if sold == self.token_amount {
+                    self.cost_sol
+                } else {
+                    self.cost_sol * sold as f64 / self.token_amount as f64
+                };
+                self.token_amount -= sold;
+                self.cost_sol -= released;
+                let realized = sol - released;
+                self.realized_sol += realized;
+                realized
+            }
+        }
+    }
+
+    /// Market value of the tokens still held at `price_sol` per whole token.
+    pub fn market_value(&self, price_sol: f64) -> f64 {
+        ui_amount(self.token_amount, self.token_decimals) * price_sol
+    }
+
+    /// SOL per whole token at which the tokens still held are worth `value_sol`.
+    pub fn price_for_value(&self, value_sol: f64) -> f64 {
+        let tokens = ui_amount(self.token_amount, self.token_decimals);
+        if tokens > 0.0 {
+            value_sol / tokens
+        } else {
+            0.0
+        }
+    }
+
+    /// Unrealized PnL in SOL and in percent of the remaining cost, or `None`
+    /// until a buy fill has been recorded.
+    pub fn unrealized(&self, price_sol: f64) -> Option<(f64, f64)> {
+        if self.token_amount == 0 || self.cost_sol <= 0.0 {
+            return None;
+        }
+        let pnl_sol = self.market_value(price_sol) - self.cost_sol;
+        Some((pnl_sol, pnl_sol / self.cost_sol * 100.0))
+    }
+}
+
+fn ui_amount(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10_f64.powi(decimals as i32)
+}
+
+/// Realized totals for a group of fills.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PnlSummary {
+    pub buys: usize,
+    pub sells: usize,
+    pub spent_sol: f64,
+    pub received_sol: f64,
+    pub fees_sol: f64,
+    pub tips_sol: f64,
+    pub realized_sol: f64,
+}
+
+impl PnlSummary {
+    fn add(&mut self, fill: &FillRecord, realized: f64) {
+        let sol = fill.sol_delta as f64 / LAMPORTS_PER_SOL as f64;
+        match fill.side {
+            FillSide::Buy => {
+                self.buys += 1;
+                self.spent_sol -= sol;
+            }
+            FillSide::Sell => {
+                self.sells += 1;
+                self.received_sol += sol;
+            }
+        }
+        self.fees_sol += fill.fee as f64 / LAMPORTS_PER_SOL as f64;
+        self.tips_sol += fill.tip as f64 / LAMPORTS_PER_SOL as f64;
+        self.realized_sol += realized;
+    }
+}
+
+/// `fills` in the order they landed on chain. Fills are recorded once their
+/// transaction is fetched, so a quick sell can be stored before the buy it
+/// closes; ties keep the recorded order.
+pub fn chronological(fills: &[FillRecord]) -> Vec<&FillRecord> {
+    let mut ordered: Vec<&FillRecord> = fills.iter().collect();
+    ordered.sort_by_key(|fill| (fill.slot, fill.timestamp_ms));
+    ordered
+}
+
+/// Replays `fills` per mint in chain order and returns each fill with the PnL it realized.
+pub fn replay(fills: &[FillRecord]) -> Vec<(&FillRecord, f64)> {
+    let mut bases: BTreeMap<&str, CostBasis> = BTreeMap::new();
+    chronological(fills)
+        .into_iter()
+        .map(|fill| {
+            let realized = bases.entry(fill.mint.as_str()).or_default().apply(fill);
+            (fill, realized)
+        })
+        .collect()
+}
+
+/// Realized PnL grouped by `key`.
+pub fn summarize<F>(fills: &[FillRecord], key: F) -> BTreeMap<String, PnlSummary>
+where
+    F: Fn(&FillRecord) -> String,
+{
+    let mut summaries: BTreeMap<String, PnlSummary> = BTreeMap::new();
+    for (fill, realized) in replay(fills) {
+        summaries.entry(key(fill)).or_default().add(fill, realized);
+    }
+    summaries
+}
+
+pub fn by_position(fill: &FillRecord) -> String {
+    fill.mint.clone()
+}
+
+pub fn by_target(fill: &FillRecord) -> String {
+    fill.target.clone().unwrap_or_else(|| "none".to_string())
+}
+
+/// UTC day of the fill, `YYYY-MM-DD`.
+pub fn by_day(fill: &FillRecord) -> String {
+    DateTime::<Utc>::from_timestamp_millis(fill.timestamp_ms)
+        .map(|time| time.date_naive().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Logs realized PnL per target and per day from every recorded fill.
+pub fn log_summary(logger: &Logger) {
+    let Some(store) = store() else {
+        return;
+    };
+    let fills = match store.all_fills() {
+        Ok(fills) => fills,
+        Err(e) => {
+            logger.log(format!("[JOURNAL] => Failed to read fills: {}", e).red().to_string());
+            return;
+        }
+    };
+    for (title, summaries) in [
+        ("TARGET", summarize(&fills, by_target)),
+        ("DAY", summarize(&fills, by_day)),
+    ] {
+        for (key, summary) in summaries {
+            logger.log(format!(
+                "[REALIZED PNL BY {}] => {} | Buys: {} | Sells: {} | Spent: {:.4} SOL | Received: {:.4} SOL | Fees: {:.6} SOL | Tips: {:.6} SOL | Realized: {:+.4} SOL",
+                title, key, summary.buys, summary.sells, summary.spent_sol, summary.received_sol,
+                summary.fees_sol, summary.tips_sol, summary.realized_sol
+            ).magenta().to_string());
+        }
+    }
+}
+
+/// Reads a landed transaction and turns the wallet's balance changes into a fill.
+pub async fn fetch_fill(
+    rpc_client: &RpcClient,
+    signature: &str,
+    mint: &str,
+    owner: &Pubkey,
+    side: FillSide,
+    reason: Option<SellReason>,
+    target: Option<String>,
+) -> Result<FillRecord> {
+    let parsed_signature = Signature::from_str(signature)?;
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+
+    let mut attempt = 0;
+    let transaction = loop {
+        attempt += 1;
+        match rpc_client.get_transaction_with_config(&parsed_signature, config).await {
+            Ok(transaction) => break transaction,
+            Err(e) if attempt >= FILL_FETCH_ATTEMPTS => {
+                return Err(anyhow!("transaction {} not found: {}", signature, e));
+            }
+            Err(_) => tokio::time::sleep(FILL_FETCH_INTERVAL).await,
+        }
+    };
+    parse_fill(&transaction, signature, mint, owner, side, reason, target)
+}
+
+fn parse_fill(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    signature: &str,
+    mint: &str,
+    owner: &Pubkey,
+    side: FillSide,
+    reason: Option<SellReason>,
+    target: Option<String>,
+) -> Result<FillRecord> {
+    let meta = transaction
+        .transaction
+        .meta
+        .as_ref()
+        .ok_or_else(|| anyhow!("transaction {} has no meta", signature))?;
+    if let Some(err) = &meta.err {
+        return Err(anyhow!("transaction {} failed: {:?}", signature, err));
+    }
+
+    let decoded = transaction
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("transaction {} could not be decoded", signature))?;
+    let mut account_keys: Vec<String> = decoded
+        .message
+        .static_account_keys()
+        .iter()
+        .map(|key| key.to_string())
+        .collect();
+    if let Some(loaded) = Option::<UiLoadedAddresses>::from(meta.loaded_addresses.clone()) {
+        account_keys.extend(loaded.writable);
+        account_keys.extend(loaded.readonly);
+    }
+
+    let owner = owner.to_string();
+    let wallet_index = account_keys
+        .iter()
+        .position(|key| *key == owner)
+        .ok_or_else(|| anyhow!("wallet is not part of transaction {}", signature))?;
+    let lamport_delta = |index: usize| -> i64 {
+        meta.post_balances.get(index).copied().unwrap_or_default() as i64
+            - meta.pre_balances.get(index).copied().unwrap_or_default() as i64
+    };
+    let sol_delta = lamport_delta(wallet_index);
+    let fee = if wallet_index == 0 { meta.fee } else { 0 };
+    let tip = account_keys
+        .iter()
+        .enumerate()
+        .filter(|(_, key)| JITO_TIP_ACCOUNTS.contains(&key.as_str()) || key.as_str() == JITO_SECOND_TIP_ACCOUNT)
+        .map(|(index, _)| lamport_delta(index).max(0) as u64)
+        .sum();
+
+    let pre_token_balances = Option::<Vec<UiTransactionTokenBalance>>::from(meta.pre_token_balances.clone()).unwrap_or_default();
+    let post_token_balances = Option::<Vec<UiTransactionTokenBalance>>::from(meta.post_token_balances.clone()).unwrap_or_default();
+    let owned = |balance: &&UiTransactionTokenBalance| {
+        balance.mint == mint && matches!(&balance.owner, OptionSerializer::Some(o) if *o == owner)
+    };
+    let pre_token = pre_token_balances.iter().find(owned);
+    let post_token = post_token_balances.iter().find(owned);
+    let raw_amount = |balance: Option<&UiTransactionTokenBalance>| -> i64 {
+        balance
+            .and_then(|b| b.ui_token_amount.amount.parse::<i64>().ok())
+            .unwrap_or_default()
+    };
+    let token_delta = raw_amount(post_token) - raw_amount(pre_token);
+    let token_decimals = post_token
+        .or(pre_token)
+        .map(|b| b.ui_token_amount.decimals)
+        .unwrap_or_default();
+
+    // Rent paid to open the token account, or refunded when a sell closes it
+    let token_account_index = post_token.or(pre_token).map(|b| b.account_index as usize);
+    let rent = match token_account_index {
+        Some(index) => {
+            let pre = meta.pre_balances.get(index).copied().unwrap_or_default();
+            let post = meta.post_balances.get(index).copied().unwrap_or_default();
+            if pre == 0 && post > 0 {
+                post as i64
+            } else if pre > 0 && post == 0 {
+                -(pre as i64)
+            } else {
+                0
+            }
+        }
+        None => 0,
+    };
+
+    Ok(FillRecord {
+        signature: signature.to_string(),
+        mint: mint.to_string(),
+        side,
+        slot: transaction.slot,
+        target,
+        token_delta,
+        token_decimals,
+        sol_delta,
+        fee,
+        tip,
+        rent,
+        reason,
+        timestamp_ms: transaction
+            .block_time
+            .map(|time| time * 1000)
+            .unwrap_or_else(|| Utc::now().timestamp_millis()),
+    })
+}
+
+/// Waits for the transaction to land, records its fill against the position
+/// and logs the realized result.
+pub fn spawn_record_fill(
+    app_state: Arc<AppState>,
+    positions: Arc<PositionManager>,
+    signature: String,
+    mint: String,
+    side: FillSide,
+    reason: Option<SellReason>,
+    logger: Logger,
+) {
+    tokio::spawn(async move {
+        let target = positions.get(&mint).and_then(|position| position.target);
+        let fill = match fetch_fill(
+            &app_state.rpc_nonblocking_client,
+            &signature,
+            &mint,
+            &app_state.wallet.pubkey(),
+            side,
+            reason,
+            target,
+        )
+        .await
+        {
+            Ok(fill) => fill,
+            Err(e) => {
+                logger.log(format!(
+                    "[JOURNAL] => Failed to record fill of {}: {}", mint, e
+                ).red().to_string());
+                return;
+            }
+        };
+
+        let Some((position, realized)) = positions.apply_fill(&fill) else {
+            return;
+        };
+        logger.log(format!(
+            "[FILL] => {:?} {} | Slot: {} | Tokens: {:+} | SOL: {:+.6} | Fee: {:.6} | Tip: {:.6} | Rent: {:+.6} | Cost basis: {:.6} SOL | Realized: {:+.6} SOL (position {:+.6} SOL)",
+            fill.side, mint, fill.slot, fill.token_delta,
+            fill.sol_delta as f64 / LAMPORTS_PER_SOL as f64,
+            fill.fee as f64 / LAMPORTS_PER_SOL as f64,
+            fill.tip as f64 / LAMPORTS_PER_SOL as f64,
+            fill.rent as f64 / LAMPORTS_PER_SOL as f64,
+            position.basis.cost_sol, realized, position.basis.realized_sol
+        ).cyan().to_string());
+
+        if fill.side == FillSide::Sell && position.basis.token_amount == 0 {
+            log_summary(&logger);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::{
+        hash::Hash, message::Message, transaction::Transaction,
+    };
+    use serde_json::json;
+
+    const MINT: &str = "So11111111111111111111111111111111111111112";
+    const FEE: u64 = 5_000;
+    const TIP: u64 = 1_000_000;
+    const RENT: i64 = 2_039_280;
+    // 1000 whole tokens at 6 decimals
+    const TOKENS: i64 = 1_000_000_000;
+
+    fn fill(mint: &str, side: FillSide, token_delta: i64, sol_delta: i64, rent: i64) -> FillRecord {
+        FillRecord {
+            signature: format!("{:?}-{}-{}", side, token_delta, sol_delta),
+            mint: mint.to_string(),
+            side,
+            slot: 1,
+            target: None,
+            token_delta,
+            token_decimals: 6,
+            sol_delta,
+            fee: FEE,
+            tip: TIP,
+            rent,
+            reason: None,
+            timestamp_ms: 1_717_200_000_000,
+        }
+    }
+
+    /// Buys 1000 tokens for 0.1 SOL, opening the token account, then sells
+    /// 40% for 0.06 SOL and the rest for 0.09 SOL, closing the account.
+    fn round_trip(mint: &str) -> Vec<FillRecord> {
+        let (fee, tip) = (FEE as i64, TIP as i64);
+        vec![
+            fill(mint, FillSide::Buy, TOKENS, -(100_000_000 + fee + tip + RENT), RENT),
+            fill(mint, FillSide::Sell, -TOKENS * 2 / 5, 60_000_000 - fee - tip, 0),
+            fill(mint, FillSide::Sell, -TOKENS * 3 / 5, 90_000_000 - fee - tip + RENT, -RENT),
+        ]
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-12, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn cost_basis_through_partial_and_full_sell() {
+        let fills = round_trip(MINT);
+        let mut basis = CostBasis::default();
+        // (tokens held, cost, realized by the fill, realized in total)
+        let expected = [
+            (1_000_000_000, 0.10304428, 0.0, 0.0),
+            (600_000_000, 0.10304428 * 0.6, 0.058995 - 0.10304428 * 0.4, 0.058995 - 0.10304428 * 0.4),
+            (0, 0.0, 0.09103428 - 0.10304428 * 0.6, 0.046985),
+        ];
+        for (fill, (tokens, cost, realized, total)) in fills.iter().zip(expected) {
+            assert_close(basis.apply(fill), realized);
+            assert_eq!(basis.token_amount, tokens);
+            assert_close(basis.cost_sol, cost);
+            assert_close(basis.realized_sol, total);
+        }
+        // The entry price is what the pool was paid, without fee, tip or rent
+        assert_close(basis.entry_price_sol, 0.0001);
+        assert_eq!(basis.cost_sol, 0.0);
+        assert_eq!(basis.unrealized(0.0002), None);
+    }
+
+    #[test]
+    fn cost_basis_averages_entry_over_buys() {
+        let mut basis = CostBasis::default();
+        basis.apply(&fill(MINT, FillSide::Buy, TOKENS, -(100_000_000 + FEE as i64 + TIP as i64), 0));
+        basis.apply(&fill(MINT, FillSide::Buy, TOKENS, -(300_000_000 + FEE as i64 + TIP as i64), 0));
+        assert_eq!(basis.token_amount, 2 * TOKENS as u64);
+        assert_close(basis.entry_price_sol, 0.0002);
+        assert_close(basis.cost_sol, 0.40201);
+        let (pnl_sol, pnl_pct) = basis.unrealized(0.0003).unwrap();
+        assert_close(pnl_sol, 0.6 - 0.40201);
+        assert_close(pnl_pct, (0.6 - 0.40201) / 0.40201 * 100.0);
+    }
+
+    #[test]
+    fn price_for_value_values_the_tokens_held() {
+        let mut basis = CostBasis::default();
+        assert_eq!(basis.price_for_value(1.0), 0.0);
+        basis.apply(&fill(MINT, FillSide::Buy, 2 * TOKENS, -(400_000_000 + FEE as i64 + TIP as i64), 0));
+        // 0.5 SOL for 2000 whole tokens
+        let price = basis.price_for_value(0.5);
+        assert_close(price, 0.00025);
+        assert_close(basis.market_value(price), 0.5);
+        assert_close(basis.unrealized(price).unwrap().0, 0.5 - basis.cost_sol);
+    }
+
+    #[test]
+    fn replay_orders_fills_by_slot() {
+        let mut fills = round_trip(MINT);
+        for (slot, fill) in fills.iter_mut().enumerate() {
+            fill.slot = 100 + slot as u64;
+        }
+        // The last sell was fetched and recorded before the buy and the first sell
+        let recorded = vec![fills[2].clone(), fills[0].clone(), fills[1].clone()];
+
+        let replayed = replay(&recorded);
+        let slots: Vec<u64> = replayed.iter().map(|(fill, _)| fill.slot).collect();
+        assert_eq!(slots, vec![100, 101, 102]);
+        assert_close(replayed[2].1, 0.09103428 - 0.10304428 * 0.6);
+        assert_close(summarize(&recorded, by_position)[MINT].realized_sol, 0.046985);
+    }
+
+    #[test]
+    fn summarize_ignores_interleaving_across_mints() {
+        const OTHER_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let (first, second) = (round_trip(MINT), round_trip(OTHER_MINT));
+        let sequential: Vec<FillRecord> = first.iter().chain(second.iter()).cloned().collect();
+        let interleaved: Vec<FillRecord> = first
+            .iter()
+            .zip(second.iter())
+            .flat_map(|(a, b)| [b.clone(), a.clone()])
+            .collect();
+
+        let totals = |fills: &[FillRecord]| summarize(fills, |_| "all".to_string()).remove("all").unwrap();
+        let (a, b) = (totals(&sequential), totals(&interleaved));
+        assert_eq!((a.buys, a.sells), (2, 4));
+        assert_eq!((a.buys, a.sells), (b.buys, b.sells));
+        for (x, y) in [
+            (a.spent_sol, b.spent_sol),
+            (a.received_sol, b.received_sol),
+            (a.fees_sol, b.fees_sol),
+            (a.tips_sol, b.tips_sol),
+            (a.realized_sol, b.realized_sol),
+        ] {
+            assert_close(x, y);
+        }
+        assert_close(a.realized_sol, 2.0 * 0.046985);
+        assert_close(a.tips_sol, 0.006);
+
+        let by_mint = summarize(&interleaved, by_position);
+        assert_eq!(by_mint.len(), 2);
+        for summary in by_mint.values() {
+            assert_close(summary.realized_sol, 0.046985);
+        }
+    }
+
+    /// A confirmed transaction touching the wallet, its token account, the
+    /// pool and both kinds of Jito tip account.
+    fn transaction(
+        owner: &Pubkey,
+        token_account: &Pubkey,
+        pre_balances: [u64; 5],
+        post_balances: [u64; 5],
+        pre_token: Option<&str>,
+        post_token: Option<&str>,
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        let account_keys = vec![
+            *owner,
+            *token_account,
+            Pubkey::new_unique(),
+            Pubkey::from_str(JITO_TIP_ACCOUNTS[0]).unwrap(),
+            Pubkey::from_str(JITO_SECOND_TIP_ACCOUNT).unwrap(),
+        ];
+        let message = Message::new_with_compiled_instructions(1, 0, 2, account_keys, Hash::default(), vec![]);
+        let transaction = Transaction { signatures: vec![Signature::default()], message };
+        let encoded = base64::encode(bincode::serialize(&transaction).unwrap());
+        let token_balance = |amount: &str| {
+            json!([{
+                "accountIndex": 1,
+                "mint": MINT,
+                "owner": owner.to_string(),
+                "programId": spl_token::ID.to_string(),
+                "uiTokenAmount": { "amount": amount, "decimals": 6, "uiAmount": null, "uiAmountString": "" },
+            }])
+        };
+        serde_json::from_value(json!({
+            "slot": 42,
+            "blockTime": 1_717_200_000,
+            "transaction": [encoded, "base64"],
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": FEE,
+                "preBalances": pre_balances,
+                "postBalances": post_balances,
+                "preTokenBalances": pre_token.map(token_balance).unwrap_or(json!([])),
+                "postTokenBalances": post_token.map(token_balance).unwrap_or(json!([])),
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_fill_reads_buy_with_rent_and_tips() {
+        let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let spent = 100_000_000 + FEE + 2 * TIP + RENT as u64;
+        let transaction = transaction(
+            &owner,
+            &token_account,
+            [1_000_000_000, 0, 50_000_000_000, 7, 9],
+            [1_000_000_000 - spent, RENT as u64, 50_100_000_000, 7 + TIP, 9 + TIP],
+            None,
+            Some("1000000000"),
+        );
+        let fill = parse_fill(&transaction, "buy", MINT, &owner, FillSide::Buy, None, None).unwrap();
+        assert_eq!(fill.token_delta, TOKENS);
+        assert_eq!(fill.token_decimals, 6);
+        assert_eq!(fill.sol_delta, -(spent as i64));
+        assert_eq!((fill.fee, fill.tip, fill.rent), (FEE, 2 * TIP, RENT));
+        assert_eq!((fill.slot, fill.timestamp_ms), (42, 1_717_200_000_000));
+
+        let mut basis = CostBasis::default();
+        basis.apply(&fill);
+        assert_close(basis.entry_price_sol, 0.0001);
+    }
+
+    #[test]
+    fn parse_fill_reads_rent_refund_when_sell_closes_account() {
+        let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let received = 90_000_000 - FEE - TIP + RENT as u64;
+        let transaction = transaction(
+            &owner,
+            &token_account,
+            [1_000_000_000, RENT as u64, 50_000_000_000, 7, 9],
+            [1_000_000_000 + received, 0, 49_910_000_000, 7 + TIP, 9],
+            Some("600000000"),
+            None,
+        );
+        let fill = parse_fill(&transaction, "sell", MINT, &owner, FillSide::Sell, None, None).unwrap();
+        assert_eq!(fill.token_delta, -TOKENS * 3 / 5);
+        assert_eq!(fill.sol_delta, received as i64);
+        assert_eq!((fill.fee, fill.tip, fill.rent), (FEE, TIP, -RENT));
+    }
+
+    #[test]
+    fn parse_fill_charges_no_fee_when_another_account_pays() {
+        let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let transaction = transaction(
+            &token_account,
+            &owner,
+            [RENT as u64, 1_000_000_000, 0, 0, 0],
+            [RENT as u64, 1_000_000_000, 0, 0, 0],
+            None,
+            None,
+        );
+        let fill = parse_fill(&transaction, "other", MINT, &owner, FillSide::Buy, None, None).unwrap();
+        assert_eq!((fill.fee, fill.tip, fill.sol_delta, fill.token_delta), (0, 0, 0, 0));
+        assert!(parse_fill(&transaction, "other", MINT, &Pubkey::new_unique(), FillSide::Buy, None, None).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, signer::Signer};
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use futures::future::join_all;
+use std::{sync::Arc, time::Duration};
+use tokio::time::Instant;
+
+use super::journal::fetch_fill;
+use super::params::params;
+use super::position::{Position, PositionManager};
+use super::router::build_swap_ixn;
+use super::store::{store, FillSide, PositionRecord};
+use super::strategy::SellReason;
+use super::swap::{SwapDirection, SwapInType, SwapProtocol};
+use crate::common::{
+    config::{AppState, SwapConfig},
+    logger::Logger,
+};
+use crate::core::{token::get_wallet_token_balances, tx};
+
+/// Result of selling one mint by hand.
+#[derive(Debug, Clone)]
+pub struct ManualSellReport {
+    pub signature: String,
+    pub sol_received: Option<f64>,
+    /// PnL realized against the stored cost basis, when the mint was a tracked position.
+    pub realized_sol: Option<f64>,
+    pub elapsed: Duration,
+}
+
+/// Sells outside the trading loop: one mint, or everything the wallet holds.
+///
+/// Positions found in the store are moved through the usual sell transitions
+/// and journaled; mints the bot never bought are sold without bookkeeping.
+/// Without a store nothing is journaled, which callers only allow when asked to.
+pub struct ManualSeller {
+    app_state: Arc<AppState>,
+    positions: PositionManager,
+    use_jito: bool,
+    logger: Logger,
+}
+
+impl ManualSeller {
+    pub fn new(app_state: Arc<AppState>, use_jito: bool) -> Result<Self> {
+        let logger = Logger::new("[MANUAL-SELL] => ".yellow().bold().to_string());
+        let positions = PositionManager::new();
+        match store() {
+            Some(store) => {
+                let records = store.positions().map_err(|e| anyhow!("failed to load positions: {}", e))?;
+                // Closed positions stay in the store for `positions --all` but have nothing to sell
+                for position in records.iter().map(PositionRecord::to_position).filter(Position::is_active) {
+                    positions.restore(position);
+                }
+            }
+            None => logger.log("Position store unavailable, sells will not be journaled".yellow().to_string()),
+        }
+        Ok(Self {
+            app_state,
+            positions,
+            use_jito,
+            logger,
+        })
+    }
+
+    /// Sells `pct` percent of the wallet's holding of `mint`.
+    pub async fn sell(&self, mint: &str, pct: u64, protocol: SwapProtocol) -> Result<ManualSellReport> {
+        if pct == 0 || pct > 100 {
+            return Err(anyhow!("percentage must be between 1 and 100, got {}", pct));
+        }
+        let journaled = self.positions.begin_sell(mint);
+        let start_time = Instant::now();
+
+        let (signature, token_price) = match self.send_sell(mint, pct, protocol, start_time).await {
+            Ok(sent) => sent,
+            Err(e) => {
+                // Only undo a sell this call claimed; a `Selling` record left by a crashed run stays as it is
+                if journaled {
+                    self.positions.sell_failed(mint);
+                }
+                return Err(e);
+            }
+        };
+        let elapsed = start_time.elapsed();
+        self.logger.log(format!(
+            "Sold {}% of {} => https://solscan.io/tx/{} ({:?})",
+            pct, mint, signature, elapsed
+        ).green().to_string());
+
+        let mut report = ManualSellReport {
+            signature: signature.clone(),
+            sol_received: None,
+            realized_sol: None,
+            elapsed,
+        };
+        if journaled {
+            self.positions.finish_sell(mint, token_price, pct as f64 / 100.0);
+        }
+        let target = self.positions.get(mint).and_then(|position| position.target);
+        match fetch_fill(
+            &self.app_state.rpc_nonblocking_client,
+            &signature,
+            mint,
+            &self.app_state.wallet.pubkey(),
+            FillSide::Sell,
+            Some(SellReason::Manual),
+            target,
+        )
+        .await
+        {
+            Ok(fill) => {
+                report.sol_received = Some(fill.sol_delta as f64 / LAMPORTS_PER_SOL as f64);
+                if journaled {
+                    report.realized_sol = self.positions.apply_fill(&fill).map(|(_, realized)| realized);
+                }
+            }
+            Err(e) => self.logger.log(format!(
+                "Sell of {} was sent but its fill could not be read: {}", mint, e
+            ).yellow().to_string()),
+        }
+        Ok(report)
+    }
+
+    /// Sells `pct` percent of every non-empty token account in the wallet,
+    /// all at once, and returns the result for each mint.
+    pub async fn sell_all(&self, pct: u64, protocol: SwapProtocol) -> Result<Vec<(String, Result<ManualSellReport>)>> {
+        let balances = get_wallet_token_balances(
+            self.app_state.rpc_nonblocking_client.clone(),
+            &self.app_state.wallet.pubkey(),
+        )
+        .await?;
+        let mints: Vec<String> = balances
+            .into_iter()
+            .map(|balance| balance.mint)
+            .filter(|mint| *mint != spl_token::native_mint::ID.to_string())
+            .collect();
+        self.logger.log(format!("Selling {}% of {} token(s)", pct, mints.len()).yellow().to_string());
+
+        let results = join_all(mints.iter().map(|mint| self.sell(mint, pct, protocol.clone()))).await;
+        Ok(mints.into_iter().zip(results).collect())
+    }
+
+    async fn send_sell(
+        &self,
+        mint: &str,
+        pct: u64,
+        protocol: SwapProtocol,
+        start_time: Instant,
+    ) -> Result<(String, f64)> {
+        let sell_config = SwapConfig {
+            swap_direction: SwapDirection::Sell,
+            in_type: SwapInType::Pct,
+            amount_in: pct as f64 / 100.0,
+            slippage: params().slippage,
+            use_jito: self.use_jito,
+        };
+        // A position already known to have migrated goes straight to its pool
+        let protocol = match protocol {
+            SwapProtocol::Auto => self.positions.get(mint).map(|position| position.swap_protocol()).unwrap_or_default(),
+            protocol => protocol,
+        };
+        let (keypair, instructions, token_price, route) =
+            build_swap_ixn(&self.app_state, mint, protocol, None, sell_config, start_time, &self.logger).await?;
+        self.positions.set_route(mint, route.protocol(), route.pool_id().map(|pool| pool.to_string()));
+        let recent_blockhash = self.app_state.rpc_nonblocking_client.get_latest_blockhash().await?;
+        let signatures = tx::new_signed_and_send_zeroslot(recent_blockhash, &keypair, instructions, &self.logger).await?;
+        let signature = signatures
+            .first()
+            .cloned()
+            .ok_or_else(|| anyhow!("no signature returned for the sell of {}", mint))?;
+        Ok((signature, token_price))
+    }
+}
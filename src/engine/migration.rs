@@ -0,0 +1,261 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use colored::Colorize;
+use futures_util::{SinkExt, StreamExt};
+use maplit::hashmap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time;
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+use yellowstone_grpc_proto::geyser::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest, SubscribeRequestFilterTransactions,
+    SubscribeRequestPing, SubscribeUpdateTransaction,
+};
+
+use super::position::PositionManager;
+use super::router::{bonding_curve_complete, find_pool};
+use super::swap::SwapProtocol;
+use crate::common::{
+    config::{AppState, PUMP_FUN_MIGRATE_LOG},
+    logger::Logger,
+};
+use crate::dex::pump_fun::PUMP_PROGRAM;
+
+/// How often the watched mints are compared against the held positions.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+/// The new pool can take a moment to show up on the RPC node after the migration lands.
+const POOL_LOOKUP_RETRIES: u32 = 10;
+const POOL_LOOKUP_DELAY: Duration = Duration::from_secs(1);
+
+/// Watches held positions that are still on a bonding curve and switches each
+/// one to its PumpSwap pool when the curve migrates, so sells and pricing
+/// follow the token without waiting for a failed swap.
+pub fn spawn_migration_watcher(
+    yellowstone_grpc_http: String,
+    yellowstone_grpc_token: String,
+    app_state: Arc<AppState>,
+    positions: Arc<PositionManager>,
+) {
+    let logger = Logger::new("[MIGRATION] => ".magenta().bold().to_string());
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = watch_migrations(
+                &yellowstone_grpc_http,
+                &yellowstone_grpc_token,
+                &app_state,
+                &positions,
+                &logger,
+            )
+            .await
+            {
+                logger.log(format!(
+                    "[CONNECTION ERROR] => {}. Reconnecting in {:?}...",
+                    e, RECONNECT_DELAY
+                ).red().to_string());
+            }
+            time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn watch_migrations(
+    yellowstone_grpc_http: &str,
+    yellowstone_grpc_token: &str,
+    app_state: &Arc<AppState>,
+    positions: &Arc<PositionManager>,
+    logger: &Logger,
+) -> Result<(), String> {
+    let mut client = GeyserGrpcClient::build_from_shared(yellowstone_grpc_http.to_string())
+        .map_err(|e| format!("Failed to build client: {}", e))?
+        .x_token::<String>(Some(yellowstone_grpc_token.to_string()))
+        .map_err(|e| format!("Failed to set x_token: {}", e))?
+        .tls_config(ClientTlsConfig::new().with_native_roots())
+        .map_err(|e| format!("Failed to set tls config: {}", e))?
+        .connect()
+        .await
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let (mut subscribe_tx, mut stream) = client
+        .subscribe()
+        .await
+        .map_err(|e| format!("Failed to subscribe: {}", e))?;
+
+    let mut watched = BTreeSet::new();
+    let mut refresh = time::interval(REFRESH_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = refresh.tick() => {
+                let mints = curve_mints(positions);
+                if mints == watched {
+                    continue;
+                }
+                // Mints new to the watch list may have migrated before we subscribed,
+                // e.g. while the bot was stopped
+                for mint in mints.difference(&watched) {
+                    tokio::spawn(check_migrated(
+                        Arc::clone(app_state),
+                        Arc::clone(positions),
+                        mint.clone(),
+                        logger.clone(),
+                    ));
+                }
+                subscribe_tx
+                    .send(build_migration_request(&mints))
+                    .await
+                    .map_err(|e| format!("Failed to update subscription: {:?}", e))?;
+                watched = mints;
+            }
+            message = stream.next() => {
+                let update = match message {
+                    Some(Ok(update)) => update,
+                    Some(Err(e)) => return Err(format!("Stream error: {}", e)),
+                    None => return Err("Stream closed".to_string()),
+                };
+                match update.update_oneof {
+                    Some(UpdateOneof::Ping(_)) => {
+                        subscribe_tx
+                            .send(SubscribeRequest {
+                                ping: Some(SubscribeRequestPing { id: 1 }),
+                                ..Default::default()
+                            })
+                            .await
+                            .map_err(|e| format!("Failed to send ping response: {:?}", e))?;
+                    }
+                    Some(UpdateOneof::Transaction(txn)) => {
+                        let signature = txn
+                            .transaction
+                            .as_ref()
+                            .map(|info| bs58::encode(&info.signature).into_string());
+                        for mint in migrated_mints(&txn, &watched) {
+                            tokio::spawn(switch_to_pool(
+                                Arc::clone(app_state),
+                                Arc::clone(positions),
+                                mint,
+                                signature.clone(),
+                                POOL_LOOKUP_RETRIES,
+                                logger.clone(),
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+// Mints of held positions that may still be on a Pump.fun curve.
+fn curve_mints(positions: &PositionManager) -> BTreeSet<String> {
+    positions
+        .held()
+        .into_iter()
+        .filter(|position| matches!(position.protocol, SwapProtocol::PumpFun | SwapProtocol::Auto))
+        .map(|position| position.mint)
+        .collect()
+}
+
+// PumpFun transactions touching any of `mints`; an empty filter set stops the stream
+// from delivering transactions without closing it.
+fn build_migration_request(mints: &BTreeSet<String>) -> SubscribeRequest {
+    let transactions = if mints.is_empty() {
+        HashMap::new()
+    } else {
+        hashmap! {
+            "Migrations".to_owned() => SubscribeRequestFilterTransactions {
+                vote: Some(false),
+                failed: Some(false),
+                signature: None,
+                account_include: mints.iter().cloned().collect(),
+                account_exclude: vec![],
+                account_required: vec![PUMP_PROGRAM.to_string()],
+            }
+        }
+    };
+    SubscribeRequest {
+        transactions,
+        commitment: Some(CommitmentLevel::Confirmed as i32),
+        ..Default::default()
+    }
+}
+
+// Watched mints migrated by this transaction, if it is a migration.
+fn migrated_mints(txn: &SubscribeUpdateTransaction, watched: &BTreeSet<String>) -> BTreeSet<String> {
+    let Some(info) = txn.transaction.as_ref() else {
+        return BTreeSet::new();
+    };
+    let Some(meta) = info.meta.as_ref() else {
+        return BTreeSet::new();
+    };
+    if !meta.log_messages.iter().any(|log| log.contains(PUMP_FUN_MIGRATE_LOG)) {
+        return BTreeSet::new();
+    }
+    let static_keys = info
+        .transaction
+        .as_ref()
+        .and_then(|transaction| transaction.message.as_ref())
+        .map(|message| message.account_keys.as_slice())
+        .unwrap_or_default();
+    static_keys
+        .iter()
+        .chain(meta.loaded_writable_addresses.iter())
+        .chain(meta.loaded_readonly_addresses.iter())
+        .filter_map(|key| Pubkey::try_from(key.as_slice()).ok())
+        .map(|key| key.to_string())
+        .filter(|key| watched.contains(key))
+        .collect()
+}
+
+async fn check_migrated(app_state: Arc<AppState>, positions: Arc<PositionManager>, mint: String, logger: Logger) {
+    let Ok(mint_pubkey) = Pubkey::from_str(&mint) else {
+        return;
+    };
+    // A completed curve whose pool does not exist yet is picked up by the stream later
+    if let Ok(Some(true)) = bonding_curve_complete(&app_state, &mint_pubkey).await {
+        switch_to_pool(app_state, positions, mint, None, 1, logger).await;
+    }
+}
+
+async fn switch_to_pool(
+    app_state: Arc<AppState>,
+    positions: Arc<PositionManager>,
+    mint: String,
+    signature: Option<String>,
+    retries: u32,
+    logger: Logger,
+) {
+    let Ok(mint_pubkey) = Pubkey::from_str(&mint) else {
+        return;
+    };
+    for attempt in 1..=retries {
+        match find_pool(&app_state, &mint_pubkey).await {
+            Ok(pool_info) => {
+                let pool = pool_info.pool_id.to_string();
+                if positions.set_route(&mint, SwapProtocol::PumpSwap, Some(pool.clone())) {
+                    // This log line is the migration notification
+                    logger.log(format!(
+                        "\n\t * [MIGRATED] => {} left the bonding curve \n\t * [POOL] => {} \n\t * [TX] => {} \n\t * Selling and pricing now use the PumpSwap pool",
+                        mint,
+                        pool,
+                        signature
+                            .as_ref()
+                            .map(|signature| format!("https://solscan.io/tx/{}", signature))
+                            .unwrap_or_else(|| "before subscription".to_string())
+                    ).magenta().bold().to_string());
+                }
+                return;
+            }
+            Err(e) if attempt == retries => {
+                if retries > 1 {
+                    logger.log(format!(
+                        "{} migrated but its pool was not found, the next sell will look it up: {}",
+                        mint, e
+                    ).yellow().to_string());
+                }
+            }
+            Err(_) => time::sleep(POOL_LOOKUP_DELAY).await,
+        }
+    }
+}
@@ -1,5 +1,15 @@
 pub mod monitor;
 pub mod swap;
+pub mod router;
+pub mod migration;
+pub mod params;
+pub mod strategy;
+pub mod sizing;
+pub mod position;
+pub mod store;
+pub mod journal;
+pub mod export;
+pub mod manual_sell;
 pub mod copy_trading;
 pub mod transaction_parser;
 pub mod selling_strategy;
@@ -1,30 +1,39 @@
 use maplit::hashmap;
-use anchor_client::solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature};
+use anchor_client::solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature, signer::Signer};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::{collections::HashSet, time::Duration};
-use base64;
-use bs58;
-
-use super::swap::{SwapDirection, SwapInType};
-use crate::common::config::{
-    JUPITER_PROGRAM,
-    OKX_DEX_PROGRAM,
-    PUMP_FUN_PROGRAM_DATA_PREFIX,
-    PUMP_FUN_BUY_LOG_INSTRUCTION,
-    PUMP_FUN_BUY_OR_SELL_PROGRAM_DATA_PREFIX,
-    PUMP_FUN_SELL_LOG_INSTRUCTION,
-    PUMP_SWAP_BUY_LOG_INSTRUCTION,
-    PUMP_SWAP_BUY_PROGRAM_DATA_PREFIX,
-    PUMP_SWAP_SELL_LOG_INSTRUCTION,
-    PUMP_SWAP_SELL_PROGRAM_DATA_PREFIX,
+
+use super::params::{params, spawn_config_watcher, subscribe_params};
+use super::journal::spawn_record_fill;
+use super::migration::spawn_migration_watcher;
+use super::position::PositionManager;
+use super::store::{
+    init_store, instant_to_unix_ms, store, unix_ms_to_instant, FillSide, PositionRecord,
+    TrackingRecord,
+};
+use super::strategy::{launch_source, SellReason, StrategyProfile};
+use super::router::{
+    build_swap_ixn, get_token_price, pump_sell_quote, remember_curve_fees, remember_pool, remember_pool_fees,
+    remember_pool_id, PUMP_SWAP_PROGRAM,
 };
+use super::swap::{SwapDirection, SwapInType, SwapProtocol};
+use crate::common::config::{JUPITER_PROGRAM, OKX_DEX_PROGRAM};
 use crate::common::{    
-    config::{AppState, SwapConfig},
+    config::{AppState, Status, SwapConfig},
     logger::Logger,
+    settings::{settings, MirrorExitMode},
 };
-use crate::core::tx;
-use crate::dex::pump_fun::{Pump, INITIAL_VIRTUAL_SOL_RESERVES, INITIAL_VIRTUAL_TOKEN_RESERVES,   PUMP_FUN_CREATE_IX_DISCRIMINATOR, PUMP_PROGRAM, get_bonding_curve_account};
+use crate::core::{token, tx};
+use crate::dex::{meteora_dbc, meteora_dlmm};
+use crate::dex::pump_events::{self, PumpEvent};
+use crate::dex::pump_instructions::PumpInstruction;
+use crate::dex::pump_quote::{PumpCurve, PumpSwapPool};
+use crate::dex::raydium_launchpad::{
+    BUY_EXACT_IN_DISCRIMINATOR, BUY_EXACT_OUT_DISCRIMINATOR, INITIALIZE_DISCRIMINATOR, INITIALIZE_V2_DISCRIMINATOR,
+    RAYDIUM_LAUNCHPAD_PROGRAM, SELL_EXACT_IN_DISCRIMINATOR, SELL_EXACT_OUT_DISCRIMINATOR,
+};
+use crate::dex::pump_fun::{PUMP_FUN_CREATE_IX_DISCRIMINATOR, PUMP_PROGRAM, get_bonding_curve_account};
 use anyhow::{Result};
 use chrono::{Utc, Local};
 use colored::Colorize;
@@ -34,7 +43,9 @@ use futures;
 use tokio::{
     time::{self, Instant},
 };
+use spl_token_client::token::TokenError;
 use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+use yellowstone_grpc_proto::prelude::{Message, TokenBalance, TransactionStatusMeta};
 use yellowstone_grpc_proto::geyser::{
     subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest, SubscribeRequestPing,
     SubscribeRequestFilterTransactions, SubscribeUpdateTransaction, SubscribeUpdate,
@@ -47,7 +58,14 @@ pub enum InstructionType {
     PumpBuy,
     PumpSell,
     PumpSwapBuy,
-    PumpSwapSell
+    PumpSwapSell,
+    RaydiumLaunchpadMint,
+    RaydiumLaunchpadBuy,
+    RaydiumLaunchpadSell,
+    MeteoraDbcBuy,
+    MeteoraDbcSell,
+    MeteoraDlmmBuy,
+    MeteoraDlmmSell,
 }
 
 #[derive(Clone, Debug)]
@@ -98,18 +116,53 @@ pub struct FilterConfig {
     is_multi_copy_trading: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct RetracementLevel {
-    pub percentage: u64,
-    pub threshold: u64,
-    pub sell_amount: u64,
-}
-
 #[derive(Clone, Debug)]
 pub struct TokenTrackingInfo {
+    /// Name of the strategy profile managing this position.
+    pub strategy: String,
     pub top_pnl: f64,
     pub last_sell_time: Instant,
     pub completed_intervals: HashSet<String>,
+    /// When PnL last dropped under the time-in-loss floor, if it is still there.
+    pub loss_since: Option<Instant>,
+    /// What triggered the most recent sell.
+    pub exit_reason: Option<SellReason>,
+}
+
+impl TokenTrackingInfo {
+    pub fn new(strategy: String, top_pnl: f64) -> Self {
+        Self {
+            strategy,
+            top_pnl,
+            last_sell_time: Instant::now(),
+            completed_intervals: HashSet::new(),
+            loss_since: None,
+            exit_reason: None,
+        }
+    }
+
+    pub fn to_record(&self) -> TrackingRecord {
+        let mut completed_intervals: Vec<String> = self.completed_intervals.iter().cloned().collect();
+        completed_intervals.sort();
+        TrackingRecord {
+            strategy: self.strategy.clone(),
+            top_pnl: self.top_pnl,
+            completed_intervals,
+            loss_since_ms: self.loss_since.map(instant_to_unix_ms),
+            exit_reason: self.exit_reason,
+        }
+    }
+
+    pub fn from_record(record: TrackingRecord) -> Self {
+        Self {
+            strategy: record.strategy,
+            top_pnl: record.top_pnl,
+            last_sell_time: Instant::now(),
+            completed_intervals: record.completed_intervals.into_iter().collect(),
+            loss_since: record.loss_since_ms.map(unix_ms_to_instant),
+            exit_reason: record.exit_reason,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -129,70 +182,8 @@ lazy_static::lazy_static! {
     static ref SOLD: Arc<Mutex<u64>> = Arc::new(Mutex::new(0));
     static ref BOUGHTS: Arc<Mutex<u64>> = Arc::new(Mutex::new(0));
     static ref LAST_BUY_PAUSE_TIME: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
-    static ref BUYING_ENABLED: Arc<Mutex<bool>> = Arc::new(Mutex::new(true));
     static ref TOKEN_TRACKING: Arc<Mutex<HashMap<String, TokenTrackingInfo>>> = Arc::new(Mutex::new(HashMap::new()));
     
-    static ref THRESHOLD_BUY: Arc<Mutex<u64>> = Arc::new(Mutex::new(
-        std::env::var("THRESHOLD_BUY")
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(1_000_000_000)
-    ));
-    
-    static ref THRESHOLD_SELL: Arc<Mutex<u64>> = Arc::new(Mutex::new(
-        std::env::var("THRESHOLD_SELL")
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(1_000_000_000)
-    ));
-    
-    static ref MAX_WAIT_TIME: Arc<Mutex<u64>> = Arc::new(Mutex::new(
-        std::env::var("MAX_WAIT_TIME")
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(60000)
-    ));
-
-    static ref TAKE_PROFIT_LEVELS: Vec<(u64, u64)> = vec![
-        (2000, 100),
-        (1500, 40),
-        (1000, 40),
-        (800, 20),
-        (600, 20),
-        (400, 20),
-        (300, 20),
-        (250, 20),
-        (200, 20),
-        (120, 20),
-        (80, 20),
-        (50, 10),
-        (20, 10),
-    ];
-    
-    static ref RETRACEMENT_LEVELS: Vec<RetracementLevel> = vec![
-        RetracementLevel { percentage: 3, threshold: 2000, sell_amount: 100 },
-        RetracementLevel { percentage: 4, threshold: 1500, sell_amount: 50 },
-        RetracementLevel { percentage: 5, threshold: 1000, sell_amount: 40 },
-        RetracementLevel { percentage: 6, threshold: 800, sell_amount: 35 },
-        RetracementLevel { percentage: 6, threshold: 700, sell_amount: 35 },
-        RetracementLevel { percentage: 6, threshold: 600, sell_amount: 30 },
-        RetracementLevel { percentage: 7, threshold: 500, sell_amount: 30 },
-        RetracementLevel { percentage: 7, threshold: 400, sell_amount: 30 },
-        RetracementLevel { percentage: 8, threshold: 300, sell_amount: 20 },
-        RetracementLevel { percentage: 10, threshold: 200, sell_amount: 15 },
-        RetracementLevel { percentage: 12, threshold: 100, sell_amount: 15 },
-        RetracementLevel { percentage: 20, threshold: 50, sell_amount: 10 },
-        RetracementLevel { percentage: 30, threshold: 30, sell_amount: 10 },
-        RetracementLevel { percentage: 42, threshold: 20, sell_amount: 100 },
-    ];
-    
-    static ref DOWNING_PERCENT: Arc<Mutex<u64>> = Arc::new(Mutex::new(
-        std::env::var("DOWNING_PERCENT")
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(42)
-    ));
-    
     static ref LAST_MESSAGE_TIME: Arc<Mutex<Instant>> = Arc::new(Mutex::new(Instant::now()));
 }
 
@@ -218,1019 +209,650 @@ async fn check_connection_health(logger: &Logger) {
     }
 }
 
+// Every account a transaction references, in the order its instructions index
+// them: the static keys, then the writable and readonly addresses a v0
+// transaction loads from lookup tables.
+fn resolve_account_keys(message: &Message, meta: &TransactionStatusMeta) -> Vec<Pubkey> {
+    message
+        .account_keys
+        .iter()
+        .chain(meta.loaded_writable_addresses.iter())
+        .chain(meta.loaded_readonly_addresses.iter())
+        .map(|key| Pubkey::try_from(key.as_slice()).unwrap_or_default())
+        .collect()
+}
+
+// Top-level instructions followed by the inner (CPI) ones, as
+// (program id index, account indices, data).
+fn all_instructions<'a>(
+    message: &'a Message,
+    meta: &'a TransactionStatusMeta,
+) -> impl Iterator<Item = (u32, &'a [u8], &'a [u8])> {
+    message
+        .instructions
+        .iter()
+        .map(|ix| (ix.program_id_index, ix.accounts.as_slice(), ix.data.as_slice()))
+        .chain(meta.inner_instructions.iter().flat_map(|inner| {
+            inner.instructions.iter().map(|ix| (ix.program_id_index, ix.accounts.as_slice(), ix.data.as_slice()))
+        }))
+}
+
+// Takes the first event not yet paired with an instruction that `pick` accepts.
+fn take_event<T>(events: &mut [Option<PumpEvent>], pick: impl Fn(&PumpEvent) -> Option<T>) -> Option<T> {
+    events.iter_mut().find_map(|slot| {
+        let picked = slot.as_ref().and_then(&pick)?;
+        *slot = None;
+        Some(picked)
+    })
+}
+
+// Lamports `account` gained over the transaction, negative if it lost some.
+fn lamport_change(account_keys: &[Pubkey], meta: &TransactionStatusMeta, account: &Pubkey) -> i64 {
+    account_keys
+        .iter()
+        .position(|account_key| account_key == account)
+        .map(|index| {
+            let post = *meta.post_balances.get(index).unwrap_or(&0_u64) as i64;
+            let pre = *meta.pre_balances.get(index).unwrap_or(&0_u64) as i64;
+            post - pre
+        })
+        .unwrap_or(0)
+}
+
+// An instruction as (program id index, account indices, data).
+type RawInstruction<'a> = (u32, &'a [u8], &'a [u8]);
+// A top-level instruction and the inner instructions it invoked.
+type InstructionWithCpis<'a> = (u32, &'a [u8], &'a [u8], Vec<RawInstruction<'a>>);
+
+// Every instruction with the inner instructions it invoked, as
+// (program id index, account indices, data, its CPIs). Inner instructions
+// reported without a stack height are attributed to the top-level instruction.
+fn instructions_with_cpis<'a>(
+    message: &'a Message,
+    meta: &'a TransactionStatusMeta,
+) -> Vec<InstructionWithCpis<'a>> {
+    let mut calls = Vec::new();
+    for (index, ix) in message.instructions.iter().enumerate() {
+        let inner = meta
+            .inner_instructions
+            .iter()
+            .find(|inner| inner.index == index as u32)
+            .map(|inner| inner.instructions.as_slice())
+            .unwrap_or_default();
+        let group: Vec<(u32, u32, &[u8], &[u8])> = std::iter::once((1, ix.program_id_index, ix.accounts.as_slice(), ix.data.as_slice()))
+            .chain(inner.iter().map(|ix| {
+                (ix.stack_height.unwrap_or(2), ix.program_id_index, ix.accounts.as_slice(), ix.data.as_slice())
+            }))
+            .collect();
+        for (position, (height, program_id_index, accounts, data)) in group.iter().enumerate() {
+            let cpis = group[position + 1..]
+                .iter()
+                .take_while(|(inner_height, ..)| inner_height > height)
+                .map(|(_, program_id_index, accounts, data)| (*program_id_index, *accounts, *data))
+                .collect();
+            calls.push((*program_id_index, *accounts, *data, cpis));
+        }
+    }
+    calls
+}
+
+// Net amount the SPL token transfers among `cpis` moved into `vault`,
+// negative if they moved it out.
+fn vault_transfers(account_keys: &[Pubkey], cpis: &[RawInstruction<'_>], vault: &Pubkey) -> i64 {
+    let key = |accounts: &[u8], index: usize| accounts.get(index).and_then(|account| account_keys.get(*account as usize));
+    cpis.iter()
+        .filter(|(program_id_index, _, _)| {
+            account_keys
+                .get(*program_id_index as usize)
+                .is_some_and(|program| *program == spl_token::ID || *program == spl_token_2022::ID)
+        })
+        .filter_map(|(_, accounts, data)| {
+            let amount = data.get(1..9).and_then(|bytes| bytes.try_into().ok()).map(u64::from_le_bytes)? as i64;
+            // Transfer: source 0, destination 1. TransferChecked: source 0, mint 1, destination 2.
+            let (source, destination) = match data.first() {
+                Some(3) => (key(accounts, 0), key(accounts, 1)),
+                Some(12) => (key(accounts, 0), key(accounts, 2)),
+                _ => return None,
+            };
+            if destination == Some(vault) {
+                Some(amount)
+            } else if source == Some(vault) {
+                Some(-amount)
+            } else {
+                None
+            }
+        })
+        .sum()
+}
+
+// Mint of the token account `account`, from the transaction's token balances.
+fn token_account_mint<'a>(account_keys: &[Pubkey], meta: &'a TransactionStatusMeta, account: &Pubkey) -> Option<&'a str> {
+    let index = account_keys.iter().position(|key| key == account)? as u32;
+    meta.pre_token_balances
+        .iter()
+        .chain(meta.post_token_balances.iter())
+        .find(|balance| balance.account_index == index)
+        .map(|balance| balance.mint.as_str())
+}
+
+// Raw change of `owner`'s `mint` balance over the transaction.
+fn owner_token_change(meta: &TransactionStatusMeta, owner: &str, mint: &str) -> i128 {
+    let balance_of = |balances: &[TokenBalance]| -> i128 {
+        balances
+            .iter()
+            .filter(|balance| balance.owner == owner && balance.mint == mint)
+            .filter_map(|balance| balance.ui_token_amount.as_ref())
+            .filter_map(|amount| amount.amount.parse::<i128>().ok())
+            .sum()
+    };
+    balance_of(&meta.post_token_balances) - balance_of(&meta.pre_token_balances)
+}
+
 impl TradeInfoFromToken {
-    pub fn from_json(txn: SubscribeUpdateTransaction, log_messages: Vec<String>) -> Result<Self> {
-        let slot = txn.slot;
-            let mut instruction_type = InstructionType::PumpMint;
-            let mut encoded_data = String::new();
-            let mut amount: Option<u64> = None;
-            let mut max_sol_cost: Option<u64> = None;
-            let mut min_sol_output: Option<u64> = None;
-        let mut base_amount_in: Option<u64> = None;
-        let mut min_quote_amount_out: Option<u64> = None;
-        let mut base_amount_out: Option<u64> = None;
-        let mut max_quote_amount_in: Option<u64> = None;
-            
-            println!("Searching for instruction type in logs...");
-            
-            for log in log_messages.iter() {
-                println!("Checking log: {}", log);
-                
-                if log.starts_with(PUMP_FUN_PROGRAM_DATA_PREFIX) {
-                    encoded_data = log.split_whitespace().nth(2).unwrap_or("").to_string();
-                    instruction_type = InstructionType::PumpMint;
-                    println!("DETECTED PumpMint instruction: {}", log);
-                    break;
-                } else if log.contains(PUMP_FUN_BUY_LOG_INSTRUCTION) && log_messages.iter().any(|l| l.contains(PUMP_FUN_BUY_OR_SELL_PROGRAM_DATA_PREFIX)) {
-                    encoded_data = log.split_whitespace().nth(2).unwrap_or("").to_string();
-                    instruction_type = InstructionType::PumpBuy;
-                    println!("DETECTED PumpBuy instruction: {}", log);
-                    break;
-                } else if log.contains(PUMP_FUN_SELL_LOG_INSTRUCTION) && log_messages.iter().any(|l| l.contains(PUMP_FUN_BUY_OR_SELL_PROGRAM_DATA_PREFIX)) {
-                    encoded_data = log.split_whitespace().nth(2).unwrap_or("").to_string();
-                    instruction_type = InstructionType::PumpSell;
-                    println!("DETECTED PumpSell instruction: {}", log);
-                    break;
-                } else if log.contains(PUMP_SWAP_BUY_LOG_INSTRUCTION) && log_messages.iter().any(|l| l.contains(PUMP_SWAP_BUY_PROGRAM_DATA_PREFIX)) {
-                    instruction_type = InstructionType::PumpSwapBuy;
-                    println!("DETECTED PumpSwapBuy instruction: {}", log);
-                    break;
-                } else if log.contains(PUMP_SWAP_SELL_LOG_INSTRUCTION) && log_messages.iter().any(|l| l.contains(PUMP_SWAP_SELL_PROGRAM_DATA_PREFIX)) {
-                    instruction_type = InstructionType::PumpSwapSell;
-                    println!("DETECTED PumpSwapSell instruction: {}", log);
-                    break;
-                } else if log.contains("Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA") {
-                    for other_log in log_messages.iter() {
-                        if other_log.contains("BuyEvent") {
-                            instruction_type = InstructionType::PumpSwapBuy;
-                            println!("DETECTED PumpSwapBuy instruction via fallback: {}", other_log);
-                            break;
-                        } else if other_log.contains("SellEvent") {
-                            instruction_type = InstructionType::PumpSwapSell;
-                            println!("DETECTED PumpSwapSell instruction via fallback: {}", other_log);
-                            break;
-                        }
-                    }
-                    
-                    if matches!(instruction_type, InstructionType::PumpSwapBuy | InstructionType::PumpSwapSell) {
-                        break;
-                    }
+    /// Every trade in the transaction, each with its own signer, mint and amounts.
+    pub fn from_json(txn: SubscribeUpdateTransaction, log_messages: Vec<String>) -> Result<Vec<Self>> {
+        let mut trades = Vec::new();
+        if log_messages.iter().any(|log| log.contains(RAYDIUM_LAUNCHPAD_PROGRAM)) {
+            trades.extend(Self::from_raydium_launchpad(&txn)?);
+        }
+        if log_messages
+            .iter()
+            .any(|log| log.contains(meteora_dbc::METEORA_DBC_PROGRAM) || log.contains(meteora_dlmm::METEORA_DLMM_PROGRAM))
+        {
+            trades.extend(Self::from_meteora(&txn)?);
+        }
+        trades.extend(Self::from_pump(&txn, &log_messages)?);
+        if trades.is_empty() {
+            return Err(anyhow::anyhow!("No supported trade found in transaction"));
+        }
+        Ok(trades)
+    }
+
+    /// Decodes every PumpFun create, buy or sell, and PumpSwap buy or sell,
+    /// from the instructions themselves, whether top-level or CPIs from a
+    /// router or bot. The event each instruction emitted supplies the reserves
+    /// and the SOL that actually moved.
+    fn from_pump(txn: &SubscribeUpdateTransaction, log_messages: &[String]) -> Result<Vec<Self>> {
+        let transaction = txn.transaction.as_ref().ok_or_else(|| anyhow::anyhow!("Transaction is None"))?;
+        let message = transaction
+            .transaction
+            .as_ref()
+            .and_then(|t| t.message.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("Failed to get transaction message"))?;
+        let meta = transaction.meta.as_ref().ok_or_else(|| anyhow::anyhow!("Failed to get transaction meta"))?;
+
+        let account_keys = resolve_account_keys(message, meta);
+        let pump_program = Pubkey::from_str(PUMP_PROGRAM)?;
+        let pump_swap_program = Pubkey::from_str(PUMP_SWAP_PROGRAM)?;
+        let instructions: Vec<PumpInstruction> = all_instructions(message, meta)
+            .filter_map(|(program_id_index, accounts, data)| {
+                let program = account_keys.get(program_id_index as usize)?;
+                let keys = accounts
+                    .iter()
+                    .map(|account| account_keys.get(*account as usize).copied().unwrap_or_default())
+                    .collect();
+                if *program == pump_program {
+                    PumpInstruction::decode_pump_fun(data, keys)
+                } else if *program == pump_swap_program {
+                    PumpInstruction::decode_pump_swap(data, keys)
+                } else {
+                    None
                 }
-            }
-            
-            println!("Instruction type detected: {:?}", instruction_type);
-
-            match instruction_type {
-                InstructionType::PumpMint => {
-                    println!("Processing PumpMint instruction");
-                    if !encoded_data.is_empty() {
-                        let decoded_data = match base64::decode(&encoded_data) {
-                            Ok(data) => {
-                                println!("Base64 decoded data successfully, length: {}", data.len());
-                                data
-                            },
-                            Err(e) => {
-                                println!("Error decoding base64 data: {}", e);
-                                Vec::new()
-                            }
-                        };
-                        
-                        if decoded_data.len() > 8 {
-                            let mut offset = 8;
-                            
-                            let mut name: String = String::new();
-                            let mut symbol = String::new();
-                            let mut uri = String::new();
-                            let mut mint = String::new();
-                            let mut bonding_curve = String::new();
-                            let mut target = String::new();
-
-                            let mut fields = vec![
-                                (&mut name, "string"),
-                                (&mut symbol, "string"),
-                                (&mut uri, "string"),
-                                (&mut mint, "publicKey"),
-                                (&mut bonding_curve, "publicKey"),
-                                (&mut target, "publicKey"),
-                            ];
-
-                            for (idx, (value, field_type)) in fields.iter_mut().enumerate() {
-                                if offset >= decoded_data.len() {
-                                    println!("Data too short at field {}, breaking", idx);
-                                    break;
-                                }
-                                
-                                if field_type == &"string" {
-                                    if offset + 4 <= decoded_data.len() {
-                                        let length: u32 = u32::from_le_bytes(decoded_data[offset..offset + 4].try_into().unwrap());
-                                        offset += 4;
-                                        
-                                        if offset + length as usize <= decoded_data.len() {
-                                            let new_value = String::from_utf8(decoded_data[offset..offset + length as usize].to_vec()).ok().unwrap_or_default();
-                                        // Update the string directly
-                                        **value = new_value;
-                                            offset += length as usize;
-                                        println!("Parsed string: {}", **value);
-                                        } else {
-                                            println!("String data out of bounds");
-                                        }
-                                    }
-                                } else if field_type == &"publicKey" {
-                                    if offset + 32 <= decoded_data.len() {
-                                        let key_bytes: [u8; 32] = decoded_data[offset..offset + 32].try_into().expect("Slice with incorrect length");
-                                        let new_value = Pubkey::new_from_array(key_bytes).to_string(); // Convert bytes to Base58
-                                    // Update the string directly
-                                    **value = new_value;
-                                        offset += 32;
-                                    println!("Parsed publicKey: {}", **value);
-                                    }
-                                }
-                            }
+            })
+            .collect();
+        if instructions.is_empty() {
+            return Ok(Vec::new());
+        }
 
-                            if let Some(transaction) = txn.transaction.clone() {
-                                let signature = match Signature::try_from(transaction.signature.clone()) {
-                                    Ok(signature) => {
-                                        let sig_str = format!("{:?}", signature);
-                                        println!("Parsed signature: {}", sig_str);
-                                        sig_str
-                                    },
-                                    Err(_) => "".to_string(),
-                                };
-                                
-                                let recent_blockhash_slice = match transaction.transaction.as_ref()
-                                    .and_then(|t| t.message.as_ref())
-                                    .map(|m| &m.recent_blockhash) {
-                                    Some(hash) => {
-                                        println!("Found blockhash");
-                                        hash
-                                    },
-                                    None => {
-                                        println!("Failed to get blockhash");
-                                        return Err(anyhow::anyhow!("Failed to get recent blockhash"));
-                                    }
-                                };
-                                
-                                let recent_blockhash = Hash::new(recent_blockhash_slice);
-                
-                                let account_keys = match transaction.transaction.as_ref()
-                                    .and_then(|t| t.message.as_ref())
-                                    .map(|m| &m.account_keys) {
-                                    Some(keys) => {
-                                        println!("Found account keys, count: {}", keys.len());
-                                        keys
-                                    },
-                                    None => {
-                                        println!("Failed to get account keys");
-                                        return Err(anyhow::anyhow!("Failed to get account keys"));
-                                    }
-                                };
-                                
-                                let mut sol_post_amount = 0_u64;
-                                let mut volume_change = 0_i64;    
-                
-                                if let Some(ref meta) = transaction.meta {
-                                    println!("Processing transaction metadata");
-                                    
-                                    let bonding_curve_index = account_keys
-                                        .iter()
-                                        .position(|account_key| {
-                                            Pubkey::try_from(account_key.clone()).map(|k| k.to_string() == bonding_curve).unwrap_or(false)
-                                        })
-                                        .unwrap_or(0);
-                
-                                    println!("Bonding curve index: {}", bonding_curve_index);
-                                    
-                                    sol_post_amount = *meta
-                                        .post_balances
-                                        .get(bonding_curve_index)
-                                        .unwrap_or(&0_u64);
-                
-                                    let sol_pre_amount = *meta
-                                        .pre_balances
-                                        .get(bonding_curve_index)
-                                        .unwrap_or(&0_u64);
-                
-                                    volume_change = (sol_post_amount as i64) - (sol_pre_amount as i64);
-                                    
-                                    println!("Volume change: {} lamports ({} SOL)", 
-                                        volume_change, 
-                                        lamports_to_sol(volume_change.abs() as u64));
-                                }
-                
-                                let bonding_curve_pubkey = match Pubkey::from_str(&bonding_curve) {
-                                    Ok(pk) => pk,
-                                    Err(e) => {
-                                        println!("Failed to parse bonding curve pubkey: {}", e);
-                                        return Err(anyhow::anyhow!("Invalid bonding curve public key"));
-                                    }
-                                };
-                
-                                let new_virtual_sol_reserve = INITIAL_VIRTUAL_SOL_RESERVES + sol_post_amount;
-                                let new_virtual_token_reserve = ((INITIAL_VIRTUAL_SOL_RESERVES as u128 * INITIAL_VIRTUAL_TOKEN_RESERVES as u128) as f64 / new_virtual_sol_reserve as f64) as u64;
-                                
-                                println!("New virtual SOL reserve: {}", new_virtual_sol_reserve);
-                                println!("New virtual token reserve: {}", new_virtual_token_reserve);
-                                
-                                let bonding_curve_info = BondingCurveInfo {
-                                    bonding_curve: bonding_curve_pubkey,
-                                    new_virtual_sol_reserve,
-                                    new_virtual_token_reserve,
-                                };
-                
-                                let mut token_post_amount = 0_f64;
-                                
-                                if let Some(meta) = &transaction.meta {
-                                    token_post_amount = meta
-                                        .post_token_balances
-                                        .iter()
-                                        .filter_map(|token_balance| {
-                                            if token_balance.owner == target {
-                                                token_balance
-                                                    .ui_token_amount
-                                                    .as_ref()
-                                                    .map(|ui| ui.ui_amount)
-                                            } else {
-                                                None
-                                            }
-                                        })
-                                        .next()
-                                        .unwrap_or(0_f64);
-                                    
-                                    println!("Token post amount: {}", token_post_amount);
-                                }
-                                
-                                return Ok(Self {
-                                    instruction_type,
-                                    slot,
-                                    recent_blockhash,
-                                    signature,
-                                    target,
-                                    mint,
-                                    bonding_curve,
-                                    volume_change,
-                                bonding_curve_info: Some(bonding_curve_info),
-                                pool_info: None,
-                                    token_amount: token_post_amount,
-                                    amount: None,
-                                    max_sol_cost: None,
-                                    min_sol_output: None,
-                                base_amount_in: None,
-                                min_quote_amount_out: None,
-                                base_amount_out: None,
-                                max_quote_amount_in: None,
-                                });
-                            } else {
-                                println!("Transaction is None, cannot proceed");
-                                return Err(anyhow::anyhow!("Transaction is None"));
-                            }
-                        } else {
-                            println!("Decoded data too short to process");
-                        }
-                    } else {
-                        println!("No encoded data found in log");
-                    }
-                },
-                
-                InstructionType::PumpBuy => {
-                    println!("Processing PumpBuy instruction");
-                    let mut buy_amount: u64 = 0;
-                    let mut buy_max_sol_cost: u64 = 0;
-                    let mut token_mint = String::new();
-                    
-                    // Find the encoded data in log messages
-                    let encoded_data = log_messages.iter()
-                        .find(|msg| msg.contains(PUMP_FUN_BUY_OR_SELL_PROGRAM_DATA_PREFIX))
-                        .map_or("", |v| v)
-                        .split_whitespace()
-                        .nth(2)
-                        .unwrap_or("")
-                        .to_string();
-                    
-                    if !encoded_data.is_empty() {
-                        let decoded_data = match base64::decode(&encoded_data) {
-                            Ok(data) => {
-                                println!("Base64 decoded buy data successfully, length: {}", data.len());
-                                println!("encoded_data: {}", encoded_data);
-                                data
-                            },
-                            Err(e) => {
-                                println!("Error decoding buy base64 data: {}", e);
-                                Vec::new()
-                            }
-                        };
-                        
-                        // Extract mint address if available (at position 8..40)
-                        if decoded_data.len() >= 40 {  // 8 (discriminator) + 32 (mint)
-                            let mint_bytes = &decoded_data[8..40];
-                            token_mint = bs58::encode(mint_bytes).into_string();
-                            println!("Extracted mint address: {}", token_mint);
-                        }
-                        
-                        // Extract amount if available
-                        if decoded_data.len() >= 48 {  // 8 + 32 + 8 (amount)
-                            let amount_bytes = &decoded_data[40..48];
-                            buy_amount = u64::from_le_bytes(amount_bytes.try_into().unwrap());
-                            println!("Detected transaction amount: {} tokens", buy_amount);
-                        }
-                        
-                        // Extract max_sol_cost if available
-                        if decoded_data.len() >= 56 {  // 8 + 32 + 8 + 8 (max_sol_cost)
-                            let max_sol_cost_bytes = &decoded_data[48..56];
-                            buy_max_sol_cost = u64::from_le_bytes(max_sol_cost_bytes.try_into().unwrap());
-                            println!("Max SOL cost: {}", buy_max_sol_cost);
-                            println!("Max SOL cost in SOL: {} SOL", lamports_to_sol(buy_max_sol_cost));
-                        }
-                        
-                        amount = Some(buy_amount);
-                        max_sol_cost = Some(buy_max_sol_cost);
-                    }
-                
-                // Here we need to use the variables from the PumpMint scope
-                if let Some(transaction) = txn.transaction.clone() {
-                    let signature = match Signature::try_from(transaction.signature.clone()) {
-                        Ok(signature) => {
-                            let sig_str = format!("{:?}", signature);
-                            println!("Parsed signature: {}", sig_str);
-                            sig_str
-                        },
-                        Err(_) => "".to_string(),
-                    };
-                    
-                    let recent_blockhash_slice = match transaction.transaction.as_ref()
-                        .and_then(|t| t.message.as_ref())
-                        .map(|m| &m.recent_blockhash) {
-                        Some(hash) => {
-                            println!("Found blockhash");
-                            hash
-                        },
-                        None => {
-                            println!("Failed to get blockhash");
-                            return Err(anyhow::anyhow!("Failed to get recent blockhash"));
-                        }
-                    };
-                    
-                    let recent_blockhash = Hash::new(recent_blockhash_slice);
-                    
-                    let account_keys = match transaction.transaction.as_ref()
-                        .and_then(|t| t.message.as_ref())
-                        .map(|m| &m.account_keys) {
-                        Some(keys) => {
-                            println!("Found account keys, count: {}", keys.len());
-                            keys
-                        },
-                        None => {
-                            println!("Failed to get account keys");
-                            return Err(anyhow::anyhow!("Failed to get account keys"));
-                        }
-                    };
-                    
-                    // Extract target, mint, bonding_curve, etc. from transaction information
-                    let mut target = String::new();
-                    let mut mint = token_mint; // Use the extracted mint if available
-                    let mut bonding_curve = String::new();
-                    let mut sol_post_amount = 0_u64;
-                    let mut volume_change = 0_i64;
-                    let mut token_post_amount = 0_f64;
-                    
-                    // Process transaction metadata to extract information
-                    if let Some(meta) = &transaction.meta {
-                        println!("Processing transaction metadata");
-                        
-                        // Extract necessary information from metadata and logs
-                        for token_balance in &meta.post_token_balances {
-                            if mint.is_empty() {
-                                mint = token_balance.mint.clone();
-                                println!("Found mint: {}", mint);
-                            }
-                            target = token_balance.owner.clone();
-                            println!("Found target: {}", target);
-                            
-                            // Extract token amount
-                            token_post_amount = token_balance
-                                .ui_token_amount
-                                .as_ref()
-                                .map(|ui| ui.ui_amount)
-                                .unwrap_or(0_f64);
-                            
-                            println!("Token post amount: {}", token_post_amount);
-                        }
-                        
-                        // Find bonding curve from logs
-                        for log in log_messages.iter() {
-                            if log.contains("bonding curve:") {
-                                if let Some(bc) = log.split("bonding curve:").nth(1).map(|s| s.trim()) {
-                                    bonding_curve = bc.to_string();
-                                    println!("Found bonding curve: {}", bonding_curve);
-                                }
-                            }
-                        }
-                        
-                        // Calculate volume change
-                        if !bonding_curve.is_empty() {
-                            let bonding_curve_index = account_keys
-                                .iter()
-                                .position(|account_key| {
-                                    Pubkey::try_from(account_key.clone()).map(|k| k.to_string() == bonding_curve).unwrap_or(false)
-                                })
-                                .unwrap_or(0);
-                            
-                            sol_post_amount = *meta
-                                .post_balances
-                                .get(bonding_curve_index)
-                                .unwrap_or(&0_u64);
-                            
-                            let sol_pre_amount = *meta
-                                .pre_balances
-                                .get(bonding_curve_index)
-                                .unwrap_or(&0_u64);
-                            
-                            volume_change = (sol_post_amount as i64) - (sol_pre_amount as i64);
-                            println!("Volume change: {} lamports", volume_change);
-                        }
-                    }
-                    
-                    // Create BondingCurveInfo if we have bonding curve information
-                    let bonding_curve_info_option = if !bonding_curve.is_empty() {
-                        let bonding_curve_pubkey = match Pubkey::from_str(&bonding_curve) {
-                            Ok(pk) => pk,
-                            Err(e) => {
-                                println!("Failed to parse bonding curve pubkey: {}", e);
-                                return Err(anyhow::anyhow!("Invalid bonding curve public key"));
-                            }
-                        };
-                        
-                        let new_virtual_sol_reserve = INITIAL_VIRTUAL_SOL_RESERVES + sol_post_amount;
-                        let new_virtual_token_reserve = ((INITIAL_VIRTUAL_SOL_RESERVES as u128 * INITIAL_VIRTUAL_TOKEN_RESERVES as u128) as f64 / new_virtual_sol_reserve as f64) as u64;
-                        
-                        println!("New virtual SOL reserve: {}", new_virtual_sol_reserve);
-                        println!("New virtual token reserve: {}", new_virtual_token_reserve);
-                        
-                        Some(BondingCurveInfo {
-                            bonding_curve: bonding_curve_pubkey,
-                            new_virtual_sol_reserve,
-                            new_virtual_token_reserve,
-                        })
-                    } else {
-                        None // We'll let build_swap_ixn_by_mint fetch the bonding curve
+        // Each event pairs with one instruction, so repeated trades by one user each get their own
+        let mut events: Vec<Option<PumpEvent>> = pump_events::parse_logs(log_messages)
+            .into_iter()
+            .chain(
+                meta.inner_instructions
+                    .iter()
+                    .flat_map(|inner| inner.instructions.iter())
+                    .filter_map(|ix| PumpEvent::decode_cpi(&ix.data)),
+            )
+            .map(Some)
+            .collect();
+
+        let token_amount = |owner: &str, mint: &str| {
+            meta.post_token_balances
+                .iter()
+                .filter(|balance| balance.owner == owner && balance.mint == mint)
+                .filter_map(|balance| balance.ui_token_amount.as_ref())
+                .map(|amount| amount.ui_amount)
+                .next()
+                .unwrap_or(0_f64)
+        };
+        let signature = match Signature::try_from(transaction.signature.clone()) {
+            Ok(signature) => format!("{:?}", signature),
+            Err(_) => String::new(),
+        };
+        let recent_blockhash = Hash::new(&message.recent_blockhash);
+
+        let mut trades = Vec::with_capacity(instructions.len());
+        for instruction in &instructions {
+            let accounts = instruction.accounts();
+            // Later swaps of the mint derive its accounts under the program the trade used
+            match instruction {
+                PumpInstruction::PumpSwapBuy { .. } | PumpInstruction::PumpSwapSell { .. } => {
+                    token::remember_token_program(&accounts.get("base_mint"), &accounts.get("base_token_program"))
+                }
+                _ => token::remember_token_program(&accounts.get("mint"), &accounts.get("token_program")),
+            }
+            let trade = match instruction {
+                PumpInstruction::PumpFunCreate { .. } => {
+                    let (mint, bonding_curve, user) = (accounts.get("mint"), accounts.get("bonding_curve"), accounts.get("user"));
+                    // A dev buy bundled with the create leaves the curve where its trade event says
+                    // (the buy instruction still pairs with that event itself)
+                    let dev_buy = events.iter().flatten().find_map(|event| match event {
+                        PumpEvent::Trade(trade) if trade.mint == mint && trade.is_buy => Some(trade.clone()),
+                        _ => None,
+                    });
+                    let created = take_event(&mut events, |event| match event {
+                        PumpEvent::Create(create) if create.mint == mint => Some(create.clone()),
+                        _ => None,
+                    });
+                    let reserves = match (&dev_buy, created) {
+                        (Some(trade), _) => Some((trade.virtual_sol_reserves, trade.virtual_token_reserves)),
+                        (None, Some(create)) => Some((create.virtual_sol_reserves, create.virtual_token_reserves)),
+                        (None, None) => None,
                     };
-                    
-                    return Ok(Self {
-                        instruction_type,
-                        slot,
+                    let volume_change = dev_buy
+                        .as_ref()
+                        .map(|trade| trade.sol_amount as i64)
+                        .unwrap_or_else(|| lamport_change(&account_keys, meta, &bonding_curve));
+                    let target = user.to_string();
+                    let mint = mint.to_string();
+                    Self {
+                        instruction_type: InstructionType::PumpMint,
+                        slot: txn.slot,
                         recent_blockhash,
-                        signature,
+                        signature: signature.clone(),
+                        token_amount: token_amount(&target, &mint),
                         target,
                         mint,
-                        bonding_curve,
+                        bonding_curve: bonding_curve.to_string(),
                         volume_change,
-                        bonding_curve_info: bonding_curve_info_option,
+                        bonding_curve_info: reserves.map(|(new_virtual_sol_reserve, new_virtual_token_reserve)| BondingCurveInfo {
+                            bonding_curve,
+                            new_virtual_sol_reserve,
+                            new_virtual_token_reserve,
+                        }),
                         pool_info: None,
-                        token_amount: token_post_amount,
-                        amount,
-                        max_sol_cost,
+                        amount: None,
+                        max_sol_cost: None,
                         min_sol_output: None,
                         base_amount_in: None,
                         min_quote_amount_out: None,
                         base_amount_out: None,
                         max_quote_amount_in: None,
-                    });
-                } else {
-                    println!("Transaction is None, cannot proceed");
-                    return Err(anyhow::anyhow!("Transaction is None"));
-                }
-                },
-                
-                InstructionType::PumpSell => {
-                    println!("Processing PumpSell instruction");
-                    let mut sell_amount: u64 = 0;
-                    let mut sell_min_sol_output: u64 = 0;
-                    let mut token_mint = String::new();
-                    
-                    // Find the encoded data in log messages
-                    let encoded_data = log_messages.iter()
-                        .find(|msg| msg.contains(PUMP_FUN_BUY_OR_SELL_PROGRAM_DATA_PREFIX))
-                        .map_or("", |v| v)
-                        .split_whitespace()
-                        .nth(2)
-                        .unwrap_or("")
-                        .to_string();
-                    
-                    if !encoded_data.is_empty() {
-                        let decoded_data = match base64::decode(&encoded_data) {
-                            Ok(data) => {
-                                println!("Base64 decoded sell data successfully, length: {}", data.len());
-                                println!("encoded_data: {}", encoded_data);
-                                data
-                            },
-                            Err(e) => {
-                                println!("Error decoding sell base64 data: {}", e);
-                                Vec::new()
-                            }
-                        };
-                        
-                        // Extract mint address if available (at position 8..40)
-                        if decoded_data.len() >= 40 {  // 8 (discriminator) + 32 (mint)
-                            let mint_bytes = &decoded_data[8..40];
-                            token_mint = bs58::encode(mint_bytes).into_string();
-                            println!("Extracted mint address: {}", token_mint);
-                        }
-                        
-                        // Extract amount if available
-                        if decoded_data.len() >= 48 {  // 8 + 32 + 8 (amount)
-                            let amount_bytes = &decoded_data[40..48];
-                            sell_amount = u64::from_le_bytes(amount_bytes.try_into().unwrap());
-                            println!("Detected sell amount: {} tokens", sell_amount);
-                        }
-                        
-                        // Extract min_sol_output if available
-                        if decoded_data.len() >= 56 {  // 8 + 32 + 8 + 8 (min_sol_output)
-                            let min_sol_output_bytes = &decoded_data[48..56];
-                            sell_min_sol_output = u64::from_le_bytes(min_sol_output_bytes.try_into().unwrap());
-                            println!("Min SOL output: {}", sell_min_sol_output);
-                            println!("Min SOL output in SOL: {} SOL", lamports_to_sol(sell_min_sol_output));
-                        }
-                        
-                        amount = Some(sell_amount);
-                        min_sol_output = Some(sell_min_sol_output);
                     }
-                
-                // Here we need to use the variables from the PumpMint scope
-                if let Some(transaction) = txn.transaction.clone() {
-                    let signature = match Signature::try_from(transaction.signature.clone()) {
-                        Ok(signature) => {
-                            let sig_str = format!("{:?}", signature);
-                            println!("Parsed signature: {}", sig_str);
-                            sig_str
-                        },
-                        Err(_) => "".to_string(),
-                    };
-                    
-                    let recent_blockhash_slice = match transaction.transaction.as_ref()
-                        .and_then(|t| t.message.as_ref())
-                        .map(|m| &m.recent_blockhash) {
-                        Some(hash) => {
-                            println!("Found blockhash");
-                            hash
-                        },
-                        None => {
-                            println!("Failed to get blockhash");
-                            return Err(anyhow::anyhow!("Failed to get recent blockhash"));
-                        }
-                    };
-                    
-                    let recent_blockhash = Hash::new(recent_blockhash_slice);
-                    
-                    let account_keys = match transaction.transaction.as_ref()
-                        .and_then(|t| t.message.as_ref())
-                        .map(|m| &m.account_keys) {
-                        Some(keys) => {
-                            println!("Found account keys, count: {}", keys.len());
-                            keys
-                        },
-                        None => {
-                            println!("Failed to get account keys");
-                            return Err(anyhow::anyhow!("Failed to get account keys"));
+                }
+                PumpInstruction::PumpFunBuy { amount, .. } | PumpInstruction::PumpFunSell { amount, .. } => {
+                    let (mint, bonding_curve, user) = (accounts.get("mint"), accounts.get("bonding_curve"), accounts.get("user"));
+                    let trade = take_event(&mut events, |event| match event {
+                        PumpEvent::Trade(trade) if trade.mint == mint && trade.user == user => Some(trade.clone()),
+                        _ => None,
+                    });
+                    let (instruction_type, max_sol_cost, min_sol_output, sign) = match instruction {
+                        PumpInstruction::PumpFunBuy { max_sol_cost, .. } => (InstructionType::PumpBuy, Some(*max_sol_cost), None, 1),
+                        PumpInstruction::PumpFunSell { min_sol_output, .. } => {
+                            (InstructionType::PumpSell, None, Some(*min_sol_output), -1)
                         }
+                        _ => unreachable!("matched on PumpFun trades above"),
                     };
-                    
-                    // Extract target, mint, bonding_curve, etc. from transaction information
-                    let mut target = String::new();
-                    let mut mint = token_mint; // Use the extracted mint if available
-                    let mut bonding_curve = String::new();
-                    let mut sol_post_amount = 0_u64;
-                    let mut volume_change = 0_i64;
-                    let mut token_post_amount = 0_f64;
-                    
-                    // Process transaction metadata to extract information
-                    if let Some(meta) = &transaction.meta {
-                        println!("Processing transaction metadata");
-                        
-                        // Extract necessary information from metadata and logs
-                        for token_balance in &meta.post_token_balances {
-                            if mint.is_empty() {
-                                mint = token_balance.mint.clone();
-                                println!("Found mint: {}", mint);
-                            }
-                            target = token_balance.owner.clone();
-                            println!("Found target: {}", target);
-                            
-                            // Extract token amount
-                            token_post_amount = token_balance
-                                .ui_token_amount
-                                .as_ref()
-                                .map(|ui| ui.ui_amount)
-                                .unwrap_or(0_f64);
-                            
-                            println!("Token post amount: {}", token_post_amount);
-                        }
-                        
-                        // Find bonding curve from logs
-                        for log in log_messages.iter() {
-                            if log.contains("bonding curve:") {
-                                if let Some(bc) = log.split("bonding curve:").nth(1).map(|s| s.trim()) {
-                                    bonding_curve = bc.to_string();
-                                    println!("Found bonding curve: {}", bonding_curve);
-                                }
-                            }
-                        }
-                        
-                        // Calculate volume change
-                        if !bonding_curve.is_empty() {
-                            let bonding_curve_index = account_keys
-                                .iter()
-                                .position(|account_key| {
-                                    Pubkey::try_from(account_key.clone()).map(|k| k.to_string() == bonding_curve).unwrap_or(false)
-                                })
-                                .unwrap_or(0);
-                            
-                            sol_post_amount = *meta
-                                .post_balances
-                                .get(bonding_curve_index)
-                                .unwrap_or(&0_u64);
-                            
-                            let sol_pre_amount = *meta
-                                .pre_balances
-                                .get(bonding_curve_index)
-                                .unwrap_or(&0_u64);
-                            
-                            volume_change = (sol_post_amount as i64) - (sol_pre_amount as i64);
-                            println!("Volume change: {} lamports", volume_change);
-                        }
+                    let volume_change = trade
+                        .as_ref()
+                        .map(|trade| sign * trade.sol_amount as i64)
+                        .unwrap_or_else(|| lamport_change(&account_keys, meta, &bonding_curve));
+                    let target = user.to_string();
+                    let mint = mint.to_string();
+                    if let Some(trade) = &trade {
+                        remember_curve_fees(&mint, PumpCurve::from(trade));
                     }
-                    
-                    // Create BondingCurveInfo if we have bonding curve information
-                    let bonding_curve_info_option = if !bonding_curve.is_empty() {
-                        let bonding_curve_pubkey = match Pubkey::from_str(&bonding_curve) {
-                            Ok(pk) => pk,
-                            Err(e) => {
-                                println!("Failed to parse bonding curve pubkey: {}", e);
-                                return Err(anyhow::anyhow!("Invalid bonding curve public key"));
-                            }
-                        };
-                        
-                        let new_virtual_sol_reserve = INITIAL_VIRTUAL_SOL_RESERVES + sol_post_amount;
-                        let new_virtual_token_reserve = ((INITIAL_VIRTUAL_SOL_RESERVES as u128 * INITIAL_VIRTUAL_TOKEN_RESERVES as u128) as f64 / new_virtual_sol_reserve as f64) as u64;
-                        
-                        println!("New virtual SOL reserve: {}", new_virtual_sol_reserve);
-                        println!("New virtual token reserve: {}", new_virtual_token_reserve);
-                        
-                        Some(BondingCurveInfo {
-                            bonding_curve: bonding_curve_pubkey,
-                            new_virtual_sol_reserve,
-                            new_virtual_token_reserve,
-                        })
-                    } else {
-                        None // We'll let build_swap_ixn_by_mint fetch the bonding curve
-                    };
-                
-                    return Ok(Self {
+                    Self {
                         instruction_type,
-                        slot,
+                        slot: txn.slot,
                         recent_blockhash,
-                        signature,
+                        signature: signature.clone(),
+                        token_amount: token_amount(&target, &mint),
                         target,
                         mint,
-                        bonding_curve,
+                        bonding_curve: bonding_curve.to_string(),
                         volume_change,
-                        bonding_curve_info: bonding_curve_info_option,
+                        bonding_curve_info: trade.map(|trade| BondingCurveInfo {
+                            bonding_curve,
+                            new_virtual_sol_reserve: trade.virtual_sol_reserves,
+                            new_virtual_token_reserve: trade.virtual_token_reserves,
+                        }),
                         pool_info: None,
-                        token_amount: token_post_amount,
-                        amount,
-                        max_sol_cost: None,
+                        amount: Some(*amount),
+                        max_sol_cost,
                         min_sol_output,
                         base_amount_in: None,
                         min_quote_amount_out: None,
                         base_amount_out: None,
                         max_quote_amount_in: None,
-                    });
-                } else {
-                    println!("Transaction is None, cannot proceed");
-                    return Err(anyhow::anyhow!("Transaction is None"));
+                    }
                 }
-                },
-            
-            InstructionType::PumpSwapBuy | InstructionType::PumpSwapSell => {
-                println!("Processing PumpSwap {:?} instruction", instruction_type);
-                
-                // Parse PumpSwap transaction information
-            if let Some(transaction) = txn.transaction.clone() {
-                let signature = match Signature::try_from(transaction.signature.clone()) {
-                        Ok(signature) => {
-                            let sig_str = format!("{:?}", signature);
-                            println!("Parsed signature: {}", sig_str);
-                            sig_str
-                        },
-                    Err(_) => "".to_string(),
-                };
-                
-                let recent_blockhash_slice = match transaction.transaction.as_ref()
-                    .and_then(|t| t.message.as_ref())
-                    .map(|m| &m.recent_blockhash) {
-                        Some(hash) => {
-                            println!("Found blockhash");
-                            hash
-                        },
-                        None => {
-                            println!("Failed to get blockhash");
-                            return Err(anyhow::anyhow!("Failed to get recent blockhash"));
+                PumpInstruction::PumpSwapBuy { .. } | PumpInstruction::PumpSwapSell { .. } => {
+                    let (pool_id, user) = (accounts.get("pool"), accounts.get("user"));
+                    let pool_quote_token_account = accounts.get("pool_quote_token_account");
+                    // Pool reserves before the trade with the fees it paid, the coin creator and the SOL moved, from the event
+                    let (instruction_type, event, amounts) = match instruction {
+                        PumpInstruction::PumpSwapBuy { base_amount_out, max_quote_amount_in, .. } => {
+                            let event = take_event(&mut events, |event| match event {
+                                PumpEvent::Buy(buy) if buy.pool == pool_id && buy.user == user => Some((
+                                    PumpSwapPool::from(buy),
+                                    buy.coin_creator,
+                                    buy.quote_amount_in as i64,
+                                )),
+                                _ => None,
+                            });
+                            (InstructionType::PumpSwapBuy, event, (None, None, Some(base_amount_out), Some(max_quote_amount_in)))
                         }
-                };
-                
-                let recent_blockhash = Hash::new(recent_blockhash_slice);
-
-                let account_keys = match transaction.transaction.as_ref()
-                    .and_then(|t| t.message.as_ref())
-                    .map(|m| &m.account_keys) {
-                        Some(keys) => {
-                            println!("Found account keys, count: {}", keys.len());
-                            keys
-                        },
-                        None => {
-                            println!("Failed to get account keys");
-                            return Err(anyhow::anyhow!("Failed to get account keys"));
+                        PumpInstruction::PumpSwapSell { base_amount_in, min_quote_amount_out, .. } => {
+                            let event = take_event(&mut events, |event| match event {
+                                PumpEvent::Sell(sell) if sell.pool == pool_id && sell.user == user => Some((
+                                    PumpSwapPool::from(sell),
+                                    sell.coin_creator,
+                                    -(sell.quote_amount_out as i64),
+                                )),
+                                _ => None,
+                            });
+                            (InstructionType::PumpSwapSell, event, (Some(base_amount_in), Some(min_quote_amount_out), None, None))
                         }
+                        _ => unreachable!("matched on PumpSwap trades above"),
                     };
-                    
-                    // Get transaction data for PumpSwap specific information
-                    let pump_amm_program_id = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
-                    
-                    // Find the instruction indices for PumpSwap program
-                    let mut pump_swap_instruction_indices = Vec::new();
-                    if let Some(transaction_info) = transaction.transaction.as_ref() {
-                        if let Some(message) = transaction_info.message.as_ref() {
-                            for (i, account_indices) in message.instructions.iter().enumerate() {
-                                let program_idx = account_indices.program_id_index;
-                                if let Some(program_key) = account_keys.get(program_idx as usize) {
-                                    if let Ok(program_key_pubkey) = Pubkey::try_from(program_key.clone()) {
-                                        if program_key_pubkey.to_string() == pump_amm_program_id {
-                                            pump_swap_instruction_indices.push(i);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    
-                    println!("PumpSwap instruction indices: {:?}", pump_swap_instruction_indices);
-                    
-                    // Extract pool, base_mint, and quote_mint information from logs
-                    let mut pool_id = Pubkey::default();
-                    let mut base_mint = Pubkey::default();
-                    let mut quote_mint = Pubkey::default();
-                    let mut pool_base_token_account = Pubkey::default();
-                    let mut pool_quote_token_account = Pubkey::default();
-                    let mut base_reserve = 0u64;
-                    let mut quote_reserve = 0u64;
-                    
-                    // Get mint addresses
-                    if !pump_swap_instruction_indices.is_empty() {
-                        // By examining the structure of PumpSwap instructions from the IDL
-                        // Accounts in a buy/sell instruction:
-                        // 0: pool ID
-                        // 3: base mint (token)
-                        // 4: quote mint (SOL)
-                        
-                        if let Some(tx_info) = transaction.transaction.as_ref() {
-                            if let Some(message) = tx_info.message.as_ref() {
-                                if let Some(instr_idx) = pump_swap_instruction_indices.first() {
-                                    if let Some(instruction) = message.instructions.get(*instr_idx) {
-                                        // Get accounts directly without using as_ref()
-                                        let accounts = &instruction.accounts;
-                                        // Pool ID is the first account
-                                        if accounts.len() > 0 {
-                                            if let Some(pool_account_key) = account_keys.get(accounts[0] as usize) {
-                                                if let Ok(pubkey) = Pubkey::try_from(pool_account_key.clone()) {
-                                                    pool_id = pubkey;
-                                                    println!("Pool ID: {}", pool_id);
-                                                }
-                                            }
-                                        }
-                                        
-                                        // Base mint is the 4th account
-                                        if accounts.len() > 3 {
-                                            if let Some(base_mint_key) = account_keys.get(accounts[3] as usize) {
-                                                if let Ok(pubkey) = Pubkey::try_from(base_mint_key.clone()) {
-                                                    base_mint = pubkey;
-                                                    println!("Base mint: {}", base_mint);
-                                                }
-                                            }
-                                        }
-                                        
-                                        // Quote mint is the 5th account
-                                        if accounts.len() > 4 {
-                                            if let Some(quote_mint_key) = account_keys.get(accounts[4] as usize) {
-                                                if let Ok(pubkey) = Pubkey::try_from(quote_mint_key.clone()) {
-                                                    quote_mint = pubkey;
-                                                    println!("Quote mint: {}", quote_mint);
-                                                }
-                                            }
-                                        }
-                                        
-                                        // Pool base token account is the 8th account
-                                        if accounts.len() > 7 {
-                                            if let Some(pool_base_key) = account_keys.get(accounts[7] as usize) {
-                                                if let Ok(pubkey) = Pubkey::try_from(pool_base_key.clone()) {
-                                                    pool_base_token_account = pubkey;
-                                                    println!("Pool base token account: {}", pool_base_token_account);
-                                                }
-                                            }
-                                        }
-                                        
-                                        // Pool quote token account is the 9th account
-                                        if accounts.len() > 8 {
-                                            if let Some(pool_quote_key) = account_keys.get(accounts[8] as usize) {
-                                                if let Ok(pubkey) = Pubkey::try_from(pool_quote_key.clone()) {
-                                                    pool_quote_token_account = pubkey;
-                                                    println!("Pool quote token account: {}", pool_quote_token_account);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    
-                    // Extract base_amount_in, min_quote_amount_out, base_amount_out, max_quote_amount_in from logs
-                    for log in log_messages.iter() {
-                        // Extract parameters from logs - example patterns based on PumpSwap events
-                        if instruction_type == InstructionType::PumpSwapBuy {
-                            if log.contains("base_amount_out:") {
-                                // Example: Extract base_amount_out from a log like "base_amount_out: 1000000"
-                                if let Some(value_str) = log.split("base_amount_out:").nth(1).map(|s| s.trim()) {
-                                    if let Ok(value) = value_str.parse::<u64>() {
-                                        base_amount_out = Some(value);
-                                        println!("Extracted base_amount_out: {}", value);
-                                    }
-                                }
-                            }
-                            if log.contains("max_quote_amount_in:") {
-                                // Example: Extract max_quote_amount_in from a log like "max_quote_amount_in: 2000000"
-                                if let Some(value_str) = log.split("max_quote_amount_in:").nth(1).map(|s| s.trim()) {
-                                    if let Ok(value) = value_str.parse::<u64>() {
-                                        max_quote_amount_in = Some(value);
-                                        println!("Extracted max_quote_amount_in: {}", value);
-                                    }
-                                }
-                            }
-                        } else if instruction_type == InstructionType::PumpSwapSell {
-                            if log.contains("base_amount_in:") {
-                                // Example: Extract base_amount_in from a log like "base_amount_in: 1000000"
-                                if let Some(value_str) = log.split("base_amount_in:").nth(1).map(|s| s.trim()) {
-                                    if let Ok(value) = value_str.parse::<u64>() {
-                                        base_amount_in = Some(value);
-                                        println!("Extracted base_amount_in: {}", value);
-                                    }
-                                }
-                            }
-                            if log.contains("min_quote_amount_out:") {
-                                // Example: Extract min_quote_amount_out from a log like "min_quote_amount_out: 500000"
-                                if let Some(value_str) = log.split("min_quote_amount_out:").nth(1).map(|s| s.trim()) {
-                                    if let Ok(value) = value_str.parse::<u64>() {
-                                        min_quote_amount_out = Some(value);
-                                        println!("Extracted min_quote_amount_out: {}", value);
-                                    }
-                                }
-                            }
-                        }
-                        
-                        // Extract pool balances
-                        if log.contains("pool_base_token_reserves:") {
-                            if let Some(value_str) = log.split("pool_base_token_reserves:").nth(1).map(|s| s.trim()) {
-                                if let Ok(value) = value_str.parse::<u64>() {
-                                    base_reserve = value;
-                                    println!("Extracted pool_base_token_reserves: {}", value);
-                                }
-                            }
-                        }
-                        if log.contains("pool_quote_token_reserves:") {
-                            if let Some(value_str) = log.split("pool_quote_token_reserves:").nth(1).map(|s| s.trim()) {
-                                if let Ok(value) = value_str.parse::<u64>() {
-                                    quote_reserve = value;
-                                    println!("Extracted pool_quote_token_reserves: {}", value);
-                                }
-                            }
-                        }
-                    }
-                    
-                    // Find target wallet and token amount
-                    let mut target = String::new();
-                    let mut volume_change = 0_i64;
-                    let mut token_post_amount = 0_f64;
-                    
-                    if let Some(meta) = &transaction.meta {
-                        // Find the user account from instruction accounts
-                        // In PumpSwap instructions, the user is typically the 2nd account
-                        if !pump_swap_instruction_indices.is_empty() {
-                            if let Some(tx_info) = transaction.transaction.as_ref() {
-                                if let Some(message) = tx_info.message.as_ref() {
-                                    if let Some(instr_idx) = pump_swap_instruction_indices.first() {
-                                        if let Some(instruction) = message.instructions.get(*instr_idx) {
-                                            // Get accounts directly without using as_ref()
-                                            let accounts = &instruction.accounts;
-                                            // User is the 2nd account
-                                            if accounts.len() > 1 {
-                                                if let Some(user_account_key) = account_keys.get(accounts[1] as usize) {
-                                                    if let Ok(pubkey) = Pubkey::try_from(user_account_key.clone()) {
-                                                        target = pubkey.to_string();
-                                                        println!("Target user: {}", target);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        
-                        // Calculate volume change for pool quote token account
-                        let pool_quote_index = account_keys
-                        .iter()
-                        .position(|account_key| {
-                                Pubkey::try_from(account_key.clone()).map(|k| k == pool_quote_token_account).unwrap_or(false)
-                        })
-                        .unwrap_or(0);
-
-                        let quote_post_amount = *meta
-                        .post_balances
-                            .get(pool_quote_index)
-                        .unwrap_or(&0_u64);
-
-                        let quote_pre_amount = *meta
-                        .pre_balances
-                            .get(pool_quote_index)
-                        .unwrap_or(&0_u64);
-
-                        volume_change = (quote_post_amount as i64) - (quote_pre_amount as i64);
-                        
-                        // Get token amount from post token balances
-                    token_post_amount = meta
-                        .post_token_balances
-                        .iter()
-                        .filter_map(|token_balance| {
-                            if token_balance.owner == target {
-                                token_balance
-                                    .ui_token_amount
-                                    .as_ref()
-                                    .map(|ui| ui.ui_amount)
-                            } else {
-                                None
-                            }
-                        })
-                        .next()
-                        .unwrap_or(0_f64);
-                    
-                    println!("Token post amount: {}", token_post_amount);
-                    }
-                    
-                    // Create pool info
+                    let (base_amount_in, min_quote_amount_out, base_amount_out, max_quote_amount_in) = amounts;
+                    let (pool, coin_creator, volume_change) = match event {
+                        Some((pool, coin_creator, volume_change)) => (Some(pool), coin_creator, volume_change),
+                        None => (None, Pubkey::default(), lamport_change(&account_keys, meta, &pool_quote_token_account)),
+                    };
+                    let (base_reserve, quote_reserve) = pool.map(|pool| (pool.base_reserve, pool.quote_reserve)).unwrap_or_default();
+
                     let pool_info = PoolInfo {
                         pool_id,
-                        base_mint,
-                        quote_mint,
-                        pool_base_token_account,
+                        base_mint: accounts.get("base_mint"),
+                        quote_mint: accounts.get("quote_mint"),
+                        pool_base_token_account: accounts.get("pool_base_token_account"),
                         pool_quote_token_account,
                         base_reserve,
                         quote_reserve,
-                        coin_creator: Pubkey::default(),
+                        coin_creator,
                     };
-                    
-                    // Determine bonding curve (in PumpSwap case, we don't have one)
-                    let bonding_curve = String::new();
-                    
-                    // Get mint
-                    let mint = base_mint.to_string();
-                    
-                    return Ok(Self {
-                    instruction_type,
-                        slot,
-                    recent_blockhash,
-                    signature,
-                    target,
-                    mint,
-                    bonding_curve,
-                    volume_change,
+                    let target = user.to_string();
+                    let mint = pool_info.base_mint.to_string();
+                    remember_pool(&mint, pool_id, coin_creator);
+                    if let Some(pool) = pool {
+                        remember_pool_fees(&mint, pool);
+                    }
+                    Self {
+                        instruction_type,
+                        slot: txn.slot,
+                        recent_blockhash,
+                        signature: signature.clone(),
+                        token_amount: token_amount(&target, &mint),
+                        target,
+                        mint,
+                        bonding_curve: String::new(),
+                        volume_change,
                         bonding_curve_info: None,
                         pool_info: Some(pool_info),
-                        token_amount: token_post_amount,
                         amount: None,
                         max_sol_cost: None,
                         min_sol_output: None,
-                        base_amount_in,
-                        min_quote_amount_out,
-                        base_amount_out,
-                        max_quote_amount_in,
-                    });
+                        base_amount_in: base_amount_in.copied(),
+                        min_quote_amount_out: min_quote_amount_out.copied(),
+                        base_amount_out: base_amount_out.copied(),
+                        max_quote_amount_in: max_quote_amount_in.copied(),
+                    }
+                }
+            };
+            trades.push(trade);
+        }
+        Ok(trades)
+    }
+
+    /// Decodes every Raydium LaunchLab buy, sell or pool creation from the
+    /// LaunchLab instructions themselves, whether top-level or CPIs.
+    fn from_raydium_launchpad(txn: &SubscribeUpdateTransaction) -> Result<Vec<Self>> {
+        let transaction = txn.transaction.as_ref().ok_or_else(|| anyhow::anyhow!("Transaction is None"))?;
+        let message = transaction
+            .transaction
+            .as_ref()
+            .and_then(|t| t.message.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("Failed to get transaction message"))?;
+        let meta = transaction.meta.as_ref().ok_or_else(|| anyhow::anyhow!("Failed to get transaction meta"))?;
+
+        let account_keys = resolve_account_keys(message, meta);
+        let key = |accounts: &[u8], index: usize| -> Pubkey {
+            accounts
+                .get(index)
+                .and_then(|account| account_keys.get(*account as usize))
+                .copied()
+                .unwrap_or_default()
+        };
+        let program = Pubkey::from_str(RAYDIUM_LAUNCHPAD_PROGRAM)?;
+        let instructions = all_instructions(message, meta)
+            .filter(|(program_id_index, _, _)| account_keys.get(*program_id_index as usize) == Some(&program));
+
+        let mut trades = Vec::new();
+        for (_, accounts, data) in instructions {
+            let Some(discriminator) = data.get(..8).and_then(|bytes| <[u8; 8]>::try_from(bytes).ok()) else {
+                continue;
+            };
+            let arg = |index: usize| {
+                data.get(8 + index * 8..16 + index * 8)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .map(u64::from_le_bytes)
+            };
+            // Buys and sells: payer 0, pool 4, quote vault 8, base mint 9.
+            // Initialize: creator 1, pool 5, base mint 6, quote vault 9.
+            let (instruction_type, amount, max_sol_cost, min_sol_output) = match discriminator {
+                BUY_EXACT_IN_DISCRIMINATOR => (InstructionType::RaydiumLaunchpadBuy, arg(1), arg(0), None),
+                BUY_EXACT_OUT_DISCRIMINATOR => (InstructionType::RaydiumLaunchpadBuy, arg(0), arg(1), None),
+                SELL_EXACT_IN_DISCRIMINATOR => (InstructionType::RaydiumLaunchpadSell, arg(0), None, arg(1)),
+                SELL_EXACT_OUT_DISCRIMINATOR => (InstructionType::RaydiumLaunchpadSell, arg(1), None, arg(0)),
+                INITIALIZE_DISCRIMINATOR | INITIALIZE_V2_DISCRIMINATOR => {
+                    (InstructionType::RaydiumLaunchpadMint, None, None, None)
+                }
+                _ => continue,
+            };
+            let (target, pool, quote_vault, mint) = if instruction_type == InstructionType::RaydiumLaunchpadMint {
+                (key(accounts, 1), key(accounts, 5), key(accounts, 9), key(accounts, 6))
             } else {
-                    println!("Transaction is None, cannot proceed");
-                return Err(anyhow::anyhow!("Transaction is None"));
-            }
-            }
+                (key(accounts, 0), key(accounts, 4), key(accounts, 8), key(accounts, 9))
+            };
+            let target = target.to_string();
+            let mint = mint.to_string();
+
+            // SOL moved into or out of the curve, from the quote vault's lamports
+            let volume_change = lamport_change(&account_keys, meta, &quote_vault);
+            let token_amount = meta
+                .post_token_balances
+                .iter()
+                .filter(|balance| balance.owner == target && balance.mint == mint)
+                .filter_map(|balance| balance.ui_token_amount.as_ref())
+                .map(|amount| amount.ui_amount)
+                .next()
+                .unwrap_or(0_f64);
+            let signature = match Signature::try_from(transaction.signature.clone()) {
+                Ok(signature) => format!("{:?}", signature),
+                Err(_) => String::new(),
+            };
+
+            trades.push(Self {
+                instruction_type,
+                slot: txn.slot,
+                recent_blockhash: Hash::new(&message.recent_blockhash),
+                signature,
+                target,
+                mint,
+                bonding_curve: pool.to_string(),
+                volume_change,
+                bonding_curve_info: None,
+                pool_info: None,
+                token_amount,
+                amount,
+                max_sol_cost,
+                min_sol_output,
+                base_amount_in: None,
+                min_quote_amount_out: None,
+                base_amount_out: None,
+                max_quote_amount_in: None,
+            });
         }
-        
-        // If we reach here, we failed to parse the transaction
-        println!("Failed to parse transaction");
-        Err(anyhow::anyhow!("Failed to parse transaction"))
+        Ok(trades)
+    }
+
+    // Decodes every Meteora Dynamic Bonding Curve or DLMM swap of a SOL pair. Which
+    // side the signer took follows from the mints of its input and output token
+    // accounts, and the volume from the SOL each swap moved through the pool's vault.
+    fn from_meteora(txn: &SubscribeUpdateTransaction) -> Result<Vec<Self>> {
+        let transaction = txn.transaction.as_ref().ok_or_else(|| anyhow::anyhow!("Transaction is None"))?;
+        let message = transaction
+            .transaction
+            .as_ref()
+            .and_then(|t| t.message.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("Failed to get transaction message"))?;
+        let meta = transaction.meta.as_ref().ok_or_else(|| anyhow::anyhow!("Failed to get transaction meta"))?;
+
+        let account_keys = resolve_account_keys(message, meta);
+        let key = |accounts: &[u8], index: usize| -> Pubkey {
+            accounts
+                .get(index)
+                .and_then(|account| account_keys.get(*account as usize))
+                .copied()
+                .unwrap_or_default()
+        };
+        let dbc_program = Pubkey::from_str(meteora_dbc::METEORA_DBC_PROGRAM)?;
+        let dlmm_program = Pubkey::from_str(meteora_dlmm::METEORA_DLMM_PROGRAM)?;
+        let wsol = spl_token::native_mint::ID;
+        let instructions = instructions_with_cpis(message, meta);
+
+        let mut trades = Vec::new();
+        for (program_id_index, accounts, data, cpis) in instructions {
+            let Some(program) = account_keys.get(program_id_index as usize) else {
+                continue;
+            };
+            let Some(discriminator) = data.get(..8).and_then(|bytes| <[u8; 8]>::try_from(bytes).ok()) else {
+                continue;
+            };
+            let arg = |index: usize| {
+                data.get(8 + index * 8..16 + index * 8)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .map(u64::from_le_bytes)
+            };
+            // Every swap is read as (amount in, amount out), one exact and the other its slippage bound
+            // DBC swaps: pool 2, input 3, output 4, quote vault 6, base mint 7, quote mint 8, payer 9.
+            // DLMM swaps: pair 0, reserve x 2, reserve y 3, user in 4, user out 5, mint x 6, mint y 7, user 10.
+            let (is_dbc, (amount_in, amount_out), target, pool, mint, sol_vault, (user_in, user_out)) = if *program == dbc_program {
+                let amounts = match discriminator {
+                    meteora_dbc::SWAP_DISCRIMINATOR => (arg(0), arg(1)),
+                    // swap2 puts the exact amount first whichever side it is on
+                    meteora_dbc::SWAP2_DISCRIMINATOR if data.get(24) == Some(&meteora_dbc::SWAP_MODE_EXACT_OUT) => {
+                        (arg(1), arg(0))
+                    }
+                    meteora_dbc::SWAP2_DISCRIMINATOR => (arg(0), arg(1)),
+                    _ => continue,
+                };
+                if key(accounts, 8) != wsol {
+                    continue;
+                }
+                let user_accounts = (key(accounts, 3), key(accounts, 4));
+                (true, amounts, key(accounts, 9), key(accounts, 2), key(accounts, 7), key(accounts, 6), user_accounts)
+            } else if *program == dlmm_program {
+                let amounts = match discriminator {
+                    meteora_dlmm::SWAP_DISCRIMINATOR
+                    | meteora_dlmm::SWAP2_DISCRIMINATOR
+                    | meteora_dlmm::SWAP_EXACT_OUT_DISCRIMINATOR
+                    | meteora_dlmm::SWAP_EXACT_OUT2_DISCRIMINATOR => (arg(0), arg(1)),
+                    meteora_dlmm::SWAP_WITH_PRICE_IMPACT_DISCRIMINATOR
+                    | meteora_dlmm::SWAP_WITH_PRICE_IMPACT2_DISCRIMINATOR => (arg(0), None),
+                    _ => continue,
+                };
+                let (mint, sol_vault) = match (key(accounts, 6), key(accounts, 7)) {
+                    (mint_x, mint_y) if mint_y == wsol => (mint_x, key(accounts, 3)),
+                    (mint_x, mint_y) if mint_x == wsol => (mint_y, key(accounts, 2)),
+                    _ => continue,
+                };
+                let user_accounts = (key(accounts, 4), key(accounts, 5));
+                (false, amounts, key(accounts, 10), key(accounts, 0), mint, sol_vault, user_accounts)
+            } else {
+                continue;
+            };
+
+            // A buy pays SOL in and takes the mint out. A token account opened or
+            // closed in the same transaction may have no balance, so fall back to
+            // how the signer's holding of the mint changed.
+            let target = target.to_string();
+            let mint = mint.to_string();
+            let is_buy = if token_account_mint(&account_keys, meta, &user_out) == Some(mint.as_str()) {
+                true
+            } else if token_account_mint(&account_keys, meta, &user_in) == Some(mint.as_str()) {
+                false
+            } else {
+                match owner_token_change(meta, &target, &mint) {
+                    change if change > 0 => true,
+                    change if change < 0 => false,
+                    _ => continue,
+                }
+            };
+            // SOL this swap moved into or out of the pool, so several swaps in one
+            // transaction each get their own volume
+            let volume_change = vault_transfers(&account_keys, &cpis, &sol_vault);
+            let (instruction_type, protocol) = match (is_dbc, is_buy) {
+                (true, true) => (InstructionType::MeteoraDbcBuy, SwapProtocol::MeteoraDbc),
+                (true, false) => (InstructionType::MeteoraDbcSell, SwapProtocol::MeteoraDbc),
+                (false, true) => (InstructionType::MeteoraDlmmBuy, SwapProtocol::MeteoraDlmm),
+                (false, false) => (InstructionType::MeteoraDlmmSell, SwapProtocol::MeteoraDlmm),
+            };
+            // Buys spend SOL for tokens, sells the other way round
+            let (amount, max_sol_cost, min_sol_output) = if is_buy {
+                (amount_out, amount_in, None)
+            } else {
+                (amount_in, None, amount_out)
+            };
+            remember_pool_id(&mint, protocol, pool);
+
+            let token_amount = meta
+                .post_token_balances
+                .iter()
+                .filter(|balance| balance.owner == target && balance.mint == mint)
+                .filter_map(|balance| balance.ui_token_amount.as_ref())
+                .map(|amount| amount.ui_amount)
+                .next()
+                .unwrap_or(0_f64);
+            let signature = match Signature::try_from(transaction.signature.clone()) {
+                Ok(signature) => format!("{:?}", signature),
+                Err(_) => String::new(),
+            };
+
+            trades.push(Self {
+                instruction_type,
+                slot: txn.slot,
+                recent_blockhash: Hash::new(&message.recent_blockhash),
+                signature,
+                target,
+                mint,
+                bonding_curve: pool.to_string(),
+                volume_change,
+                bonding_curve_info: None,
+                pool_info: None,
+                token_amount,
+                amount,
+                max_sol_cost,
+                min_sol_output,
+                base_amount_in: None,
+                min_quote_amount_out: None,
+                base_amount_out: None,
+                max_quote_amount_in: None,
+            });
+        }
+        Ok(trades)
     }
 }
 
@@ -1289,6 +911,76 @@ async fn process_stream_message(
 }
 
 // Heartbeat function to periodically send pings
+fn build_subscribe_request(program_ids: Vec<String>) -> SubscribeRequest {
+    SubscribeRequest {
+        slots: HashMap::new(),
+        accounts: HashMap::new(),
+        transactions: hashmap! {
+            "All".to_owned() => SubscribeRequestFilterTransactions {
+                vote: None,
+                failed: Some(false),
+                signature: None,
+                account_include: program_ids,
+                account_exclude: vec![JUPITER_PROGRAM.to_string(), OKX_DEX_PROGRAM.to_string()],
+                account_required: Vec::<String>::new()
+            }
+        },
+        transactions_status: HashMap::new(),
+        entry: HashMap::new(),
+        blocks: HashMap::new(),
+        blocks_meta: HashMap::new(),
+        commitment: Some(CommitmentLevel::Processed as i32),
+        accounts_data_slice: vec![],
+        ping: None,
+        from_slot: None,
+    }
+}
+
+// Re-sends the transaction filter on the existing stream whenever a config
+// reload changes the copy targets, so the subscription is never torn down.
+fn spawn_resubscribe_on_reload(
+    subscribe_tx: Arc<tokio::sync::Mutex<impl Sink<SubscribeRequest, Error = impl std::fmt::Debug> + Unpin + Send + 'static>>,
+    base_program_ids: Vec<String>,
+    logger: Logger,
+) {
+    let mut params_rx = subscribe_params();
+    let mut current_targets = params_rx.borrow().copy_targets.clone();
+    tokio::spawn(async move {
+        while params_rx.changed().await.is_ok() {
+            let targets = params_rx.borrow_and_update().copy_targets.clone();
+            if targets == current_targets {
+                continue;
+            }
+            let mut program_ids = base_program_ids.clone();
+            program_ids.extend(targets.iter().cloned());
+            if program_ids.is_empty() {
+                logger.log("[COPY TRADING] => Reload removed every copy target, keeping the previous subscription".yellow().to_string());
+                continue;
+            }
+            let result = subscribe_tx
+                .lock()
+                .await
+                .send(build_subscribe_request(program_ids))
+                .await;
+            match result {
+                Ok(_) => {
+                    logger.log(format!(
+                        "[COPY TRADING] => Subscription updated, monitoring {} address(es)",
+                        targets.len()
+                    ).green().to_string());
+                    current_targets = targets;
+                }
+                Err(e) => {
+                    logger.log(format!(
+                        "[CONNECTION ERROR] => Failed to update subscription: {:?}",
+                        e
+                    ).red().to_string());
+                }
+            }
+        }
+    });
+}
+
 async fn send_heartbeat_ping(
     subscribe_tx: &Arc<tokio::sync::Mutex<impl Sink<SubscribeRequest, Error = impl std::fmt::Debug> + Unpin>>,
     logger: &Logger
@@ -1325,6 +1017,8 @@ pub async fn new_token_trader_pumpfun(
     // Log the copy trading configuration
     let logger = Logger::new("[PUMPFUN-MONITOR] => ".blue().bold().to_string());
 
+    spawn_config_watcher();
+
     // INITIAL SETTING FOR SUBSCIBE
     // -----------------------------------------------------------------------------------------------------------------------------
     let mut client = GeyserGrpcClient::build_from_shared(yellowstone_grpc_http.clone())
@@ -1368,36 +1062,13 @@ pub async fn new_token_trader_pumpfun(
     // Convert to Arc to allow cloning across tasks
     let subscribe_tx = Arc::new(tokio::sync::Mutex::new(subscribe_tx));
 
-    // Get copy trading configuration from environment
-    let copy_trading_target_address = std::env::var("COPY_TRADING_TARGET_ADDRESS").ok();
-    let is_multi_copy_trading = std::env::var("IS_MULTI_COPY_TRADING")
-        .ok()
-        .and_then(|v| v.parse::<bool>().ok())
-        .unwrap_or(false);
+    // Get copy trading configuration from the live parameters
+    let copy_trading_target_addresses = params().copy_targets.clone();
+    let is_multi_copy_trading = copy_trading_target_addresses.len() > 1;
     
     // Prepare program IDs for monitoring
-    let mut program_ids = vec![PUMP_PROGRAM.to_string()];
-    let mut copy_trading_target_addresses = Vec::new();
-    
-    // Handle multiple copy trading targets if enabled
-    if is_multi_copy_trading {
-        if let Some(address_str) = copy_trading_target_address {
-            // Parse comma-separated addresses
-            for addr in address_str.split(',') {
-                let trimmed_addr = addr.trim();
-                if !trimmed_addr.is_empty() {
-                    program_ids.push(trimmed_addr.to_string());
-                    copy_trading_target_addresses.push(trimmed_addr.to_string());
-                }
-            }
-        }
-    } else if let Some(address) = copy_trading_target_address {
-        // Single address mode
-        if !address.is_empty() {
-            program_ids.push(address.clone());
-            copy_trading_target_addresses.push(address);
-        }
-    }
+    let mut program_ids = vec![PUMP_PROGRAM.to_string(), RAYDIUM_LAUNCHPAD_PROGRAM.to_string()];
+    program_ids.extend(copy_trading_target_addresses.iter().cloned());
 
     let filter_config = FilterConfig {
         program_ids: program_ids.clone(),
@@ -1424,49 +1095,26 @@ pub async fn new_token_trader_pumpfun(
     subscribe_tx
         .lock()
         .await
-        .send(SubscribeRequest {
-            slots: HashMap::new(),
-            accounts: HashMap::new(),
-            transactions: hashmap! {
-                "All".to_owned() => SubscribeRequestFilterTransactions {
-                    vote: None,
-                    failed: Some(false),
-                    signature: None,
-                    account_include: program_ids,
-                    account_exclude: vec![JUPITER_PROGRAM.to_string(), OKX_DEX_PROGRAM.to_string()],
-                    account_required: Vec::<String>::new()
-                }
-            },
-            transactions_status: HashMap::new(),
-            entry: HashMap::new(),
-            blocks: HashMap::new(),
-            blocks_meta: HashMap::new(),
-            commitment: Some(CommitmentLevel::Processed as i32),
-            accounts_data_slice: vec![],
-            ping: None,
-            from_slot: None,
-        })
+        .send(build_subscribe_request(program_ids))
         .await
         .map_err(|e| format!("Failed to send subscribe request: {}", e))?;
 
-    let existing_liquidity_pools = Arc::new(Mutex::new(HashSet::<LiquidityPool>::new()));
+    spawn_resubscribe_on_reload(
+        subscribe_tx.clone(),
+        vec![PUMP_PROGRAM.to_string(), RAYDIUM_LAUNCHPAD_PROGRAM.to_string()],
+        logger.clone(),
+    );
 
-    let rpc_nonblocking_client = app_state.clone().rpc_nonblocking_client.clone();
-    let rpc_client = app_state.clone().rpc_client.clone();
-    let wallet = app_state.clone().wallet.clone();
-    let swapx = Pump::new(
-        rpc_nonblocking_client.clone(),
-        rpc_client.clone(),
-        wallet.clone(),
+    let positions = Arc::new(PositionManager::new());
+    restore_positions(&app_state, &positions, &logger).await;
+    spawn_migration_watcher(
+        yellowstone_grpc_http.to_string(),
+        yellowstone_grpc_token.to_string(),
+        Arc::clone(&app_state),
+        Arc::clone(&positions),
     );
 
     logger.log("[STARTED. MONITORING]...".blue().bold().to_string());
-    
-    // Set buying enabled to true at start
-    {
-        let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-        *buying_enabled = true;
-    }
 
     // After all setup and before the main loop, add a heartbeat ping task
     let subscribe_tx_clone = subscribe_tx.clone();
@@ -1486,188 +1134,13 @@ pub async fn new_token_trader_pumpfun(
         }
     });
 
-    // Start a background task to check the status of tokens periodically
-    let existing_liquidity_pools_clone = Arc::clone(&existing_liquidity_pools);
-    let logger_clone = logger.clone();
-    let app_state_for_background = Arc::clone(&app_state);
-    let swap_config_for_background = Arc::clone(&swap_config);
-    
-    tokio::spawn(async move {
-        let pools_clone = Arc::clone(&existing_liquidity_pools_clone);
-        let check_logger = logger_clone.clone();
-        let app_state_clone = Arc::clone(&app_state_for_background);
-        let swap_config_clone = Arc::clone(&swap_config_for_background);
-        
-        loop {
-            tokio::time::sleep(Duration::from_secs(5)).await;
-            
-            // Check if there are any bought tokens and if any have exceeded MAX_WAIT_TIME
-            let now = Instant::now();
-            let max_wait_time_millis = *MAX_WAIT_TIME.lock().unwrap();
-            let max_wait_duration = Duration::from_millis(max_wait_time_millis);
-            
-            let (has_bought_tokens, tokens_to_sell) = {
-                let pools = pools_clone.lock().unwrap();
-                let bought_tokens: Vec<String> = pools.iter()
-                    .filter(|pool| pool.status == Status::Bought)
-                    .map(|pool| pool.mint.clone())
-                    .collect();
-                
-                let timed_out_tokens: Vec<(String, Instant)> = pools.iter()
-                    .filter(|pool| pool.status == Status::Bought && 
-                           pool.timestamp.map_or(false, |ts| now.duration_since(ts) > max_wait_duration))
-                    .map(|pool| (pool.mint.clone(), pool.timestamp.unwrap()))
-                    .collect();
-                
-                // Log bought tokens that are waiting to be sold
-                if !bought_tokens.is_empty() {
-                    check_logger.log(format!(
-                        "\n\t * [BUYING PAUSED] => Waiting for tokens to be sold: {:?}",
-                        bought_tokens
-                    ).yellow().to_string());
-                }
-                
-                // Log tokens that have timed out and will be force-sold
-                if !timed_out_tokens.is_empty() {
-                    check_logger.log(format!(
-                        "\n\t * [TIMEOUT DETECTED] => Will force-sell tokens that exceeded {} ms wait time: {:?}",
-                        max_wait_time_millis,
-                        timed_out_tokens.iter().map(|(mint, _)| mint).collect::<Vec<_>>()
-                    ).red().bold().to_string());
-                }
-                
-                (bought_tokens.len() > 0, timed_out_tokens)
-            };
-            
-            // Update buying status
-            {
-                let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                *buying_enabled = !has_bought_tokens;
-            }
-            
-            // Force-sell tokens that have exceeded MAX_WAIT_TIME
-            for (mint, timestamp) in tokens_to_sell {
-                // Clone the necessary state for this token
-                let logger_for_selling = check_logger.clone();
-                let pools_clone_for_selling = Arc::clone(&pools_clone);
-                let app_state_for_selling = app_state_clone.clone();
-                let swap_config_for_selling = swap_config_clone.clone();
-                
-                check_logger.log(format!(
-                    "\n\t * [FORCE SELLING] => Token {} exceeded wait time (elapsed: {:?})",
-                    mint, now.duration_since(timestamp)
-                ).red().to_string());
-                
-                tokio::spawn(async move {
-                    // Get the existing pool for this mint
-                    let existing_pool = {
-                        let pools = pools_clone_for_selling.lock().unwrap();
-                        pools.iter()
-                            .find(|pool| pool.mint == mint)
-                            .cloned()
-                            .unwrap_or(LiquidityPool {
-                                mint: mint.clone(),
-                                buy_price: 0_f64,
-                                sell_price: 0_f64,
-                                status: Status::Bought,
-                                timestamp: Some(timestamp),
-                            })
-                    };
-                    
-                    // Set up sell config
-                    let sell_config = SwapConfig {
-                        swap_direction: SwapDirection::Sell,
-                        in_type: SwapInType::Pct,
-                        amount_in: 1_f64,  // Sell 100%
-                        slippage: 100_u64, // Use full slippage
-                        use_jito: swap_config_for_selling.clone().use_jito,
-                    };
-                    
-                    // Create Pump instance for selling
-                    let app_state_for_task = app_state_for_selling.clone();
-                    let rpc_nonblocking_client = app_state_for_task.rpc_nonblocking_client.clone();
-                    let rpc_client = app_state_for_task.rpc_client.clone();
-                    let wallet = app_state_for_task.wallet.clone();
-                    let swapx = Pump::new(rpc_nonblocking_client.clone(), rpc_client.clone(), wallet.clone());
-                    
-                    // Execute the sell operation
-                    let start_time = Instant::now();
-                    match swapx.build_swap_ixn_by_mint(&mint, None, sell_config, start_time).await {
-                        Ok(result) => {
-                            // Send instructions and confirm
-                            let (keypair, instructions, token_price) = (result.0, result.1, result.2);
-                            let recent_blockhash = match rpc_nonblocking_client.get_latest_blockhash().await {
-                                Ok(hash) => hash,
-                                Err(e) => {
-                                    logger_for_selling.log(format!(
-                                        "Error getting blockhash for force-selling {}: {}", mint, e
-                                    ).red().to_string());
-                                    return;
-                                }
-                            };
-                            
-                            match tx::new_signed_and_send_zeroslot(
-                                recent_blockhash,
-                                &keypair,
-                                instructions,
-                                &logger_for_selling,
-                            ).await {
-                                Ok(res) => {
-                                    let sold_pool = LiquidityPool {
-                                        mint: mint.clone(),
-                                        buy_price: existing_pool.buy_price,
-                                        sell_price: token_price,
-                                        status: Status::Sold,
-                                        timestamp: Some(Instant::now()),
-                                    };
-                                    
-                                    // Update pool status to sold
-                                    {
-                                        let mut pools = pools_clone_for_selling.lock().unwrap();
-                                        pools.retain(|pool| pool.mint != mint);
-                                        pools.insert(sold_pool.clone());
-                                    }
-                                    
-                                    logger_for_selling.log(format!(
-                                        "\n\t * [SUCCESSFUL FORCE-SELL] => TX_HASH: (https://solscan.io/tx/{}) \n\t * [POOL] => ({}) \n\t * [SOLD] => {} :: ({:?}).",
-                                        &res[0], mint, Utc::now(), start_time.elapsed()
-                                    ).green().to_string());
-                                    
-                                    // Check if all tokens are sold
-                                    let all_sold = {
-                                        let pools = pools_clone_for_selling.lock().unwrap();
-                                        !pools.iter().any(|pool| pool.status == Status::Bought)
-                                    };
-                                    
-                                    if all_sold {
-                                        // If all tokens are sold, enable buying
-                                        let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                        *buying_enabled = true;
-                                        
-                                        logger_for_selling.log(
-                                            "\n\t * [BUYING ENABLED] => All tokens sold, can buy new tokens now"
-                                            .green()
-                                            .to_string(),
-                                        );
-                                    }
-                                },
-                                Err(e) => {
-                                    logger_for_selling.log(format!(
-                                        "Force-sell failed for {}: {}", mint, e
-                                    ).red().to_string());
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            logger_for_selling.log(format!(
-                                "Error building swap instruction for force-selling {}: {}", mint, e
-                            ).red().to_string());
-                        }
-                    }
-                });
-            }
-        }
-    });
+    // Start a background task that force-sells positions held longer than max_wait_time_ms
+    spawn_force_sell_task(
+        Arc::clone(&positions),
+        Arc::clone(&app_state),
+        swap_config.use_jito,
+        logger.clone(),
+    );
 
     // In new_token_trader_pumpfun after the heartbeat task
     // Add a connection health check task
@@ -1695,6 +1168,15 @@ pub async fn new_token_trader_pumpfun(
         }
     });
 
+    let ctx = CopyTradeContext {
+        app_state: Arc::clone(&app_state),
+        swap_config: Arc::clone(&swap_config),
+        positions: Arc::clone(&positions),
+        logger: logger.clone(),
+        min_dev_buy,
+        max_dev_buy,
+    };
+
     while let Some(message) = stream.next().await {
         match message {
             Ok(msg) => {
@@ -1713,9 +1195,8 @@ pub async fn new_token_trader_pumpfun(
                         .and_then(|txn1| txn1.meta)
                         .map(|meta| meta.log_messages)
                     {
-                        let mut mint_flag = false;
-                        let trade_info = match TradeInfoFromToken::from_json(txn.clone(), log_messages.clone()) {
-                            Ok(info) => info,
+                        let trades = match TradeInfoFromToken::from_json(txn.clone(), log_messages.clone()) {
+                            Ok(trades) => trades,
                             Err(e) => {
                                 logger.log(
                                     format!("Error in parsing txn: {}", e)
@@ -1727,315 +1208,598 @@ pub async fn new_token_trader_pumpfun(
                             }
                         };
 
-                        // Check if this is a buy transaction (PumpBuy or PumpSwapBuy)
-                        let is_buy_transaction = matches!(trade_info.instruction_type, 
-                            InstructionType::PumpBuy | InstructionType::PumpSwapBuy);
-                        
-                        // Process copy trading for buy transactions
-                        if is_buy_transaction {
-                            // Check if this transaction is from one of our copy trading addresses
-                            let is_copy_trading_tx = filter_config.copy_trading_target_addresses.iter()
-                                .any(|addr| trade_info.target == *addr);
-                            
-                            if is_copy_trading_tx {
-                                logger.log(format!(
-                                    "\n\t * [COPY TRADING BUY DETECTED] => (https://solscan.io/tx/{}) - SLOT:({}) \n\t * [TARGET] => ({}) \n\t * [TOKEN] => ({}) \n\t * [BUY AMOUNT] => ({}) SOL \n\t * [TIMESTAMP] => {} :: ({:?}).",
-                                    trade_info.signature,
-                                    trade_info.slot,
-                                    trade_info.target,
-                                    trade_info.mint,
-                                    lamports_to_sol(trade_info.volume_change.abs() as u64),
-                                    Utc::now(),
-                                    start_time.elapsed(),
-                                ).blue().to_string());
-
-                                // Check if buying is enabled
-                                let buying_enabled = {
-                                    let enabled = BUYING_ENABLED.lock().unwrap();
-                                    *enabled
-                                };
-                                
-                                if !buying_enabled {
-                                    logger.log(format!(
-                                        "\n\t * [SKIPPING BUY] => Waiting for all tokens to be sold first"
-                                    ).yellow().to_string());
-                                    continue;
-                                }
+                        // Each trade is evaluated on its own; a sell mirrors once per target and mint
+                        let mut mirrored = HashSet::new();
+                        for trade_info in trades {
+                            // Check if this is a buy transaction (PumpBuy, PumpSwapBuy or RaydiumLaunchpadBuy)
+                            let is_buy_transaction = matches!(trade_info.instruction_type, 
+                                InstructionType::PumpBuy | InstructionType::PumpSwapBuy | InstructionType::RaydiumLaunchpadBuy
+                                | InstructionType::MeteoraDbcBuy | InstructionType::MeteoraDlmmBuy);
+
+                            // Process copy trading for buy transactions
+                            if is_buy_transaction {
+                                handle_trade(trade_info, start_time, &ctx).await;
+                            } else if matches!(trade_info.instruction_type,
+                                InstructionType::PumpSell | InstructionType::PumpSwapSell | InstructionType::RaydiumLaunchpadSell
+                                | InstructionType::MeteoraDbcSell | InstructionType::MeteoraDlmmSell)
+                                && mirrored.insert((trade_info.target.clone(), trade_info.mint.clone())) {
+                                mirror_target_sell(
+                                    &txn,
+                                    &trade_info,
+                                    &positions,
+                                    &app_state,
+                                    swap_config.use_jito,
+                                    &logger,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+            Err(error) => {
+                logger.log(
+                    format!("Yellowstone gRpc Error: {:?}", error)
+                        .red()
+                        .to_string(),
+                );
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+// State shared by every trade a copy-trading loop hands to `handle_trade`.
+struct CopyTradeContext {
+    app_state: Arc<AppState>,
+    swap_config: Arc<SwapConfig>,
+    positions: Arc<PositionManager>,
+    logger: Logger,
+    min_dev_buy: u64,
+    max_dev_buy: u64,
+}
+
+// Copies a single buy parsed from a transaction if it was made by one of the
+// copy targets and its SOL spend is within the dev buy bounds. The buy itself
+// is sized, opened and sent on a spawned task.
+async fn handle_trade(trade_info: TradeInfoFromToken, start_time: Instant, ctx: &CopyTradeContext) {
+    // Check if this transaction is from one of our copy trading addresses
+    if !params().copy_targets.iter().any(|addr| trade_info.target == *addr) {
+        return;
+    }
+
+    ctx.logger.log(format!(
+        "\n\t * [COPY TRADING BUY DETECTED] => (https://solscan.io/tx/{}) - SLOT:({}) \n\t * [TARGET] => ({}) \n\t * [TOKEN] => ({}) \n\t * [BUY AMOUNT] => ({}) SOL \n\t * [TIMESTAMP] => {} :: ({:?}).",
+        trade_info.signature,
+        trade_info.slot,
+        trade_info.target,
+        trade_info.mint,
+        lamports_to_sol(trade_info.volume_change.abs() as u64),
+        Utc::now(),
+        start_time.elapsed(),
+    ).blue().to_string());
+
+    // Check dev buy amount
+    let dev_buy_amount = lamports_to_sol(trade_info.volume_change.abs() as u64);
+    if dev_buy_amount > ctx.max_dev_buy as f64 {
+        ctx.logger.log(format!(
+            "\n\t * [BUY AMOUNT EXCEEDS MAX] => {} > {}",
+            dev_buy_amount, ctx.max_dev_buy
+        ).yellow().to_string());
+        return;
+    }
+    if dev_buy_amount < ctx.min_dev_buy as f64 {
+        ctx.logger.log(format!(
+            "\n\t * [BUY AMOUNT BELOW MIN] => {} < {}",
+            dev_buy_amount, ctx.min_dev_buy
+        ).yellow().to_string());
+        return;
+    }
+
+    // Cheap early check; the position manager re-checks atomically when opening
+    if ctx.positions.is_active(&trade_info.mint) {
+        ctx.logger.log(format!(
+            "\n\t * [DUPLICATE TOKEN] => Position already open: {}",
+            trade_info.mint
+        ).yellow().to_string());
+        return;
+    }
+
+    // Clone the shared variables for this task
+    let logger_clone = ctx.logger.clone();
+    let mut swap_config_clone = (*ctx.swap_config).clone();
+    let current_params = params();
+    swap_config_clone.slippage = current_params.slippage;
+    let app_state_clone = Arc::clone(&ctx.app_state);
+
+    let mint_str = trade_info.mint.clone();
+    let strategy_name = current_params.strategies.profile_name_for(
+        Some(&trade_info.target),
+        launch_source(trade_info.instruction_type),
+    );
+
+    // Get bonding curve information for PumpBuy transactions
+    let bonding_curve_info = if matches!(trade_info.instruction_type, InstructionType::PumpBuy) {
+        // For PumpBuy, we need to get the bonding curve account
+        let rpc_client = app_state_clone.rpc_client.clone();
+        let mint_pubkey = Pubkey::from_str(&mint_str).unwrap_or_default();
+        let pump_program = Pubkey::from_str(PUMP_PROGRAM).unwrap_or_default();
+
+        match tokio::task::block_in_place(|| {
+            futures::executor::block_on(async {
+                get_bonding_curve_account(rpc_client, mint_pubkey, pump_program).await
+            })
+        }) {
+            Ok((bonding_curve, _, reserves)) => {
+                Some(BondingCurveInfo {
+                    bonding_curve,
+                    new_virtual_sol_reserve: reserves.virtual_sol_reserves,
+                    new_virtual_token_reserve: reserves.virtual_token_reserves,
+                })
+            },
+            Err(e) => {
+                ctx.logger.log(format!(
+                    "\n\t * [BONDING CURVE ERROR] => Failed to get bonding curve for {}: {}",
+                    mint_str, e
+                ).red().to_string());
+                trade_info.bonding_curve_info.clone()
+            }
+        }
+    } else {
+        trade_info.bonding_curve_info.clone()
+    };
+
+    let positions_clone = Arc::clone(&ctx.positions);
+    let recent_blockhash = trade_info.clone().recent_blockhash;
+
+    // Size the buy from the target's SOL spend using its sizing mode
+    let target_spend = lamports_to_sol(trade_info.volume_change.abs() as u64);
+    let sizing = current_params.sizing_for(&trade_info.target).clone();
+    let target = trade_info.target.clone();
+
+    let task = tokio::spawn(async move {
+        let wallet_balance = if sizing.needs_wallet_balance() {
+            match app_state_clone
+                .rpc_nonblocking_client
+                .get_balance(&app_state_clone.wallet.pubkey())
+                .await
+            {
+                Ok(lamports) => Some(lamports_to_sol(lamports)),
+                Err(e) => {
+                    logger_clone.log(format!(
+                        "\n\t * [SIZING] => Failed to get wallet balance, wallet cap not applied: {}",
+                        e
+                    ).yellow().to_string());
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        swap_config_clone.amount_in = sizing.amount_in(target_spend, wallet_balance);
+        if swap_config_clone.amount_in <= 0.0 {
+            logger_clone.log(format!(
+                "\n\t * [SKIPPING BUY] => Target spend of {} SOL is below every sizing tier",
+                target_spend
+            ).yellow().to_string());
+            return;
+        }
+        if let Err(reason) = positions_clone.try_open(
+            &mint_str,
+            swap_config_clone.amount_in,
+            Some(&target),
+            params().position_limits,
+        ) {
+            logger_clone.log(format!(
+                "\n\t * [SKIPPING BUY] => {}: {}",
+                mint_str, reason
+            ).yellow().to_string());
+            return;
+        }
+        logger_clone.log(format!(
+            "\n\t * [SIZING] => Target spent {} SOL, buying {} SOL",
+            target_spend, swap_config_clone.amount_in
+        ).green().to_string());
+
+        match build_swap_ixn(
+            &app_state_clone,
+            &mint_str,
+            SwapProtocol::Auto,
+            bonding_curve_info,
+            swap_config_clone.clone(),
+            start_time,
+            &logger_clone,
+        )
+        .await
+        {
+            Ok(result) => {
+                let (keypair, instructions, token_price, route) =
+                    (result.0, result.1, result.2, result.3);
+
+                match tx::new_signed_and_send_zeroslot(
+                    recent_blockhash,
+                    &keypair,
+                    instructions,
+                    &logger_clone,
+                ).await {
+                    Ok(res) => {
+                        let tracking_info = TokenTrackingInfo::new(strategy_name.clone(), 0.0);
+                        positions_clone.mark_bought(&mint_str, token_price, &tracking_info.to_record());
+                        positions_clone.set_route(&mint_str, route.protocol(), route.pool_id().map(|pool| pool.to_string()));
+                        spawn_record_fill(
+                            Arc::clone(&app_state_clone),
+                            Arc::clone(&positions_clone),
+                            res[0].clone(),
+                            mint_str.clone(),
+                            FillSide::Buy,
+                            None,
+                            logger_clone.clone(),
+                        );
+                        TOKEN_TRACKING.lock().unwrap().insert(mint_str.clone(), tracking_info);
+
+                        logger_clone.log(format!(
+                            "\n\t * [SUCCESSFUL-COPY-BUY] => TX_HASH: (https://solscan.io/tx/{}) \n\t * [TOKEN] => ({}) \n\t * [DONE] => {} :: ({:?}) \n\t * [OPEN POSITIONS] => {} ({:.4} SOL)",
+                            &res[0], mint_str, Utc::now(), start_time.elapsed(),
+                            positions_clone.active_count(), positions_clone.exposure()
+                        ).green().to_string());
+                    },
+                    Err(e) => {
+                        logger_clone.log(
+                            format!("Failed to copy buy for {}: {}", mint_str.clone(), e)
+                                .red()
+                                .italic()
+                                .to_string(),
+                        );
+
+                        positions_clone.mark_failed(&mint_str);
+                    }
+                }
+            },
+            Err(error) => {
+                logger_clone.log(
+                    format!("Error building swap instruction: {}", error)
+                        .red()
+                        .italic()
+                        .to_string(),
+                );
+
+                positions_clone.mark_failed(&mint_str);
+            }
+        }
+    });
+    drop(task);
+}
+
+// Fraction of its `mint` balance that `target` sold in this transaction,
+// taken from the raw pre/post token balances in the transaction meta.
+fn target_sell_fraction(txn: &SubscribeUpdateTransaction, target: &str, mint: &str) -> Option<f64> {
+    fn balance_of(balances: &[TokenBalance], target: &str, mint: &str) -> u128 {
+        balances
+            .iter()
+            .filter(|balance| balance.owner == target && balance.mint == mint)
+            .filter_map(|balance| balance.ui_token_amount.as_ref())
+            .filter_map(|amount| amount.amount.parse::<u128>().ok())
+            .sum()
+    }
+
+    let meta = txn.transaction.as_ref()?.meta.as_ref()?;
+    let pre = balance_of(&meta.pre_token_balances, target, mint);
+    let post = balance_of(&meta.post_token_balances, target, mint);
+    if pre == 0 || post >= pre {
+        return None;
+    }
+    Some((pre - post) as f64 / pre as f64)
+}
+
+// Sells part of our position when a copy target sells a mint we hold,
+// according to the target's mirror-exit mode.
+fn mirror_target_sell(
+    txn: &SubscribeUpdateTransaction,
+    trade_info: &TradeInfoFromToken,
+    positions: &Arc<PositionManager>,
+    app_state: &Arc<AppState>,
+    use_jito: bool,
+    logger: &Logger,
+) {
+    let current_params = params();
+    if !current_params.copy_targets.iter().any(|addr| *addr == trade_info.target) {
+        return;
+    }
+    let mode = current_params.mirror_exit_for(&trade_info.target);
+    if mode == MirrorExitMode::Off {
+        return;
+    }
+    let is_held = positions
+        .get(&trade_info.mint)
+        .map_or(false, |position| position.status == Status::Bought);
+    if !is_held {
+        return;
+    }
+
+    let sell_percentage = match mode {
+        MirrorExitMode::Off => return,
+        MirrorExitMode::Full => 100,
+        MirrorExitMode::Proportional => {
+            match target_sell_fraction(txn, &trade_info.target, &trade_info.mint) {
+                Some(fraction) => ((fraction * 100.0).round() as u64).clamp(1, 100),
+                None => {
+                    logger.log(format!(
+                        "\n\t * [MIRROR EXIT SKIPPED] => Could not work out how much {} sold of {}",
+                        trade_info.target, trade_info.mint
+                    ).yellow().to_string());
+                    return;
+                }
+            }
+        }
+    };
+
+    if !positions.begin_sell(&trade_info.mint) {
+        return;
+    }
+    logger.log(format!(
+        "\n\t * [MIRROR EXIT] => (https://solscan.io/tx/{}) \n\t * [TARGET] => ({}) \n\t * [TOKEN] => ({}) \n\t * [SELLING] => {}%",
+        trade_info.signature, trade_info.target, trade_info.mint, sell_percentage
+    ).yellow().bold().to_string());
+
+    tokio::spawn(sell_position(
+        Arc::clone(app_state),
+        Arc::clone(positions),
+        trade_info.mint.clone(),
+        sell_percentage,
+        SellReason::MirrorExit,
+        use_jito,
+        logger.clone(),
+    ));
+}
+
+// Sells `sell_percentage` of our holding of `mint`. The caller must already have
+// claimed the position with `PositionManager::begin_sell`.
+async fn sell_position(
+    app_state: Arc<AppState>,
+    positions: Arc<PositionManager>,
+    mint: String,
+    sell_percentage: u64,
+    trigger: SellReason,
+    use_jito: bool,
+    logger: Logger,
+) -> bool {
+    let sell_config = SwapConfig {
+        swap_direction: SwapDirection::Sell,
+        in_type: SwapInType::Pct,
+        amount_in: sell_percentage as f64 / 100.0,
+        slippage: params().slippage,
+        use_jito,
+    };
+
+    let start_time = Instant::now();
+    let protocol = positions.get(&mint).map(|position| position.swap_protocol()).unwrap_or_default();
+    let (keypair, instructions, token_price) =
+        match build_swap_ixn(&app_state, &mint, protocol, None, sell_config, start_time, &logger).await {
+            Ok(result) => {
+                let route = result.3;
+                if positions.set_route(&mint, route.protocol(), route.pool_id().map(|pool| pool.to_string())) {
+                    logger.log(format!("[ROUTE] => {} now trades on {:?}", mint, route.protocol()).yellow().to_string());
+                }
+                (result.0, result.1, result.2)
+            }
+            Err(e) => {
+                logger.log(format!(
+                    "Error building swap instruction for selling {}: {}", mint, e
+                ).red().to_string());
+                positions.sell_failed(&mint);
+                return false;
+            }
+        };
+    let recent_blockhash = match app_state.rpc_nonblocking_client.get_latest_blockhash().await {
+        Ok(hash) => hash,
+        Err(e) => {
+            logger.log(format!(
+                "Error getting blockhash for selling {}: {}", mint, e
+            ).red().to_string());
+            positions.sell_failed(&mint);
+            return false;
+        }
+    };
 
-                                // Check dev buy amount
-                                let dev_buy_amount = lamports_to_sol(trade_info.volume_change.abs() as u64);
-                                if dev_buy_amount > max_dev_buy as f64 {
-                                    logger.log(format!(
-                                        "\n\t * [BUY AMOUNT EXCEEDS MAX] => {} > {}",
-                                        dev_buy_amount, max_dev_buy
-                                    ).yellow().to_string());
-                                    continue;
-                                }
-                                if dev_buy_amount < min_dev_buy as f64 {
-                                    logger.log(format!(
-                                        "\n\t * [BUY AMOUNT BELOW MIN] => {} < {}",
-                                        dev_buy_amount, min_dev_buy
-                                    ).yellow().to_string());
-                                    continue;
-                                }
+    match tx::new_signed_and_send_zeroslot(recent_blockhash, &keypair, instructions, &logger).await {
+        Ok(res) => {
+            positions.finish_sell(&mint, token_price, sell_percentage as f64 / 100.0);
+            spawn_record_fill(
+                Arc::clone(&app_state),
+                Arc::clone(&positions),
+                res[0].clone(),
+                mint.clone(),
+                FillSide::Sell,
+                Some(trigger),
+                logger.clone(),
+            );
+            if sell_percentage >= 100 {
+                TOKEN_TRACKING.lock().unwrap().remove(&mint);
+            } else {
+                if let Some(info) = TOKEN_TRACKING.lock().unwrap().get_mut(&mint) {
+                    info.last_sell_time = Instant::now();
+                    info.exit_reason = Some(trigger);
+                }
+                persist_tracking(&mint, &logger);
+            }
 
-                                // Check if this token is already in our pools
-                                let is_duplicate = {
-                                    let pools = existing_liquidity_pools.lock().unwrap();
-                                    pools.iter().any(|pool| pool.mint == trade_info.mint)
-                                };
-                                
-                                if is_duplicate {
-                                    logger.log(format!(
-                                        "\n\t * [DUPLICATE TOKEN] => Token already in our pools: {}",
-                                        trade_info.mint
-                                    ).yellow().to_string());
-                                    continue;
-                                }
+            logger.log(format!(
+                "\n[{} SELL] => TX_HASH: (https://solscan.io/tx/{}) \n\t * [POOL] => ({}) \n\t * [TRIGGER] => {} \n\t * [SOLD {}%] => {} :: ({:?}).",
+                if sell_percentage >= 100 { "COMPLETE" } else { "PARTIAL" },
+                &res[0], mint, trigger, sell_percentage, Local::now(), start_time.elapsed()
+            ).green().bold().to_string());
+            true
+        }
+        Err(e) => {
+            logger.log(format!(
+                "Sell failed for {}: {}", mint, e
+            ).red().to_string());
+            positions.sell_failed(&mint);
+            false
+        }
+    }
+}
 
-                                // Temporarily disable buying while we're processing this buy
-                                {
-                                    let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                    *buying_enabled = false;
-                                }
+// Writes the tracking state of `mint` to the position store.
+fn persist_tracking(mint: &str, logger: &Logger) {
+    let Some(store) = store() else {
+        return;
+    };
+    let record = TOKEN_TRACKING.lock().unwrap().get(mint).map(|info| info.to_record());
+    let result = match record {
+        Some(record) => store.save_tracking(mint, &record),
+        None => store.remove_tracking(mint),
+    };
+    if let Err(e) = result {
+        logger.log(format!("Failed to save tracking state of {}: {}", mint, e).red().to_string());
+    }
+}
 
-                                // Clone the shared variables for this task
-                                let swapx_clone = swapx.clone();
-                                let logger_clone = logger.clone();
-                                let mut swap_config_clone = (*Arc::clone(&swap_config)).clone();
-                                let app_state_clone = Arc::clone(&app_state).clone();
-                                let yellowstone_grpc_http_clone = yellowstone_grpc_http.clone();
-                                let yellowstone_grpc_token_clone = yellowstone_grpc_token.clone();
-                                
-                                let mint_str = trade_info.mint.clone();
-                                
-                                // Get bonding curve information for PumpBuy transactions
-                                let bonding_curve_info = if matches!(trade_info.instruction_type, InstructionType::PumpBuy) {
-                                    // For PumpBuy, we need to get the bonding curve account
-                                    let rpc_client = app_state_clone.rpc_client.clone();
-                                    let mint_pubkey = Pubkey::from_str(&mint_str).unwrap_or_default();
-                                    let pump_program = Pubkey::from_str(PUMP_PROGRAM).unwrap_or_default();
-                                    
-                                    match tokio::task::block_in_place(|| {
-                                        futures::executor::block_on(async {
-                                            get_bonding_curve_account(rpc_client, mint_pubkey, pump_program).await
-                                        })
-                                    }) {
-                                        Ok((bonding_curve, _, reserves)) => {
-                                            Some(BondingCurveInfo {
-                                                bonding_curve,
-                                                new_virtual_sol_reserve: reserves.virtual_sol_reserves,
-                                                new_virtual_token_reserve: reserves.virtual_token_reserves,
-                                            })
-                                        },
-                                        Err(e) => {
-                                            logger.log(format!(
-                                                "\n\t * [BONDING CURVE ERROR] => Failed to get bonding curve for {}: {}",
-                                                mint_str, e
-                                            ).red().to_string());
-                                            trade_info.bonding_curve_info.clone()
-                                        }
-                                    }
-                                } else {
-                                    trade_info.bonding_curve_info.clone()
-                                };
-                                
-                                let existing_liquidity_pools_clone = Arc::clone(&existing_liquidity_pools);
-                                let recent_blockhash = trade_info.clone().recent_blockhash;
-                                
-                                // Determine trading amount based on comparing SOL amount and TOKEN_AMOUNT
-                                let sol_amount = lamports_to_sol(trade_info.volume_change.abs() as u64);
-                                let token_amount = trade_info.token_amount;
-                                
-                                // If token amount is smaller than SOL amount, use token amount for trading
-                                if token_amount > 0.0 && token_amount < sol_amount {
-                                    // Modify swap_config to use the detected token amount
-                                    swap_config_clone.amount_in = token_amount;
-                                    logger.log(format!(
-                                        "\n\t * [USING TOKEN AMOUNT] => {}, SOL Amount: {}",
-                                        token_amount, sol_amount
-                                    ).green().to_string());
-                                }
-    
-                                let task = tokio::spawn(async move {
-                                    match swapx_clone
-                                        .build_swap_ixn_by_mint(
-                                            &mint_str,
-                                            bonding_curve_info,
-                                            swap_config_clone.clone(),
-                                            start_time,
-                                        )
-                                        .await
-                                    {
-                                        Ok(result) => {
-                                            let (keypair, instructions, token_price) =
-                                                (result.0, result.1, result.2);
-                                            
-                                            match tx::new_signed_and_send_zeroslot(
-                                                recent_blockhash,
-                                                &keypair,
-                                                instructions,
-                                                &logger_clone,
-                                            ).await {
-                                                Ok(res) => {
-                                                    let bought_pool = LiquidityPool {
-                                                        mint: mint_str.clone(),
-                                                        buy_price: token_price,
-                                                        sell_price: 0_f64,
-                                                        status: Status::Bought,
-                                                        timestamp: Some(Instant::now()),
-                                                    };
-                                                    
-                                                    // Create a local copy before modifying
-                                                    {
-                                                        let mut existing_pools =
-                                                            existing_liquidity_pools_clone.lock().unwrap();
-                                                        existing_pools.retain(|pool| pool.mint != mint_str);
-                                                        existing_pools.insert(bought_pool.clone());
-                                                        
-                                                        // Log after modification within the lock scope
-                                                        logger_clone.log(format!(
-                                                            "\n\t * [SUCCESSFUL-COPY-BUY] => TX_HASH: (https://solscan.io/tx/{}) \n\t * [TOKEN] => ({}) \n\t * [DONE] => {} :: ({:?}) \n\t * [TOTAL TOKENS] => {}",
-                                                            &res[0], mint_str, Utc::now(), start_time.elapsed(), existing_pools.len()
-                                                        ).green().to_string());
-                                                    }
-                                                },
-                                                Err(e) => {
-                                                    logger_clone.log(
-                                                        format!("Failed to copy buy for {}: {}", mint_str.clone(), e)
-                                                            .red()
-                                                            .italic()
-                                                            .to_string(),
-                                                    );
-                                                    
-                                                    // Re-enable buying since this one failed
-                                                    let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                                    *buying_enabled = true;
-                                                    
-                                                    let failed_pool = LiquidityPool {
-                                                        mint: mint_str.clone(),
-                                                        buy_price: 0_f64,
-                                                        sell_price: 0_f64,
-                                                        status: Status::Failure,
-                                                        timestamp: None,
-                                                    };
-                                                    
-                                                    // Use a local scope for the mutex lock
-                                                    {
-                                                        let mut update_pools =
-                                                            existing_liquidity_pools_clone.lock().unwrap();
-                                                        update_pools.retain(|pool| pool.mint != mint_str);
-                                                        update_pools.insert(failed_pool.clone());
-                                                    }
-                                                }
-                                            }
-                                        },
-                                        Err(error) => {
-                                            logger_clone.log(
-                                                format!("Error building swap instruction: {}", error)
-                                                    .red()
-                                                    .italic()
-                                                    .to_string(),
-                                            );
-                                            
-                                            // Re-enable buying since this one failed
-                                            let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                            *buying_enabled = true;
-                                            
-                                            let failed_pool = LiquidityPool {
-                                                mint: mint_str.clone(),
-                                                buy_price: 0_f64,
-                                                sell_price: 0_f64,
-                                                status: Status::Failure,
-                                                timestamp: None,
-                                            };
-                                            
-                                            // Use a local scope for the mutex lock
-                                            {
-                                                let mut update_pools =
-                                                    existing_liquidity_pools_clone.lock().unwrap();
-                                                update_pools.retain(|pool| pool.mint != mint_str);
-                                                update_pools.insert(failed_pool.clone());
-                                            }
-                                        }
-                                    }
-                                });
-                                drop(task);
-                            }
-                        }
+// Opens the position store and reloads the positions it holds. Each open
+// position is checked against the wallet's token balance: if the tokens are
+// gone it is closed, otherwise it is restored with its tracking state.
+async fn restore_positions(app_state: &Arc<AppState>, positions: &PositionManager, logger: &Logger) {
+    let store = match init_store(&settings().store.path) {
+        Ok(store) => store,
+        Err(e) => {
+            logger.log(format!(
+                "[STORE] => Failed to open {}: {}. Positions will not be persisted.",
+                settings().store.path, e
+            ).red().to_string());
+            return;
+        }
+    };
+    let (records, mut tracking) = match store.positions().and_then(|p| Ok((p, store.tracking()?))) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            logger.log(format!("[STORE] => Failed to load positions: {}", e).red().to_string());
+            return;
+        }
+    };
+
+    let owner = app_state.wallet.pubkey();
+    let mut restored = 0;
+    for record in records {
+        if !matches!(record.status, Status::Buying | Status::Bought | Status::Selling) {
+            continue;
+        }
+        let mut position = record.to_position();
+        let held = match Pubkey::from_str(&record.mint) {
+            Ok(mint) => {
+                let account = match token::get_associated_token_address_for_mint(
+                    app_state.rpc_nonblocking_client.clone(),
+                    &mint,
+                    &owner,
+                )
+                .await
+                {
+                    Ok(ata) => token::get_account_info(app_state.rpc_nonblocking_client.clone(), mint, ata).await,
+                    Err(e) => Err(e),
+                };
+                match account {
+                    Ok(account) => account.base.amount > 0,
+                    Err(TokenError::AccountNotFound) => false,
+                    Err(e) => {
+                        // Keep managing the position rather than forgetting tokens we may hold
+                        logger.log(format!(
+                            "[STORE] => Could not check balance of {}: {}. Restoring it anyway.",
+                            record.mint, e
+                        ).yellow().to_string());
+                        true
                     }
                 }
             }
-            Err(error) => {
-                logger.log(
-                    format!("Yellowstone gRpc Error: {:?}", error)
-                        .red()
-                        .to_string(),
-                );
-                break;
+            Err(_) => false,
+        };
+
+        // Load it first so closing goes through the normal transitions and updates the store
+        positions.restore(position.clone());
+        if !held {
+            positions.mark_closed(&record.mint);
+            if let Err(e) = store.remove_tracking(&record.mint) {
+                logger.log(format!("[STORE] => Failed to drop tracking of {}: {}", record.mint, e).red().to_string());
             }
+            logger.log(format!(
+                "[STORE] => {} is no longer in the wallet, closing position",
+                record.mint
+            ).yellow().to_string());
+            continue;
         }
+
+        // A buy or sell interrupted by the restart left tokens in the wallet, so treat it as held
+        if position.status != Status::Bought || position.opened_at.is_none() {
+            position.status = Status::Bought;
+            position.opened_at.get_or_insert_with(Instant::now);
+            if let Err(e) = store.save_position(&PositionRecord::from(&position)) {
+                logger.log(format!("[STORE] => Failed to save position {}: {}", record.mint, e).red().to_string());
+            }
+        }
+        positions.restore(position);
+        let info = tracking
+            .remove(&record.mint)
+            .map(TokenTrackingInfo::from_record)
+            .unwrap_or_else(|| TokenTrackingInfo::new(params().strategies.default_profile.clone(), 0.0));
+        TOKEN_TRACKING.lock().unwrap().insert(record.mint.clone(), info);
+        persist_tracking(&record.mint, logger);
+        restored += 1;
     }
-    Ok(())
-}// Function to get the appropriate retracement levels based on PNL and time elapsed
-fn get_retracement_levels(current_pnl: f64, time_elapsed_seconds: u64) -> Vec<RetracementLevel> {
-    // For high PNL tokens with shorter holding time, use more aggressive retracement triggers
-    if time_elapsed_seconds <= 30 {
-        vec![
-            RetracementLevel { percentage: 3, threshold: 2000, sell_amount: 100 },  // 2000%+ PNL
-            RetracementLevel { percentage: 4, threshold: 1500, sell_amount: 50 },   // 1500%+ PNL
-            RetracementLevel { percentage: 5, threshold: 1000, sell_amount: 40 },   // 1000%+ PNL
-            RetracementLevel { percentage: 6, threshold: 800, sell_amount: 35 },    // 800%+ PNL
-            RetracementLevel { percentage: 6, threshold: 700, sell_amount: 35 },    // 700%+ PNL
-            RetracementLevel { percentage: 6, threshold: 600, sell_amount: 30 },    // 600%+ PNL
-            RetracementLevel { percentage: 7, threshold: 500, sell_amount: 30 },    // 500%+ PNL
-            RetracementLevel { percentage: 7, threshold: 400, sell_amount: 30 },    // 400%+ PNL
-            RetracementLevel { percentage: 8, threshold: 300, sell_amount: 20 },    // 300%+ PNL
-            RetracementLevel { percentage: 10, threshold: 200, sell_amount: 15 },   // 200%+ PNL
-            RetracementLevel { percentage: 12, threshold: 100, sell_amount: 15 },   // 100%+ PNL
-            // Lower PNL thresholds with larger retracement triggers
-            RetracementLevel { percentage: 20, threshold: 50, sell_amount: 10 },    // 50%+ PNL
-            RetracementLevel { percentage: 30, threshold: 30, sell_amount: 10 },    // 30%+ PNL
-            RetracementLevel { percentage: 42, threshold: 20, sell_amount: 100 },   // 20%+ PNL
-        ]
-    } else if current_pnl > 500.0 {
-        // For high PNL tokens with longer holding time, use more aggressive retracement triggers
-        vec![
-            RetracementLevel { percentage: 3, threshold: 2000, sell_amount: 100 },  // 2000%+ PNL
-            RetracementLevel { percentage: 4, threshold: 1500, sell_amount: 50 },   // 1500%+ PNL
-            RetracementLevel { percentage: 5, threshold: 1000, sell_amount: 40 },   // 1000%+ PNL
-            RetracementLevel { percentage: 6, threshold: 800, sell_amount: 35 },    // 800%+ PNL
-            RetracementLevel { percentage: 6, threshold: 700, sell_amount: 35 },    // 700%+ PNL
-            RetracementLevel { percentage: 6, threshold: 600, sell_amount: 30 },    // 600%+ PNL
-            RetracementLevel { percentage: 7, threshold: 500, sell_amount: 30 },    // 500%+ PNL
-            RetracementLevel { percentage: 7, threshold: 400, sell_amount: 30 },    // 400%+ PNL
-            RetracementLevel { percentage: 8, threshold: 300, sell_amount: 20 },    // 300%+ PNL
-            RetracementLevel { percentage: 15, threshold: 20, sell_amount: 100 },   // Just 20%+ PNL but significant time passed
-        ]
-    } else if current_pnl > 200.0 {
-        // Medium PNL tokens with longer holding time
-        vec![
-            RetracementLevel { percentage: 3, threshold: 2000, sell_amount: 100 },  // 2000%+ PNL
-            RetracementLevel { percentage: 4, threshold: 1500, sell_amount: 50 },   // 1500%+ PNL
-            RetracementLevel { percentage: 5, threshold: 1000, sell_amount: 40 },   // 1000%+ PNL
-            RetracementLevel { percentage: 6, threshold: 800, sell_amount: 35 },    // 800%+ PNL
-            RetracementLevel { percentage: 6, threshold: 700, sell_amount: 35 },    // 700%+ PNL
-            RetracementLevel { percentage: 8, threshold: 600, sell_amount: 30 },    // 600%+ PNL
-            RetracementLevel { percentage: 10, threshold: 500, sell_amount: 30 },   // 500%+ PNL
-            RetracementLevel { percentage: 10, threshold: 400, sell_amount: 30 },   // 400%+ PNL
-            RetracementLevel { percentage: 10, threshold: 300, sell_amount: 20 },   // 300%+ PNL
-            RetracementLevel { percentage: 10, threshold: 200, sell_amount: 20 },   // 200%+ PNL
-            RetracementLevel { percentage: 20, threshold: 20, sell_amount: 100 },   // Just 20%+ PNL but significant time passed
-        ]
-    } else {
-        // Standard retracement levels for default scenario
-        RETRACEMENT_LEVELS.to_vec()
-    }
+
+    logger.log(format!(
+        "[STORE] => Restored {} open position(s) ({:.4} SOL)",
+        restored,
+        positions.exposure()
+    ).green().to_string());
+}
+
+// Force-sells every position held longer than `max_wait_time_ms`. Each position
+// is claimed on its own, so one slow sell does not hold up the others.
+fn spawn_force_sell_task(
+    positions: Arc<PositionManager>,
+    app_state: Arc<AppState>,
+    use_jito: bool,
+    logger: Logger,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+
+            let max_wait_time_millis = params().max_wait_time_ms;
+            let timed_out = positions.timed_out(Duration::from_millis(max_wait_time_millis));
+            if timed_out.is_empty() {
+                continue;
+            }
+            logger.log(format!(
+                "\n\t * [TIMEOUT DETECTED] => Will force-sell tokens that exceeded {} ms wait time: {:?}",
+                max_wait_time_millis,
+                timed_out.iter().map(|position| &position.mint).collect::<Vec<_>>()
+            ).red().bold().to_string());
+
+            for position in timed_out {
+                if !positions.begin_sell(&position.mint) {
+                    continue;
+                }
+                logger.log(format!(
+                    "\n\t * [FORCE SELLING] => Token {} exceeded wait time (elapsed: {:?})",
+                    position.mint,
+                    position.opened_at.map(|opened_at| opened_at.elapsed())
+                ).red().to_string());
+                tokio::spawn(sell_position(
+                    Arc::clone(&app_state),
+                    Arc::clone(&positions),
+                    position.mint,
+                    100,
+                    SellReason::MaxHoldTime,
+                    use_jito,
+                    logger.clone(),
+                ));
+            }
+        }
+    });
 }
 
 // Function to check if we should execute trailing stop
-fn check_trailing_stop_loss(current_pnl: f64, top_pnl: f64, logger: &Logger) -> bool {
-    if top_pnl > 10.0 && current_pnl < top_pnl * 0.4 {
+fn check_trailing_stop_loss(profile: &StrategyProfile, current_pnl: f64, top_pnl: f64, logger: &Logger) -> bool {
+    if profile.trailing_stop_triggered(current_pnl, top_pnl) {
         logger.log(format!(
             "\n\t * [EXECUTING TRAILING STOP] => Current PNL: {}%, Peak PNL: {}%, Drop: {}%",
             current_pnl, top_pnl, (top_pnl - current_pnl) / top_pnl * 100.0
@@ -2057,6 +1821,8 @@ pub async fn copy_trader_pumpfun(
 ) -> Result<(), String> {
     // Log the copy trading configuration
     let logger = Logger::new("[COPY-TRADER] => ".blue().bold().to_string());
+
+    spawn_config_watcher();
     
     // INITIAL SETTING FOR SUBSCRIBE
     // -----------------------------------------------------------------------------------------------------------------------------
@@ -2101,40 +1867,16 @@ pub async fn copy_trader_pumpfun(
     // Convert to Arc to allow cloning across tasks
     let subscribe_tx = Arc::new(tokio::sync::Mutex::new(subscribe_tx));
 
-    // Get copy trading configuration from environment
-    let copy_trading_target_address = std::env::var("COPY_TRADING_TARGET_ADDRESS").ok();
-    let is_multi_copy_trading = std::env::var("IS_MULTI_COPY_TRADING")
-        .ok()
-        .and_then(|v| v.parse::<bool>().ok())
-        .unwrap_or(false);
+    // Get copy trading configuration from the live parameters
+    let copy_trading_target_addresses = params().copy_targets.clone();
+    let is_multi_copy_trading = copy_trading_target_addresses.len() > 1;
     
     // Prepare target addresses for monitoring
-    let mut program_ids = vec![];
-    let mut copy_trading_target_addresses = Vec::new();
-    
-    // Handle multiple copy trading targets if enabled
-    if is_multi_copy_trading {
-        if let Some(address_str) = copy_trading_target_address {
-            // Parse comma-separated addresses
-            for addr in address_str.split(',') {
-                let trimmed_addr = addr.trim();
-                if !trimmed_addr.is_empty() {
-                    program_ids.push(trimmed_addr.to_string());
-                    copy_trading_target_addresses.push(trimmed_addr.to_string());
-                }
-            }
-        }
-    } else if let Some(address) = copy_trading_target_address {
-        // Single address mode
-        if !address.is_empty() {
-            program_ids.push(address.clone());
-            copy_trading_target_addresses.push(address);
-        }
-    }
+    let program_ids = copy_trading_target_addresses.clone();
 
     // Ensure we have at least one target address
     if copy_trading_target_addresses.is_empty() {
-        return Err("No COPY_TRADING_TARGET_ADDRESS specified. Please set this environment variable.".to_string());
+        return Err("No copy targets specified. Please add at least one [[copy.targets]] entry to the config file.".to_string());
     }
 
     let filter_config = FilterConfig {
@@ -2160,49 +1902,22 @@ pub async fn copy_trader_pumpfun(
     subscribe_tx
         .lock()
         .await
-        .send(SubscribeRequest {
-            slots: HashMap::new(),
-            accounts: HashMap::new(),
-            transactions: hashmap! {
-                "All".to_owned() => SubscribeRequestFilterTransactions {
-                    vote: None,
-                    failed: Some(false),
-                    signature: None,
-                    account_include: program_ids,
-                    account_exclude: vec![JUPITER_PROGRAM.to_string(), OKX_DEX_PROGRAM.to_string()],
-                    account_required: Vec::<String>::new()
-                }
-            },
-            transactions_status: HashMap::new(),
-            entry: HashMap::new(),
-            blocks: HashMap::new(),
-            blocks_meta: HashMap::new(),
-            commitment: Some(CommitmentLevel::Processed as i32),
-            accounts_data_slice: vec![],
-            ping: None,
-            from_slot: None,
-        })
+        .send(build_subscribe_request(program_ids))
         .await
         .map_err(|e| format!("Failed to send subscribe request: {}", e))?;
 
-    let existing_liquidity_pools = Arc::new(Mutex::new(HashSet::<LiquidityPool>::new()));
+    spawn_resubscribe_on_reload(subscribe_tx.clone(), vec![], logger.clone());
 
-    let rpc_nonblocking_client = app_state.clone().rpc_nonblocking_client.clone();
-    let rpc_client = app_state.clone().rpc_client.clone();
-    let wallet = app_state.clone().wallet.clone();
-    let swapx = Pump::new(
-        rpc_nonblocking_client.clone(),
-        rpc_client.clone(),
-        wallet.clone(),
+    let positions = Arc::new(PositionManager::new());
+    restore_positions(&app_state, &positions, &logger).await;
+    spawn_migration_watcher(
+        yellowstone_grpc_http.to_string(),
+        yellowstone_grpc_token.to_string(),
+        Arc::clone(&app_state),
+        Arc::clone(&positions),
     );
 
     logger.log("[STARTED. MONITORING COPY TARGETS]...".blue().bold().to_string());
-    
-    // Set buying enabled to true at start
-    {
-        let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-        *buying_enabled = true;
-    }
 
     // After all setup and before the main loop, add a heartbeat ping task
     let subscribe_tx_clone = subscribe_tx.clone();
@@ -2222,189 +1937,13 @@ pub async fn copy_trader_pumpfun(
         }
     });
 
-    // Start a background task to check the status of tokens periodically
-    let existing_liquidity_pools_clone = Arc::clone(&existing_liquidity_pools);
-    let logger_clone = logger.clone();
-    let app_state_for_background = Arc::clone(&app_state);
-    let swap_config_for_background = Arc::clone(&swap_config);
-    
-    tokio::spawn(async move {
-        let pools_clone = Arc::clone(&existing_liquidity_pools_clone);
-        let check_logger = logger_clone.clone();
-        let app_state_clone = Arc::clone(&app_state_for_background);
-        let swap_config_clone = Arc::clone(&swap_config_for_background);
-        
-        loop {
-            tokio::time::sleep(Duration::from_secs(5)).await;
-            
-            // Check if there are any bought tokens and if any have exceeded MAX_WAIT_TIME
-            let now = Instant::now();
-            let max_wait_time_millis = *MAX_WAIT_TIME.lock().unwrap();
-            let max_wait_duration = Duration::from_millis(max_wait_time_millis);
-            
-            let (has_bought_tokens, tokens_to_sell) = {
-                let pools = pools_clone.lock().unwrap();
-                let bought_tokens: Vec<String> = pools.iter()
-                    .filter(|pool| pool.status == Status::Bought)
-                    .map(|pool| pool.mint.clone())
-                    .collect();
-                
-                let timed_out_tokens: Vec<(String, Instant)> = pools.iter()
-                    .filter(|pool| pool.status == Status::Bought && 
-                           pool.timestamp.map_or(false, |ts| now.duration_since(ts) > max_wait_duration))
-                    .map(|pool| (pool.mint.clone(), pool.timestamp.unwrap()))
-                    .collect();
-                
-                // Log bought tokens that are waiting to be sold
-                if !bought_tokens.is_empty() {
-                    check_logger.log(format!(
-                        "\n\t * [BUYING PAUSED] => Waiting for tokens to be sold: {:?}",
-                        bought_tokens
-                    ).yellow().to_string());
-                }
-                
-                // Log tokens that have timed out and will be force-sold
-                if !timed_out_tokens.is_empty() {
-                    check_logger.log(format!(
-                        "\n\t * [TIMEOUT DETECTED] => Will force-sell tokens that exceeded {} ms wait time: {:?}",
-                        max_wait_time_millis,
-                        timed_out_tokens.iter().map(|(mint, _)| mint).collect::<Vec<_>>()
-                    ).red().bold().to_string());
-                }
-                
-                (bought_tokens.len() > 0, timed_out_tokens)
-            };
-            
-            // Update buying status
-            {
-                let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                *buying_enabled = !has_bought_tokens;
-                
-            }
-            
-            // Force-sell tokens that have exceeded MAX_WAIT_TIME
-            for (mint, timestamp) in tokens_to_sell {
-                // Clone the necessary state for this token
-                let logger_for_selling = check_logger.clone();
-                let pools_clone_for_selling = Arc::clone(&pools_clone);
-                let app_state_for_selling = app_state_clone.clone();
-                let swap_config_for_selling = swap_config_clone.clone();
-                
-                check_logger.log(format!(
-                    "\n\t * [FORCE SELLING] => Token {} exceeded wait time (elapsed: {:?})",
-                    mint, now.duration_since(timestamp)
-                ).red().to_string());
-                
-                tokio::spawn(async move {
-                    // Get the existing pool for this mint
-                    let existing_pool = {
-                        let pools = pools_clone_for_selling.lock().unwrap();
-                        pools.iter()
-                            .find(|pool| pool.mint == mint)
-                            .cloned()
-                            .unwrap_or(LiquidityPool {
-                                mint: mint.clone(),
-                                buy_price: 0_f64,
-                                sell_price: 0_f64,
-                                status: Status::Bought,
-                                timestamp: Some(timestamp),
-                            })
-                    };
-                    
-                    // Set up sell config
-                    let sell_config = SwapConfig {
-                        swap_direction: SwapDirection::Sell,
-                        in_type: SwapInType::Pct,
-                        amount_in: 1_f64,  // Sell 100%
-                        slippage: 100_u64, // Use full slippage
-                        use_jito: swap_config_for_selling.clone().use_jito,
-                    };
-                    
-                    // Create Pump instance for selling
-                    let app_state_for_task = app_state_for_selling.clone();
-                    let rpc_nonblocking_client = app_state_for_task.rpc_nonblocking_client.clone();
-                    let rpc_client = app_state_for_task.rpc_client.clone();
-                    let wallet = app_state_for_task.wallet.clone();
-                    let swapx = Pump::new(rpc_nonblocking_client.clone(), rpc_client.clone(), wallet.clone());
-                    
-                    // Execute the sell operation
-                    let start_time = Instant::now();
-                    match swapx.build_swap_ixn_by_mint(&mint, None, sell_config, start_time).await {
-                        Ok(result) => {
-                            // Send instructions and confirm
-                            let (keypair, instructions, token_price) = (result.0, result.1, result.2);
-                            let recent_blockhash = match rpc_nonblocking_client.get_latest_blockhash().await {
-                                Ok(hash) => hash,
-                                Err(e) => {
-                                    logger_for_selling.log(format!(
-                                        "Error getting blockhash for force-selling {}: {}", mint, e
-                                    ).red().to_string());
-                                    return;
-                                }
-                            };
-                            
-                            match tx::new_signed_and_send_zeroslot(
-                                recent_blockhash,
-                                &keypair,
-                                instructions,
-                                &logger_for_selling,
-                            ).await {
-                                Ok(res) => {
-                                    let sold_pool = LiquidityPool {
-                                        mint: mint.clone(),
-                                        buy_price: existing_pool.buy_price,
-                                        sell_price: token_price,
-                                        status: Status::Sold,
-                                        timestamp: Some(Instant::now()),
-                                    };
-                                    
-                                    // Update pool status to sold
-                                    {
-                                        let mut pools = pools_clone_for_selling.lock().unwrap();
-                                        pools.retain(|pool| pool.mint != mint);
-                                        pools.insert(sold_pool.clone());
-                                    }
-                                    
-                                    logger_for_selling.log(format!(
-                                        "\n\t * [SUCCESSFUL FORCE-SELL] => TX_HASH: (https://solscan.io/tx/{}) \n\t * [POOL] => ({}) \n\t * [SOLD] => {} :: ({:?}).",
-                                        &res[0], mint, Utc::now(), start_time.elapsed()
-                                    ).green().to_string());
-                                    
-                                    // Check if all tokens are sold
-                                    let all_sold = {
-                                        let pools = pools_clone_for_selling.lock().unwrap();
-                                        !pools.iter().any(|pool| pool.status == Status::Bought)
-                                    };
-                                    
-                                    if all_sold {
-                                        // If all tokens are sold, enable buying
-                                        let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                        *buying_enabled = true;
-                                        
-                                        logger_for_selling.log(
-                                            "\n\t * [BUYING ENABLED] => All tokens sold, can buy new tokens now"
-                                            .green()
-                                            .to_string(),
-                                        );
-                                    }
-                                },
-                                Err(e) => {
-                                    logger_for_selling.log(format!(
-                                        "Force-sell failed for {}: {}", mint, e
-                                    ).red().to_string());
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            logger_for_selling.log(format!(
-                                "Error building swap instruction for force-selling {}: {}", mint, e
-                            ).red().to_string());
-                        }
-                    }
-                });
-            }
-        }
-    });
+    // Start a background task that force-sells positions held longer than max_wait_time_ms
+    spawn_force_sell_task(
+        Arc::clone(&positions),
+        Arc::clone(&app_state),
+        swap_config.use_jito,
+        logger.clone(),
+    );
 
     // In copy_trader_pumpfun after the heartbeat task
     // Add a connection health check task
@@ -2432,14 +1971,14 @@ pub async fn copy_trader_pumpfun(
 
     // In copy_trader_pumpfun after setting up the initial subscription and before the main event loop
     // Add a PNL monitoring and auto-sell task
-    let pnl_liquidity_pools_clone = Arc::clone(&existing_liquidity_pools);
+    let pnl_positions_clone = Arc::clone(&positions);
     let pnl_logger_clone = logger.clone();
     let pnl_app_state_clone = Arc::clone(&app_state);
     let pnl_swap_config_clone = Arc::clone(&swap_config);
     let pnl_token_tracking = Arc::clone(&TOKEN_TRACKING);
 
     tokio::spawn(async move {
-        let pools_clone = Arc::clone(&pnl_liquidity_pools_clone);
+        let positions_clone = Arc::clone(&pnl_positions_clone);
         let pnl_check_logger = pnl_logger_clone.clone();
         let app_state_clone = Arc::clone(&pnl_app_state_clone);
         let swap_config_clone = Arc::clone(&pnl_swap_config_clone);
@@ -2451,14 +1990,8 @@ pub async fn copy_trader_pumpfun(
         loop {
             interval.tick().await;
             
-            // Get current pools to check
-            let tokens_to_check = {
-                let pools = pools_clone.lock().unwrap();
-                pools.iter()
-                    .filter(|pool| pool.status == Status::Bought)
-                    .map(|pool| pool.clone())
-                    .collect::<Vec<LiquidityPool>>()
-            };
+            // Get current positions to check
+            let tokens_to_check = positions_clone.held();
             
             if tokens_to_check.is_empty() {
                 continue;
@@ -2470,29 +2003,43 @@ pub async fn copy_trader_pumpfun(
             ).blue().to_string());
             
             // Check each token's current price and PNL
-            for pool in tokens_to_check {
-                let mint = pool.mint.clone();
-                let buy_price = pool.buy_price;
-                let bought_time = pool.timestamp.unwrap_or(Instant::now());
+            for position in tokens_to_check {
+                let mint = position.mint.clone();
+                let buy_price = position.buy_price;
+                let basis = position.basis.clone();
+                let bought_time = position.opened_at.unwrap_or(Instant::now());
                 let time_elapsed = Instant::now().duration_since(bought_time);
                 
                 // Clone necessary variables
                 let logger_for_pnl = pnl_check_logger.clone();
-                let pools_clone_for_pnl = Arc::clone(&pools_clone);
+                let positions_for_pnl = Arc::clone(&positions_clone);
                 let token_tracking_clone = Arc::clone(&token_tracking);
                 let app_state_for_pnl = app_state_clone.clone();
                 let swap_config_for_pnl = Arc::clone(&swap_config_clone);
-                
-                // Create Pump instance for price checking
-                let rpc_nonblocking_client = app_state_for_pnl.rpc_nonblocking_client.clone();
-                let rpc_client = app_state_for_pnl.rpc_client.clone();
-                let wallet = app_state_for_pnl.wallet.clone();
-                let swapx = Pump::new(rpc_nonblocking_client.clone(), rpc_client.clone(), wallet.clone());
+                // Priced on the curve or the pool, whichever the position currently trades on
+                let price_protocol = position.swap_protocol();
                 
                 // Execute as a separate task to avoid blocking PNL check loop
                 tokio::spawn(async move {
+                    // What selling the tokens held would pay out right now, fees and price impact
+                    // included, where the curve or pool can be quoted exactly
+                    let sell_quote = if basis.token_amount > 0 {
+                        match pump_sell_quote(&app_state_for_pnl, &mint, price_protocol.clone(), basis.token_amount).await {
+                            Ok(quote) => quote,
+                            Err(e) => {
+                                logger_for_pnl.log(format!(
+                                    "[PNL ERROR] => Failed to quote a sell of {}: {}",
+                                    mint, e
+                                ).yellow().to_string());
+                                None
+                            }
+                        }
+                    } else {
+                        None
+                    };
+
                     // Get current price estimate
-                    let current_price = match swapx.get_token_price(&mint).await {
+                    let current_price = match get_token_price(&app_state_for_pnl, &mint, price_protocol).await {
                         Ok(price) => price,
                         Err(e) => {
                             logger_for_pnl.log(format!(
@@ -2503,20 +2050,42 @@ pub async fn copy_trader_pumpfun(
                         }
                     };
                     
-                    // Calculate PNL
-                    let pnl = if buy_price > 0.0 {
-                        ((current_price - buy_price) / buy_price) * 100.0
-                    } else {
-                        0.0
+                    // Current price in SOL: what the quoted sell pays per token, or the price
+                    // estimate anchored to what the buy fill actually paid the pool
+                    let price_sol = match sell_quote {
+                        Some(quote) => basis.price_for_value(lamports_to_sol(quote.amount_out)),
+                        None if buy_price > 0.0 => basis.entry_price_sol * current_price / buy_price,
+                        None => 0.0,
+                    };
+                    
+                    // Calculate PNL against the real cost basis (fees, tips and rent included)
+                    // once the buy fill is journaled, from the price estimate until then
+                    let unrealized = basis.unrealized(price_sol);
+                    let pnl = match unrealized {
+                        Some((_, pnl_percent)) => pnl_percent,
+                        None if buy_price > 0.0 => ((current_price - buy_price) / buy_price) * 100.0,
+                        None => 0.0,
+                    };
+                    let mut cost_status = match unrealized {
+                        Some((pnl_sol, _)) => format!(
+                            "Cost: {:.6} SOL | Value: {:.6} SOL | Unrealized: {:+.6} SOL | Realized: {:+.6} SOL",
+                            basis.cost_sol, basis.market_value(price_sol), pnl_sol, basis.realized_sol
+                        ),
+                        None => "Cost: pending fill".to_string(),
                     };
+                    if let Some(quote) = sell_quote {
+                        // Least a sell right now would accept at the configured slippage
+                        cost_status.push_str(&format!(
+                            " | Sell floor: {:.6} SOL",
+                            lamports_to_sol(quote.minimum_out(params().slippage))
+                        ));
+                    }
                     
                     // Get or create token tracking info
                     let mut tracking_info = {
                         let mut tracking = token_tracking_clone.lock().unwrap();
-                        tracking.entry(mint.clone()).or_insert_with(|| TokenTrackingInfo {
-                            top_pnl: pnl,
-                            last_sell_time: Instant::now(),
-                            completed_intervals: HashSet::new(),
+                        tracking.entry(mint.clone()).or_insert_with(|| {
+                            TokenTrackingInfo::new(params().strategies.default_profile.clone(), pnl)
                         }).clone()
                     };
                     
@@ -2536,28 +2105,65 @@ pub async fn copy_trader_pumpfun(
                     
                     // Log current PNL status
                     logger_for_pnl.log(format!(
-                        "[PNL STATUS] => Token: {} | Buy: ${:.6} | Current: ${:.6} | PNL: {:.2}% | Peak PNL: {:.2}% | Time: {:?}",
-                        mint, buy_price, current_price, pnl, tracking_info.top_pnl, time_elapsed
+                        "[PNL STATUS] => Token: {} | Strategy: {} | Buy: ${:.6} | Current: ${:.6} | {} | PNL: {:.2}% | Peak PNL: {:.2}% | Time: {:?}",
+                        mint, tracking_info.strategy, buy_price, current_price, cost_status, pnl, tracking_info.top_pnl, time_elapsed
                     ).cyan().to_string());
                     
-                    // Get appropriate retracement levels based on current PNL and time
-                    let retracement_levels = get_retracement_levels(pnl, time_elapsed.as_secs());
+                    // Get appropriate retracement levels from the position's strategy profile
+                    let profile = params().strategies.profile(&tracking_info.strategy);
+                    let retracement_levels = profile.retracement_levels(pnl, time_elapsed.as_secs());
                     
                     // Decision variables
-                    let mut should_sell = false;
+                    let mut sell_trigger: Option<SellReason> = None;
                     let mut sell_percentage = 0;
                     let mut sell_reason = String::new();
                     
+                    // Track how long the position has been continuously under the time-in-loss floor
+                    let loss_since = if profile.stop_loss.time_in_loss_secs.is_some() && pnl < profile.stop_loss.time_in_loss_pnl {
+                        let mut tracking = token_tracking_clone.lock().unwrap();
+                        tracking.get_mut(&mint).map(|info| *info.loss_since.get_or_insert_with(Instant::now))
+                    } else {
+                        let mut tracking = token_tracking_clone.lock().unwrap();
+                        if let Some(info) = tracking.get_mut(&mint) {
+                            info.loss_since = None;
+                        }
+                        None
+                    };
+                    
+                    // Hard stop-loss from the buy price
+                    if profile.stop_loss_triggered(pnl) {
+                        sell_trigger = Some(SellReason::StopLoss);
+                        sell_percentage = 100;
+                        sell_reason = format!(
+                            "Stop loss: PNL of {:.2}% is below the {:.2}% limit",
+                            pnl, profile.stop_loss.max_loss_pnl.unwrap_or_default()
+                        );
+                    }
+                    
+                    // Time-in-loss exit
+                    if sell_trigger.is_none() {
+                        if let Some(in_loss_for) = loss_since.map(|since| Instant::now().duration_since(since)) {
+                            if profile.time_in_loss_exceeded(in_loss_for) {
+                                sell_trigger = Some(SellReason::TimeInLoss);
+                                sell_percentage = 100;
+                                sell_reason = format!(
+                                    "Time in loss: PNL below {:.2}% for {:?}",
+                                    profile.stop_loss.time_in_loss_pnl, in_loss_for
+                                );
+                            }
+                        }
+                    }
+                    
                     // Check if we should sell based on time-based thresholds
                     // First check - Time-based PNL thresholds (more aggressive selling with time)
-                    if time_elapsed.as_secs() > 300 { // If held for more than 5 minutes
-                        for level in &retracement_levels {
+                    if sell_trigger.is_none() && time_elapsed.as_secs() > profile.retracement_min_hold_secs {
+                        for level in retracement_levels {
                             if pnl >= level.threshold as f64 {
                                 // Calculate how much the price has retraced from peak
                                 let retracement = ((tracking_info.top_pnl - pnl) / tracking_info.top_pnl) * 100.0;
                                 
                                 if retracement >= level.percentage as f64 {
-                                    should_sell = true;
+                                    sell_trigger = Some(SellReason::Retracement);
                                     sell_percentage = level.sell_amount;
                                     sell_reason = format!(
                                         "Retracement of {:.2}% from peak PNL of {:.2}%", 
@@ -2570,8 +2176,8 @@ pub async fn copy_trader_pumpfun(
                     }
                     
                     // Second check - Trailing stop loss for significant drops from peak
-                    if !should_sell && check_trailing_stop_loss(pnl, tracking_info.top_pnl, &logger_for_pnl) {
-                        should_sell = true;
+                    if sell_trigger.is_none() && check_trailing_stop_loss(&profile, pnl, tracking_info.top_pnl, &logger_for_pnl) {
+                        sell_trigger = Some(SellReason::TrailingStop);
                         sell_percentage = 100; // Sell all when trailing stop triggers
                         sell_reason = format!(
                             "Trailing stop loss triggered. Current PNL: {:.2}%, Peak PNL: {:.2}%", 
@@ -2580,34 +2186,29 @@ pub async fn copy_trader_pumpfun(
                     }
                     
                     // Third check - Fixed take-profit levels
-                    if !should_sell {
-                        for (threshold, percentage) in TAKE_PROFIT_LEVELS.iter() {
+                    let mut take_profit_key = None;
+                    if sell_trigger.is_none() {
+                        for (threshold, percentage) in profile.take_profit_levels.iter() {
                             let threshold_key = format!("take_profit_{}", threshold);
                             
                             // Only trigger if we haven't sold at this level before and PNL exceeds threshold
                             if pnl >= *threshold as f64 && !tracking_info.completed_intervals.contains(&threshold_key) {
-                                should_sell = true;
+                                sell_trigger = Some(SellReason::TakeProfit);
                                 sell_percentage = *percentage;
                                 sell_reason = format!(
                                     "Take profit at {:.2}% PNL threshold ({:.2}% of holding)", 
                                     pnl, percentage
                                 );
-                                
-                                // Mark this threshold as completed
-                                {
-                                    let mut tracking = token_tracking_clone.lock().unwrap();
-                                    if let Some(info) = tracking.get_mut(&mint) {
-                                        info.completed_intervals.insert(threshold_key);
-                                    }
-                                }
+                                // Marked completed once the sell goes through
+                                take_profit_key = Some(threshold_key);
                                 break;
                             }
                         }
                     }
                     
                     // Fourth check - Force sell if PNL drops below threshold after significant profit
-                    if !should_sell && tracking_info.top_pnl > 100.0 && pnl < 20.0 {
-                        should_sell = true;
+                    if sell_trigger.is_none() && profile.emergency_exit_triggered(pnl, tracking_info.top_pnl) {
+                        sell_trigger = Some(SellReason::EmergencyExit);
                         sell_percentage = 100;
                         sell_reason = format!(
                             "Emergency exit: PNL dropped to {:.2}% after peaking at {:.2}%", 
@@ -2615,127 +2216,40 @@ pub async fn copy_trader_pumpfun(
                         );
                     }
                     
+                    if let Some(sell_trigger) = sell_trigger {
+                        let mut tracking = token_tracking_clone.lock().unwrap();
+                        if let Some(info) = tracking.get_mut(&mint) {
+                            info.exit_reason = Some(sell_trigger);
+                        }
+                    }
+                    // Save peak PnL, completed levels and loss timer so a restart resumes them
+                    persist_tracking(&mint, &logger_for_pnl);
+                    
                     // Execute sell if conditions are met
-                    if should_sell {
+                    if let Some(sell_trigger) = sell_trigger {
                         logger_for_pnl.log(format!(
-                            "\n[SELL DECISION] => Token: {} | Selling {}% | Reason: {}",
-                            mint, sell_percentage, sell_reason
+                            "\n[SELL DECISION] => Token: {} | Selling {}% | Trigger: {} | Reason: {}",
+                            mint, sell_percentage, sell_trigger, sell_reason
                         ).yellow().bold().to_string());
                         
-                        // Create sell configuration
-                        let sell_config = SwapConfig {
-                            swap_direction: SwapDirection::Sell,
-                            in_type: SwapInType::Pct,
-                            amount_in: sell_percentage as f64 / 100.0,  // Convert percentage to decimal
-                            slippage: 100_u64, // Use full slippage for sell
-                            use_jito: swap_config_for_pnl.use_jito,
-                        };
-                        
-                        // Execute the sell
-                        let start_time = Instant::now();
-                        match swapx.build_swap_ixn_by_mint(&mint, None, sell_config, start_time).await {
-                            Ok(result) => {
-                                // Send instructions and confirm
-                                let (keypair, instructions, token_price) = (result.0, result.1, result.2);
-                                let recent_blockhash = match rpc_nonblocking_client.get_latest_blockhash().await {
-                                    Ok(hash) => hash,
-                                    Err(e) => {
-                                        logger_for_pnl.log(format!(
-                                            "Error getting blockhash for selling {}: {}", mint, e
-                                        ).red().to_string());
-                                        return;
-                                    }
-                                };
-                                
-                                match tx::new_signed_and_send_zeroslot(
-                                    recent_blockhash,
-                                    &keypair,
-                                    instructions,
-                                    &logger_for_pnl,
-                                ).await {
-                                    Ok(res) => {
-                                        // Update pool status
-                                        if sell_percentage >= 100 {
-                                            // If selling all, mark as sold
-                                            let sold_pool = LiquidityPool {
-                                                mint: mint.clone(),
-                                                buy_price: buy_price,
-                                                sell_price: token_price,
-                                                status: Status::Sold,
-                                                timestamp: Some(Instant::now()),
-                                            };
-                                            
-                                            {
-                                                let mut pools = pools_clone_for_pnl.lock().unwrap();
-                                                pools.retain(|pool| pool.mint != mint);
-                                                pools.insert(sold_pool.clone());
-                                            }
-                                            
-                                            // Clean up tracking info
-                                            {
-                                                let mut tracking = token_tracking_clone.lock().unwrap();
-                                                tracking.remove(&mint);
-                                            }
-                                            
-                                            logger_for_pnl.log(format!(
-                                                "\n[COMPLETE SELL] => TX_HASH: (https://solscan.io/tx/{}) \n\t * [POOL] => ({}) \n\t * [PNL] => {:.2}% \n\t * [SOLD] => {} :: ({:?}).",
-                                                &res[0], mint, pnl, Local::now(), start_time.elapsed()
-                                            ).green().bold().to_string());
-                                            
-                                            // Check if all tokens are sold to re-enable buying
-                                            let all_sold = {
-                                                let pools = pools_clone_for_pnl.lock().unwrap();
-                                                !pools.iter().any(|pool| pool.status == Status::Bought)
-                                            };
-                                            
-                                            if all_sold {
-                                                let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                                *buying_enabled = true;
-                                                
-                                                logger_for_pnl.log(
-                                                    "\n[BUYING ENABLED] => All tokens sold, can buy new tokens now"
-                                                    .green()
-                                                    .to_string(),
-                                                );
-                                            }
-                                        } else {
-                                            // If partial sell, just update timestamp
-                                            {
-                                                let mut pools = pools_clone_for_pnl.lock().unwrap();
-                                                if let Some(pool) = pools.iter().find(|p| p.mint == mint) {
-                                                    let mut updated_pool = pool.clone();
-                                                    updated_pool.timestamp = Some(Instant::now());
-                                                    pools.retain(|p| p.mint != mint);
-                                                    pools.insert(updated_pool);
-                                                }
-                                            }
-                                            
-                                            // Update last sell time
-                                            {
-                                                let mut tracking = token_tracking_clone.lock().unwrap();
-                                                if let Some(info) = tracking.get_mut(&mint) {
-                                                    info.last_sell_time = Instant::now();
-                                                }
-                                            }
-                                            
-                                            logger_for_pnl.log(format!(
-                                                "\n[PARTIAL SELL] => TX_HASH: (https://solscan.io/tx/{}) \n\t * [POOL] => ({}) \n\t * [PNL] => {:.2}% \n\t * [SOLD {}%] => {} :: ({:?}).",
-                                                &res[0], mint, pnl, sell_percentage, Local::now(), start_time.elapsed()
-                                            ).yellow().bold().to_string());
-                                        }
-                                    },
-                                    Err(e) => {
-                                        logger_for_pnl.log(format!(
-                                            "Sell failed for {}: {}", mint, e
-                                        ).red().to_string());
-                                    }
-                                }
-                            },
-                            Err(e) => {
-                                logger_for_pnl.log(format!(
-                                    "Error building swap instruction for selling {}: {}", mint, e
-                                ).red().to_string());
+                        // Another exit (mirror, force sell) may already be selling this position
+                        if !positions_for_pnl.begin_sell(&mint) {
+                            return;
+                        }
+                        let sold = sell_position(
+                            app_state_for_pnl,
+                            positions_for_pnl,
+                            mint.clone(),
+                            sell_percentage,
+                            sell_trigger,
+                            swap_config_for_pnl.use_jito,
+                            logger_for_pnl.clone(),
+                        ).await;
+                        if let (true, Some(threshold_key)) = (sold, take_profit_key) {
+                            if let Some(info) = token_tracking_clone.lock().unwrap().get_mut(&mint) {
+                                info.completed_intervals.insert(threshold_key);
                             }
+                            persist_tracking(&mint, &logger_for_pnl);
                         }
                     }
                 });
@@ -2743,6 +2257,15 @@ pub async fn copy_trader_pumpfun(
         }
     });
 
+    let ctx = CopyTradeContext {
+        app_state: Arc::clone(&app_state),
+        swap_config: Arc::clone(&swap_config),
+        positions: Arc::clone(&positions),
+        logger: logger.clone(),
+        min_dev_buy,
+        max_dev_buy,
+    };
+
     while let Some(message) = stream.next().await {
         match message {
             Ok(msg) => {
@@ -2761,9 +2284,8 @@ pub async fn copy_trader_pumpfun(
                         .and_then(|txn1| txn1.meta)
                         .map(|meta| meta.log_messages)
                     {
-                        let mut mint_flag = false;
-                        let trade_info = match TradeInfoFromToken::from_json(txn.clone(), log_messages.clone()) {
-                            Ok(info) => info,
+                        let trades = match TradeInfoFromToken::from_json(txn.clone(), log_messages.clone()) {
+                            Ok(trades) => trades,
                             Err(e) => {
                                 logger.log(
                                     format!("Error in parsing txn: {}", e)
@@ -2775,240 +2297,29 @@ pub async fn copy_trader_pumpfun(
                             }
                         };
 
-                        // Check if this is a buy transaction (PumpBuy or PumpSwapBuy)
-                        let is_buy_transaction = matches!(trade_info.instruction_type, 
-                            InstructionType::PumpBuy | InstructionType::PumpSwapBuy);
-                        
-                        // Process copy trading for buy transactions
-                        if is_buy_transaction {
-                            // Check if this transaction is from one of our copy trading addresses
-                            let is_copy_trading_tx = filter_config.copy_trading_target_addresses.iter()
-                                .any(|addr| trade_info.target == *addr);
-                            
-                            if is_copy_trading_tx {
-                                logger.log(format!(
-                                    "\n\t * [COPY TRADING BUY DETECTED] => (https://solscan.io/tx/{}) - SLOT:({}) \n\t * [TARGET] => ({}) \n\t * [TOKEN] => ({}) \n\t * [BUY AMOUNT] => ({}) SOL \n\t * [TIMESTAMP] => {} :: ({:?}).",
-                                    trade_info.signature,
-                                    trade_info.slot,
-                                    trade_info.target,
-                                    trade_info.mint,
-                                    lamports_to_sol(trade_info.volume_change.abs() as u64),
-                                    Utc::now(),
-                                    start_time.elapsed(),
-                                ).blue().to_string());
-
-                                // Check if buying is enabled
-                                let buying_enabled = {
-                                    let enabled = BUYING_ENABLED.lock().unwrap();
-                                    *enabled
-                                };
-                                
-                                if !buying_enabled {
-                                    logger.log(format!(
-                                        "\n\t * [SKIPPING BUY] => Waiting for all tokens to be sold first"
-                                    ).yellow().to_string());
-                                    continue;
-                                }
-
-                                // Check dev buy amount
-                                let dev_buy_amount = lamports_to_sol(trade_info.volume_change.abs() as u64);
-                                if dev_buy_amount > max_dev_buy as f64 {
-                                    logger.log(format!(
-                                        "\n\t * [BUY AMOUNT EXCEEDS MAX] => {} > {}",
-                                        dev_buy_amount, max_dev_buy
-                                    ).yellow().to_string());
-                                    continue;
-                                }
-                                if dev_buy_amount < min_dev_buy as f64 {
-                                    logger.log(format!(
-                                        "\n\t * [BUY AMOUNT BELOW MIN] => {} < {}",
-                                        dev_buy_amount, min_dev_buy
-                                    ).yellow().to_string());
-                                    continue;
-                                }
-
-                                // Check if this token is already in our pools
-                                let is_duplicate = {
-                                    let pools = existing_liquidity_pools.lock().unwrap();
-                                    pools.iter().any(|pool| pool.mint == trade_info.mint)
-                                };
-                                
-                                if is_duplicate {
-                                    logger.log(format!(
-                                        "\n\t * [DUPLICATE TOKEN] => Token already in our pools: {}",
-                                        trade_info.mint
-                                    ).yellow().to_string());
-                                    continue;
-                                }
-
-                                // Temporarily disable buying while we're processing this buy
-                                {
-                                    let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                    *buying_enabled = false;
-                                }
-
-                                // Clone the shared variables for this task
-                                let swapx_clone = swapx.clone();
-                                let logger_clone = logger.clone();
-                                let mut swap_config_clone = (*Arc::clone(&swap_config)).clone();
-                                let app_state_clone = Arc::clone(&app_state).clone();
-                                let yellowstone_grpc_http_clone = yellowstone_grpc_http.clone();
-                                let yellowstone_grpc_token_clone = yellowstone_grpc_token.clone();
-                                
-                                let mint_str = trade_info.mint.clone();
-                                
-                                // Get bonding curve information for PumpBuy transactions
-                                let bonding_curve_info = if matches!(trade_info.instruction_type, InstructionType::PumpBuy) {
-                                    // For PumpBuy, we need to get the bonding curve account
-                                    let rpc_client = app_state_clone.rpc_client.clone();
-                                    let mint_pubkey = Pubkey::from_str(&mint_str).unwrap_or_default();
-                                    let pump_program = Pubkey::from_str(PUMP_PROGRAM).unwrap_or_default();
-                                    
-                                    match tokio::task::block_in_place(|| {
-                                        futures::executor::block_on(async {
-                                            get_bonding_curve_account(rpc_client, mint_pubkey, pump_program).await
-                                        })
-                                    }) {
-                                        Ok((bonding_curve, _, reserves)) => {
-                                            Some(BondingCurveInfo {
-                                                bonding_curve,
-                                                new_virtual_sol_reserve: reserves.virtual_sol_reserves,
-                                                new_virtual_token_reserve: reserves.virtual_token_reserves,
-                                            })
-                                        },
-                                        Err(e) => {
-                                            logger.log(format!(
-                                                "\n\t * [BONDING CURVE ERROR] => Failed to get bonding curve for {}: {}",
-                                                mint_str, e
-                                            ).red().to_string());
-                                            trade_info.bonding_curve_info.clone()
-                                        }
-                                    }
-                                } else {
-                                    trade_info.bonding_curve_info.clone()
-                                };
-                                
-                                let existing_liquidity_pools_clone = Arc::clone(&existing_liquidity_pools);
-                                let recent_blockhash = trade_info.clone().recent_blockhash;
-                                
-                                // Determine trading amount based on comparing SOL amount and TOKEN_AMOUNT
-                                let sol_amount = lamports_to_sol(trade_info.volume_change.abs() as u64);
-                                let token_amount = trade_info.token_amount;
-                                
-                                // If token amount is smaller than SOL amount, use token amount for trading
-                                if token_amount > 0.0 && token_amount < sol_amount {
-                                    // Modify swap_config to use the detected token amount
-                                    swap_config_clone.amount_in = token_amount;
-                                    logger.log(format!(
-                                        "\n\t * [USING TOKEN AMOUNT] => {}, SOL Amount: {}",
-                                        token_amount, sol_amount
-                                    ).green().to_string());
-                                }
-    
-                                let task = tokio::spawn(async move {
-                                    match swapx_clone
-                                        .build_swap_ixn_by_mint(
-                                            &mint_str,
-                                            bonding_curve_info,
-                                            swap_config_clone.clone(),
-                                            start_time,
-                                        )
-                                        .await
-                                    {
-                                        Ok(result) => {
-                                            let (keypair, instructions, token_price) =
-                                                (result.0, result.1, result.2);
-                                            
-                                            match tx::new_signed_and_send_zeroslot(
-                                                recent_blockhash,
-                                                &keypair,
-                                                instructions,
-                                                &logger_clone,
-                                            ).await {
-                                                Ok(res) => {
-                                                    let bought_pool = LiquidityPool {
-                                                        mint: mint_str.clone(),
-                                                        buy_price: token_price,
-                                                        sell_price: 0_f64,
-                                                        status: Status::Bought,
-                                                        timestamp: Some(Instant::now()),
-                                                    };
-                                                    
-                                                    // Create a local copy before modifying
-                                                    {
-                                                        let mut existing_pools =
-                                                            existing_liquidity_pools_clone.lock().unwrap();
-                                                        existing_pools.retain(|pool| pool.mint != mint_str);
-                                                        existing_pools.insert(bought_pool.clone());
-                                                        
-                                                        // Log after modification within the lock scope
-                                                        logger_clone.log(format!(
-                                                            "\n\t * [SUCCESSFUL-COPY-BUY] => TX_HASH: (https://solscan.io/tx/{}) \n\t * [TOKEN] => ({}) \n\t * [DONE] => {} :: ({:?}) \n\t * [TOTAL TOKENS] => {}",
-                                                            &res[0], mint_str, Utc::now(), start_time.elapsed(), existing_pools.len()
-                                                        ).green().to_string());
-                                                    }
-                                                },
-                                                Err(e) => {
-                                                    logger_clone.log(
-                                                        format!("Failed to copy buy for {}: {}", mint_str.clone(), e)
-                                                            .red()
-                                                            .italic()
-                                                            .to_string(),
-                                                    );
-                                                    
-                                                    // Re-enable buying since this one failed
-                                                    let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                                    *buying_enabled = true;
-                                                    
-                                                    let failed_pool = LiquidityPool {
-                                                        mint: mint_str.clone(),
-                                                        buy_price: 0_f64,
-                                                        sell_price: 0_f64,
-                                                        status: Status::Failure,
-                                                        timestamp: None,
-                                                    };
-                                                    
-                                                    // Use a local scope for the mutex lock
-                                                    {
-                                                        let mut update_pools =
-                                                            existing_liquidity_pools_clone.lock().unwrap();
-                                                        update_pools.retain(|pool| pool.mint != mint_str);
-                                                        update_pools.insert(failed_pool.clone());
-                                                    }
-                                                }
-                                            }
-                                        },
-                                        Err(error) => {
-                                            logger_clone.log(
-                                                format!("Error building swap instruction: {}", error)
-                                                    .red()
-                                                    .italic()
-                                                    .to_string(),
-                                            );
-                                            
-                                            // Re-enable buying since this one failed
-                                            let mut buying_enabled = BUYING_ENABLED.lock().unwrap();
-                                            *buying_enabled = true;
-                                            
-                                            let failed_pool = LiquidityPool {
-                                                mint: mint_str.clone(),
-                                                buy_price: 0_f64,
-                                                sell_price: 0_f64,
-                                                status: Status::Failure,
-                                                timestamp: None,
-                                            };
-                                            
-                                            // Use a local scope for the mutex lock
-                                            {
-                                                let mut update_pools =
-                                                    existing_liquidity_pools_clone.lock().unwrap();
-                                                update_pools.retain(|pool| pool.mint != mint_str);
-                                                update_pools.insert(failed_pool.clone());
-                                            }
-                                        }
-                                    }
-                                });
-                                drop(task);
+                        // Each trade is evaluated on its own; a sell mirrors once per target and mint
+                        let mut mirrored = HashSet::new();
+                        for trade_info in trades {
+                            // Check if this is a buy transaction (PumpBuy, PumpSwapBuy or RaydiumLaunchpadBuy)
+                            let is_buy_transaction = matches!(trade_info.instruction_type, 
+                                InstructionType::PumpBuy | InstructionType::PumpSwapBuy | InstructionType::RaydiumLaunchpadBuy
+                                | InstructionType::MeteoraDbcBuy | InstructionType::MeteoraDlmmBuy);
+
+                            // Process copy trading for buy transactions
+                            if is_buy_transaction {
+                                handle_trade(trade_info, start_time, &ctx).await;
+                            } else if matches!(trade_info.instruction_type,
+                                InstructionType::PumpSell | InstructionType::PumpSwapSell | InstructionType::RaydiumLaunchpadSell
+                                | InstructionType::MeteoraDbcSell | InstructionType::MeteoraDlmmSell)
+                                && mirrored.insert((trade_info.target.clone(), trade_info.mint.clone())) {
+                                mirror_target_sell(
+                                    &txn,
+                                    &trade_info,
+                                    &positions,
+                                    &app_state,
+                                    swap_config.use_jito,
+                                    &logger,
+                                );
                             }
                         }
                     }
@@ -3029,3 +2340,361 @@ pub async fn copy_trader_pumpfun(
 
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dex::pump_events::{CREATE_EVENT_DISCRIMINATOR, TRADE_EVENT_DISCRIMINATOR};
+    use crate::dex::pump_instructions::{BUY_DISCRIMINATOR, CREATE_DISCRIMINATOR};
+    use yellowstone_grpc_proto::prelude::{CompiledInstruction, SubscribeUpdateTransactionInfo, Transaction, UiTokenAmount};
+
+    /// A transaction as the geyser stream delivers it. Accounts are indexed
+    /// as static keys, then writable and readonly keys loaded from lookup tables.
+    #[derive(Default)]
+    struct TxBuilder {
+        static_keys: Vec<Pubkey>,
+        loaded_writable: Vec<Pubkey>,
+        loaded_readonly: Vec<Pubkey>,
+        instructions: Vec<(Pubkey, Vec<Pubkey>, Vec<u8>)>,
+        logs: Vec<String>,
+        lamport_changes: Vec<(Pubkey, i64)>,
+        token_balances: Vec<(Pubkey, String, String, f64)>,
+    }
+
+    impl TxBuilder {
+        fn all_keys(&self) -> Vec<Pubkey> {
+            [&self.static_keys, &self.loaded_writable, &self.loaded_readonly].into_iter().flatten().copied().collect()
+        }
+
+        fn instruction(mut self, program: &str, accounts: &[Pubkey], data: Vec<u8>) -> Self {
+            self.instructions.push((Pubkey::from_str(program).unwrap(), accounts.to_vec(), data));
+            self
+        }
+
+        fn log(mut self, log: String) -> Self {
+            self.logs.push(log);
+            self
+        }
+
+        fn lamport_change(mut self, account: Pubkey, change: i64) -> Self {
+            self.lamport_changes.push((account, change));
+            self
+        }
+
+        /// `owner`'s balance of `mint` after the transaction, held in `account`.
+        fn token_balance(mut self, account: Pubkey, owner: &Pubkey, mint: &Pubkey, ui_amount: f64) -> Self {
+            self.token_balances.push((account, owner.to_string(), mint.to_string(), ui_amount));
+            self
+        }
+
+        fn build(mut self) -> (SubscribeUpdateTransaction, Vec<String>) {
+            // Every account not loaded from a lookup table is a static key
+            let referenced: Vec<Pubkey> = self
+                .instructions
+                .iter()
+                .flat_map(|(program, accounts, _)| std::iter::once(program).chain(accounts))
+                .chain(self.token_balances.iter().map(|(account, ..)| account))
+                .copied()
+                .collect();
+            for key in referenced {
+                if !self.all_keys().contains(&key) {
+                    self.static_keys.push(key);
+                }
+            }
+            let keys = self.all_keys();
+            let index = |key: &Pubkey| keys.iter().position(|account| account == key).unwrap() as u8;
+
+            let instructions = self
+                .instructions
+                .iter()
+                .map(|(program, accounts, data)| CompiledInstruction {
+                    program_id_index: index(program) as u32,
+                    accounts: accounts.iter().map(index).collect(),
+                    data: data.clone(),
+                })
+                .collect();
+            let token_balances: Vec<TokenBalance> = self
+                .token_balances
+                .iter()
+                .map(|(account, owner, mint, ui_amount)| TokenBalance {
+                    account_index: index(account) as u32,
+                    mint: mint.clone(),
+                    owner: owner.clone(),
+                    ui_token_amount: Some(UiTokenAmount {
+                        ui_amount: *ui_amount,
+                        decimals: 6,
+                        amount: ((ui_amount * 1e6) as u64).to_string(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .collect();
+            let pre_balances = vec![10_000_000_000; keys.len()];
+            let post_balances = keys
+                .iter()
+                .map(|key| {
+                    let change: i64 = self.lamport_changes.iter().filter(|(account, _)| account == key).map(|(_, change)| change).sum();
+                    (10_000_000_000 + change) as u64
+                })
+                .collect();
+            let to_bytes = |keys: &[Pubkey]| keys.iter().map(|key| key.to_bytes().to_vec()).collect::<Vec<_>>();
+            let message = Message {
+                account_keys: to_bytes(&self.static_keys),
+                recent_blockhash: Hash::new_unique().to_bytes().to_vec(),
+                instructions,
+                versioned: !self.loaded_writable.is_empty() || !self.loaded_readonly.is_empty(),
+                ..Default::default()
+            };
+            let meta = TransactionStatusMeta {
+                pre_balances,
+                post_balances,
+                log_messages: self.logs.clone(),
+                post_token_balances: token_balances,
+                loaded_writable_addresses: to_bytes(&self.loaded_writable),
+                loaded_readonly_addresses: to_bytes(&self.loaded_readonly),
+                ..Default::default()
+            };
+            let txn = SubscribeUpdateTransaction {
+                transaction: Some(SubscribeUpdateTransactionInfo {
+                    signature: vec![7; 64],
+                    transaction: Some(Transaction { signatures: vec![vec![7; 64]], message: Some(message) }),
+                    meta: Some(meta),
+                    ..Default::default()
+                }),
+                slot: 300_000_000,
+            };
+            (txn, self.logs)
+        }
+    }
+
+    /// Accounts of a PumpFun buy or sell, in IDL order.
+    fn pump_fun_accounts(mint: &Pubkey, bonding_curve: &Pubkey, user: &Pubkey) -> Vec<Pubkey> {
+        let mut accounts: Vec<Pubkey> = (0..12).map(|_| Pubkey::new_unique()).collect();
+        accounts[2] = *mint;
+        accounts[3] = *bonding_curve;
+        accounts[6] = *user;
+        accounts
+    }
+
+    /// Accounts of a PumpFun create, in IDL order.
+    fn pump_fun_create_accounts(mint: &Pubkey, bonding_curve: &Pubkey, user: &Pubkey) -> Vec<Pubkey> {
+        let mut accounts: Vec<Pubkey> = (0..14).map(|_| Pubkey::new_unique()).collect();
+        accounts[0] = *mint;
+        accounts[2] = *bonding_curve;
+        accounts[7] = *user;
+        accounts
+    }
+
+    /// Accounts of a PumpSwap buy or sell, in IDL order, for a WSOL-quoted pool.
+    fn pump_swap_accounts(pool: &Pubkey, user: &Pubkey, mint: &Pubkey, pool_quote_token_account: &Pubkey) -> Vec<Pubkey> {
+        let mut accounts: Vec<Pubkey> = (0..19).map(|_| Pubkey::new_unique()).collect();
+        accounts[0] = *pool;
+        accounts[1] = *user;
+        accounts[3] = *mint;
+        accounts[4] = spl_token::native_mint::ID;
+        accounts[8] = *pool_quote_token_account;
+        accounts
+    }
+
+    fn swap_data(discriminator: [u8; 8], first: u64, second: u64) -> Vec<u8> {
+        [&discriminator[..], &first.to_le_bytes(), &second.to_le_bytes()].concat()
+    }
+
+    fn borsh_string(value: &str) -> Vec<u8> {
+        [&(value.len() as u32).to_le_bytes()[..], value.as_bytes()].concat()
+    }
+
+    fn create_data(creator: &Pubkey) -> Vec<u8> {
+        [&CREATE_DISCRIMINATOR[..], &borsh_string("Token"), &borsh_string("TKN"), &borsh_string("https://example.com/token.json"), &creator.to_bytes()].concat()
+    }
+
+    /// The `Program data:` log of a PumpFun `CreateEvent` for a fresh curve.
+    fn create_event_log(mint: &Pubkey, bonding_curve: &Pubkey, user: &Pubkey) -> String {
+        let mut data = CREATE_EVENT_DISCRIMINATOR.to_vec();
+        for value in ["Token", "TKN", "https://example.com/token.json"] {
+            data.extend(borsh_string(value));
+        }
+        for key in [mint, bonding_curve, user, user] {
+            data.extend(key.to_bytes());
+        }
+        data.extend(1_717_200_000i64.to_le_bytes());
+        for amount in [1_073_000_000_000_000u64, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000] {
+            data.extend(amount.to_le_bytes());
+        }
+        format!("Program data: {}", base64::encode(data))
+    }
+
+    /// The `Program data:` log of a PumpFun `TradeEvent`, with the curve's
+    /// virtual reserves after the trade.
+    fn trade_event_log(mint: &Pubkey, user: &Pubkey, is_buy: bool, sol_amount: u64, token_amount: u64, reserves: (u64, u64)) -> String {
+        let mut data = TRADE_EVENT_DISCRIMINATOR.to_vec();
+        data.extend(mint.to_bytes());
+        data.extend(sol_amount.to_le_bytes());
+        data.extend(token_amount.to_le_bytes());
+        data.push(is_buy as u8);
+        data.extend(user.to_bytes());
+        data.extend(1_717_200_000i64.to_le_bytes());
+        for amount in [reserves.0, reserves.1, reserves.0 - 30_000_000_000, reserves.1 - 279_900_000_000_000] {
+            data.extend(amount.to_le_bytes());
+        }
+        data.extend(Pubkey::new_unique().to_bytes());
+        for amount in [95, sol_amount * 95 / 10_000] {
+            data.extend(amount.to_le_bytes());
+        }
+        data.extend(Pubkey::new_unique().to_bytes());
+        for amount in [5, sol_amount * 5 / 10_000] {
+            data.extend(amount.to_le_bytes());
+        }
+        format!("Program data: {}", base64::encode(data))
+    }
+
+    #[test]
+    fn v0_accounts_resolve_through_the_loaded_addresses() {
+        let (user, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (bonding_curve, pool, pool_quote_token_account) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let readonly = Pubkey::new_unique();
+        let builder = TxBuilder {
+            static_keys: vec![user],
+            loaded_writable: vec![bonding_curve, pool, pool_quote_token_account],
+            loaded_readonly: vec![readonly],
+            ..Default::default()
+        };
+        let (txn, logs) = builder
+            .instruction(PUMP_PROGRAM, &pump_fun_accounts(&mint, &bonding_curve, &user), swap_data(BUY_DISCRIMINATOR, 34_612_903_225_806, 1_010_000_000))
+            .instruction(PUMP_SWAP_PROGRAM, &pump_swap_accounts(&pool, &user, &mint, &pool_quote_token_account), swap_data(BUY_DISCRIMINATOR, 5_000_000, 2_000_000_000))
+            .log(trade_event_log(&mint, &user, true, 1_000_000_000, 34_612_903_225_806, (31_000_000_000, 1_038_387_096_774_194)))
+            .lamport_change(pool_quote_token_account, 1_500_000_000)
+            .token_balance(Pubkey::new_unique(), &user, &mint, 34_612_903.225806)
+            .build();
+
+        let info = txn.transaction.as_ref().unwrap();
+        let (message, meta) = (info.transaction.as_ref().unwrap().message.as_ref().unwrap(), info.meta.as_ref().unwrap());
+        let keys = resolve_account_keys(message, meta);
+        // The programs and the other accounts of both swaps are static keys
+        let static_count = message.account_keys.len();
+        assert_eq!(keys.len(), static_count + 4);
+        assert_eq!(keys[0], user);
+        assert_eq!(keys[static_count..], [bonding_curve, pool, pool_quote_token_account, readonly]);
+        // The instructions reach the loaded accounts only through indices past the static keys
+        let curve_index = message.instructions[0].accounts[3] as usize;
+        assert!(curve_index >= message.account_keys.len());
+        assert_eq!(keys[curve_index], bonding_curve);
+
+        let trades = TradeInfoFromToken::from_json(txn, logs).unwrap();
+        assert_eq!(trades.len(), 2);
+        let (curve_buy, pool_buy) = (&trades[0], &trades[1]);
+        assert_eq!(curve_buy.instruction_type, InstructionType::PumpBuy);
+        assert_eq!(curve_buy.bonding_curve, bonding_curve.to_string());
+        assert_eq!(curve_buy.mint, mint.to_string());
+        assert_eq!(curve_buy.target, user.to_string());
+        assert_eq!(curve_buy.token_amount, 34_612_903.225806);
+        let curve = curve_buy.bonding_curve_info.as_ref().unwrap();
+        assert_eq!(curve.bonding_curve, bonding_curve);
+        assert_eq!((curve.new_virtual_sol_reserve, curve.new_virtual_token_reserve), (31_000_000_000, 1_038_387_096_774_194));
+
+        assert_eq!(pool_buy.instruction_type, InstructionType::PumpSwapBuy);
+        let pool_info = pool_buy.pool_info.as_ref().unwrap();
+        assert_eq!(pool_info.pool_id, pool);
+        assert_eq!(pool_info.pool_quote_token_account, pool_quote_token_account);
+        assert_eq!(pool_info.base_mint, mint);
+        // Without its event the SOL moved comes from the loaded quote account's balance
+        assert_eq!(pool_buy.volume_change, 1_500_000_000);
+    }
+
+    #[test]
+    fn several_buys_in_one_transaction_are_separate_trades() {
+        let (first, second, mint, bonding_curve) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (txn, logs) = TxBuilder::default()
+            .instruction(PUMP_PROGRAM, &pump_fun_accounts(&mint, &bonding_curve, &first), swap_data(BUY_DISCRIMINATOR, 34_612_903_225_806, 1_010_000_000))
+            .instruction(PUMP_PROGRAM, &pump_fun_accounts(&mint, &bonding_curve, &second), swap_data(BUY_DISCRIMINATOR, 62_499_999_999_999, 2_020_000_000))
+            .instruction(PUMP_PROGRAM, &pump_fun_accounts(&mint, &bonding_curve, &first), swap_data(BUY_DISCRIMINATOR, 14_204_545_454_545, 505_000_000))
+            .log(trade_event_log(&mint, &first, true, 1_000_000_000, 34_612_903_225_806, (31_000_000_000, 1_038_387_096_774_194)))
+            .log(trade_event_log(&mint, &second, true, 2_000_000_000, 62_499_999_999_999, (33_000_000_000, 975_887_096_774_195)))
+            .log(trade_event_log(&mint, &first, true, 500_000_000, 14_204_545_454_545, (33_500_000_000, 961_682_551_319_650)))
+            .token_balance(Pubkey::new_unique(), &first, &mint, 48_817_448.680350)
+            .token_balance(Pubkey::new_unique(), &second, &mint, 62_499_999.999999)
+            .build();
+
+        let trades = TradeInfoFromToken::from_json(txn, logs).unwrap();
+        assert_eq!(trades.len(), 3);
+        assert!(trades.iter().all(|trade| trade.instruction_type == InstructionType::PumpBuy && trade.mint == mint.to_string()));
+        // Each buy keeps its own signer, even though the second buyer's balance is listed last
+        let targets: Vec<&str> = trades.iter().map(|trade| trade.target.as_str()).collect();
+        assert_eq!(targets, [first.to_string(), second.to_string(), first.to_string()]);
+        let volumes: Vec<i64> = trades.iter().map(|trade| trade.volume_change).collect();
+        assert_eq!(volumes, [1_000_000_000, 2_000_000_000, 500_000_000]);
+        let amounts: Vec<Option<u64>> = trades.iter().map(|trade| trade.amount).collect();
+        assert_eq!(amounts, [Some(34_612_903_225_806), Some(62_499_999_999_999), Some(14_204_545_454_545)]);
+        assert_eq!(trades[0].max_sol_cost, Some(1_010_000_000));
+        assert_eq!(trades[1].token_amount, 62_499_999.999999);
+        assert_eq!(trades[2].token_amount, 48_817_448.680350);
+        // The curve after each buy comes from that buy's own event
+        let reserves: Vec<(u64, u64)> = trades
+            .iter()
+            .map(|trade| trade.bonding_curve_info.as_ref().unwrap())
+            .map(|curve| (curve.new_virtual_sol_reserve, curve.new_virtual_token_reserve))
+            .collect();
+        assert_eq!(
+            reserves,
+            [(31_000_000_000, 1_038_387_096_774_194), (33_000_000_000, 975_887_096_774_195), (33_500_000_000, 961_682_551_319_650)]
+        );
+    }
+
+    #[test]
+    fn create_and_dev_buy_are_separate_trades() {
+        let (dev, mint, bonding_curve) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (txn, logs) = TxBuilder::default()
+            .instruction(PUMP_PROGRAM, &pump_fun_create_accounts(&mint, &bonding_curve, &dev), create_data(&dev))
+            .instruction(PUMP_PROGRAM, &pump_fun_accounts(&mint, &bonding_curve, &dev), swap_data(BUY_DISCRIMINATOR, 34_612_903_225_806, 1_010_000_000))
+            .log(create_event_log(&mint, &bonding_curve, &dev))
+            .log(trade_event_log(&mint, &dev, true, 1_000_000_000, 34_612_903_225_806, (31_000_000_000, 1_038_387_096_774_194)))
+            .token_balance(Pubkey::new_unique(), &dev, &mint, 34_612_903.225806)
+            .build();
+
+        let trades = TradeInfoFromToken::from_json(txn, logs).unwrap();
+        assert_eq!(trades.len(), 2);
+        let (create, buy) = (&trades[0], &trades[1]);
+        assert_eq!(create.instruction_type, InstructionType::PumpMint);
+        assert_eq!(buy.instruction_type, InstructionType::PumpBuy);
+        for trade in [create, buy] {
+            assert_eq!(trade.target, dev.to_string());
+            assert_eq!(trade.mint, mint.to_string());
+            assert_eq!(trade.bonding_curve, bonding_curve.to_string());
+            assert_eq!(trade.volume_change, 1_000_000_000);
+            assert_eq!(trade.token_amount, 34_612_903.225806);
+            // The curve is where the dev buy left it, not where the create event started it
+            let curve = trade.bonding_curve_info.as_ref().unwrap();
+            assert_eq!((curve.new_virtual_sol_reserve, curve.new_virtual_token_reserve), (31_000_000_000, 1_038_387_096_774_194));
+        }
+        assert_eq!(create.amount, None);
+        assert_eq!(buy.amount, Some(34_612_903_225_806));
+    }
+
+    #[test]
+    fn buys_of_two_mints_keep_their_own_mint_and_amounts() {
+        let user = Pubkey::new_unique();
+        let (first_mint, first_curve) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (second_mint, second_curve) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (txn, logs) = TxBuilder::default()
+            .instruction(PUMP_PROGRAM, &pump_fun_accounts(&first_mint, &first_curve, &user), swap_data(BUY_DISCRIMINATOR, 34_612_903_225_806, 1_010_000_000))
+            .instruction(PUMP_PROGRAM, &pump_fun_accounts(&second_mint, &second_curve, &user), swap_data(BUY_DISCRIMINATOR, 100_000_000_000, 3_030_000_000))
+            .log(trade_event_log(&first_mint, &user, true, 1_000_000_000, 34_612_903_225_806, (31_000_000_000, 1_038_387_096_774_194)))
+            .log(trade_event_log(&second_mint, &user, true, 3_000_000_000, 100_000_000_000, (60_000_000_000, 536_500_000_000_000)))
+            .token_balance(Pubkey::new_unique(), &user, &first_mint, 34_612_903.225806)
+            .token_balance(Pubkey::new_unique(), &user, &second_mint, 100_000.0)
+            .build();
+
+        let trades = TradeInfoFromToken::from_json(txn, logs).unwrap();
+        assert_eq!(trades.len(), 2);
+        let (first, second) = (&trades[0], &trades[1]);
+        assert_eq!((first.mint.as_str(), first.bonding_curve.as_str()), (first_mint.to_string().as_str(), first_curve.to_string().as_str()));
+        assert_eq!((second.mint.as_str(), second.bonding_curve.as_str()), (second_mint.to_string().as_str(), second_curve.to_string().as_str()));
+        assert!(trades.iter().all(|trade| trade.target == user.to_string()));
+        assert_eq!((first.volume_change, second.volume_change), (1_000_000_000, 3_000_000_000));
+        assert_eq!((first.amount, second.amount), (Some(34_612_903_225_806), Some(100_000_000_000)));
+        assert_eq!((first.token_amount, second.token_amount), (34_612_903.225806, 100_000.0));
+        let second_curve_info = second.bonding_curve_info.as_ref().unwrap();
+        assert_eq!(second_curve_info.bonding_curve, second_curve);
+        assert_eq!(second_curve_info.new_virtual_sol_reserve, 60_000_000_000);
+    }
+}
@@ -0,0 +1,379 @@
+use colored::Colorize;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, OnceLock, RwLock},
+    time::{Duration, SystemTime},
+};
+use tokio::sync::watch;
+
+use super::position::PositionLimits;
+use super::sizing::CopySizing;
+use super::strategy::StrategyBook;
+use crate::common::{
+    logger::Logger,
+    settings::{config_path, settings, MirrorExitMode, Settings},
+};
+
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Trading parameters that can change while the bot is running.
+///
+/// A snapshot is immutable; a reload swaps in a whole new snapshot, so a
+/// reader never sees half of an update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradingParams {
+    pub slippage: u64,
+    pub copy_targets: Vec<String>,
+    pub max_wait_time_ms: u64,
+    pub position_limits: PositionLimits,
+    pub strategies: StrategyBook,
+    pub mirror_exit: MirrorExitMode,
+    /// Per-target overrides of `mirror_exit`.
+    pub target_mirror_exits: HashMap<String, MirrorExitMode>,
+    pub sizing: CopySizing,
+    /// Per-target overrides of `sizing`.
+    pub target_sizing: HashMap<String, CopySizing>,
+}
+
+impl TradingParams {
+    pub fn from_settings(settings: &Settings) -> Self {
+        let target_strategies = settings
+            .copy
+            .targets
+            .iter()
+            .filter_map(|target| {
+                target
+                    .strategy
+                    .as_ref()
+                    .map(|strategy| (target.address.clone(), strategy.clone()))
+            })
+            .collect();
+
+        Self {
+            slippage: settings.swap.slippage,
+            copy_targets: settings.copy_target_addresses(),
+            max_wait_time_ms: settings.selling.max_wait_time_ms,
+            position_limits: PositionLimits {
+                max_open_positions: settings.positions.max_open_positions,
+                max_exposure_sol: settings.positions.max_exposure_sol,
+            },
+            strategies: StrategyBook::from_settings(&settings.strategy, target_strategies),
+            mirror_exit: settings.copy.mirror_exit,
+            target_mirror_exits: settings
+                .copy
+                .targets
+                .iter()
+                .filter_map(|target| target.mirror_exit.map(|mode| (target.address.clone(), mode)))
+                .collect(),
+            sizing: match &settings.copy.sizing {
+                Some(sizing) => CopySizing::new(sizing.clone(), settings.swap.amount_in),
+                None => CopySizing::fixed(settings.swap.amount_in),
+            },
+            target_sizing: settings
+                .copy
+                .targets
+                .iter()
+                .filter_map(|target| {
+                    target.sizing.as_ref().map(|sizing| {
+                        (
+                            target.address.clone(),
+                            CopySizing::new(sizing.clone(), settings.swap.amount_in),
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    pub fn sizing_for(&self, target: &str) -> &CopySizing {
+        self.target_sizing.get(target).unwrap_or(&self.sizing)
+    }
+
+    pub fn mirror_exit_for(&self, target: &str) -> MirrorExitMode {
+        self.target_mirror_exits
+            .get(target)
+            .copied()
+            .unwrap_or(self.mirror_exit)
+    }
+
+    /// One line per changed field, in `field: old -> new` form.
+    pub fn diff(&self, other: &TradingParams) -> Vec<String> {
+        let mut changes = Vec::new();
+        macro_rules! compare {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field != other.$field {
+                        changes.push(format!(
+                            "{}: {:?} -> {:?}",
+                            stringify!($field),
+                            self.$field,
+                            other.$field
+                        ));
+                    }
+                )*
+            };
+        }
+        compare!(
+            slippage,
+            copy_targets,
+            max_wait_time_ms,
+            position_limits,
+            mirror_exit,
+            target_mirror_exits,
+            sizing,
+            target_sizing
+        );
+        let strategies = &self.strategies;
+        let other_strategies = &other.strategies;
+        if strategies.default_profile != other_strategies.default_profile {
+            changes.push(format!(
+                "strategy.default_profile: {:?} -> {:?}",
+                strategies.default_profile, other_strategies.default_profile
+            ));
+        }
+        if strategies.launch_sources != other_strategies.launch_sources {
+            changes.push(format!(
+                "strategy.launch_sources: {:?} -> {:?}",
+                strategies.launch_sources, other_strategies.launch_sources
+            ));
+        }
+        if strategies.copy_targets != other_strategies.copy_targets {
+            changes.push(format!(
+                "copy target strategies: {:?} -> {:?}",
+                strategies.copy_targets, other_strategies.copy_targets
+            ));
+        }
+        for name in strategies.changed_profiles(other_strategies) {
+            changes.push(format!("strategy.profiles.{}: updated", name));
+        }
+        changes
+    }
+}
+
+struct ParamsState {
+    current: RwLock<Arc<TradingParams>>,
+    tx: watch::Sender<Arc<TradingParams>>,
+}
+
+impl ParamsState {
+    fn new(initial: TradingParams) -> Self {
+        let initial = Arc::new(initial);
+        let (tx, _) = watch::channel(initial.clone());
+        Self {
+            current: RwLock::new(initial),
+            tx,
+        }
+    }
+
+    fn apply(&self, new_params: TradingParams) -> Vec<String> {
+        let new_params = Arc::new(new_params);
+        let changes = {
+            let mut current = self.current.write().unwrap();
+            let changes = current.diff(&new_params);
+            if !changes.is_empty() {
+                *current = new_params.clone();
+            }
+            changes
+        };
+        if !changes.is_empty() {
+            self.tx.send_replace(new_params);
+        }
+        changes
+    }
+}
+
+static PARAMS: OnceLock<ParamsState> = OnceLock::new();
+static WATCHER_STARTED: OnceLock<()> = OnceLock::new();
+
+fn state() -> &'static ParamsState {
+    PARAMS.get_or_init(|| ParamsState::new(TradingParams::from_settings(settings())))
+}
+
+/// Current parameter snapshot.
+pub fn params() -> Arc<TradingParams> {
+    state().current.read().unwrap().clone()
+}
+
+/// Receiver that is notified every time a reload changes the parameters.
+pub fn subscribe_params() -> watch::Receiver<Arc<TradingParams>> {
+    state().tx.subscribe()
+}
+
+/// Replaces the current snapshot and returns what changed.
+pub fn apply_params(new_params: TradingParams) -> Vec<String> {
+    state().apply(new_params)
+}
+
+/// Re-reads the config file and applies it. An invalid file is logged and
+/// ignored, leaving the running parameters untouched.
+pub fn reload_params(logger: &Logger) {
+    let path = PathBuf::from(config_path());
+    let settings = match Settings::load(&path) {
+        Ok(settings) => settings,
+        Err(errors) => {
+            logger.log(format!(
+                "[CONFIG RELOAD] => {} is invalid ({} error(s)), keeping current parameters:",
+                path.display(),
+                errors.len()
+            ).red().to_string());
+            for error in errors.iter() {
+                logger.log(format!("\t * {}", error).red().to_string());
+            }
+            return;
+        }
+    };
+
+    let changes = apply_params(TradingParams::from_settings(&settings));
+    if changes.is_empty() {
+        logger.log("[CONFIG RELOAD] => No parameter changes".to_string());
+        return;
+    }
+    logger.log(format!("[CONFIG RELOAD] => Applied {} change(s):", changes.len()).green().to_string());
+    for change in changes.iter() {
+        logger.log(format!("\t * {}", change).green().to_string());
+    }
+}
+
+/// Starts the background task that reloads parameters when the config file
+/// changes on disk or, on Unix, when the process receives SIGHUP.
+/// Calling it more than once has no effect.
+pub fn spawn_config_watcher() {
+    if WATCHER_STARTED.set(()).is_err() {
+        return;
+    }
+    // Make sure the initial snapshot exists before anything is reloaded.
+    state();
+
+    tokio::spawn(async move {
+        let logger = Logger::new("[CONFIG-WATCHER] => ".magenta().bold().to_string());
+        let path = PathBuf::from(config_path());
+        let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last_modified: Option<SystemTime> = modified(&path);
+
+        #[cfg(unix)]
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+            Ok(signal) => Some(signal),
+            Err(e) => {
+                logger.log(format!("Failed to install SIGHUP handler: {}", e).red().to_string());
+                None
+            }
+        };
+
+        let mut interval = tokio::time::interval(CONFIG_POLL_INTERVAL);
+        loop {
+            #[cfg(unix)]
+            {
+                let hangup_received = async {
+                    match hangup.as_mut() {
+                        Some(signal) => signal.recv().await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = hangup_received => {
+                        logger.log("[CONFIG RELOAD] => SIGHUP received".to_string());
+                        last_modified = modified(&path);
+                        reload_params(&logger);
+                        continue;
+                    }
+                }
+            }
+            #[cfg(not(unix))]
+            interval.tick().await;
+
+            let current = modified(&path);
+            if current.is_some() && current != last_modified {
+                last_modified = current;
+                reload_params(&logger);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+
+    fn params_from(name: &str, extra: &str) -> TradingParams {
+        let dir = std::env::temp_dir().join(format!("params-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.toml", name));
+        let config = format!(
+            r#"
+[rpc]
+http = "https://rpc.example"
+
+[grpc]
+http = "https://grpc.example"
+
+[signer]
+keystore_path = "wallet.keystore.json"
+{extra}
+"#
+        );
+        std::fs::write(&path, config).unwrap();
+        TradingParams::from_settings(&Settings::load(&path).unwrap())
+    }
+
+    const SWAP: &str = "[swap]\namount_in = 0.1\nslippage = 10\nuse_jito = false\ntime_exceed = 60\n";
+
+    #[test]
+    fn diff_of_unchanged_params_is_empty() {
+        let params = params_from("unchanged", SWAP);
+        assert!(params.diff(&params.clone()).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_each_changed_field() {
+        let before = params_from("before", SWAP);
+        let after = params_from(
+            "after",
+            &format!(
+                "{}\n[[copy.targets]]\naddress = \"{TARGET}\"\nmirror_exit = \"full\"\n\n[selling]\nmax_wait_time_ms = 5000\n\n[strategy.profiles.default]\nretracement_min_hold_secs = 10\n",
+                SWAP.replace("slippage = 10", "slippage = 25")
+            ),
+        );
+        let changes = before.diff(&after);
+        assert_eq!(
+            changes,
+            vec![
+                "slippage: 10 -> 25".to_string(),
+                format!("copy_targets: [] -> [{:?}]", TARGET),
+                "max_wait_time_ms: 60000 -> 5000".to_string(),
+                format!("target_mirror_exits: {{}} -> {{{:?}: Full}}", TARGET),
+                "strategy.profiles.default: updated".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn buy_size_changes_show_up_as_sizing() {
+        let before = params_from("small", SWAP);
+        let after = params_from("large", &SWAP.replace("amount_in = 0.1", "amount_in = 0.5"));
+        let changes = before.diff(&after);
+        assert_eq!(changes.len(), 1, "{:?}", changes);
+        assert!(changes[0].starts_with("sizing: "), "{:?}", changes);
+        assert_eq!(after.sizing_for(TARGET).amount_in(1.0, None), 0.5);
+    }
+
+    #[test]
+    fn apply_swaps_the_snapshot_and_notifies_only_on_change() {
+        let before = params_from("apply-before", SWAP);
+        let after = params_from("apply-after", &SWAP.replace("slippage = 10", "slippage = 50"));
+        let state = ParamsState::new(before.clone());
+        let mut updates = state.tx.subscribe();
+
+        assert!(state.apply(before.clone()).is_empty());
+        assert!(!updates.has_changed().unwrap());
+        assert_eq!(**state.current.read().unwrap(), before);
+
+        assert_eq!(state.apply(after.clone()), vec!["slippage: 10 -> 50".to_string()]);
+        assert!(updates.has_changed().unwrap());
+        assert_eq!(**updates.borrow_and_update(), after);
+        assert_eq!(**state.current.read().unwrap(), after);
+    }
+}
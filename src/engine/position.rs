@@ -0,0 +1,595 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Mutex,
+    time::Duration,
+};
+use colored::Colorize;
+use tokio::time::Instant;
+
+use super::journal::CostBasis;
+use super::store::{store, FillRecord, PositionRecord, TrackingRecord, TrackingUpdate};
+use super::swap::SwapProtocol;
+use crate::common::{config::Status, logger::Logger};
+
+/// One position, from the moment a buy is attempted until it is sold or fails.
+///
+/// Status moves `Buying -> Bought -> Selling -> Sold`, with `Selling -> Bought`
+/// after a partial or failed sell and `Buying -> Failure` after a failed buy.
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub mint: String,
+    pub status: Status,
+    pub buy_price: f64,
+    pub sell_price: f64,
+    /// SOL still committed to the position; reduced by partial sells.
+    pub sol_spent: f64,
+    pub opened_at: Option<Instant>,
+    pub updated_at: Instant,
+    /// Copy target whose buy opened the position.
+    pub target: Option<String>,
+    /// Cost basis and realized PnL from confirmed fills.
+    pub basis: CostBasis,
+    /// Protocol the position was last swapped or priced on; `Auto` until known.
+    pub protocol: SwapProtocol,
+    /// Pool the position trades on, for protocols that have one.
+    pub pool: Option<String>,
+}
+
+impl Position {
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, Status::Buying | Status::Bought | Status::Selling)
+    }
+
+    /// Protocol to swap on: the AMM once the mint is known to trade on one,
+    /// otherwise left to the router so a migration mid-position is picked up.
+    pub fn swap_protocol(&self) -> SwapProtocol {
+        match self.protocol {
+            SwapProtocol::PumpSwap
+            | SwapProtocol::RaydiumAmm
+            | SwapProtocol::RaydiumCpmm
+            | SwapProtocol::MeteoraDlmm => self.protocol.clone(),
+            _ => SwapProtocol::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpenRejection {
+    AlreadyOpen,
+    MaxPositions(usize),
+    MaxExposure { exposure: f64, limit: f64 },
+}
+
+impl fmt::Display for OpenRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenRejection::AlreadyOpen => write!(f, "position already open"),
+            OpenRejection::MaxPositions(max) => write!(f, "{} positions already open", max),
+            OpenRejection::MaxExposure { exposure, limit } => write!(
+                f,
+                "exposure would be {:.4} SOL, limit is {:.4} SOL",
+                exposure, limit
+            ),
+        }
+    }
+}
+
+/// Limits applied when opening a position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionLimits {
+    pub max_open_positions: usize,
+    pub max_exposure_sol: Option<f64>,
+}
+
+/// Tracks every position by mint. Each position has its own state, so
+/// buying continues while other positions are open, up to `PositionLimits`.
+///
+/// Every change is also written to the position store when one is open.
+pub struct PositionManager {
+    positions: Mutex<HashMap<String, Position>>,
+    logger: Logger,
+}
+
+impl Default for PositionManager {
+    fn default() -> Self {
+        Self {
+            positions: Mutex::new(HashMap::new()),
+            logger: Logger::new("[POSITIONS] => ".to_string()),
+        }
+    }
+}
+
+impl PositionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts back a position loaded from the store, without writing it again.
+    pub fn restore(&self, position: Position) {
+        self.positions
+            .lock()
+            .unwrap()
+            .insert(position.mint.clone(), position);
+    }
+
+    /// Reserves a position for `mint` in the `Buying` state, committing `sol_amount`.
+    /// The store is written after the lock is released, so other trades are
+    /// not held up by disk I/O.
+    pub fn try_open(
+        &self,
+        mint: &str,
+        sol_amount: f64,
+        target: Option<&str>,
+        limits: PositionLimits,
+    ) -> Result<(), OpenRejection> {
+        let position = self.reserve(mint, sol_amount, target, limits)?;
+        self.persist(&position);
+        Ok(())
+    }
+
+    fn reserve(
+        &self,
+        mint: &str,
+        sol_amount: f64,
+        target: Option<&str>,
+        limits: PositionLimits,
+    ) -> Result<Position, OpenRejection> {
+        let mut positions = self.positions.lock().unwrap();
+        if positions.get(mint).is_some_and(|position| position.is_active()) {
+            return Err(OpenRejection::AlreadyOpen);
+        }
+
+        let active: Vec<&Position> = positions.values().filter(|p| p.is_active()).collect();
+        if active.len() >= limits.max_open_positions {
+            return Err(OpenRejection::MaxPositions(limits.max_open_positions));
+        }
+        if let Some(limit) = limits.max_exposure_sol {
+            let exposure = active.iter().map(|p| p.sol_spent).sum::<f64>() + sol_amount;
+            if exposure > limit {
+                return Err(OpenRejection::MaxExposure { exposure, limit });
+            }
+        }
+
+        let position = Position {
+            mint: mint.to_string(),
+            status: Status::Buying,
+            buy_price: 0_f64,
+            sell_price: 0_f64,
+            sol_spent: sol_amount,
+            opened_at: None,
+            updated_at: Instant::now(),
+            target: target.map(str::to_string),
+            basis: CostBasis::default(),
+            protocol: SwapProtocol::Auto,
+            pool: None,
+        };
+        positions.insert(mint.to_string(), position.clone());
+        Ok(position)
+    }
+
+    /// Records a sent buy together with the position's initial tracking state.
+    pub fn mark_bought(&self, mint: &str, buy_price: f64, tracking: &TrackingRecord) -> bool {
+        let updated = self.transition(mint, &[Status::Buying], Status::Bought, |position| {
+            position.buy_price = buy_price;
+            position.opened_at = Some(Instant::now());
+        });
+        match updated {
+            Some(position) => {
+                self.commit(&position, None, TrackingUpdate::Save(tracking));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn mark_failed(&self, mint: &str) -> bool {
+        self.transition_and_persist(mint, &[Status::Buying], Status::Failure, |position| {
+            position.sol_spent = 0_f64;
+        })
+    }
+
+    /// Claims the position for a sell. Returns false if it is not `Bought`,
+    /// e.g. because another exit is already selling it.
+    pub fn begin_sell(&self, mint: &str) -> bool {
+        self.transition_and_persist(mint, &[Status::Bought], Status::Selling, |_| {})
+    }
+
+    /// Records a sent sell of `fraction` (0..=1) of the remaining position.
+    /// A full sell also drops the position's tracking state from the store.
+    pub fn finish_sell(&self, mint: &str, sell_price: f64, fraction: f64) -> bool {
+        let next = if fraction >= 1.0 { Status::Sold } else { Status::Bought };
+        let updated = self.transition(mint, &[Status::Selling], next, |position| {
+            position.sell_price = sell_price;
+            position.sol_spent *= (1.0 - fraction).max(0.0);
+        });
+        match updated {
+            Some(position) => {
+                let tracking = if position.status == Status::Sold {
+                    TrackingUpdate::Remove
+                } else {
+                    TrackingUpdate::Keep
+                };
+                self.commit(&position, None, tracking);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies a confirmed fill to the position's cost basis and appends it to
+    /// the journal. Returns the updated position and the PnL the fill realized.
+    pub fn apply_fill(&self, fill: &FillRecord) -> Option<(Position, f64)> {
+        let (position, realized) = {
+            let mut positions = self.positions.lock().unwrap();
+            let position = positions.get_mut(&fill.mint)?;
+            let realized = position.basis.apply(fill);
+            position.updated_at = Instant::now();
+            (position.clone(), realized)
+        };
+        self.commit(&position, Some(fill), TrackingUpdate::Keep);
+        Some((position, realized))
+    }
+
+    /// Records the protocol and pool the position trades on. Returns true if
+    /// they changed, e.g. after the bonding curve migrated.
+    pub fn set_route(&self, mint: &str, protocol: SwapProtocol, pool: Option<String>) -> bool {
+        let updated = {
+            let mut positions = self.positions.lock().unwrap();
+            match positions.get_mut(mint) {
+                Some(position) if position.protocol != protocol || position.pool != pool => {
+                    position.protocol = protocol;
+                    position.pool = pool;
+                    position.updated_at = Instant::now();
+                    Some(position.clone())
+                }
+                _ => None,
+            }
+        };
+        match updated {
+            Some(position) => {
+                self.persist(&position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn sell_failed(&self, mint: &str) -> bool {
+        self.transition_and_persist(mint, &[Status::Selling], Status::Bought, |_| {})
+    }
+
+    /// Closes a position whose tokens are no longer in the wallet, e.g. sold
+    /// outside the bot while it was stopped.
+    pub fn mark_closed(&self, mint: &str) -> bool {
+        let closed = self.transition_and_persist(
+            mint,
+            &[Status::Bought, Status::Selling],
+            Status::Sold,
+            |position| position.sol_spent = 0_f64,
+        );
+        closed || self.mark_failed(mint)
+    }
+
+    pub fn get(&self, mint: &str) -> Option<Position> {
+        self.positions.lock().unwrap().get(mint).cloned()
+    }
+
+    pub fn is_active(&self, mint: &str) -> bool {
+        self.positions
+            .lock()
+            .unwrap()
+            .get(mint)
+            .is_some_and(|position| position.is_active())
+    }
+
+    /// Snapshot of positions currently held and not being sold.
+    pub fn held(&self) -> Vec<Position> {
+        self.positions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|position| position.status == Status::Bought)
+            .cloned()
+            .collect()
+    }
+
+    /// Held positions opened longer ago than `max_hold`.
+    pub fn timed_out(&self, max_hold: Duration) -> Vec<Position> {
+        let now = Instant::now();
+        self.held()
+            .into_iter()
+            .filter(|position| {
+                position
+                    .opened_at
+                    .is_some_and(|opened_at| now.duration_since(opened_at) > max_hold)
+            })
+            .collect()
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.positions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|position| position.is_active())
+            .count()
+    }
+
+    pub fn exposure(&self) -> f64 {
+        self.positions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|position| position.is_active())
+            .map(|position| position.sol_spent)
+            .sum()
+    }
+
+    /// Applies the transition and returns the updated position.
+    fn transition(
+        &self,
+        mint: &str,
+        from: &[Status],
+        to: Status,
+        update: impl FnOnce(&mut Position),
+    ) -> Option<Position> {
+        let mut positions = self.positions.lock().unwrap();
+        match positions.get_mut(mint) {
+            Some(position) if from.contains(&position.status) => {
+                position.status = to;
+                position.updated_at = Instant::now();
+                update(position);
+                Some(position.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn transition_and_persist(
+        &self,
+        mint: &str,
+        from: &[Status],
+        to: Status,
+        update: impl FnOnce(&mut Position),
+    ) -> bool {
+        match self.transition(mint, from, to, update) {
+            Some(position) => {
+                self.persist(&position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn persist(&self, position: &Position) {
+        if let Some(store) = store() {
+            if let Err(e) = store.save_position(&PositionRecord::from(position)) {
+                self.logger.log(format!("Failed to save position {}: {}", position.mint, e).red().to_string());
+            }
+        }
+    }
+
+    fn commit(&self, position: &Position, fill: Option<&FillRecord>, tracking: TrackingUpdate<'_>) {
+        if let Some(store) = store() {
+            if let Err(e) = store.commit(&PositionRecord::from(position), fill, tracking) {
+                self.logger.log(format!("Failed to save position {}: {}", position.mint, e).red().to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::store::FillSide;
+
+    const MINT: &str = "So11111111111111111111111111111111111111112";
+    const OTHER_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const THIRD_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+
+    fn limits(max_open_positions: usize, max_exposure_sol: Option<f64>) -> PositionLimits {
+        PositionLimits { max_open_positions, max_exposure_sol }
+    }
+
+    fn tracking() -> TrackingRecord {
+        TrackingRecord {
+            strategy: "default".to_string(),
+            top_pnl: 0.0,
+            completed_intervals: Vec::new(),
+            loss_since_ms: None,
+            exit_reason: None,
+        }
+    }
+
+    fn fill(side: FillSide, token_delta: i64, sol_delta: i64) -> FillRecord {
+        FillRecord {
+            signature: format!("{:?}-{}", side, token_delta),
+            mint: MINT.to_string(),
+            side,
+            slot: 1,
+            target: None,
+            token_delta,
+            token_decimals: 6,
+            sol_delta,
+            fee: 0,
+            tip: 0,
+            rent: 0,
+            reason: None,
+            timestamp_ms: 1_717_200_000_000,
+        }
+    }
+
+    /// A manager holding `MINT`, bought for 0.1 SOL.
+    fn holding() -> PositionManager {
+        let positions = PositionManager::new();
+        positions.try_open(MINT, 0.1, Some("target"), limits(5, None)).unwrap();
+        assert!(positions.mark_bought(MINT, 0.00001, &tracking()));
+        positions
+    }
+
+    fn status(positions: &PositionManager, mint: &str) -> Status {
+        positions.get(mint).unwrap().status
+    }
+
+    #[test]
+    fn try_open_reserves_a_buying_position() {
+        let positions = PositionManager::new();
+        positions.try_open(MINT, 0.1, Some("target"), limits(5, None)).unwrap();
+        let position = positions.get(MINT).unwrap();
+        assert_eq!(position.status, Status::Buying);
+        assert_eq!(position.sol_spent, 0.1);
+        assert_eq!(position.target.as_deref(), Some("target"));
+        assert_eq!(position.protocol, SwapProtocol::Auto);
+        assert_eq!(position.opened_at, None);
+        assert!(positions.is_active(MINT));
+    }
+
+    #[test]
+    fn try_open_enforces_the_limits() {
+        let positions = PositionManager::new();
+        positions.try_open(MINT, 0.3, None, limits(2, Some(0.5))).unwrap();
+        assert_eq!(positions.try_open(MINT, 0.1, None, limits(2, Some(0.5))), Err(OpenRejection::AlreadyOpen));
+        assert_eq!(
+            positions.try_open(OTHER_MINT, 0.3, None, limits(2, Some(0.5))),
+            Err(OpenRejection::MaxExposure { exposure: 0.6, limit: 0.5 })
+        );
+        positions.try_open(OTHER_MINT, 0.2, None, limits(2, Some(0.5))).unwrap();
+        assert_eq!(
+            positions.try_open(THIRD_MINT, 0.0, None, limits(2, None)),
+            Err(OpenRejection::MaxPositions(2))
+        );
+        assert_eq!(positions.active_count(), 2);
+        assert!((positions.exposure() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn closed_positions_free_their_slot_and_can_reopen() {
+        let positions = PositionManager::new();
+        positions.try_open(MINT, 0.3, None, limits(1, Some(0.3))).unwrap();
+        assert!(positions.mark_failed(MINT));
+        assert_eq!(status(&positions, MINT), Status::Failure);
+        assert_eq!(positions.exposure(), 0.0);
+
+        positions.try_open(OTHER_MINT, 0.3, None, limits(1, Some(0.3))).unwrap();
+        assert!(positions.mark_bought(OTHER_MINT, 0.00001, &tracking()));
+        assert!(positions.begin_sell(OTHER_MINT));
+        assert!(positions.finish_sell(OTHER_MINT, 0.00002, 1.0));
+        assert_eq!(status(&positions, OTHER_MINT), Status::Sold);
+
+        positions.try_open(MINT, 0.3, None, limits(1, Some(0.3))).unwrap();
+        assert_eq!(status(&positions, MINT), Status::Buying);
+    }
+
+    #[test]
+    fn transitions_only_apply_from_their_source_state() {
+        let positions = PositionManager::new();
+        assert!(!positions.mark_bought(MINT, 0.00001, &tracking()));
+        positions.try_open(MINT, 0.1, None, limits(5, None)).unwrap();
+        assert!(!positions.begin_sell(MINT));
+        assert!(!positions.sell_failed(MINT));
+        assert!(!positions.finish_sell(MINT, 0.00002, 1.0));
+
+        assert!(positions.mark_bought(MINT, 0.00001, &tracking()));
+        assert!(positions.get(MINT).unwrap().opened_at.is_some());
+        assert!(!positions.mark_bought(MINT, 0.00001, &tracking()));
+        assert!(!positions.mark_failed(MINT));
+        assert_eq!(positions.held().len(), 1);
+    }
+
+    #[test]
+    fn begin_sell_claims_the_position_once() {
+        let positions = holding();
+        assert!(positions.begin_sell(MINT));
+        assert!(!positions.begin_sell(MINT));
+        assert_eq!(status(&positions, MINT), Status::Selling);
+        assert!(positions.held().is_empty());
+        // Still counts against the limits while it sells
+        assert_eq!(positions.active_count(), 1);
+
+        assert!(positions.sell_failed(MINT));
+        assert_eq!(status(&positions, MINT), Status::Bought);
+        assert!(positions.begin_sell(MINT));
+    }
+
+    #[test]
+    fn partial_sells_keep_the_position_and_scale_its_exposure() {
+        let positions = holding();
+        assert!(positions.begin_sell(MINT));
+        assert!(positions.finish_sell(MINT, 0.00002, 0.4));
+        let position = positions.get(MINT).unwrap();
+        assert_eq!(position.status, Status::Bought);
+        assert_eq!(position.sell_price, 0.00002);
+        assert!((position.sol_spent - 0.06).abs() < 1e-12);
+
+        assert!(positions.begin_sell(MINT));
+        assert!(positions.finish_sell(MINT, 0.00003, 1.0));
+        let position = positions.get(MINT).unwrap();
+        assert_eq!(position.status, Status::Sold);
+        assert_eq!(position.sol_spent, 0.0);
+        assert!(!positions.is_active(MINT));
+    }
+
+    #[test]
+    fn fills_build_the_basis_through_a_partial_sell() {
+        let positions = holding();
+        let (_, realized) = positions.apply_fill(&fill(FillSide::Buy, 1_000_000_000, -100_000_000)).unwrap();
+        assert_eq!(realized, 0.0);
+
+        let (position, realized) = positions.apply_fill(&fill(FillSide::Sell, -250_000_000, 40_000_000)).unwrap();
+        assert!((realized - 0.015).abs() < 1e-12, "{}", realized);
+        assert_eq!(position.basis.token_amount, 750_000_000);
+        assert!((position.basis.cost_sol - 0.075).abs() < 1e-12);
+        assert!((position.basis.entry_price_sol - 0.0001).abs() < 1e-12);
+
+        let (position, realized) = positions.apply_fill(&fill(FillSide::Sell, -750_000_000, 60_000_000)).unwrap();
+        assert!((realized + 0.015).abs() < 1e-12, "{}", realized);
+        assert_eq!(position.basis.token_amount, 0);
+        assert!(position.basis.realized_sol.abs() < 1e-12);
+
+        let unknown = FillRecord { mint: OTHER_MINT.to_string(), ..fill(FillSide::Buy, 1, -1) };
+        assert!(positions.apply_fill(&unknown).is_none());
+    }
+
+    #[test]
+    fn set_route_reports_only_changes() {
+        let positions = holding();
+        let pool = Some("pool".to_string());
+        assert_eq!(positions.get(MINT).unwrap().swap_protocol(), SwapProtocol::Auto);
+        assert!(positions.set_route(MINT, SwapProtocol::PumpSwap, pool.clone()));
+        assert!(!positions.set_route(MINT, SwapProtocol::PumpSwap, pool.clone()));
+        let position = positions.get(MINT).unwrap();
+        assert_eq!(position.pool, pool);
+        assert_eq!(position.swap_protocol(), SwapProtocol::PumpSwap);
+
+        // Curve protocols stay with the router, which notices a migration
+        assert!(positions.set_route(MINT, SwapProtocol::PumpFun, None));
+        assert_eq!(positions.get(MINT).unwrap().swap_protocol(), SwapProtocol::Auto);
+        assert!(!positions.set_route(OTHER_MINT, SwapProtocol::PumpSwap, None));
+    }
+
+    #[test]
+    fn mark_closed_settles_held_and_pending_positions() {
+        let positions = holding();
+        assert!(positions.mark_closed(MINT));
+        assert_eq!(status(&positions, MINT), Status::Sold);
+
+        positions.try_open(OTHER_MINT, 0.1, None, limits(5, None)).unwrap();
+        assert!(positions.mark_closed(OTHER_MINT));
+        assert_eq!(status(&positions, OTHER_MINT), Status::Failure);
+        assert!(!positions.mark_closed(THIRD_MINT));
+    }
+
+    #[test]
+    fn timed_out_lists_held_positions_past_the_limit() {
+        let positions = holding();
+        let mut old = positions.get(MINT).unwrap();
+        old.mint = OTHER_MINT.to_string();
+        old.opened_at = Instant::now().checked_sub(Duration::from_secs(120));
+        positions.restore(old);
+
+        let timed_out: Vec<String> =
+            positions.timed_out(Duration::from_secs(60)).into_iter().map(|position| position.mint).collect();
+        assert_eq!(timed_out, vec![OTHER_MINT.to_string()]);
+    }
+}
@@ -0,0 +1,570 @@
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use std::{collections::HashMap, str::FromStr, sync::Mutex};
+use tokio::time::Instant;
+
+use super::monitor::{BondingCurveInfo, PoolInfo};
+use super::swap::SwapProtocol;
+use crate::common::{
+    config::{AppState, SwapConfig},
+    logger::Logger,
+};
+use crate::core::signer::SharedSigner;
+use crate::dex::{
+    meteora_dbc::{self, DbcPool, MeteoraDbc},
+    meteora_dlmm::{self, DlmmPair, MeteoraDlmm},
+    pump_fun::{Pump, PUMP_PROGRAM},
+    pump_quote::{PumpCurve, PumpSwapPool, Quote},
+    pump_swap::PumpSwap,
+    raydium_amm::{self, AmmPool, RaydiumAmm},
+    raydium_cpmm::{self, CpmmPool, RaydiumCpmm},
+    raydium_launchpad::{self, LaunchpadPool, RaydiumLaunchpad},
+};
+
+pub const PUMP_SWAP_PROGRAM: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
+/// Index of the pool a migration creates; only the canonical pool is looked up.
+const CANONICAL_POOL_INDEX: u16 = 0;
+
+// BondingCurve: discriminator (8) | virtual_token_reserves | virtual_sol_reserves |
+// real_token_reserves | real_sol_reserves | token_total_supply | complete (bool)
+const BONDING_CURVE_VIRTUAL_TOKEN_RESERVES_OFFSET: usize = 8;
+const BONDING_CURVE_VIRTUAL_SOL_RESERVES_OFFSET: usize = 8 + 8;
+const BONDING_CURVE_REAL_TOKEN_RESERVES_OFFSET: usize = 8 + 2 * 8;
+const BONDING_CURVE_COMPLETE_OFFSET: usize = 8 + 5 * 8;
+// Pool: discriminator (8) | bump (u8) | index (u16) | creator | base_mint | quote_mint |
+// lp_mint | pool_base_token_account | pool_quote_token_account | lp_supply (u64) | coin_creator
+const POOL_BASE_MINT_OFFSET: usize = 8 + 1 + 2 + 32;
+const POOL_QUOTE_MINT_OFFSET: usize = POOL_BASE_MINT_OFFSET + 32;
+const POOL_BASE_TOKEN_ACCOUNT_OFFSET: usize = POOL_QUOTE_MINT_OFFSET + 2 * 32;
+const POOL_QUOTE_TOKEN_ACCOUNT_OFFSET: usize = POOL_BASE_TOKEN_ACCOUNT_OFFSET + 32;
+const POOL_COIN_CREATOR_OFFSET: usize = POOL_QUOTE_TOKEN_ACCOUNT_OFFSET + 32 + 8;
+// SPL token account layout: mint (32) | owner (32) | amount (u64)
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+/// Signer, instructions and token price returned by the protocol builders.
+pub type SwapIxn = (SharedSigner, Vec<Instruction>, f64);
+/// A built swap and the route it was built on.
+pub type RoutedSwapIxn = (SharedSigner, Vec<Instruction>, f64, Route);
+
+lazy_static::lazy_static! {
+    /// PumpSwap pool and coin creator seen by the transaction parser, by mint.
+    /// Reserves are not kept: they are stale by the next trade.
+    static ref POOL_HINTS: Mutex<HashMap<String, (Pubkey, Pubkey)>> = Mutex::new(HashMap::new());
+    /// Raydium and Meteora pools found or seen for a mint, so the program
+    /// scan runs at most once per mint.
+    static ref KNOWN_POOLS: Mutex<HashMap<String, (SwapProtocol, Pubkey)>> = Mutex::new(HashMap::new());
+    /// Fee rates the last parsed trade on each mint's Pump.fun curve paid.
+    /// The reserves are read on chain whenever the curve is quoted.
+    static ref CURVE_FEES: Mutex<HashMap<String, PumpCurve>> = Mutex::new(HashMap::new());
+    /// Fee rates the last parsed trade in each mint's PumpSwap pool paid.
+    static ref POOL_FEES: Mutex<HashMap<String, PumpSwapPool>> = Mutex::new(HashMap::new());
+}
+
+/// Remembers the pool a parsed PumpSwap trade went through and its coin
+/// creator, so later swaps of the mint go to that pool.
+pub fn remember_pool(mint: &str, pool_id: Pubkey, coin_creator: Pubkey) {
+    POOL_HINTS.lock().unwrap().insert(mint.to_string(), (pool_id, coin_creator));
+}
+
+/// Remembers the fee rates a parsed trade on `mint`'s Pump.fun curve paid.
+pub fn remember_curve_fees(mint: &str, curve: PumpCurve) {
+    CURVE_FEES.lock().unwrap().insert(mint.to_string(), curve);
+}
+
+/// Remembers the fee rates a parsed trade in `mint`'s PumpSwap pool paid.
+pub fn remember_pool_fees(mint: &str, pool: PumpSwapPool) {
+    POOL_FEES.lock().unwrap().insert(mint.to_string(), pool);
+}
+
+/// Remembers the Raydium or Meteora pool a parsed trade went through.
+pub fn remember_pool_id(mint: &str, protocol: SwapProtocol, pool_id: Pubkey) {
+    KNOWN_POOLS.lock().unwrap().insert(mint.to_string(), (protocol, pool_id));
+}
+
+/// Where a swap is executed.
+#[derive(Debug, Clone)]
+pub enum Route {
+    PumpFun(Option<BondingCurveInfo>),
+    PumpSwap(PoolInfo),
+    RaydiumLaunchpad(LaunchpadPool),
+    RaydiumAmm(AmmPool),
+    RaydiumCpmm(CpmmPool),
+    MeteoraDbc(DbcPool),
+    MeteoraDlmm(DlmmPair),
+}
+
+impl Route {
+    pub fn protocol(&self) -> SwapProtocol {
+        match self {
+            Route::PumpFun(_) => SwapProtocol::PumpFun,
+            Route::PumpSwap(_) => SwapProtocol::PumpSwap,
+            Route::RaydiumLaunchpad(_) => SwapProtocol::RaydiumLaunchpad,
+            Route::RaydiumAmm(_) => SwapProtocol::RaydiumAmm,
+            Route::RaydiumCpmm(_) => SwapProtocol::RaydiumCpmm,
+            Route::MeteoraDbc(_) => SwapProtocol::MeteoraDbc,
+            Route::MeteoraDlmm(_) => SwapProtocol::MeteoraDlmm,
+        }
+    }
+
+    pub fn pool_id(&self) -> Option<Pubkey> {
+        match self {
+            Route::PumpFun(_) => None,
+            Route::PumpSwap(pool_info) => Some(pool_info.pool_id),
+            Route::RaydiumLaunchpad(pool) => Some(pool.pool_id),
+            Route::RaydiumAmm(pool) => Some(pool.pool_id),
+            Route::RaydiumCpmm(pool) => Some(pool.pool_id),
+            Route::MeteoraDbc(pool) => Some(pool.pool_id),
+            Route::MeteoraDlmm(pair) => Some(pair.pair_id),
+        }
+    }
+}
+
+/// Whether the mint's bonding curve has completed, or `None` if the mint has
+/// no Pump.fun curve at all.
+pub async fn bonding_curve_complete(app_state: &AppState, mint: &Pubkey) -> Result<Option<bool>> {
+    let pump_program = Pubkey::from_str(PUMP_PROGRAM)?;
+    let (bonding_curve, _) = Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &pump_program);
+    let account = app_state
+        .rpc_nonblocking_client
+        .get_account_with_commitment(&bonding_curve, app_state.rpc_nonblocking_client.commitment())
+        .await?
+        .value;
+    match account {
+        Some(account) => account
+            .data
+            .get(BONDING_CURVE_COMPLETE_OFFSET)
+            .map(|complete| Some(*complete != 0))
+            .ok_or_else(|| anyhow!("bonding curve {} is too short", bonding_curve)),
+        None => Ok(None),
+    }
+}
+
+/// `mint`'s Pump.fun curve with its reserves as they are on chain, priced
+/// with the fees its last parsed trade paid. `None` until a trade of the
+/// mint has been parsed, since the fee rates depend on the curve.
+pub async fn pump_curve(app_state: &AppState, mint: &Pubkey) -> Result<Option<PumpCurve>> {
+    let Some(mut curve) = CURVE_FEES.lock().unwrap().get(&mint.to_string()).copied() else {
+        return Ok(None);
+    };
+    let pump_program = Pubkey::from_str(PUMP_PROGRAM)?;
+    let (bonding_curve, _) = Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &pump_program);
+    let data = app_state.rpc_nonblocking_client.get_account_data(&bonding_curve).await?;
+    let read_u64 = |offset: usize| -> Result<u64> {
+        data.get(offset..offset + 8)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or_else(|| anyhow!("bonding curve {} is too short", bonding_curve))
+    };
+    curve.virtual_token_reserves = read_u64(BONDING_CURVE_VIRTUAL_TOKEN_RESERVES_OFFSET)?;
+    curve.virtual_sol_reserves = read_u64(BONDING_CURVE_VIRTUAL_SOL_RESERVES_OFFSET)?;
+    curve.real_token_reserves = read_u64(BONDING_CURVE_REAL_TOKEN_RESERVES_OFFSET)?;
+    Ok(Some(curve))
+}
+
+/// `pool_info`'s reserves priced with the fees the last parsed trade in the
+/// pool paid, or `None` until one has been parsed. Only pools quoted in SOL
+/// against `mint` are quoted.
+pub fn pump_swap_pool(mint: &Pubkey, pool_info: &PoolInfo) -> Option<PumpSwapPool> {
+    if pool_info.base_mint != *mint || pool_info.quote_mint != spl_token::native_mint::ID {
+        return None;
+    }
+    let fees = POOL_FEES.lock().unwrap().get(&mint.to_string()).copied()?;
+    Some(PumpSwapPool {
+        base_reserve: pool_info.base_reserve,
+        quote_reserve: pool_info.quote_reserve,
+        ..fees
+    })
+}
+
+/// Address of the pool a Pump.fun migration creates for `mint`.
+pub fn canonical_pool_address(mint: &Pubkey) -> Result<Pubkey> {
+    let pump_program = Pubkey::from_str(PUMP_PROGRAM)?;
+    let pump_swap_program = Pubkey::from_str(PUMP_SWAP_PROGRAM)?;
+    let (pool_authority, _) = Pubkey::find_program_address(&[b"pool-authority", mint.as_ref()], &pump_program);
+    let (pool, _) = Pubkey::find_program_address(
+        &[
+            b"pool",
+            &CANONICAL_POOL_INDEX.to_le_bytes(),
+            pool_authority.as_ref(),
+            mint.as_ref(),
+            spl_token::native_mint::ID.as_ref(),
+        ],
+        &pump_swap_program,
+    );
+    Ok(pool)
+}
+
+/// PumpSwap pool of `mint`: the pool a parsed trade went through if there is
+/// one, the canonical migration pool otherwise. The pool and its vault
+/// balances are always read on chain, so quotes use the current reserves.
+pub async fn find_pool(app_state: &AppState, mint: &Pubkey) -> Result<PoolInfo> {
+    let hint = POOL_HINTS.lock().unwrap().get(&mint.to_string()).copied();
+    let (pool_id, hinted_creator) = match hint {
+        Some(hint) => hint,
+        None => (canonical_pool_address(mint)?, Pubkey::default()),
+    };
+
+    let client = &app_state.rpc_nonblocking_client;
+    let data = client
+        .get_account_data(&pool_id)
+        .await
+        .map_err(|e| anyhow!("no PumpSwap pool for {} at {}: {}", mint, pool_id, e))?;
+    let read_pubkey = |offset: usize| -> Result<Pubkey> {
+        data.get(offset..offset + 32)
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .map(Pubkey::new_from_array)
+            .ok_or_else(|| anyhow!("pool {} is too short", pool_id))
+    };
+    let base_mint = read_pubkey(POOL_BASE_MINT_OFFSET)?;
+    let quote_mint = read_pubkey(POOL_QUOTE_MINT_OFFSET)?;
+    let pool_base_token_account = read_pubkey(POOL_BASE_TOKEN_ACCOUNT_OFFSET)?;
+    let pool_quote_token_account = read_pubkey(POOL_QUOTE_TOKEN_ACCOUNT_OFFSET)?;
+    // Older pools may not store the creator; the one a trade paid fees to still applies
+    let coin_creator = read_pubkey(POOL_COIN_CREATOR_OFFSET)
+        .ok()
+        .filter(|creator| *creator != Pubkey::default())
+        .unwrap_or(hinted_creator);
+
+    let vaults = client
+        .get_multiple_accounts(&[pool_base_token_account, pool_quote_token_account])
+        .await?;
+    let reserve = |index: usize| -> u64 {
+        vaults
+            .get(index)
+            .and_then(|account| account.as_ref())
+            .and_then(|account| account.data.get(TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8))
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_le_bytes)
+            .unwrap_or_default()
+    };
+
+    let pool_info = PoolInfo {
+        pool_id,
+        base_mint,
+        quote_mint,
+        pool_base_token_account,
+        pool_quote_token_account,
+        base_reserve: reserve(0),
+        quote_reserve: reserve(1),
+        coin_creator,
+    };
+    remember_pool(&mint.to_string(), pool_id, coin_creator);
+    Ok(pool_info)
+}
+
+/// The remembered pool of `mint`, re-read on chain, when it is on one of
+/// `protocols` and still trading.
+async fn known_route(app_state: &AppState, mint: &Pubkey, protocols: &[SwapProtocol]) -> Option<Route> {
+    let (protocol, pool_id) = KNOWN_POOLS.lock().unwrap().get(&mint.to_string()).cloned()?;
+    if !protocols.contains(&protocol) {
+        return None;
+    }
+    let client = &app_state.rpc_nonblocking_client;
+    match protocol {
+        SwapProtocol::RaydiumAmm => raydium_amm::get_pool(client, &pool_id)
+            .await
+            .ok()
+            .filter(|pool| pool.is_trading())
+            .map(Route::RaydiumAmm),
+        SwapProtocol::RaydiumCpmm => raydium_cpmm::get_pool(client, &pool_id)
+            .await
+            .ok()
+            .filter(|pool| pool.is_trading())
+            .map(Route::RaydiumCpmm),
+        SwapProtocol::MeteoraDbc => meteora_dbc::get_pool(client, &pool_id)
+            .await
+            .ok()
+            .filter(|pool| pool.is_trading() && pool.quote_mint == spl_token::native_mint::ID)
+            .map(Route::MeteoraDbc),
+        SwapProtocol::MeteoraDlmm => meteora_dlmm::get_pair(client, &pool_id)
+            .await
+            .ok()
+            .filter(|pair| pair.is_trading())
+            .map(Route::MeteoraDlmm),
+        _ => None,
+    }
+}
+
+fn remember_route(mint: &Pubkey, route: &Route) {
+    if let Some(pool_id) = route.pool_id() {
+        remember_pool_id(&mint.to_string(), route.protocol(), pool_id);
+    }
+}
+
+/// Deepest Raydium pool pairing `mint` with SOL, across AMM v4 and CPMM for
+/// `Auto` or on the one Raydium protocol asked for.
+pub async fn find_raydium_route(app_state: &AppState, mint: &Pubkey, protocol: SwapProtocol) -> Result<Route> {
+    let protocols = match protocol {
+        SwapProtocol::Auto => vec![SwapProtocol::RaydiumAmm, SwapProtocol::RaydiumCpmm],
+        protocol => vec![protocol],
+    };
+    if let Some(route) = known_route(app_state, mint, &protocols).await {
+        return Ok(route);
+    }
+
+    let client = &app_state.rpc_nonblocking_client;
+    let mut routes = Vec::new();
+    if protocols.contains(&SwapProtocol::RaydiumAmm) {
+        for pool in raydium_amm::find_pools(client, mint).await? {
+            routes.push((pool.sol_reserve(), Route::RaydiumAmm(pool)));
+        }
+    }
+    if protocols.contains(&SwapProtocol::RaydiumCpmm) {
+        for pool in raydium_cpmm::find_pools(client, mint).await? {
+            routes.push((pool.sol_reserve(), Route::RaydiumCpmm(pool)));
+        }
+    }
+    let (_, route) = routes
+        .into_iter()
+        .max_by_key(|(sol_reserve, _)| *sol_reserve)
+        .ok_or_else(|| anyhow!("no Raydium pool for {}", mint))?;
+    remember_route(mint, &route);
+    Ok(route)
+}
+
+/// Meteora pool of `mint`: its Dynamic Bonding Curve while it trades, its
+/// deepest DLMM SOL pair otherwise, or only the Meteora protocol asked for.
+pub async fn find_meteora_route(app_state: &AppState, mint: &Pubkey, protocol: SwapProtocol) -> Result<Route> {
+    let protocols = match protocol {
+        SwapProtocol::Auto => vec![SwapProtocol::MeteoraDbc, SwapProtocol::MeteoraDlmm],
+        protocol => vec![protocol],
+    };
+    if let Some(route) = known_route(app_state, mint, &protocols).await {
+        return Ok(route);
+    }
+
+    let client = &app_state.rpc_nonblocking_client;
+    if protocols.contains(&SwapProtocol::MeteoraDbc) {
+        let dbc = meteora_dbc::find_pool(client, mint).await.and_then(|pool| {
+            if pool.is_trading() {
+                Ok(pool)
+            } else {
+                Err(anyhow!("Meteora DBC pool {} of {} has completed", pool.pool_id, mint))
+            }
+        });
+        match dbc {
+            Ok(pool) => {
+                let route = Route::MeteoraDbc(pool);
+                remember_route(mint, &route);
+                return Ok(route);
+            }
+            Err(e) if !protocols.contains(&SwapProtocol::MeteoraDlmm) => return Err(e),
+            Err(_) => {}
+        }
+    }
+    let route = Route::MeteoraDlmm(meteora_dlmm::deepest_pair(client, mint).await?);
+    remember_route(mint, &route);
+    Ok(route)
+}
+
+/// Route `mint` trades on right now: its Pump.fun curve until it completes
+/// and its PumpSwap pool after that. Mints without a Pump.fun curve trade on
+/// their LaunchLab curve while it is open. Anything else, including tokens
+/// that graduated before PumpSwap existed, goes to the pool a parsed trade
+/// went through, then to its deepest Raydium pool, then to Meteora.
+async fn detect_route(
+    app_state: &AppState,
+    mint: &Pubkey,
+    bonding_curve_info: Option<BondingCurveInfo>,
+) -> Result<Route> {
+    match bonding_curve_complete(app_state, mint).await? {
+        Some(false) => return Ok(Route::PumpFun(bonding_curve_info)),
+        Some(true) => {}
+        None => {
+            if let Ok(pool) = raydium_launchpad::get_pool(&app_state.rpc_nonblocking_client, mint).await {
+                if pool.is_trading() {
+                    return Ok(Route::RaydiumLaunchpad(pool));
+                }
+            }
+        }
+    }
+    let pump_swap_error = match find_pool(app_state, mint).await {
+        Ok(pool_info) => return Ok(Route::PumpSwap(pool_info)),
+        Err(e) => e,
+    };
+    let known = [
+        SwapProtocol::RaydiumAmm,
+        SwapProtocol::RaydiumCpmm,
+        SwapProtocol::MeteoraDbc,
+        SwapProtocol::MeteoraDlmm,
+    ];
+    if let Some(route) = known_route(app_state, mint, &known).await {
+        return Ok(route);
+    }
+    let raydium_error = match find_raydium_route(app_state, mint, SwapProtocol::Auto).await {
+        Ok(route) => return Ok(route),
+        Err(e) => e,
+    };
+    find_meteora_route(app_state, mint, SwapProtocol::Auto)
+        .await
+        .map_err(|meteora_error| anyhow!("{}; {}; {}", pump_swap_error, raydium_error, meteora_error))
+}
+
+/// Picks where to swap `mint`, detecting the route for `Auto`.
+pub async fn resolve_route(
+    app_state: &AppState,
+    mint: &str,
+    protocol: SwapProtocol,
+    bonding_curve_info: Option<BondingCurveInfo>,
+) -> Result<Route> {
+    let mint_pubkey = Pubkey::from_str(mint)?;
+    match protocol {
+        SwapProtocol::Auto => detect_route(app_state, &mint_pubkey, bonding_curve_info).await,
+        SwapProtocol::PumpFun => Ok(Route::PumpFun(bonding_curve_info)),
+        SwapProtocol::PumpSwap => Ok(Route::PumpSwap(find_pool(app_state, &mint_pubkey).await?)),
+        SwapProtocol::RaydiumLaunchpad => Ok(Route::RaydiumLaunchpad(
+            raydium_launchpad::get_pool(&app_state.rpc_nonblocking_client, &mint_pubkey).await?,
+        )),
+        SwapProtocol::RaydiumAmm | SwapProtocol::RaydiumCpmm => {
+            find_raydium_route(app_state, &mint_pubkey, protocol).await
+        }
+        SwapProtocol::MeteoraDbc | SwapProtocol::MeteoraDlmm => {
+            find_meteora_route(app_state, &mint_pubkey, protocol).await
+        }
+    }
+}
+
+async fn build_on_route(
+    app_state: &AppState,
+    mint: &str,
+    route: Route,
+    swap_config: SwapConfig,
+    start_time: Instant,
+) -> Result<SwapIxn> {
+    match route {
+        Route::PumpFun(bonding_curve_info) => {
+            let swapx = Pump::new(
+                app_state.rpc_nonblocking_client.clone(),
+                app_state.rpc_client.clone(),
+                app_state.wallet.clone(),
+            );
+            swapx.build_swap_ixn_by_mint(mint, bonding_curve_info, swap_config, start_time).await
+        }
+        Route::PumpSwap(pool_info) => {
+            let swapx = PumpSwap::new(
+                app_state.rpc_nonblocking_client.clone(),
+                app_state.rpc_client.clone(),
+                app_state.wallet.clone(),
+            );
+            swapx.build_swap_ixn_by_mint(mint, Some(pool_info), swap_config, start_time).await
+        }
+        Route::RaydiumLaunchpad(pool) => {
+            let swapx = RaydiumLaunchpad::new(
+                app_state.rpc_nonblocking_client.clone(),
+                app_state.rpc_client.clone(),
+                app_state.wallet.clone(),
+            );
+            swapx.build_swap_ixn_by_mint(mint, Some(pool), swap_config, start_time).await
+        }
+        Route::RaydiumAmm(pool) => {
+            let swapx = RaydiumAmm::new(
+                app_state.rpc_nonblocking_client.clone(),
+                app_state.rpc_client.clone(),
+                app_state.wallet.clone(),
+            );
+            swapx.build_swap_ixn_by_mint(mint, Some(pool), swap_config, start_time).await
+        }
+        Route::RaydiumCpmm(pool) => {
+            let swapx = RaydiumCpmm::new(
+                app_state.rpc_nonblocking_client.clone(),
+                app_state.rpc_client.clone(),
+                app_state.wallet.clone(),
+            );
+            swapx.build_swap_ixn_by_mint(mint, Some(pool), swap_config, start_time).await
+        }
+        Route::MeteoraDbc(pool) => {
+            let swapx = MeteoraDbc::new(
+                app_state.rpc_nonblocking_client.clone(),
+                app_state.rpc_client.clone(),
+                app_state.wallet.clone(),
+            );
+            swapx.build_swap_ixn_by_mint(mint, Some(pool), swap_config, start_time).await
+        }
+        Route::MeteoraDlmm(pair) => {
+            let swapx = MeteoraDlmm::new(
+                app_state.rpc_nonblocking_client.clone(),
+                app_state.rpc_client.clone(),
+                app_state.wallet.clone(),
+            );
+            swapx.build_swap_ixn_by_mint(mint, Some(pair), swap_config, start_time).await
+        }
+    }
+}
+
+/// Builds the swap on the route `protocol` resolves to. With `Auto`, a curve
+/// swap that fails because the curve completed in the meantime is retried on
+/// the pool the token graduated to.
+pub async fn build_swap_ixn(
+    app_state: &AppState,
+    mint: &str,
+    protocol: SwapProtocol,
+    bonding_curve_info: Option<BondingCurveInfo>,
+    swap_config: SwapConfig,
+    start_time: Instant,
+    logger: &Logger,
+) -> Result<RoutedSwapIxn> {
+    let is_auto = protocol == SwapProtocol::Auto;
+    let route = resolve_route(app_state, mint, protocol, bonding_curve_info).await?;
+    let is_curve = matches!(route, Route::PumpFun(_));
+
+    let built = match build_on_route(app_state, mint, route.clone(), swap_config.clone(), start_time).await {
+        Ok(built) => Ok((built, route)),
+        Err(e) if is_auto && is_curve => {
+            let mint_pubkey = Pubkey::from_str(mint)?;
+            if bonding_curve_complete(app_state, &mint_pubkey).await? != Some(true) {
+                return Err(e);
+            }
+            let route = detect_route(app_state, &mint_pubkey, None).await?;
+            logger.log(format!(
+                "[ROUTER] => Bonding curve of {} completed, routing through {:?}",
+                mint,
+                route.protocol()
+            ).yellow().to_string());
+            build_on_route(app_state, mint, route.clone(), swap_config, start_time)
+                .await
+                .map(|built| (built, route))
+        }
+        Err(e) => Err(e),
+    };
+    built.map(|((signer, instructions, token_price), route)| (signer, instructions, token_price, route))
+}
+
+/// Current token price quoted by the route the mint trades on, in the
+/// same unit as the price returned by `build_swap_ixn`.
+pub async fn get_token_price(app_state: &AppState, mint: &str, protocol: SwapProtocol) -> Result<f64> {
+    let mint_pubkey = Pubkey::from_str(mint)?;
+    let route = resolve_route(app_state, mint, protocol, None).await?;
+    let rpc_nonblocking_client = app_state.rpc_nonblocking_client.clone();
+    let rpc_client = app_state.rpc_client.clone();
+    let wallet = app_state.wallet.clone();
+    match route {
+        Route::PumpFun(_) => Pump::new(rpc_nonblocking_client, rpc_client, wallet).get_token_price(mint).await,
+        Route::PumpSwap(_) => PumpSwap::new(rpc_nonblocking_client, rpc_client, wallet).get_token_price(mint).await,
+        Route::RaydiumLaunchpad(pool) => Ok(pool.price()),
+        Route::RaydiumAmm(pool) => Ok(pool.price(&mint_pubkey)),
+        Route::RaydiumCpmm(pool) => Ok(pool.price(&mint_pubkey)),
+        Route::MeteoraDbc(pool) => Ok(pool.price()),
+        Route::MeteoraDlmm(pair) => Ok(pair.price(&mint_pubkey)),
+    }
+}
+
+/// Lamports a sale of `token_amount` of `mint` pays out where it trades
+/// right now, with fees and price impact, when that is a Pump.fun curve or
+/// PumpSwap pool whose fee rates a parsed trade has shown. `None` otherwise.
+pub async fn pump_sell_quote(
+    app_state: &AppState,
+    mint: &str,
+    protocol: SwapProtocol,
+    token_amount: u64,
+) -> Result<Option<Quote>> {
+    let mint_pubkey = Pubkey::from_str(mint)?;
+    match resolve_route(app_state, mint, protocol, None).await? {
+        Route::PumpFun(_) => Ok(pump_curve(app_state, &mint_pubkey)
+            .await?
+            .and_then(|curve| curve.sell_exact_tokens_in(token_amount))),
+        Route::PumpSwap(pool_info) => Ok(pump_swap_pool(&mint_pubkey, &pool_info)
+            .and_then(|pool| pool.sell_exact_base_in(token_amount))),
+        _ => Ok(None),
+    }
+}
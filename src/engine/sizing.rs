@@ -0,0 +1,149 @@
+use crate::common::settings::{SizingMode, SizingSettings};
+
+/// How much SOL to spend copying one target buy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopySizing {
+    pub settings: SizingSettings,
+    /// `swap.amount_in`, used by `fixed` mode when no amount is set.
+    pub default_amount: f64,
+}
+
+impl CopySizing {
+    pub fn new(settings: SizingSettings, default_amount: f64) -> Self {
+        Self {
+            settings,
+            default_amount,
+        }
+    }
+
+    pub fn fixed(amount: f64) -> Self {
+        Self::new(
+            SizingSettings {
+                mode: SizingMode::Fixed,
+                amount: Some(amount),
+                percent: None,
+                tiers: Vec::new(),
+                min_amount: None,
+                max_amount: None,
+                max_wallet_percent: None,
+            },
+            amount,
+        )
+    }
+
+    pub fn needs_wallet_balance(&self) -> bool {
+        self.settings.max_wallet_percent.is_some()
+    }
+
+    /// Buy size in SOL for a target that spent `target_spend` SOL. The wallet
+    /// cap is applied last, so it wins over `min_amount`. Returns 0 when the
+    /// target's spend is below every tier.
+    pub fn amount_in(&self, target_spend: f64, wallet_balance: Option<f64>) -> f64 {
+        let settings = &self.settings;
+        let mut amount = match settings.mode {
+            SizingMode::Fixed => settings.amount.unwrap_or(self.default_amount),
+            SizingMode::Proportional => target_spend * settings.percent.unwrap_or(100.0) / 100.0,
+            SizingMode::Tiered => settings
+                .tiers
+                .iter()
+                .filter(|tier| target_spend >= tier.min_target_spend)
+                .max_by(|a, b| a.min_target_spend.total_cmp(&b.min_target_spend))
+                .map(|tier| tier.amount)
+                .unwrap_or(0.0),
+        };
+        if amount <= 0.0 {
+            return 0.0;
+        }
+
+        if let Some(min) = settings.min_amount {
+            amount = amount.max(min);
+        }
+        if let Some(max) = settings.max_amount {
+            amount = amount.min(max);
+        }
+        if let (Some(percent), Some(balance)) = (settings.max_wallet_percent, wallet_balance) {
+            amount = amount.min(balance * percent / 100.0);
+        }
+        amount.max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::settings::SizingTier;
+
+    const DEFAULT_AMOUNT: f64 = 0.1;
+
+    fn sizing(mode: SizingMode) -> SizingSettings {
+        SizingSettings {
+            mode,
+            amount: None,
+            percent: None,
+            tiers: Vec::new(),
+            min_amount: None,
+            max_amount: None,
+            max_wallet_percent: None,
+        }
+    }
+
+    fn tiers(tiers: &[(f64, f64)]) -> Vec<SizingTier> {
+        tiers
+            .iter()
+            .map(|&(min_target_spend, amount)| SizingTier { min_target_spend, amount })
+            .collect()
+    }
+
+    #[test]
+    fn amount_in_table() {
+        let fixed = SizingSettings { amount: Some(0.3), ..sizing(SizingMode::Fixed) };
+        let half = SizingSettings { percent: Some(50.0), ..sizing(SizingMode::Proportional) };
+        // Out of order on purpose: the highest tier reached wins, not the last listed
+        let tiered = SizingSettings {
+            tiers: tiers(&[(5.0, 0.5), (0.0, 0.05), (1.0, 0.2)]),
+            ..sizing(SizingMode::Tiered)
+        };
+        let tiered_from_one = SizingSettings { tiers: tiers(&[(1.0, 0.2)]), ..sizing(SizingMode::Tiered) };
+        let clamped = SizingSettings { min_amount: Some(0.05), max_amount: Some(0.4), ..half.clone() };
+        let wallet_capped = SizingSettings { max_wallet_percent: Some(10.0), ..half.clone() };
+        let min_over_cap = SizingSettings {
+            min_amount: Some(0.5),
+            max_wallet_percent: Some(10.0),
+            ..half.clone()
+        };
+
+        let cases: &[(&str, &SizingSettings, f64, Option<f64>, f64)] = &[
+            ("fixed amount", &fixed, 2.0, None, 0.3),
+            ("fixed without an amount", &sizing(SizingMode::Fixed), 2.0, None, DEFAULT_AMOUNT),
+            ("proportional", &half, 2.0, None, 1.0),
+            ("proportional defaults to 100%", &sizing(SizingMode::Proportional), 0.7, None, 0.7),
+            ("proportional of nothing", &half, 0.0, None, 0.0),
+            ("tier below all but the lowest", &tiered, 0.5, None, 0.05),
+            ("tier reached exactly", &tiered, 1.0, None, 0.2),
+            ("highest tier reached", &tiered, 9.0, None, 0.5),
+            ("below every tier", &tiered_from_one, 0.99, None, 0.0),
+            ("min raises a small buy", &clamped, 0.02, None, 0.05),
+            ("max lowers a large buy", &clamped, 10.0, None, 0.4),
+            ("between min and max", &clamped, 0.4, None, 0.2),
+            ("min does not raise a skipped buy", &clamped, 0.0, None, 0.0),
+            ("wallet cap lowers the buy", &wallet_capped, 4.0, Some(3.0), 0.3),
+            ("wallet cap above the buy", &wallet_capped, 0.4, Some(3.0), 0.2),
+            ("no balance, no wallet cap", &wallet_capped, 4.0, None, 2.0),
+            ("wallet cap wins over min", &min_over_cap, 0.2, Some(2.0), 0.2),
+            ("empty wallet", &min_over_cap, 4.0, Some(0.0), 0.0),
+        ];
+        for (name, settings, target_spend, wallet_balance, expected) in cases {
+            let amount = CopySizing::new((*settings).clone(), DEFAULT_AMOUNT).amount_in(*target_spend, *wallet_balance);
+            assert!((amount - expected).abs() < 1e-12, "{}: expected {}, got {}", name, expected, amount);
+        }
+    }
+
+    #[test]
+    fn fixed_sizing_buys_the_given_amount_and_needs_no_balance() {
+        let sizing = CopySizing::fixed(0.25);
+        assert_eq!(sizing.amount_in(10.0, None), 0.25);
+        assert!(!sizing.needs_wallet_balance());
+        let capped = SizingSettings { max_wallet_percent: Some(5.0), ..sizing.settings.clone() };
+        assert!(CopySizing::new(capped, 0.25).needs_wallet_balance());
+    }
+}
@@ -0,0 +1,410 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sled::{transaction::TransactionResult, Transactional, Tree};
+use std::{collections::HashMap, path::Path, sync::OnceLock, time::Duration};
+use tokio::time::Instant;
+
+use super::journal::CostBasis;
+use super::position::Position;
+use super::strategy::SellReason;
+use super::swap::SwapProtocol;
+use crate::common::config::Status;
+
+static STORE: OnceLock<Store> = OnceLock::new();
+
+/// A position as written to disk. Instants are stored as unix milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionRecord {
+    pub mint: String,
+    pub status: Status,
+    pub buy_price: f64,
+    pub sell_price: f64,
+    pub sol_spent: f64,
+    pub opened_at_ms: Option<i64>,
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub basis: CostBasis,
+    #[serde(default)]
+    pub protocol: SwapProtocol,
+    #[serde(default)]
+    pub pool: Option<String>,
+}
+
+impl From<&Position> for PositionRecord {
+    fn from(position: &Position) -> Self {
+        Self {
+            mint: position.mint.clone(),
+            status: position.status.clone(),
+            buy_price: position.buy_price,
+            sell_price: position.sell_price,
+            sol_spent: position.sol_spent,
+            opened_at_ms: position.opened_at.map(instant_to_unix_ms),
+            target: position.target.clone(),
+            basis: position.basis.clone(),
+            protocol: position.protocol.clone(),
+            pool: position.pool.clone(),
+        }
+    }
+}
+
+impl PositionRecord {
+    pub fn to_position(&self) -> Position {
+        Position {
+            mint: self.mint.clone(),
+            status: self.status.clone(),
+            buy_price: self.buy_price,
+            sell_price: self.sell_price,
+            sol_spent: self.sol_spent,
+            opened_at: self.opened_at_ms.map(unix_ms_to_instant),
+            updated_at: Instant::now(),
+            target: self.target.clone(),
+            basis: self.basis.clone(),
+            protocol: self.protocol.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillSide {
+    Buy,
+    Sell,
+}
+
+/// One confirmed buy or sell, read from the transaction's meta.
+/// Lamport amounts are signed from the wallet's point of view.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillRecord {
+    pub signature: String,
+    pub mint: String,
+    pub side: FillSide,
+    pub slot: u64,
+    /// Copy target whose trade opened the position.
+    pub target: Option<String>,
+    /// Change of the wallet's raw token balance.
+    pub token_delta: i64,
+    pub token_decimals: u8,
+    /// Change of the wallet's SOL balance, including fee, tip and rent.
+    pub sol_delta: i64,
+    pub fee: u64,
+    pub tip: u64,
+    /// Token account rent paid (positive) or refunded (negative).
+    pub rent: i64,
+    pub reason: Option<SellReason>,
+    pub timestamp_ms: i64,
+}
+
+/// Exit-strategy state of a position, so peak PnL and completed
+/// take-profit levels survive a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackingRecord {
+    pub strategy: String,
+    pub top_pnl: f64,
+    pub completed_intervals: Vec<String>,
+    pub loss_since_ms: Option<i64>,
+    pub exit_reason: Option<SellReason>,
+}
+
+/// What a fill does to the position's tracking state.
+pub enum TrackingUpdate<'a> {
+    Keep,
+    Save(&'a TrackingRecord),
+    Remove,
+}
+
+/// Embedded sled database holding positions, fills and tracking state.
+/// Positions and tracking are keyed by mint; fills are keyed by a
+/// monotonically increasing id so they read back in order.
+pub struct Store {
+    db: sled::Db,
+    positions: Tree,
+    fills: Tree,
+    tracking: Tree,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        let db = sled::open(path)?;
+        Ok(Self {
+            positions: db.open_tree("positions")?,
+            fills: db.open_tree("fills")?,
+            tracking: db.open_tree("tracking")?,
+            db,
+        })
+    }
+
+    pub fn save_position(&self, position: &PositionRecord) -> Result<()> {
+        self.positions
+            .insert(position.mint.as_bytes(), serde_json::to_vec(position)?)?;
+        self.positions.flush()?;
+        Ok(())
+    }
+
+    /// Writes the position, appends the fill if any and applies the
+    /// tracking update in one transaction.
+    pub fn commit(
+        &self,
+        position: &PositionRecord,
+        fill: Option<&FillRecord>,
+        tracking: TrackingUpdate<'_>,
+    ) -> Result<()> {
+        let mint = position.mint.as_bytes();
+        let position_value = serde_json::to_vec(position)?;
+        let fill_entry = match fill {
+            Some(fill) => Some((self.db.generate_id()?.to_be_bytes(), serde_json::to_vec(fill)?)),
+            None => None,
+        };
+        let tracking_value = match tracking {
+            TrackingUpdate::Save(record) => Some(serde_json::to_vec(record)?),
+            _ => None,
+        };
+        let remove_tracking = matches!(tracking, TrackingUpdate::Remove);
+
+        let result: TransactionResult<()> = (&self.positions, &self.fills, &self.tracking).transaction(
+            |(positions, fills, tracking)| {
+                positions.insert(mint, position_value.as_slice())?;
+                if let Some((key, value)) = &fill_entry {
+                    fills.insert(&key[..], value.as_slice())?;
+                }
+                if let Some(value) = &tracking_value {
+                    tracking.insert(mint, value.as_slice())?;
+                } else if remove_tracking {
+                    tracking.remove(mint)?;
+                }
+                Ok(())
+            },
+        );
+        result.map_err(|e| anyhow!("Failed to commit position {}: {:?}", position.mint, e))?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn save_tracking(&self, mint: &str, record: &TrackingRecord) -> Result<()> {
+        self.tracking.insert(mint.as_bytes(), serde_json::to_vec(record)?)?;
+        Ok(())
+    }
+
+    pub fn remove_tracking(&self, mint: &str) -> Result<()> {
+        self.tracking.remove(mint.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn positions(&self) -> Result<Vec<PositionRecord>> {
+        read_all(&self.positions).map(|records| records.into_iter().map(|(_, record)| record).collect())
+    }
+
+    pub fn tracking(&self) -> Result<HashMap<String, TrackingRecord>> {
+        read_all(&self.tracking).map(|records| records.into_iter().collect())
+    }
+
+    /// Every fill in the order it was recorded.
+    pub fn all_fills(&self) -> Result<Vec<FillRecord>> {
+        read_all::<FillRecord>(&self.fills).map(|records| records.into_iter().map(|(_, fill)| fill).collect())
+    }
+
+    /// Fills of `mint` in the order they were recorded.
+    pub fn fills(&self, mint: &str) -> Result<Vec<FillRecord>> {
+        self.all_fills()
+            .map(|fills| fills.into_iter().filter(|fill| fill.mint == mint).collect())
+    }
+}
+
+fn read_all<T: DeserializeOwned>(tree: &Tree) -> Result<Vec<(String, T)>> {
+    tree.iter()
+        .map(|entry| {
+            let (key, value) = entry?;
+            Ok((
+                String::from_utf8_lossy(&key).into_owned(),
+                serde_json::from_slice(&value)?,
+            ))
+        })
+        .collect()
+}
+
+/// Opens the store at `path`. Later calls return the store opened first.
+pub fn init_store(path: &str) -> Result<&'static Store> {
+    if let Some(store) = STORE.get() {
+        return Ok(store);
+    }
+    let store = Store::open(Path::new(path))?;
+    Ok(STORE.get_or_init(|| store))
+}
+
+/// The store, if `init_store` succeeded.
+pub fn store() -> Option<&'static Store> {
+    STORE.get()
+}
+
+pub fn instant_to_unix_ms(instant: Instant) -> i64 {
+    Utc::now().timestamp_millis() - instant.elapsed().as_millis() as i64
+}
+
+pub fn unix_ms_to_instant(unix_ms: i64) -> Instant {
+    let age = Duration::from_millis((Utc::now().timestamp_millis() - unix_ms).max(0) as u64);
+    Instant::now().checked_sub(age).unwrap_or_else(Instant::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINT: &str = "So11111111111111111111111111111111111111112";
+    const OTHER_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    /// A fresh store directory, removed when the guard drops.
+    struct TempStore(std::path::PathBuf);
+
+    impl TempStore {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("store-test-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            Self(path)
+        }
+
+        fn open(&self) -> Store {
+            Store::open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn position(mint: &str, status: Status) -> PositionRecord {
+        PositionRecord {
+            mint: mint.to_string(),
+            status,
+            buy_price: 0.00001,
+            sell_price: 0.0,
+            sol_spent: 0.1,
+            opened_at_ms: Some(1_717_200_000_000),
+            target: Some("target".to_string()),
+            basis: CostBasis::default(),
+            protocol: SwapProtocol::PumpSwap,
+            pool: Some("pool".to_string()),
+        }
+    }
+
+    fn fill(signature: &str, mint: &str, side: FillSide) -> FillRecord {
+        FillRecord {
+            signature: signature.to_string(),
+            mint: mint.to_string(),
+            side,
+            slot: 1,
+            target: None,
+            token_delta: if side == FillSide::Buy { 1_000 } else { -1_000 },
+            token_decimals: 6,
+            sol_delta: if side == FillSide::Buy { -100_000_000 } else { 90_000_000 },
+            fee: 5_000,
+            tip: 0,
+            rent: 0,
+            reason: (side == FillSide::Sell).then_some(SellReason::TakeProfit),
+            timestamp_ms: 1_717_200_000_000,
+        }
+    }
+
+    fn tracking(top_pnl: f64) -> TrackingRecord {
+        TrackingRecord {
+            strategy: "default".to_string(),
+            top_pnl,
+            completed_intervals: vec!["tp1".to_string()],
+            loss_since_ms: Some(1_717_200_000_000),
+            exit_reason: None,
+        }
+    }
+
+    #[test]
+    fn records_survive_reopening() {
+        let dir = TempStore::new("reopen");
+        {
+            let store = dir.open();
+            store.save_position(&position(MINT, Status::Bought)).unwrap();
+            store.save_tracking(MINT, &tracking(12.5)).unwrap();
+            store
+                .commit(&position(OTHER_MINT, Status::Bought), Some(&fill("a", OTHER_MINT, FillSide::Buy)), TrackingUpdate::Keep)
+                .unwrap();
+        }
+
+        let store = dir.open();
+        let mut positions = store.positions().unwrap();
+        positions.sort_by(|a, b| a.mint.cmp(&b.mint));
+        assert_eq!(positions.len(), 2);
+        let restored = &positions[1];
+        assert_eq!(restored.mint, MINT);
+        assert_eq!(restored.status, Status::Bought);
+        assert_eq!(restored.opened_at_ms, Some(1_717_200_000_000));
+        assert_eq!(restored.target.as_deref(), Some("target"));
+        assert_eq!(restored.protocol, SwapProtocol::PumpSwap);
+        assert_eq!(restored.pool.as_deref(), Some("pool"));
+
+        let tracking = store.tracking().unwrap();
+        assert_eq!(tracking.len(), 1);
+        assert_eq!(tracking[MINT].top_pnl, 12.5);
+        assert_eq!(tracking[MINT].completed_intervals, vec!["tp1".to_string()]);
+
+        let fills = store.all_fills().unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].signature, "a");
+        assert_eq!(fills[0].sol_delta, -100_000_000);
+    }
+
+    #[test]
+    fn commit_writes_position_fill_and_tracking_together() {
+        let dir = TempStore::new("commit");
+        let store = dir.open();
+
+        store
+            .commit(&position(MINT, Status::Bought), Some(&fill("buy", MINT, FillSide::Buy)), TrackingUpdate::Save(&tracking(0.0)))
+            .unwrap();
+        assert_eq!(store.tracking().unwrap()[MINT].top_pnl, 0.0);
+
+        let mut selling = position(MINT, Status::Selling);
+        store.commit(&selling, None, TrackingUpdate::Keep).unwrap();
+        assert_eq!(store.positions().unwrap()[0].status, Status::Selling);
+        assert!(store.tracking().unwrap().contains_key(MINT));
+
+        selling.status = Status::Sold;
+        store.commit(&selling, Some(&fill("sell", MINT, FillSide::Sell)), TrackingUpdate::Remove).unwrap();
+        assert_eq!(store.positions().unwrap()[0].status, Status::Sold);
+        assert!(store.tracking().unwrap().is_empty());
+        let fills: Vec<String> = store.fills(MINT).unwrap().into_iter().map(|fill| fill.signature).collect();
+        assert_eq!(fills, vec!["buy".to_string(), "sell".to_string()]);
+    }
+
+    #[test]
+    fn fills_read_back_in_commit_order_per_mint() {
+        let dir = TempStore::new("fills");
+        let store = dir.open();
+        for (signature, mint, side) in [
+            ("1", MINT, FillSide::Buy),
+            ("2", OTHER_MINT, FillSide::Buy),
+            ("3", MINT, FillSide::Sell),
+            ("4", OTHER_MINT, FillSide::Sell),
+        ] {
+            store.commit(&position(mint, Status::Bought), Some(&fill(signature, mint, side)), TrackingUpdate::Keep).unwrap();
+        }
+
+        let signatures = |fills: Vec<FillRecord>| fills.into_iter().map(|fill| fill.signature).collect::<Vec<_>>();
+        assert_eq!(signatures(store.all_fills().unwrap()), vec!["1", "2", "3", "4"]);
+        assert_eq!(signatures(store.fills(OTHER_MINT).unwrap()), vec!["2", "4"]);
+    }
+
+    #[test]
+    fn position_records_convert_both_ways() {
+        let record = position(MINT, Status::Bought);
+        let position = record.to_position();
+        let back = PositionRecord::from(&position);
+        assert_eq!(back.mint, record.mint);
+        assert_eq!(back.status, record.status);
+        assert_eq!(back.protocol, record.protocol);
+        assert_eq!(back.pool, record.pool);
+        // Instants only round-trip to the millisecond, give or take the conversion time
+        let drift = back.opened_at_ms.unwrap() - record.opened_at_ms.unwrap();
+        assert!(drift.abs() < 1_000, "{}", drift);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::common::settings::{
+    EmergencyExitSettings, StopLossSettings, StrategyProfileSettings, StrategySettings,
+    TrailingStopSettings, DEFAULT_PROFILE_NAME,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use super::monitor::InstructionType;
+
+/// What made the PnL monitor sell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SellReason {
+    StopLoss,
+    TimeInLoss,
+    Retracement,
+    TrailingStop,
+    TakeProfit,
+    EmergencyExit,
+    MirrorExit,
+    MaxHoldTime,
+    /// Sold from the command line.
+    Manual,
+}
+
+impl fmt::Display for SellReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            SellReason::StopLoss => "stop_loss",
+            SellReason::TimeInLoss => "time_in_loss",
+            SellReason::Retracement => "retracement",
+            SellReason::TrailingStop => "trailing_stop",
+            SellReason::TakeProfit => "take_profit",
+            SellReason::EmergencyExit => "emergency_exit",
+            SellReason::MirrorExit => "mirror_exit",
+            SellReason::MaxHoldTime => "max_hold_time",
+            SellReason::Manual => "manual",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetracementLevel {
+    pub percentage: u64,
+    pub threshold: u64,
+    pub sell_amount: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetracementTier {
+    pub max_hold_secs: Option<u64>,
+    pub min_pnl: Option<f64>,
+    pub levels: Vec<RetracementLevel>,
+}
+
+impl RetracementTier {
+    fn matches(&self, current_pnl: f64, hold_secs: u64) -> bool {
+        self.max_hold_secs.is_none_or(|max| hold_secs <= max)
+            && self.min_pnl.is_none_or(|min| current_pnl > min)
+    }
+}
+
+/// Exit rules applied to a position by the PnL monitor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyProfile {
+    pub name: String,
+    /// `(pnl, sell_percent)` pairs, highest PnL first.
+    pub take_profit_levels: Vec<(u64, u64)>,
+    pub retracement_min_hold_secs: u64,
+    pub retracement_tiers: Vec<RetracementTier>,
+    pub trailing_stop: TrailingStopSettings,
+    pub emergency_exit: EmergencyExitSettings,
+    pub stop_loss: StopLossSettings,
+}
+
+impl StrategyProfile {
+    pub fn from_settings(name: &str, settings: &StrategyProfileSettings) -> Self {
+        let mut take_profit_levels: Vec<(u64, u64)> = settings
+            .take_profit_levels
+            .iter()
+            .map(|level| (level.pnl, level.sell_percent))
+            .collect();
+        take_profit_levels.sort_by_key(|level| std::cmp::Reverse(level.0));
+
+        Self {
+            name: name.to_string(),
+            take_profit_levels,
+            retracement_min_hold_secs: settings.retracement_min_hold_secs,
+            retracement_tiers: settings
+                .retracement_tiers
+                .iter()
+                .map(|tier| RetracementTier {
+                    max_hold_secs: tier.max_hold_secs,
+                    min_pnl: tier.min_pnl,
+                    levels: tier
+                        .levels
+                        .iter()
+                        .map(|level| RetracementLevel {
+                            percentage: level.percentage,
+                            threshold: level.threshold,
+                            sell_amount: level.sell_amount,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            trailing_stop: settings.trailing_stop.clone(),
+            emergency_exit: settings.emergency_exit.clone(),
+            stop_loss: settings.stop_loss.clone(),
+        }
+    }
+
+    /// Levels of the first tier matching the current PnL and hold time.
+    pub fn retracement_levels(&self, current_pnl: f64, hold_secs: u64) -> &[RetracementLevel] {
+        self.retracement_tiers
+            .iter()
+            .find(|tier| tier.matches(current_pnl, hold_secs))
+            .map(|tier| tier.levels.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn trailing_stop_triggered(&self, current_pnl: f64, top_pnl: f64) -> bool {
+        top_pnl > self.trailing_stop.min_peak_pnl && current_pnl < top_pnl * self.trailing_stop.ratio
+    }
+
+    pub fn stop_loss_triggered(&self, current_pnl: f64) -> bool {
+        self.stop_loss
+            .max_loss_pnl
+            .is_some_and(|max_loss| current_pnl <= max_loss)
+    }
+
+    /// `in_loss_for` is how long PnL has stayed under `stop_loss.time_in_loss_pnl`.
+    pub fn time_in_loss_exceeded(&self, in_loss_for: Duration) -> bool {
+        self.stop_loss
+            .time_in_loss_secs
+            .is_some_and(|secs| in_loss_for >= Duration::from_secs(secs))
+    }
+
+    pub fn emergency_exit_triggered(&self, current_pnl: f64, top_pnl: f64) -> bool {
+        top_pnl > self.emergency_exit.min_peak_pnl && current_pnl < self.emergency_exit.floor_pnl
+    }
+}
+
+/// All profiles plus the rules that pick one for a new position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyBook {
+    pub default_profile: String,
+    pub profiles: HashMap<String, Arc<StrategyProfile>>,
+    pub launch_sources: HashMap<String, String>,
+    pub copy_targets: HashMap<String, String>,
+}
+
+impl StrategyBook {
+    pub fn from_settings(settings: &StrategySettings, copy_targets: HashMap<String, String>) -> Self {
+        let mut profiles = HashMap::new();
+        profiles.insert(
+            DEFAULT_PROFILE_NAME.to_string(),
+            Arc::new(StrategyProfile::from_settings(
+                DEFAULT_PROFILE_NAME,
+                &StrategyProfileSettings::default(),
+            )),
+        );
+        for (name, profile) in settings.profiles.iter() {
+            profiles.insert(name.clone(), Arc::new(StrategyProfile::from_settings(name, profile)));
+        }
+
+        Self {
+            default_profile: settings.default_profile.clone(),
+            profiles,
+            launch_sources: settings.launch_sources.clone(),
+            copy_targets,
+        }
+    }
+
+    /// Profile name for a position opened by copying `target` on `source`.
+    /// A per-target profile wins over a per-source one.
+    pub fn profile_name_for(&self, target: Option<&str>, source: &str) -> String {
+        target
+            .and_then(|target| self.copy_targets.get(target))
+            .or_else(|| self.launch_sources.get(source))
+            .unwrap_or(&self.default_profile)
+            .clone()
+    }
+
+    /// Looks up a profile by name. A profile removed by a reload falls back
+    /// to the default, so open positions keep being managed.
+    pub fn profile(&self, name: &str) -> Arc<StrategyProfile> {
+        self.profiles
+            .get(name)
+            .or_else(|| self.profiles.get(&self.default_profile))
+            .or_else(|| self.profiles.get(DEFAULT_PROFILE_NAME))
+            .cloned()
+            .expect("default strategy profile is always present")
+    }
+
+    /// Names of profiles that were added, removed or changed.
+    pub fn changed_profiles(&self, other: &StrategyBook) -> Vec<String> {
+        let mut names: Vec<&String> = self.profiles.keys().chain(other.profiles.keys()).collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .filter(|name| self.profiles.get(*name) != other.profiles.get(*name))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Launch source key used in `[strategy.launch_sources]`.
+pub fn launch_source(instruction_type: InstructionType) -> &'static str {
+    match instruction_type {
+        InstructionType::PumpSwapBuy | InstructionType::PumpSwapSell => "pump_swap",
+        InstructionType::RaydiumLaunchpadMint
+        | InstructionType::RaydiumLaunchpadBuy
+        | InstructionType::RaydiumLaunchpadSell => "raydium_launchpad",
+        InstructionType::MeteoraDbcBuy | InstructionType::MeteoraDbcSell => "meteora_dbc",
+        InstructionType::MeteoraDlmmBuy | InstructionType::MeteoraDlmmSell => "meteora_dlmm",
+        _ => "pump_fun",
+    }
+}
@@ -1,5 +1,5 @@
 use clap::ValueEnum;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(ValueEnum, Debug, Clone, Deserialize, PartialEq)]
 pub enum SwapDirection {
@@ -27,12 +27,23 @@ pub enum SwapInType {
     Pct,
 }
 
-#[derive(ValueEnum, Debug, Clone, Deserialize, PartialEq)]
+#[derive(ValueEnum, Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum SwapProtocol {
     #[serde(rename = "pumpfun")]
     PumpFun,
     #[serde(rename = "pumpswap")]
     PumpSwap,
+    #[serde(rename = "raydium_launchpad")]
+    RaydiumLaunchpad,
+    #[serde(rename = "raydium_amm")]
+    RaydiumAmm,
+    #[serde(rename = "raydium_cpmm")]
+    RaydiumCpmm,
+    #[serde(rename = "meteora_dbc")]
+    MeteoraDbc,
+    #[serde(rename = "meteora_dlmm")]
+    MeteoraDlmm,
+    #[default]
     #[serde(rename = "auto")]
     Auto,
 }
@@ -0,0 +1,358 @@
+use anchor_client::solana_sdk::{signature::read_keypair_file, signer::Signer};
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+use std::{fs::File, io, path::Path, sync::Arc};
+use zeroize::Zeroizing;
+
+use solana_vntr_sniper::{
+    common::{
+        config::{AppState, Config},
+        constants::RUN_MSG,
+        logger::Logger,
+        settings::{config_path, init_settings, settings, Settings},
+    },
+    core::{signer::encrypt_keystore, token::get_wallet_token_balances},
+    engine::{
+        export::{export_journal, parse_date, ExportFilter, ExportFormat, ExportKind},
+        manual_sell::ManualSeller,
+        monitor::{copy_trader_pumpfun, new_token_trader_pumpfun},
+        store::init_store,
+        swap::SwapProtocol,
+    },
+};
+
+/// Written by `keystore import` when neither `--output` nor the config names a path.
+const DEFAULT_KEYSTORE_PATH: &str = "wallet.keystore.json";
+
+#[derive(Parser)]
+#[command(name = "solana-vntr-sniper", version, about = "Pump.fun / PumpSwap copy trading and sniping bot")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Copy the trades of the configured copy targets
+    Copy,
+    /// Buy new Pump.fun launches
+    Snipe,
+    /// Sell a token held by the wallet, or every token with --all
+    Sell {
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        mint: Option<String>,
+        /// Sell every non-empty token account in the wallet
+        #[arg(long)]
+        all: bool,
+        /// Percentage of the holding to sell
+        #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..=100))]
+        pct: u64,
+        #[arg(long, value_enum, default_value_t = SwapProtocol::Auto)]
+        protocol: SwapProtocol,
+        /// Sell even if the position store cannot be opened, e.g. because the
+        /// bot is running. Its positions are then not updated.
+        #[arg(long)]
+        force: bool,
+    },
+    /// List positions in the position store
+    Positions {
+        /// Include sold and failed positions
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show the wallet's SOL and token balances
+    Balance,
+    /// Inspect the configuration file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage the encrypted keystore the default signer reads
+    Keystore {
+        #[command(subcommand)]
+        command: KeystoreCommand,
+    },
+    /// Export the trade journal
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportKind::Fills)]
+        kind: ExportKind,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// First day to include, YYYY-MM-DD (UTC)
+        #[arg(long, value_parser = parse_date)]
+        from: Option<chrono::NaiveDate>,
+        /// Last day to include, YYYY-MM-DD (UTC)
+        #[arg(long, value_parser = parse_date)]
+        to: Option<chrono::NaiveDate>,
+        /// Only include trades copied from this target
+        #[arg(long)]
+        target: Option<String>,
+        /// Output file; defaults to stdout
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Validate the configuration file and exit
+    Check,
+}
+
+#[derive(Subcommand)]
+enum KeystoreCommand {
+    /// Encrypt a Solana CLI keypair file into a keystore
+    Import {
+        /// JSON keypair file, as written by `solana-keygen`
+        keypair: String,
+        /// Keystore to write; defaults to `signer.keystore_path` from the config
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Copy => run_trader(TraderMode::Copy).await,
+        Command::Snipe => run_trader(TraderMode::Snipe).await,
+        Command::Sell { mint, all, pct, protocol, force } => sell(mint, all, pct, protocol, force).await,
+        Command::Positions { all } => positions(all),
+        Command::Balance => balance().await,
+        Command::Config { command: ConfigCommand::Check } => config_check(),
+        Command::Keystore { command: KeystoreCommand::Import { keypair, output } } => keystore_import(&keypair, output),
+        Command::Export { kind, format, from, to, target, output } => {
+            export(kind, format, ExportFilter { from, to, target }, output)
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{}", format!("Error: {}", e).red());
+        std::process::exit(1);
+    }
+}
+
+enum TraderMode {
+    Copy,
+    Snipe,
+}
+
+async fn run_trader(mode: TraderMode) -> Result<()> {
+    let config = Config::new().await.lock().await;
+    let yellowstone_grpc_http = config.yellowstone_grpc_http.clone();
+    let yellowstone_grpc_token = config.yellowstone_grpc_token.clone();
+    let app_state = config.app_state.clone();
+    let swap_config = config.swap_config.clone();
+    let time_exceed = config.time_exceed;
+    let counter_limit = config.counter_limit as u64;
+    let min_dev_buy = config.min_dev_buy as u64;
+    let max_dev_buy = config.max_dev_buy as u64;
+    drop(config);
+
+    println!("{}", RUN_MSG);
+    let result = match mode {
+        TraderMode::Copy => {
+            copy_trader_pumpfun(
+                yellowstone_grpc_http,
+                yellowstone_grpc_token,
+                app_state,
+                swap_config,
+                time_exceed,
+                counter_limit,
+                min_dev_buy,
+                max_dev_buy,
+            )
+            .await
+        }
+        TraderMode::Snipe => {
+            new_token_trader_pumpfun(
+                yellowstone_grpc_http,
+                yellowstone_grpc_token,
+                app_state,
+                swap_config,
+                time_exceed,
+                counter_limit,
+                min_dev_buy,
+                max_dev_buy,
+            )
+            .await
+        }
+    };
+    result.map_err(|e| anyhow!(e))
+}
+
+async fn sell(mint: Option<String>, all: bool, pct: u64, protocol: SwapProtocol, force: bool) -> Result<()> {
+    let config = Config::new().await.lock().await;
+    let app_state = Arc::new(config.app_state.clone());
+    let use_jito = config.swap_config.use_jito;
+    drop(config);
+
+    let logger = Logger::new("[SELL] => ".yellow().bold().to_string());
+    // A running bot holds the store's lock. Selling behind its back would leave
+    // it tracking tokens that are gone, so that takes --force.
+    if let Err(e) = init_store(&settings().store.path) {
+        if !force {
+            return Err(anyhow!(
+                "failed to open the position store at {} ({}). If the bot is running, stop it first or pass --force to sell without updating its positions",
+                settings().store.path, e
+            ));
+        }
+        logger.log(format!("Failed to open the position store: {}", e).yellow().to_string());
+    }
+    let seller = ManualSeller::new(app_state, use_jito)?;
+    let results = match (mint, all) {
+        (Some(mint), false) => {
+            let result = seller.sell(&mint, pct, protocol).await;
+            vec![(mint, result)]
+        }
+        (None, true) => seller.sell_all(pct, protocol).await?,
+        _ => return Err(anyhow!("pass either a mint or --all")),
+    };
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    println!("{:<45} {:<10} {:>14} {:>14}  {}", "MINT", "RESULT", "SOL", "REALIZED", "SIGNATURE / ERROR");
+    for (mint, result) in results.iter() {
+        match result {
+            Ok(report) => println!(
+                "{:<45} {:<10} {:>14} {:>14}  {}",
+                mint,
+                "sold".green(),
+                report.sol_received.map(|sol| format!("{:+.6}", sol)).unwrap_or_default(),
+                report.realized_sol.map(|sol| format!("{:+.6}", sol)).unwrap_or_default(),
+                report.signature
+            ),
+            Err(e) => println!("{:<45} {:<10} {:>14} {:>14}  {}", mint, "failed".red(), "", "", e),
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} of {} sell(s) failed", failed, results.len()));
+    }
+    Ok(())
+}
+
+fn positions(all: bool) -> Result<()> {
+    load_settings()?;
+    let store = init_store(&settings().store.path)?;
+    let mut records = store.positions()?;
+    records.retain(|record| all || record.to_position().is_active());
+    records.sort_by_key(|record| record.opened_at_ms);
+
+    if records.is_empty() {
+        println!("No positions");
+        return Ok(());
+    }
+    println!(
+        "{:<45} {:<8} {:>14} {:>14} {:>14} {:<25} {}",
+        "MINT", "STATUS", "TOKENS", "COST (SOL)", "REALIZED", "OPENED", "TARGET"
+    );
+    for record in records {
+        let opened = record
+            .opened_at_ms
+            .and_then(chrono::DateTime::<chrono::Utc>::from_timestamp_millis)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        println!(
+            "{:<45} {:<8} {:>14} {:>14.6} {:>+14.6} {:<25} {}",
+            record.mint,
+            format!("{:?}", record.status),
+            record.basis.token_amount,
+            record.basis.cost_sol,
+            record.basis.realized_sol,
+            opened,
+            record.target.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+async fn balance() -> Result<()> {
+    let config = Config::new().await.lock().await;
+    let app_state: AppState = config.app_state.clone();
+    drop(config);
+
+    let owner = app_state.wallet.pubkey();
+    let lamports = app_state.rpc_nonblocking_client.get_balance(&owner).await?;
+    println!("Wallet: {}", owner);
+    println!("SOL:    {:.9}", lamports as f64 / 1_000_000_000_f64);
+
+    for balance in get_wallet_token_balances(app_state.rpc_nonblocking_client.clone(), &owner).await? {
+        println!("{:<45} {}", balance.mint, balance.ui_amount);
+    }
+    Ok(())
+}
+
+fn config_check() -> Result<()> {
+    let path = config_path();
+    match Settings::load(Path::new(&path)) {
+        Ok(settings) => {
+            println!("{}", format!("{} is valid", path).green());
+            println!("\t * copy targets: {}", settings.copy.targets.len());
+            println!("\t * strategy profiles: {}", settings.strategy.profiles.len());
+            println!("\t * signer: {}", settings.signer.source);
+            Ok(())
+        }
+        Err(errors) => {
+            for error in errors.iter() {
+                eprintln!("{}", format!("\t * {}", error).red());
+            }
+            Err(anyhow!("{} is invalid ({} error(s))", path, errors.len()))
+        }
+    }
+}
+
+fn keystore_import(keypair_path: &str, output: Option<String>) -> Result<()> {
+    let output = match output {
+        Some(output) => output,
+        None => Settings::load(Path::new(&config_path()))
+            .ok()
+            .and_then(|settings| settings.signer.keystore_path)
+            .unwrap_or_else(|| DEFAULT_KEYSTORE_PATH.to_string()),
+    };
+    if Path::new(&output).exists() {
+        return Err(anyhow!("{} already exists; remove it or pass --output", output));
+    }
+    let keypair = read_keypair_file(keypair_path)
+        .map_err(|e| anyhow!("failed to read keypair file {}: {}", keypair_path, e))?;
+
+    let passphrase = match std::env::var("KEYSTORE_PASSPHRASE") {
+        Ok(passphrase) => Zeroizing::new(passphrase),
+        Err(_) => {
+            let passphrase = Zeroizing::new(rpassword::prompt_password("New keystore passphrase: ")?);
+            let repeated = Zeroizing::new(rpassword::prompt_password("Repeat passphrase: ")?);
+            if passphrase != repeated {
+                return Err(anyhow!("passphrases do not match"));
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(anyhow!("the passphrase must not be empty"));
+    }
+
+    encrypt_keystore(&keypair, &passphrase, Path::new(&output))?;
+    println!("{}", format!("Wrote keystore for {} to {}", keypair.pubkey(), output).green());
+    println!("\t * set signer.source = \"keystore\" and signer.keystore_path = \"{}\"", output);
+    Ok(())
+}
+
+fn export(kind: ExportKind, format: ExportFormat, filter: ExportFilter, output: Option<String>) -> Result<()> {
+    load_settings()?;
+    let store = init_store(&settings().store.path)?;
+    let fills = store.all_fills()?;
+    let rows = match &output {
+        Some(path) => export_journal(&fills, kind, format, &filter, &mut File::create(path)?)?,
+        None => export_journal(&fills, kind, format, &filter, &mut io::stdout().lock())?,
+    };
+    if let Some(path) = output {
+        println!("Wrote {} row(s) to {}", rows, path);
+    }
+    Ok(())
+}
+
+fn load_settings() -> Result<()> {
+    init_settings().map(|_| ()).map_err(|errors| {
+        let details: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        anyhow!("invalid configuration:\n\t * {}", details.join("\n\t * "))
+    })
+}
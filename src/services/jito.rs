@@ -7,29 +7,40 @@ use anchor_client::solana_sdk::pubkey::Pubkey;
 use std::{future::Future, str::FromStr, sync::LazyLock, time::Duration};
 use tokio::time::{sleep, Instant};
 
-use crate::common::config::import_env_var;
+use crate::common::settings::settings;
 
-pub static BLOCK_ENGINE_URL: LazyLock<String> =
-    LazyLock::new(|| import_env_var("JITO_BLOCK_ENGINE_URL"));
+/// `relays.jito_block_engine_url`, or `JITO_BLOCK_ENGINE_URL` applied when
+/// the config loads. Validation requires one of them whenever Jito is used.
+pub static BLOCK_ENGINE_URL: LazyLock<String> = LazyLock::new(|| {
+    settings()
+        .relays
+        .jito_block_engine_url
+        .clone()
+        .unwrap_or_default()
+});
+
+/// Tip accounts `get_tip_account` picks one from at random.
+pub const JITO_TIP_ACCOUNTS: [&str; 7] = [
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+];
+/// Tip account returned alongside the random one.
+pub const JITO_SECOND_TIP_ACCOUNT: &str = "JitoFSvbiCrygnx4HZzau4LdeyBU6VUeyf9jt8F8bMk";
 
 pub fn get_tip_account() -> Result<(Pubkey, Pubkey)> {
-    let accounts = [
-        "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt".to_string(),
-        "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5".to_string(),
-        "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL".to_string(),
-        "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT".to_string(),
-        "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe".to_string(),
-        "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh".to_string(),
-        "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49".to_string(),
-    ];
     let mut rng = thread_rng();
-    let tip_account = match accounts.iter().choose(&mut rng) {
+    let tip_account = match JITO_TIP_ACCOUNTS.iter().choose(&mut rng) {
         Some(acc) => Ok(Pubkey::from_str(acc).inspect_err(|err| {
             println!("jito: failed to parse Pubkey: {:?}", err);
         })?),
         None => Err(anyhow!("jito: no tip accounts available")),
     };
-    let tip1_account = Pubkey::from_str("JitoFSvbiCrygnx4HZzau4LdeyBU6VUeyf9jt8F8bMk")
+    let tip1_account = Pubkey::from_str(JITO_SECOND_TIP_ACCOUNT)
         .inspect_err(|err| {
             println!("jito: failed to parse Pubkey: {:?}", err);
         })?;
@@ -38,39 +49,17 @@ pub fn get_tip_account() -> Result<(Pubkey, Pubkey)> {
 }
 // unit sol
 pub async fn get_tip_value() -> Result<f64> {
-    // If TIP_VALUE is set, use it
-    if let Ok(tip_value) = std::env::var("JITO_TIP_VALUE") {
-        match f64::from_str(&tip_value) {
-            Ok(value) => Ok(value),
-            Err(_) => {
-                println!(
-                    "Invalid JITO_TIP_VALUE in environment variable: '{}'. Falling back to percentile calculation.",
-                    tip_value
-                );
-                Err(anyhow!("Invalid TIP_VALUE in environment variable"))
-            }
-        }
-    } else {
-        Err(anyhow!("JITO_TIP_VALUE environment variable not set"))
-    }
+    settings()
+        .relays
+        .jito_tip_value
+        .ok_or_else(|| anyhow!("relays.jito_tip_value is not set"))
 }
 
 pub async fn get_priority_fee() -> Result<f64> {
-    // If TIP_VALUE is set, use it
-    if let Ok(priority_fee) = std::env::var("JITO_PRIORITY_FEE") {
-        match f64::from_str(&priority_fee) {
-            Ok(value) => Ok(value),
-            Err(_) => {
-                println!(
-                    "Invalid JITO_PRIORITY_FEE in environment variable: '{}'. Falling back to percentile calculation.",
-                    priority_fee
-                );
-                Err(anyhow!("Invalid JITO_PRIORITY_FEE in environment variable"))
-            }
-        }
-    } else {
-        Err(anyhow!("JITO_PRIORITY_FEE environment variable not set"))
-    }
+    settings()
+        .relays
+        .jito_priority_fee
+        .ok_or_else(|| anyhow!("relays.jito_priority_fee is not set"))
 }
 
 #[derive(Deserialize, Debug)]
@@ -2,3 +2,4 @@ pub mod jito;
 pub mod nozomi;
 pub mod zeroslot;
 pub mod telegram;
+pub mod price_oracle;
@@ -0,0 +1,397 @@
+use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use std::{str::FromStr, sync::Arc};
+
+use crate::common::{logger::Logger, settings::OracleSettings};
+
+/// Pyth SOL/USD price update account (sponsored push feed, shard 0).
+pub const PYTH_SOL_USD_PRICE_ACCOUNT: &str = "7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE";
+/// Raydium AMM v4 SOL/USDC pool vaults.
+pub const SOL_USDC_POOL_SOL_VAULT: &str = "DQyrAcCrDXQ7NeoqGgDCZwBvWDcYmFCjSb9JtteuvPpz";
+pub const SOL_USDC_POOL_USDC_VAULT: &str = "HLmqeL62xR1QoZ1HKKbXRrdN1p3phKpXRTkbg3bNmVHx";
+/// Price used when every configured source fails.
+pub const DEFAULT_SOL_PRICE_USD: f64 = 200.0;
+/// Pyth updates older than this are rejected.
+pub const PYTH_MAX_PRICE_AGE_SECS: i64 = 120;
+
+const SOL_DECIMALS: i32 = 9;
+const USDC_DECIMALS: i32 = 6;
+// SPL token account layout: mint (32) | owner (32) | amount (u64)
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+/// Source of the SOL/USD price.
+///
+/// Implementations only ever receive an RPC client, never the wallet, so no
+/// oracle can ship key material off the machine.
+pub trait PriceOracle: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn sol_usd_price(&self) -> BoxFuture<'_, Result<f64>>;
+}
+
+/// Reads a Pyth `PriceUpdateV2` account.
+pub struct PythOracle {
+    rpc_client: Arc<RpcClient>,
+    price_account: Pubkey,
+    max_age_secs: i64,
+}
+
+impl PythOracle {
+    pub fn new(rpc_client: Arc<RpcClient>, price_account: Pubkey) -> Self {
+        Self {
+            rpc_client,
+            price_account,
+            max_age_secs: PYTH_MAX_PRICE_AGE_SECS,
+        }
+    }
+
+    pub fn decode_price_update(data: &[u8]) -> Result<(f64, i64)> {
+        // discriminator (8) | write_authority (32) | verification_level (enum)
+        let mut offset = 8 + 32;
+        let level_tag = *data.get(offset).ok_or_else(|| anyhow!("pyth: account too short"))?;
+        offset += match level_tag {
+            0 => 2, // Partial { num_signatures: u8 }
+            1 => 1, // Full
+            _ => return Err(anyhow!("pyth: unknown verification level {}", level_tag)),
+        };
+        // feed_id (32) | price (i64) | conf (u64) | exponent (i32) | publish_time (i64)
+        offset += 32;
+        let price = read_i64(data, offset)?;
+        let exponent = read_i32(data, offset + 16)?;
+        let publish_time = read_i64(data, offset + 20)?;
+        if price <= 0 {
+            return Err(anyhow!("pyth: non-positive price {}", price));
+        }
+        Ok((price as f64 * 10f64.powi(exponent), publish_time))
+    }
+}
+
+impl PriceOracle for PythOracle {
+    fn name(&self) -> &'static str {
+        "pyth"
+    }
+
+    fn sol_usd_price(&self) -> BoxFuture<'_, Result<f64>> {
+        Box::pin(async move {
+            let account = self.rpc_client.get_account(&self.price_account).await?;
+            let (price, publish_time) = Self::decode_price_update(&account.data)?;
+            let age = chrono::Utc::now().timestamp() - publish_time;
+            if age > self.max_age_secs {
+                return Err(anyhow!("pyth: price is stale ({}s old)", age));
+            }
+            Ok(price)
+        })
+    }
+}
+
+/// Derives the price from the SOL and USDC vault balances of a constant-product pool.
+pub struct PoolOracle {
+    rpc_client: Arc<RpcClient>,
+    sol_vault: Pubkey,
+    usdc_vault: Pubkey,
+}
+
+impl PoolOracle {
+    pub fn new(rpc_client: Arc<RpcClient>, sol_vault: Pubkey, usdc_vault: Pubkey) -> Self {
+        Self {
+            rpc_client,
+            sol_vault,
+            usdc_vault,
+        }
+    }
+}
+
+impl PriceOracle for PoolOracle {
+    fn name(&self) -> &'static str {
+        "pool"
+    }
+
+    fn sol_usd_price(&self) -> BoxFuture<'_, Result<f64>> {
+        Box::pin(async move {
+            let accounts = self
+                .rpc_client
+                .get_multiple_accounts(&[self.sol_vault, self.usdc_vault])
+                .await?;
+            let sol_amount = accounts
+                .first()
+                .and_then(|a| a.as_ref())
+                .and_then(|a| token_account_amount(&a.data))
+                .ok_or_else(|| anyhow!("pool: SOL vault not found"))?;
+            let usdc_amount = accounts
+                .get(1)
+                .and_then(|a| a.as_ref())
+                .and_then(|a| token_account_amount(&a.data))
+                .ok_or_else(|| anyhow!("pool: USDC vault not found"))?;
+            if sol_amount == 0 {
+                return Err(anyhow!("pool: SOL vault is empty"));
+            }
+            let sol = sol_amount as f64 / 10f64.powi(SOL_DECIMALS);
+            let usdc = usdc_amount as f64 / 10f64.powi(USDC_DECIMALS);
+            Ok(usdc / sol)
+        })
+    }
+}
+
+/// Fixed price, typically taken from `oracle.static_price`.
+pub struct StaticOracle {
+    price: f64,
+}
+
+impl StaticOracle {
+    pub fn new(price: f64) -> Self {
+        Self { price }
+    }
+}
+
+impl PriceOracle for StaticOracle {
+    fn name(&self) -> &'static str {
+        "static"
+    }
+
+    fn sol_usd_price(&self) -> BoxFuture<'_, Result<f64>> {
+        let price = self.price;
+        Box::pin(async move { Ok(price) })
+    }
+}
+
+/// Tries each oracle in order and returns the first price it gets.
+pub struct FallbackOracle {
+    oracles: Vec<Arc<dyn PriceOracle>>,
+    logger: Logger,
+}
+
+impl FallbackOracle {
+    pub fn new(oracles: Vec<Arc<dyn PriceOracle>>) -> Self {
+        Self {
+            oracles,
+            logger: Logger::new("[PRICE-ORACLE] => ".to_string()),
+        }
+    }
+}
+
+impl PriceOracle for FallbackOracle {
+    fn name(&self) -> &'static str {
+        "fallback"
+    }
+
+    fn sol_usd_price(&self) -> BoxFuture<'_, Result<f64>> {
+        Box::pin(async move {
+            for oracle in self.oracles.iter() {
+                match oracle.sol_usd_price().await {
+                    Ok(price) => return Ok(price),
+                    Err(e) => {
+                        self.logger
+                            .debug(format!("{} oracle failed: {}", oracle.name(), e));
+                    }
+                }
+            }
+            Err(anyhow!("no price oracle returned a price"))
+        })
+    }
+}
+
+/// Builds the oracle chain selected by `oracle.source` (`pyth`, `pool` or `static`).
+/// Every chain ends with a static price so startup never blocks on a price.
+pub fn create_price_oracle(rpc_client: Arc<RpcClient>, settings: &OracleSettings) -> Arc<dyn PriceOracle> {
+    let static_price = settings.static_price.unwrap_or(DEFAULT_SOL_PRICE_USD);
+
+    let mut oracles: Vec<Arc<dyn PriceOracle>> = Vec::new();
+    match settings.source.as_str() {
+        "pyth" => {
+            let account = settings
+                .pyth_account
+                .as_deref()
+                .unwrap_or(PYTH_SOL_USD_PRICE_ACCOUNT);
+            if let Ok(account) = Pubkey::from_str(account) {
+                oracles.push(Arc::new(PythOracle::new(rpc_client.clone(), account)));
+            }
+        }
+        "pool" => {
+            let sol_vault = settings.sol_vault.as_deref().unwrap_or(SOL_USDC_POOL_SOL_VAULT);
+            let usdc_vault = settings.usdc_vault.as_deref().unwrap_or(SOL_USDC_POOL_USDC_VAULT);
+            if let (Ok(sol_vault), Ok(usdc_vault)) =
+                (Pubkey::from_str(sol_vault), Pubkey::from_str(usdc_vault))
+            {
+                oracles.push(Arc::new(PoolOracle::new(
+                    rpc_client.clone(),
+                    sol_vault,
+                    usdc_vault,
+                )));
+            }
+        }
+        _ => {}
+    }
+    oracles.push(Arc::new(StaticOracle::new(static_price)));
+
+    Arc::new(FallbackOracle::new(oracles))
+}
+
+fn token_account_amount(data: &[u8]) -> Option<u64> {
+    data.get(TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_i64(data: &[u8], offset: usize) -> Result<i64> {
+    data.get(offset..offset + 8)
+        .map(|bytes| i64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("pyth: account too short"))
+}
+
+fn read_i32(data: &[u8], offset: usize) -> Result<i32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("pyth: account too short"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::settings::Settings;
+    use crate::core::signer::import_signer;
+    use anchor_client::solana_sdk::signature::{write_keypair_file, Keypair, Signer};
+    use std::sync::Mutex;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const STATIC_PRICE: f64 = 150.0;
+
+    /// A JSON-RPC endpoint on a local port that records every request it gets
+    /// and answers each with an error, so the oracles fall through to the static price.
+    async fn recording_rpc() -> (Arc<RpcClient>, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                while !request_complete(&request) {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                recorded.lock().unwrap().push(request);
+                let body = r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"recorded"},"id":1}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (Arc::new(RpcClient::new(url)), requests)
+    }
+
+    fn request_complete(request: &[u8]) -> bool {
+        let text = String::from_utf8_lossy(request);
+        let Some(header_end) = text.find("\r\n\r\n") else {
+            return false;
+        };
+        let content_length = text[..header_end]
+            .lines()
+            .find_map(|line| {
+                let line = line.to_ascii_lowercase();
+                line.strip_prefix("content-length:").and_then(|value| value.trim().parse::<usize>().ok())
+            })
+            .unwrap_or(0);
+        request.len() >= header_end + 4 + content_length
+    }
+
+    /// Loads a config whose signer is a keypair file holding a fresh keypair.
+    fn settings_with_signer() -> (Settings, Keypair) {
+        let dir = std::env::temp_dir().join(format!("price-oracle-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let keypair = Keypair::new();
+        let keypair_path = dir.join("id.json");
+        write_keypair_file(&keypair, &keypair_path).unwrap();
+        let config_path = dir.join("config.toml");
+        let config = format!(
+            r#"
+[rpc]
+http = "http://127.0.0.1:1"
+
+[grpc]
+http = "http://127.0.0.1:1"
+
+[swap]
+amount_in = 0.1
+slippage = 10
+use_jito = false
+time_exceed = 60
+
+[oracle]
+source = "pyth"
+static_price = {STATIC_PRICE:?}
+
+[signer]
+source = "keypair_file"
+keypair_path = {keypair_path:?}
+
+[store]
+path = {store_path:?}
+"#,
+            keypair_path = keypair_path.display().to_string(),
+            store_path = dir.join("store").display().to_string(),
+        );
+        std::fs::write(&config_path, config).unwrap();
+        let settings = Settings::load(&config_path).unwrap();
+        (settings, keypair)
+    }
+
+    /// Every encoding of the secret key a request could carry it in.
+    fn leaks_secret(request: &[u8], keypair: &Keypair) -> bool {
+        let text = String::from_utf8_lossy(request);
+        let bytes = keypair.to_bytes();
+        let secret = &bytes[..32];
+        text.contains(&keypair.to_base58_string())
+            || text.contains(&bs58::encode(secret).into_string())
+            || text.contains(&base64::encode(bytes))
+            || text.contains(&base64::encode(secret))
+            || text.contains(&serde_json::to_string(&bytes.to_vec()).unwrap())
+            || request.windows(secret.len()).any(|window| window == secret)
+    }
+
+    #[tokio::test]
+    async fn oracles_never_send_key_material() {
+        let (mut settings, keypair) = settings_with_signer();
+        // The key is loaded into this process, as it is when the bot runs
+        let signer = import_signer(&settings.signer).unwrap();
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+
+        let (rpc_client, requests) = recording_rpc().await;
+        // None of the constructors accept a signer; the oracles only ever see the RPC client
+        let mut oracles: Vec<Arc<dyn PriceOracle>> = vec![
+            Arc::new(PythOracle::new(rpc_client.clone(), Pubkey::from_str(PYTH_SOL_USD_PRICE_ACCOUNT).unwrap())),
+            Arc::new(PoolOracle::new(
+                rpc_client.clone(),
+                Pubkey::from_str(SOL_USDC_POOL_SOL_VAULT).unwrap(),
+                Pubkey::from_str(SOL_USDC_POOL_USDC_VAULT).unwrap(),
+            )),
+            Arc::new(StaticOracle::new(STATIC_PRICE)),
+        ];
+        for source in ["pyth", "pool", "static"] {
+            settings.oracle.source = source.to_string();
+            oracles.push(create_price_oracle(rpc_client.clone(), &settings.oracle));
+        }
+        oracles.push(Arc::new(FallbackOracle::new(oracles.clone())));
+
+        for oracle in oracles.iter() {
+            let price = oracle.sol_usd_price().await;
+            if oracle.name() != "pyth" && oracle.name() != "pool" {
+                assert_eq!(price.unwrap(), STATIC_PRICE, "{} oracle", oracle.name());
+            }
+        }
+
+        let requests = requests.lock().unwrap();
+        assert!(!requests.is_empty(), "the on-chain oracles never reached the RPC endpoint");
+        for request in requests.iter() {
+            assert!(!leaks_secret(request, &keypair), "request carried key material:\n{}", String::from_utf8_lossy(request));
+        }
+    }
+}